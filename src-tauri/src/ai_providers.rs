@@ -1,3 +1,4 @@
+use crate::secret_redaction::SecretRedactor;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -84,6 +85,27 @@ pub struct TokenUsage {
     pub estimated_cost: Option<f64>,
 }
 
+/// Role-tagged chat message ("system", "user" or "assistant")
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AIMessage {
+    pub role: String,
+    pub content: String,
+}
+
+impl AIMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self { role: "system".to_string(), content: content.into() }
+    }
+    
+    pub fn user(content: impl Into<String>) -> Self {
+        Self { role: "user".to_string(), content: content.into() }
+    }
+    
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self { role: "assistant".to_string(), content: content.into() }
+    }
+}
+
 /// Streaming chunk response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamChunk {
@@ -112,6 +134,15 @@ pub trait AIProviderTrait: Send + Sync {
         options: Option<GenerationOptions>,
     ) -> Result<AIResponse, Box<dyn std::error::Error + Send + Sync>>;
     
+    /// Generate a completion from a conversation. Each provider decides how a
+    /// system message is sent (inline message vs. dedicated request field).
+    async fn generate_chat(
+        &self,
+        model_id: &str,
+        messages: Vec<AIMessage>,
+        options: Option<GenerationOptions>,
+    ) -> Result<AIResponse, Box<dyn std::error::Error + Send + Sync>>;
+    
     /// Generate a streaming completion
     async fn generate_stream(
         &self,
@@ -133,6 +164,8 @@ pub struct AIClientManager {
     model_cache: HashMap<String, AIModel>,
     default_provider: AIProvider,
     task_routing: HashMap<ModelCapability, Vec<String>>, // capability -> preferred model IDs
+    /// Mask likely secrets in text sent to cloud providers
+    redact_cloud_requests: bool,
 }
 
 impl AIClientManager {
@@ -142,6 +175,7 @@ impl AIClientManager {
             model_cache: HashMap::new(),
             default_provider: AIProvider::Ollama,
             task_routing: HashMap::new(),
+            redact_cloud_requests: false,
         }
     }
     
@@ -156,6 +190,28 @@ impl AIClientManager {
         self.default_provider = provider;
     }
     
+    /// Mask likely secrets in prompts sent to cloud providers; local Ollama is exempt
+    pub fn set_secret_redaction(&mut self, enabled: bool) {
+        self.redact_cloud_requests = enabled;
+    }
+    
+    /// A fresh redactor when requests to `provider` must be masked
+    fn redactor_for(&self, provider: &AIProvider) -> Option<SecretRedactor> {
+        (self.redact_cloud_requests && *provider != AIProvider::Ollama).then(SecretRedactor::new)
+    }
+    
+    /// Put the masked secrets back into a response and list what was masked in its metadata
+    fn restore_secrets(redactor: &SecretRedactor, mut response: AIResponse) -> AIResponse {
+        if !redactor.secrets().is_empty() {
+            response.content = redactor.restore(&response.content);
+            response.metadata.insert(
+                "redacted_secrets".to_string(),
+                serde_json::to_value(redactor.secrets()).unwrap_or_default(),
+            );
+        }
+        response
+    }
+    
     /// Get all available models across providers
     pub async fn get_all_models(&mut self) -> Result<Vec<AIModel>, Box<dyn std::error::Error + Send + Sync>> {
         let mut all_models = Vec::new();
@@ -227,7 +283,39 @@ impl AIClientManager {
         let provider = self.providers.get(&model.provider)
             .ok_or(format!("Provider {:?} not available", model.provider))?;
             
-        provider.generate(model_id, prompt, options).await
+        match self.redactor_for(&model.provider) {
+            Some(mut redactor) => {
+                let prompt = redactor.redact(prompt);
+                let response = provider.generate(model_id, &prompt, options).await?;
+                Ok(Self::restore_secrets(&redactor, response))
+            }
+            None => provider.generate(model_id, prompt, options).await,
+        }
+    }
+    
+    /// Generate completion from a conversation with specific model
+    pub async fn generate_chat_with_model(
+        &self,
+        model_id: &str,
+        messages: Vec<AIMessage>,
+        options: Option<GenerationOptions>,
+    ) -> Result<AIResponse, Box<dyn std::error::Error + Send + Sync>> {
+        let model = self.model_cache.get(model_id)
+            .ok_or(format!("Model {} not found", model_id))?;
+            
+        let provider = self.providers.get(&model.provider)
+            .ok_or(format!("Provider {:?} not available", model.provider))?;
+            
+        match self.redactor_for(&model.provider) {
+            Some(mut redactor) => {
+                let messages = messages.into_iter()
+                    .map(|message| AIMessage { content: redactor.redact(&message.content), ..message })
+                    .collect();
+                let response = provider.generate_chat(model_id, messages, options).await?;
+                Ok(Self::restore_secrets(&redactor, response))
+            }
+            None => provider.generate_chat(model_id, messages, options).await,
+        }
     }
     
     /// Generate streaming completion with specific model
@@ -243,7 +331,11 @@ impl AIClientManager {
         let provider = self.providers.get(&model.provider)
             .ok_or(format!("Provider {:?} not available", model.provider))?;
             
-        provider.generate_stream(model_id, prompt, options).await
+        // Placeholders can be split across chunks, so streamed output isn't restored
+        match self.redactor_for(&model.provider) {
+            Some(mut redactor) => provider.generate_stream(model_id, &redactor.redact(prompt), options).await,
+            None => provider.generate_stream(model_id, prompt, options).await,
+        }
     }
     
     /// Configure task routing preferences
@@ -314,4 +406,119 @@ pub fn classify_prompt_capability(prompt: &str) -> ModelCapability {
     
     // Default to general chat
     ModelCapability::GeneralChat
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Echoes the prompts it receives and records them
+    struct RecordingProvider {
+        provider: AIProvider,
+        prompts: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl AIProviderTrait for RecordingProvider {
+        fn provider_type(&self) -> AIProvider {
+            self.provider.clone()
+        }
+
+        async fn is_healthy(&self) -> bool {
+            true
+        }
+
+        async fn list_models(&self) -> Result<Vec<AIModel>, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(vec![self.get_model_info("").await?])
+        }
+
+        async fn generate(
+            &self,
+            model_id: &str,
+            prompt: &str,
+            _options: Option<GenerationOptions>,
+        ) -> Result<AIResponse, Box<dyn std::error::Error + Send + Sync>> {
+            self.prompts.lock().unwrap().push(prompt.to_string());
+            Ok(AIResponse {
+                content: format!("You sent: {}", prompt),
+                model: model_id.to_string(),
+                provider: self.provider.clone(),
+                usage: None,
+                finish_reason: None,
+                metadata: HashMap::new(),
+            })
+        }
+
+        async fn generate_chat(
+            &self,
+            model_id: &str,
+            messages: Vec<AIMessage>,
+            options: Option<GenerationOptions>,
+        ) -> Result<AIResponse, Box<dyn std::error::Error + Send + Sync>> {
+            let prompt = messages.iter().map(|message| message.content.as_str()).collect::<Vec<_>>().join("\n");
+            self.generate(model_id, &prompt, options).await
+        }
+
+        async fn generate_stream(
+            &self,
+            _model_id: &str,
+            _prompt: &str,
+            _options: Option<GenerationOptions>,
+        ) -> Result<Box<dyn Stream<Item = Result<StreamChunk, Box<dyn std::error::Error + Send + Sync>>> + Send + Unpin>, Box<dyn std::error::Error + Send + Sync>> {
+            Err("streaming is not supported".into())
+        }
+
+        async fn get_model_info(&self, _model_id: &str) -> Result<AIModel, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(AIModel {
+                id: format!("{:?}-model", self.provider).to_lowercase(),
+                name: "Test model".to_string(),
+                provider: self.provider.clone(),
+                capabilities: vec![ModelCapability::GeneralChat],
+                context_length: 4096,
+                cost_per_token: None,
+                speed_tokens_per_second: None,
+                is_available: true,
+                description: String::new(),
+            })
+        }
+
+        async fn validate_connection(&self) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(true)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_secrets_are_redacted_for_cloud_providers_only() {
+        let cloud_prompts = Arc::new(Mutex::new(Vec::new()));
+        let local_prompts = Arc::new(Mutex::new(Vec::new()));
+        let mut manager = AIClientManager::new();
+        manager.register_provider(Box::new(RecordingProvider { provider: AIProvider::OpenAI, prompts: cloud_prompts.clone() }));
+        manager.register_provider(Box::new(RecordingProvider { provider: AIProvider::Ollama, prompts: local_prompts.clone() }));
+        manager.get_all_models().await.unwrap();
+        manager.set_secret_redaction(true);
+
+        let prompt = "Why does this fail? const key = \"sk-abcdefghijklmnopqrstuvwx\";";
+        let response = manager.generate_with_model("openai-model", prompt, None).await.unwrap();
+        let sent = cloud_prompts.lock().unwrap()[0].clone();
+        assert!(!sent.contains("sk-abcdefghijklmnopqrstuvwx"));
+        assert!(sent.contains("[REDACTED_API_KEY_1]"));
+        // The echoed placeholder is restored locally, and the mapping is reported without the secret
+        assert_eq!(response.content, format!("You sent: {}", prompt));
+        let redacted = response.metadata["redacted_secrets"].to_string();
+        assert!(redacted.contains("REDACTED_API_KEY_1") && !redacted.contains("sk-abc"));
+
+        let messages = vec![AIMessage::system("Be brief"), AIMessage::user(prompt)];
+        manager.generate_chat_with_model("openai-model", messages, None).await.unwrap();
+        assert!(!cloud_prompts.lock().unwrap()[1].contains("sk-abcdefghijklmnopqrstuvwx"));
+
+        let response = manager.generate_with_model("ollama-model", prompt, None).await.unwrap();
+        assert_eq!(local_prompts.lock().unwrap()[0], prompt);
+        assert!(!response.metadata.contains_key("redacted_secrets"));
+
+        // Redaction is opt-in
+        manager.set_secret_redaction(false);
+        manager.generate_with_model("openai-model", prompt, None).await.unwrap();
+        assert_eq!(cloud_prompts.lock().unwrap()[2], prompt);
+    }
+}
@@ -1,8 +1,13 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::time::{timeout, Duration};
 use crate::ollama_client::{OllamaClient, GenerateOptions};
-use crate::chroma_manager::ChromaManager;
+use crate::chroma_manager::{ChromaManager, DocumentMetadata};
+use crate::context_manager::ContextManager;
+use crate::float_order;
+use crate::prompt_templates::render_prompt;
+use futures::{stream, StreamExt};
 use uuid::Uuid;
 
 /// Analysis mode types for Deep Analysis Mode
@@ -11,6 +16,8 @@ pub enum AnalysisMode {
     Standard,
     Socratic,
     Systematic,
+    /// Resolved per prompt by `select_analysis_mode`
+    Auto,
 }
 
 /// Question-Answer chain for tracking reasoning
@@ -21,6 +28,34 @@ pub struct QuestionAnswerChain {
     pub round: usize,
     pub timestamp: String,
     pub confidence: f32,
+    /// Populated when rounds are generated with structured (JSON) output
+    #[serde(default)]
+    pub key_insights: Vec<String>,
+}
+
+/// A reasoning round as returned by the model in structured output mode
+#[derive(Debug, Deserialize)]
+struct StructuredRound {
+    #[serde(default)]
+    question: String,
+    answer: String,
+    confidence: f32,
+    #[serde(default)]
+    key_insights: Vec<String>,
+}
+
+/// JSON schema passed as the `format` constraint for structured rounds
+fn structured_round_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "question": { "type": "string" },
+            "answer": { "type": "string" },
+            "confidence": { "type": "number" },
+            "key_insights": { "type": "array", "items": { "type": "string" } }
+        },
+        "required": ["question", "answer", "confidence", "key_insights"]
+    })
 }
 
 /// Deep analysis result containing solution and reasoning
@@ -31,6 +66,17 @@ pub struct DeepAnalysisResult {
     pub confidence: f32,
     pub saved_to_rag: bool,
     pub mode_used: AnalysisMode,
+    /// Questioning ended before `max_rounds` because confidence was high or plateaued
+    #[serde(default)]
+    pub stopped_early: bool,
+}
+
+/// Per-stage generation overrides; unset fields keep the stage defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnalysisStageOptions {
+    pub question: Option<GenerateOptions>,
+    pub answer: Option<GenerateOptions>,
+    pub synthesis: Option<GenerateOptions>,
 }
 
 /// Configuration for deep analysis
@@ -40,6 +86,19 @@ pub struct AnalysisConfig {
     pub max_rounds: usize,
     pub time_limit: Duration,
     pub save_to_rag: bool,
+    pub stage_options: AnalysisStageOptions,
+    /// Prompt similarity (0.0-1.0) above which a saved pattern is treated as a duplicate
+    pub pattern_similarity_threshold: f32,
+    /// Estimated tokens available for the problem and reasoning rounds in the synthesis prompt
+    pub synthesis_token_budget: usize,
+    /// Stop Socratic questioning once a round reaches this confidence (None to disable)
+    pub early_stop_confidence: Option<f32>,
+    /// Stop Socratic questioning when a round's confidence doesn't improve on the previous one
+    pub stop_on_confidence_plateau: bool,
+    /// Ask the model for each round as constrained JSON instead of free text
+    pub structured_output: bool,
+    /// Maximum analyses run at once by `analyze_batch`
+    pub batch_concurrency: usize,
 }
 
 impl Default for AnalysisConfig {
@@ -49,21 +108,236 @@ impl Default for AnalysisConfig {
             max_rounds: 5,
             time_limit: Duration::from_secs(300), // 5 minutes
             save_to_rag: true,
+            stage_options: AnalysisStageOptions::default(),
+            pattern_similarity_threshold: 0.9,
+            synthesis_token_budget: 6000,
+            early_stop_confidence: Some(0.9),
+            stop_on_confidence_plateau: true,
+            structured_output: false,
+            batch_concurrency: 3,
+        }
+    }
+}
+
+/// Apply any fields set in `overrides` on top of a stage's default options
+fn merge_options(defaults: GenerateOptions, overrides: Option<&GenerateOptions>) -> GenerateOptions {
+    match overrides {
+        Some(overrides) => GenerateOptions {
+            temperature: overrides.temperature.or(defaults.temperature),
+            max_tokens: overrides.max_tokens.or(defaults.max_tokens),
+            top_p: overrides.top_p.or(defaults.top_p),
+            top_k: overrides.top_k.or(defaults.top_k),
+        },
+        None => defaults,
+    }
+}
+
+/// A reasoning pattern loaded back from the reasoning patterns collection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedReasoningPattern {
+    pub id: String,
+    pub original_prompt: String,
+    pub mode: AnalysisMode,
+    pub reasoning: Vec<QuestionAnswerChain>,
+    pub solution: String,
+}
+
+/// Collection that deep analysis reasoning patterns are saved to
+pub const REASONING_PATTERNS_COLLECTION: &str = "reasoning_patterns";
+
+/// Counts of saved reasoning patterns by analysis mode and problem type
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PatternStatistics {
+    pub total_patterns: usize,
+    pub by_mode: HashMap<String, usize>,
+    pub by_problem_type: HashMap<String, usize>,
+    pub average_confidence: f32,
+}
+
+/// Build the synthesis context from the problem and its reasoning rounds, strongest first.
+/// When everything doesn't fit in `token_budget`, the lowest-confidence rounds are dropped.
+pub fn build_synthesis_context(problem_context: &str, reasoning_chain: &[QuestionAnswerChain], token_budget: usize) -> String {
+    let mut rounds: Vec<&QuestionAnswerChain> = reasoning_chain.iter().collect();
+    // Stable sort keeps round order among equal confidences; NaN confidences go last
+    rounds.sort_by(|a, b| float_order::descending_nan_last(a.confidence, b.confidence));
+
+    let mut context = format!(
+        "{}\n\nReasoning rounds, strongest first (weigh higher-confidence insights more heavily):",
+        problem_context
+    );
+    let mut used_tokens = ContextManager::estimate_tokens(&context);
+
+    for qa in rounds {
+        let insight = format!(
+            "\n\nInsight from Round {} (confidence {:.2}): Q: {} A: {}",
+            qa.round, qa.confidence, qa.question, qa.answer
+        );
+        let insight_tokens = ContextManager::estimate_tokens(&insight);
+        if used_tokens + insight_tokens > token_budget {
+            // Remaining rounds are weaker still
+            break;
         }
+        used_tokens += insight_tokens;
+        context.push_str(&insight);
+    }
+
+    context
+}
+
+/// Confidence a round counts for when its own is NaN
+const CONFIDENCE_FLOOR: f32 = 0.0;
+
+fn average_confidence(reasoning_chain: &[QuestionAnswerChain]) -> f32 {
+    if reasoning_chain.is_empty() {
+        return 0.0;
+    }
+    reasoning_chain.iter()
+        .map(|qa| float_order::or_floor(qa.confidence, CONFIDENCE_FLOOR, "reasoning round confidence"))
+        .sum::<f32>() / reasoning_chain.len() as f32
+}
+
+/// Word-overlap (Jaccard) similarity between two prompts, ignoring case and punctuation
+fn prompt_similarity(a: &str, b: &str) -> f32 {
+    let words = |text: &str| -> std::collections::HashSet<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_lowercase())
+            .collect()
+    };
+
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f32 / a.union(&b).count() as f32
+}
+
+/// Where the engine gets its completions. `OllamaClient` is the real one; tests can
+/// supply scripted answers instead.
+#[async_trait::async_trait]
+pub trait CompletionProvider: Send + Sync {
+    async fn complete(&self, model: &str, prompt: &str, options: Option<GenerateOptions>) -> Result<String, String>;
+
+    /// A completion constrained to the JSON schema `format`
+    async fn complete_structured(
+        &self,
+        model: &str,
+        prompt: &str,
+        options: Option<GenerateOptions>,
+        format: serde_json::Value,
+    ) -> Result<String, String>;
+}
+
+#[async_trait::async_trait]
+impl CompletionProvider for OllamaClient {
+    async fn complete(&self, model: &str, prompt: &str, options: Option<GenerateOptions>) -> Result<String, String> {
+        self.generate_completion(model, prompt, options).await.map_err(|e| e.to_string())
+    }
+
+    async fn complete_structured(
+        &self,
+        model: &str,
+        prompt: &str,
+        options: Option<GenerateOptions>,
+        format: serde_json::Value,
+    ) -> Result<String, String> {
+        self.generate_structured(model, prompt, options, format).await.map_err(|e| e.to_string())
     }
 }
 
 /// Deep Analysis Engine for Socratic questioning and systematic problem-solving
 pub struct AnalysisEngine {
-    ollama_client: OllamaClient,
+    provider: Arc<dyn CompletionProvider>,
     chroma_manager: Option<ChromaManager>,
+    /// Patterns retrieved once for a batch; used instead of querying Chroma per prompt
+    shared_patterns: Option<Vec<String>>,
 }
 
 impl AnalysisEngine {
     pub fn new(ollama_client: OllamaClient, chroma_manager: Option<ChromaManager>) -> Self {
+        Self::with_provider(Arc::new(ollama_client), chroma_manager)
+    }
+
+    pub fn with_provider(provider: Arc<dyn CompletionProvider>, chroma_manager: Option<ChromaManager>) -> Self {
         Self {
-            ollama_client,
+            provider,
             chroma_manager,
+            shared_patterns: None,
+        }
+    }
+
+    /// Use `patterns` for every analysis instead of querying Chroma, e.g. when they were
+    /// retrieved by the caller under a short lock
+    pub fn with_shared_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.shared_patterns = Some(patterns);
+        self
+    }
+
+    /// Analyze several related prompts, retrieving similar patterns once for the whole
+    /// batch and running up to `config.batch_concurrency` analyses at a time.
+    /// Results are returned in prompt order; a failed prompt doesn't discard the others.
+    pub async fn analyze_batch(
+        &mut self,
+        prompts: &[String],
+        model: &str,
+        config: AnalysisConfig,
+    ) -> Vec<Result<DeepAnalysisResult, String>> {
+        let patterns = self.query_similar_patterns(&prompts.join("\n"), 3).await;
+
+        // Each prompt runs on its own engine sharing the retrieved patterns; results are
+        // saved afterwards since saving needs exclusive access to the Chroma manager
+        let analysis_config = AnalysisConfig {
+            save_to_rag: false,
+            ..config.clone()
+        };
+        let analyses = prompts.iter().map(|prompt| {
+            let mut engine = AnalysisEngine {
+                provider: self.provider.clone(),
+                chroma_manager: None,
+                shared_patterns: Some(patterns.clone()),
+            };
+            let analysis_config = analysis_config.clone();
+            async move { engine.analyze(prompt, model, analysis_config).await }
+        });
+        let mut outcomes: Vec<Result<DeepAnalysisResult, String>> = stream::iter(analyses)
+            .buffered(config.batch_concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .enumerate()
+            .map(|(index, outcome)| outcome.map_err(|e| format!("Analysis of prompt {} failed: {}", index + 1, e)))
+            .collect();
+
+        if let Some(manager) = &mut self.chroma_manager {
+            Self::save_batch_results(manager, prompts, &mut outcomes, &config);
+        }
+
+        outcomes
+    }
+
+    /// Save the successful results of `analyze_batch` when `config.save_to_rag` is set
+    pub fn save_batch_results(
+        manager: &mut ChromaManager,
+        prompts: &[String],
+        outcomes: &mut [Result<DeepAnalysisResult, String>],
+        config: &AnalysisConfig,
+    ) {
+        if !config.save_to_rag {
+            return;
+        }
+        for (prompt, outcome) in prompts.iter().zip(outcomes.iter_mut()) {
+            if let Ok(result) = outcome {
+                if !matches!(result.mode_used, AnalysisMode::Standard) {
+                    result.saved_to_rag = Self::save_pattern(
+                        manager,
+                        prompt,
+                        &result.reasoning,
+                        &result.solution,
+                        &result.mode_used,
+                        config.pattern_similarity_threshold,
+                    );
+                }
+            }
         }
     }
 
@@ -74,10 +348,15 @@ impl AnalysisEngine {
         model: &str,
         config: AnalysisConfig,
     ) -> Result<DeepAnalysisResult, String> {
-        match config.mode {
-            AnalysisMode::Standard => self.standard_analysis(prompt, model).await,
+        let mode = match config.mode {
+            AnalysisMode::Auto => select_analysis_mode(prompt),
+            ref mode => mode.clone(),
+        };
+
+        match mode {
             AnalysisMode::Socratic => self.socratic_analysis(prompt, model, &config).await,
             AnalysisMode::Systematic => self.systematic_analysis(prompt, model, &config).await,
+            AnalysisMode::Standard | AnalysisMode::Auto => self.standard_analysis(prompt, model).await,
         }
     }
 
@@ -94,11 +373,7 @@ impl AnalysisEngine {
             top_k: None,
         };
 
-        let response = self
-            .ollama_client
-            .generate_completion(model, prompt, Some(options))
-            .await
-            .map_err(|e| e.to_string())?;
+        let response = self.provider.complete(model, prompt, Some(options)).await?;
 
         Ok(DeepAnalysisResult {
             solution: response,
@@ -106,6 +381,7 @@ impl AnalysisEngine {
             confidence: 0.8, // Standard mode has good confidence
             saved_to_rag: false,
             mode_used: AnalysisMode::Standard,
+            stopped_early: false,
         })
     }
 
@@ -143,6 +419,7 @@ impl AnalysisEngine {
                 similar_patterns.join("\n---\n")
             );
         }
+        let problem_context = current_context.clone();
 
         // Define the 4-stage Socratic questioning framework
         let questioning_stages = [
@@ -152,29 +429,47 @@ impl AnalysisEngine {
             "How can we validate our understanding? What would convince us this is the right solution?",
         ];
 
+        let planned_rounds = config.max_rounds.min(questioning_stages.len());
+        let mut stopped_early = false;
+
         for (round, stage_question) in questioning_stages.iter().enumerate() {
             if round >= config.max_rounds {
                 break;
             }
 
-            // Generate a contextual question based on the stage and current understanding
-            let question_prompt = format!(
-                "Given this problem context: {}\n\nAnd considering this stage of analysis: {}\n\nAsk one specific, insightful question that will deepen understanding. Be concise and focused:",
-                current_context, stage_question
-            );
+            let (question, answer, confidence, key_insights) = if config.structured_output {
+                let round_prompt = format!(
+                    "Given this problem context: {}\n\nAnd considering this stage of analysis: {}\n\nAsk one specific, insightful question that will deepen understanding, answer it thoroughly, rate your confidence in the answer from 0.0 to 1.0, and list the key insights. Respond in JSON.",
+                    current_context, stage_question
+                );
+                let structured = self.structured_round(&round_prompt, model, round, config.stage_options.answer.as_ref()).await?;
+                let question = if structured.question.is_empty() {
+                    stage_question.to_string()
+                } else {
+                    structured.question
+                };
+                (question, structured.answer, structured.confidence, structured.key_insights)
+            } else {
+                // Generate a contextual question based on the stage and current understanding
+                let question_prompt = render_prompt(
+                    "analysis.question",
+                    &[("context", &current_context), ("stage", stage_question)],
+                );
 
-            let question = self.ask_focused_question(&question_prompt, model).await?;
-            
-            // Get the answer to the question
-            let answer_prompt = format!(
-                "Context: {}\n\nQuestion: {}\n\nProvide a thoughtful, detailed answer:",
-                current_context, question
-            );
+                let question = self.ask_focused_question(&question_prompt, model, config.stage_options.question.as_ref()).await?;
+                
+                // Get the answer to the question
+                let answer_prompt = render_prompt(
+                    "analysis.answer",
+                    &[("context", &current_context), ("question", &question)],
+                );
 
-            let answer = self.get_detailed_answer(&answer_prompt, model).await?;
-            
-            // Calculate confidence based on answer quality and stage
-            let confidence = self.calculate_confidence(&answer, round);
+                let answer = self.get_detailed_answer(&answer_prompt, model, config.stage_options.answer.as_ref()).await?;
+                
+                // Calculate confidence based on answer quality and stage
+                let confidence = self.calculate_confidence(&answer, round);
+                (question, answer, confidence, Vec::new())
+            };
 
             reasoning_chain.push(QuestionAnswerChain {
                 question: question.clone(),
@@ -182,6 +477,7 @@ impl AnalysisEngine {
                 round: round + 1,
                 timestamp: chrono::Utc::now().to_rfc3339(),
                 confidence,
+                key_insights,
             });
 
             // Update context with new insights
@@ -189,20 +485,23 @@ impl AnalysisEngine {
                 "{}\n\nInsight from Round {}: Q: {} A: {}",
                 current_context, round + 1, question, answer
             );
+
+            if round + 1 < planned_rounds && self.should_stop_early(&reasoning_chain, config) {
+                stopped_early = true;
+                break;
+            }
         }
 
         // Generate final solution based on all reasoning
-        let final_solution = self.synthesize_solution(&current_context, model).await?;
+        let synthesis_context = build_synthesis_context(&problem_context, &reasoning_chain, config.synthesis_token_budget);
+        let final_solution = self.synthesize_solution(&synthesis_context, model, config.stage_options.synthesis.as_ref()).await?;
         
         // Calculate overall confidence
-        let overall_confidence = reasoning_chain
-            .iter()
-            .map(|qa| qa.confidence)
-            .fold(0.0, |acc, conf| acc + conf) / reasoning_chain.len() as f32;
+        let overall_confidence = average_confidence(&reasoning_chain);
 
         // Save to RAG if configured
         let saved_to_rag = if config.save_to_rag {
-            self.save_reasoning_to_rag(original_prompt, &reasoning_chain, &final_solution).await
+            self.save_reasoning_to_rag(original_prompt, &reasoning_chain, &final_solution, &AnalysisMode::Socratic, config.pattern_similarity_threshold).await
         } else {
             false
         };
@@ -213,6 +512,7 @@ impl AnalysisEngine {
             confidence: overall_confidence,
             saved_to_rag,
             mode_used: AnalysisMode::Socratic,
+            stopped_early,
         })
     }
 
@@ -236,6 +536,7 @@ impl AnalysisEngine {
         } else {
             prompt.to_string()
         };
+        let problem_context = context.clone();
 
         // PDCA (Plan-Do-Check-Act) cycle adapted for problem-solving
         let systematic_stages = [
@@ -250,13 +551,23 @@ impl AnalysisEngine {
                 break;
             }
 
-            let systematic_prompt = format!(
-                "Problem Context: {}\n\n{} Stage: {}\n\nProvide a structured analysis for this stage:",
-                context, stage_name, stage_question
+            let systematic_prompt = render_prompt(
+                "analysis.systematic_stage",
+                &[("context", &context), ("stage_name", stage_name), ("stage_question", stage_question)],
             );
 
-            let analysis = self.get_detailed_answer(&systematic_prompt, model).await?;
-            let confidence = self.calculate_confidence(&analysis, round);
+            let (analysis, confidence, key_insights) = if config.structured_output {
+                let round_prompt = format!(
+                    "{}\n\nUse the stage question as the question, put your analysis in the answer, rate your confidence from 0.0 to 1.0, and list the key insights. Respond in JSON.",
+                    systematic_prompt
+                );
+                let structured = self.structured_round(&round_prompt, model, round, config.stage_options.answer.as_ref()).await?;
+                (structured.answer, structured.confidence, structured.key_insights)
+            } else {
+                let analysis = self.get_detailed_answer(&systematic_prompt, model, config.stage_options.answer.as_ref()).await?;
+                let confidence = self.calculate_confidence(&analysis, round);
+                (analysis, confidence, Vec::new())
+            };
 
             reasoning_chain.push(QuestionAnswerChain {
                 question: format!("{} Stage: {}", stage_name, stage_question),
@@ -264,20 +575,19 @@ impl AnalysisEngine {
                 round: round + 1,
                 timestamp: chrono::Utc::now().to_rfc3339(),
                 confidence,
+                key_insights,
             });
 
             context = format!("{}\n\n{} Analysis: {}", context, stage_name, analysis);
         }
 
-        let final_solution = self.synthesize_solution(&context, model).await?;
+        let synthesis_context = build_synthesis_context(&problem_context, &reasoning_chain, config.synthesis_token_budget);
+        let final_solution = self.synthesize_solution(&synthesis_context, model, config.stage_options.synthesis.as_ref()).await?;
         
-        let overall_confidence = reasoning_chain
-            .iter()
-            .map(|qa| qa.confidence)
-            .fold(0.0, |acc, conf| acc + conf) / reasoning_chain.len() as f32;
+        let overall_confidence = average_confidence(&reasoning_chain);
 
         let saved_to_rag = if config.save_to_rag {
-            self.save_reasoning_to_rag(prompt, &reasoning_chain, &final_solution).await
+            self.save_reasoning_to_rag(prompt, &reasoning_chain, &final_solution, &AnalysisMode::Systematic, config.pattern_similarity_threshold).await
         } else {
             false
         };
@@ -288,57 +598,98 @@ impl AnalysisEngine {
             confidence: overall_confidence,
             saved_to_rag,
             mode_used: AnalysisMode::Systematic,
+            stopped_early: false,
         })
     }
 
     /// Ask a focused question for Socratic method
-    async fn ask_focused_question(&self, prompt: &str, model: &str) -> Result<String, String> {
-        let options = GenerateOptions {
+    async fn ask_focused_question(&self, prompt: &str, model: &str, overrides: Option<&GenerateOptions>) -> Result<String, String> {
+        let options = merge_options(GenerateOptions {
             temperature: Some(0.7), // Higher temperature for creative questioning
             max_tokens: Some(100),  // Keep questions concise
             top_p: None,
             top_k: None,
-        };
+        }, overrides);
 
-        self.ollama_client
-            .generate_completion(model, prompt, Some(options))
-            .await
-            .map_err(|e| e.to_string())
+        self.provider.complete(model, prompt, Some(options)).await
     }
 
     /// Get a detailed answer to a specific question
-    async fn get_detailed_answer(&self, prompt: &str, model: &str) -> Result<String, String> {
-        let options = GenerateOptions {
+    async fn get_detailed_answer(&self, prompt: &str, model: &str, overrides: Option<&GenerateOptions>) -> Result<String, String> {
+        let options = merge_options(GenerateOptions {
             temperature: Some(0.3), // Lower temperature for focused answers
             max_tokens: Some(500),  // Allow detailed responses
             top_p: None,
             top_k: None,
-        };
+        }, overrides);
 
-        self.ollama_client
-            .generate_completion(model, prompt, Some(options))
-            .await
-            .map_err(|e| e.to_string())
+        self.provider.complete(model, prompt, Some(options)).await
+    }
+
+    /// Generate one reasoning round as constrained JSON. Falls back to treating the raw
+    /// response as the answer if the model ignores the format.
+    async fn structured_round(
+        &self,
+        prompt: &str,
+        model: &str,
+        round: usize,
+        overrides: Option<&GenerateOptions>,
+    ) -> Result<StructuredRound, String> {
+        let options = merge_options(GenerateOptions {
+            temperature: Some(0.3), // Same as detailed answers
+            max_tokens: Some(700),  // Room for the question and insights too
+            top_p: None,
+            top_k: None,
+        }, overrides);
+
+        let response = self.provider
+            .complete_structured(model, prompt, Some(options), structured_round_schema())
+            .await?;
+
+        match serde_json::from_str::<StructuredRound>(response.trim()) {
+            Ok(mut structured) => {
+                structured.confidence = structured.confidence.clamp(0.0, 1.0);
+                Ok(structured)
+            }
+            Err(e) => {
+                eprintln!("Model returned invalid structured round, using raw text: {}", e);
+                Ok(StructuredRound {
+                    question: String::new(),
+                    confidence: self.calculate_confidence(&response, round),
+                    answer: response,
+                    key_insights: Vec::new(),
+                })
+            }
+        }
     }
 
     /// Synthesize final solution from all reasoning
-    async fn synthesize_solution(&self, context: &str, model: &str) -> Result<String, String> {
-        let synthesis_prompt = format!(
-            "Based on all the analysis and reasoning below, provide a clear, actionable solution:\n\n{}\n\nFinal Solution:",
-            context
-        );
+    async fn synthesize_solution(&self, context: &str, model: &str, overrides: Option<&GenerateOptions>) -> Result<String, String> {
+        let synthesis_prompt = render_prompt("analysis.synthesis", &[("context", context)]);
 
-        let options = GenerateOptions {
+        let options = merge_options(GenerateOptions {
             temperature: Some(0.2), // Low temperature for precise synthesis
             max_tokens: Some(800),  // Allow comprehensive solution
             top_p: None,
             top_k: None,
+        }, overrides);
+
+        self.provider.complete(model, &synthesis_prompt, Some(options)).await
+    }
+
+    /// Whether the latest round is confident enough, or no better than the one before, to stop questioning
+    fn should_stop_early(&self, reasoning_chain: &[QuestionAnswerChain], config: &AnalysisConfig) -> bool {
+        let Some(latest) = reasoning_chain.last() else {
+            return false;
         };
 
-        self.ollama_client
-            .generate_completion(model, &synthesis_prompt, Some(options))
-            .await
-            .map_err(|e| e.to_string())
+        if config.early_stop_confidence.map_or(false, |threshold| latest.confidence >= threshold) {
+            return true;
+        }
+
+        config.stop_on_confidence_plateau
+            && reasoning_chain.len() >= 2
+            && latest.confidence <= reasoning_chain[reasoning_chain.len() - 2].confidence
     }
 
     /// Calculate confidence based on answer quality and stage
@@ -368,86 +719,294 @@ impl AnalysisEngine {
         (base_confidence * length_factor + quality_score).min(0.95).max(0.3)
     }
 
-    /// Save reasoning patterns to RAG for future learning
+    /// Save reasoning patterns to RAG for future learning. If a pattern for a near-identical
+    /// prompt in the same mode already exists, only the higher-confidence one is kept.
     async fn save_reasoning_to_rag(
         &mut self,
         original_prompt: &str,
         reasoning_chain: &[QuestionAnswerChain],
         solution: &str,
+        mode: &AnalysisMode,
+        similarity_threshold: f32,
     ) -> bool {
-        if let Some(manager) = &mut self.chroma_manager {
-            // Create a comprehensive document that captures the reasoning pattern
-            let reasoning_document = format!(
-                "Deep Analysis Pattern\n\nOriginal Problem: {}\n\nReasoning Process:\n{}\n\nFinal Solution: {}\n\nPattern Summary: This is a successful {} analysis with {} reasoning rounds and average confidence of {:.2}.",
-                original_prompt,
-                reasoning_chain
-                    .iter()
-                    .enumerate()
-                    .map(|(i, qa)| format!(
-                        "Round {}: {}\nAnswer: {} (Confidence: {:.2})",
-                        i + 1, qa.question, qa.answer, qa.confidence
-                    ))
-                    .collect::<Vec<_>>()
-                    .join("\n\n"),
-                solution,
-                if reasoning_chain.len() > 0 {
-                    match reasoning_chain[0].question.to_lowercase().contains("assumption") {
-                        true => "Socratic",
-                        false => "Systematic",
-                    }
-                } else {
-                    "Unknown"
-                },
-                reasoning_chain.len(),
-                reasoning_chain.iter().map(|qa| qa.confidence).sum::<f32>() / reasoning_chain.len() as f32
-            );
+        match &mut self.chroma_manager {
+            Some(manager) => Self::save_pattern(manager, original_prompt, reasoning_chain, solution, mode, similarity_threshold),
+            None => false,
+        }
+    }
 
-            // Generate unique ID for this reasoning pattern
-            let pattern_id = format!("deep_analysis_{}", uuid::Uuid::new_v4());
-
-            // Create metadata for pattern matching
-            let metadata = HashMap::from([
-                ("type".to_string(), "deep_analysis_pattern".to_string()),
-                ("problem_type".to_string(), self.classify_problem_type(original_prompt)),
-                ("analysis_mode".to_string(), if reasoning_chain.len() > 0 {
-                    match reasoning_chain[0].question.to_lowercase().contains("assumption") {
-                        true => "socratic".to_string(),
-                        false => "systematic".to_string(),
-                    }
-                } else {
-                    "unknown".to_string()
-                }),
-                ("rounds".to_string(), reasoning_chain.len().to_string()),
-                ("avg_confidence".to_string(), format!("{:.2}", 
-                    reasoning_chain.iter().map(|qa| qa.confidence).sum::<f32>() / reasoning_chain.len() as f32)
-                ),
-                ("solution_length".to_string(), solution.len().to_string()),
-                ("timestamp".to_string(), chrono::Utc::now().to_rfc3339()),
-            ]);
-
-            // Try to add to ChromaDB reasoning patterns collection
-            match manager.add_documents(
-                "reasoning_patterns",
+    /// Save a reasoning pattern to `manager`; see `save_reasoning_to_rag`
+    fn save_pattern(
+        manager: &mut ChromaManager,
+        original_prompt: &str,
+        reasoning_chain: &[QuestionAnswerChain],
+        solution: &str,
+        mode: &AnalysisMode,
+        similarity_threshold: f32,
+    ) -> bool {
+        let (reasoning_document, metadata) = Self::build_pattern_document(original_prompt, reasoning_chain, solution, mode);
+        let avg_confidence = average_confidence(reasoning_chain);
+
+        let mode_label = format!("{:?}", mode).to_lowercase();
+        let existing = Self::find_similar_pattern(manager, original_prompt, &mode_label, similarity_threshold);
+        if let Some((_, existing_confidence)) = &existing {
+            if *existing_confidence >= avg_confidence {
+                println!("Skipped saving reasoning pattern; a similar pattern with higher confidence exists");
+                return false;
+            }
+        }
+
+        // Replace the weaker duplicate in place, otherwise add a new pattern
+        let result = match existing {
+            Some((pattern_id, _)) => manager.update(
+                REASONING_PATTERNS_COLLECTION,
+                vec![pattern_id],
                 vec![reasoning_document],
                 vec![metadata],
-                Some(vec![pattern_id]),
-            ) {
-                Ok(_) => {
-                    println!("Successfully saved reasoning pattern to RAG");
-                    true
-                }
-                Err(e) => {
-                    eprintln!("Failed to save reasoning pattern to RAG: {}", e);
-                    false
-                }
+            ),
+            None => manager.add_documents(
+                REASONING_PATTERNS_COLLECTION,
+                vec![reasoning_document],
+                vec![metadata],
+                Some(vec![format!("deep_analysis_{}", uuid::Uuid::new_v4())]),
+            ),
+        };
+
+        match result {
+            Ok(_) => {
+                println!("Successfully saved reasoning pattern to RAG");
+                true
+            }
+            Err(e) => {
+                eprintln!("Failed to save reasoning pattern to RAG: {}", e);
+                false
             }
-        } else {
-            false
         }
     }
 
+    /// Document text and metadata for a saved reasoning pattern. The chain itself is kept
+    /// in the metadata so the pattern can be resumed later.
+    fn build_pattern_document(
+        original_prompt: &str,
+        reasoning_chain: &[QuestionAnswerChain],
+        solution: &str,
+        mode: &AnalysisMode,
+    ) -> (String, DocumentMetadata) {
+        let avg_confidence = average_confidence(reasoning_chain);
+
+        // Create a comprehensive document that captures the reasoning pattern
+        let reasoning_document = format!(
+            "Deep Analysis Pattern\n\nOriginal Problem: {}\n\nReasoning Process:\n{}\n\nFinal Solution: {}\n\nPattern Summary: This is a successful {:?} analysis with {} reasoning rounds and average confidence of {:.2}.",
+            original_prompt,
+            reasoning_chain
+                .iter()
+                .enumerate()
+                .map(|(i, qa)| format!(
+                    "Round {}: {}\nAnswer: {} (Confidence: {:.2})",
+                    i + 1, qa.question, qa.answer, qa.confidence
+                ))
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+            solution,
+            mode,
+            reasoning_chain.len(),
+            avg_confidence
+        );
+
+        // Create metadata for pattern matching
+        let metadata = DocumentMetadata {
+            source: "deep_analysis".to_string(),
+            document_type: "deep_analysis_pattern".to_string(),
+            language: None,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            file_path: None,
+            url: None,
+            title: None,
+            additional: HashMap::from([
+                ("problem_type".to_string(), serde_json::json!(Self::classify_problem_type(original_prompt))),
+                ("analysis_mode".to_string(), serde_json::json!(format!("{:?}", mode).to_lowercase())),
+                ("original_prompt".to_string(), serde_json::json!(original_prompt)),
+                ("rounds".to_string(), serde_json::json!(reasoning_chain.len())),
+                ("avg_confidence".to_string(), serde_json::json!(avg_confidence)),
+                ("solution_length".to_string(), serde_json::json!(solution.len())),
+                ("solution".to_string(), serde_json::json!(solution)),
+                ("reasoning".to_string(), serde_json::json!(reasoning_chain)),
+            ]),
+        };
+
+        (reasoning_document, metadata)
+    }
+
+    /// Load a saved reasoning pattern, including its reasoning chain
+    pub fn load_reasoning_pattern(&self, pattern_id: &str) -> Result<SavedReasoningPattern, String> {
+        let manager = self.chroma_manager.as_ref()
+            .ok_or_else(|| "No Chroma manager available to load reasoning patterns".to_string())?;
+        Self::read_reasoning_pattern(manager, pattern_id)
+    }
+
+    /// Load a saved reasoning pattern from `manager`, for callers that don't lend the
+    /// manager to an engine
+    pub fn read_reasoning_pattern(manager: &ChromaManager, pattern_id: &str) -> Result<SavedReasoningPattern, String> {
+        let document = manager.get_document(REASONING_PATTERNS_COLLECTION, pattern_id)
+            .ok_or_else(|| format!("Reasoning pattern {} not found", pattern_id))?;
+        let additional = &document.metadata.additional;
+
+        let reasoning = additional.get("reasoning")
+            .cloned()
+            .ok_or_else(|| format!("Reasoning pattern {} has no saved reasoning chain", pattern_id))
+            .and_then(|value| serde_json::from_value(value)
+                .map_err(|e| format!("Failed to parse reasoning chain for {}: {}", pattern_id, e)))?;
+        let text = |key: &str| additional.get(key).and_then(|value| value.as_str()).unwrap_or_default().to_string();
+
+        Ok(SavedReasoningPattern {
+            id: document.id.clone(),
+            original_prompt: text("original_prompt"),
+            mode: if text("analysis_mode") == "systematic" { AnalysisMode::Systematic } else { AnalysisMode::Socratic },
+            reasoning,
+            solution: text("solution"),
+        })
+    }
+
+    /// Continue a saved analysis with new information: runs refinement rounds on top of
+    /// the saved chain, synthesizes a new solution and saves the extended pattern in place
+    pub async fn resume_analysis(
+        &mut self,
+        pattern_id: &str,
+        additional_context: &str,
+        model: &str,
+        config: &AnalysisConfig,
+    ) -> Result<DeepAnalysisResult, String> {
+        let saved = self.load_reasoning_pattern(pattern_id)?;
+        let mut result = self.continue_analysis(&saved, additional_context, model, config).await?;
+        if let Some(manager) = &mut self.chroma_manager {
+            result.saved_to_rag = Self::save_resumed_pattern(manager, &saved, additional_context, &result);
+        }
+        Ok(result)
+    }
+
+    /// Run refinement rounds on top of a saved chain and synthesize a new solution.
+    /// Nothing is saved; see `save_resumed_pattern`.
+    pub async fn continue_analysis(
+        &self,
+        saved: &SavedReasoningPattern,
+        additional_context: &str,
+        model: &str,
+        config: &AnalysisConfig,
+    ) -> Result<DeepAnalysisResult, String> {
+        let previous_reasoning = saved.reasoning
+            .iter()
+            .map(|qa| format!("Round {}: Q: {} A: {}", qa.round, qa.question, qa.answer))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let problem_context = format!(
+            "{}\n\nPrevious Solution: {}\n\nNew Information: {}",
+            saved.original_prompt, saved.solution, additional_context
+        );
+        let mut current_context = format!("{}\n\nPrevious Reasoning:\n{}", problem_context, previous_reasoning);
+
+        let refinement_stages = [
+            "How does the new information change our earlier assumptions or conclusions?",
+            "Which parts of the previous reasoning still hold, and which need revisiting?",
+            "What new risks or opportunities does the new information introduce?",
+            "What would we now do differently, and how would we validate it?",
+        ];
+
+        let mut reasoning_chain = saved.reasoning.clone();
+        for (round, stage_question) in refinement_stages.iter().enumerate().take(config.max_rounds) {
+            let question_prompt = render_prompt(
+                "analysis.question",
+                &[("context", &current_context), ("stage", stage_question)],
+            );
+            let question = self.ask_focused_question(&question_prompt, model, config.stage_options.question.as_ref()).await?;
+
+            let answer_prompt = render_prompt(
+                "analysis.answer",
+                &[("context", &current_context), ("question", &question)],
+            );
+            let answer = self.get_detailed_answer(&answer_prompt, model, config.stage_options.answer.as_ref()).await?;
+            let confidence = self.calculate_confidence(&answer, round);
+            let round_number = reasoning_chain.len() + 1;
+
+            reasoning_chain.push(QuestionAnswerChain {
+                question: question.clone(),
+                answer: answer.clone(),
+                round: round_number,
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                confidence,
+                key_insights: Vec::new(),
+            });
+
+            current_context = format!(
+                "{}\n\nInsight from Round {}: Q: {} A: {}",
+                current_context, round_number, question, answer
+            );
+        }
+
+        let synthesis_context = build_synthesis_context(&problem_context, &reasoning_chain, config.synthesis_token_budget);
+        let final_solution = self.synthesize_solution(&synthesis_context, model, config.stage_options.synthesis.as_ref()).await?;
+
+        Ok(DeepAnalysisResult {
+            solution: final_solution,
+            confidence: average_confidence(&reasoning_chain),
+            reasoning: reasoning_chain,
+            saved_to_rag: false,
+            mode_used: saved.mode.clone(),
+            stopped_early: false,
+        })
+    }
+
+    /// Replace `saved` in `manager` with the extended chain and solution of `result`
+    pub fn save_resumed_pattern(
+        manager: &mut ChromaManager,
+        saved: &SavedReasoningPattern,
+        additional_context: &str,
+        result: &DeepAnalysisResult,
+    ) -> bool {
+        let (document, mut metadata) = Self::build_pattern_document(&saved.original_prompt, &result.reasoning, &result.solution, &saved.mode);
+        metadata.additional.insert("resumed_with".to_string(), serde_json::json!(additional_context));
+        match manager.update(REASONING_PATTERNS_COLLECTION, vec![saved.id.clone()], vec![document], vec![metadata]) {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!("Failed to save resumed reasoning pattern {}: {}", saved.id, e);
+                false
+            }
+        }
+    }
+
+    /// Give back the Chroma manager this engine was created with
+    pub fn into_chroma_manager(self) -> Option<ChromaManager> {
+        self.chroma_manager
+    }
+
+    /// Most similar saved pattern for this prompt and mode, as (id, avg_confidence)
+    fn find_similar_pattern(
+        manager: &ChromaManager,
+        prompt: &str,
+        mode_label: &str,
+        threshold: f32,
+    ) -> Option<(String, f32)> {
+        manager
+            .get_documents(REASONING_PATTERNS_COLLECTION)
+            .into_iter()
+            .filter(|pattern| {
+                pattern.metadata.additional.get("analysis_mode").and_then(|value| value.as_str()) == Some(mode_label)
+            })
+            .filter_map(|pattern| {
+                let saved_prompt = pattern.metadata.additional.get("original_prompt")?.as_str()?;
+                let similarity = prompt_similarity(prompt, saved_prompt);
+                let confidence = pattern.metadata.additional
+                    .get("avg_confidence")
+                    .and_then(|value| value.as_f64())
+                    .unwrap_or(0.0) as f32;
+                (similarity >= threshold).then(|| (pattern.id, confidence, similarity))
+            })
+            // First in descending order is the most similar
+            .min_by(|a, b| float_order::descending_nan_last(a.2, b.2))
+            .map(|(id, confidence, _)| (id, confidence))
+    }
+
     /// Classify the type of problem for better pattern matching
-    fn classify_problem_type(&self, prompt: &str) -> String {
+    pub fn classify_problem_type(prompt: &str) -> String {
         let prompt_lower = prompt.to_lowercase();
         
         if prompt_lower.contains("debug") || prompt_lower.contains("error") || prompt_lower.contains("fix") {
@@ -469,42 +1028,77 @@ impl AnalysisEngine {
 
     /// Query similar reasoning patterns from RAG for enhanced analysis
     pub async fn query_similar_patterns(&mut self, prompt: &str, limit: usize) -> Vec<String> {
-        if let Some(manager) = &mut self.chroma_manager {
-            // Create a query that looks for similar problem patterns
-            let query = format!(
-                "Similar problem to analyze: {} Find reasoning patterns for {} problems",
-                prompt,
-                self.classify_problem_type(prompt)
-            );
+        if let Some(patterns) = &self.shared_patterns {
+            return patterns.iter().take(limit).cloned().collect();
+        }
 
-            match manager.query("reasoning_patterns", &query, limit, None) {
-                Ok(results) => {
-                    results.into_iter()
-                        .map(|result| result.document)
-                        .collect()
-                }
-                Err(e) => {
-                    eprintln!("Failed to query similar patterns: {}", e);
-                    Vec::new()
-                }
+        match &mut self.chroma_manager {
+            Some(manager) => Self::query_patterns(manager, prompt, limit),
+            None => Vec::new(),
+        }
+    }
+
+    /// Query similar reasoning patterns from `manager`, for callers that don't lend the
+    /// manager to an engine
+    pub fn query_patterns(manager: &mut ChromaManager, prompt: &str, limit: usize) -> Vec<String> {
+        // Create a query that looks for similar problem patterns
+        let query = format!(
+            "Similar problem to analyze: {} Find reasoning patterns for {} problems",
+            prompt,
+            Self::classify_problem_type(prompt)
+        );
+
+        match manager.query(REASONING_PATTERNS_COLLECTION, &query, limit, None) {
+            Ok(results) => {
+                results.into_iter()
+                    .map(|result| result.document)
+                    .collect()
+            }
+            Err(e) => {
+                eprintln!("Failed to query similar patterns: {}", e);
+                Vec::new()
             }
-        } else {
-            Vec::new()
         }
     }
 
     /// Get statistics about saved reasoning patterns
-    pub async fn get_pattern_statistics(&self) -> HashMap<String, usize> {
-        let mut stats = HashMap::new();
-        
-        if let Some(manager) = &self.chroma_manager {
-            // This would require ChromaDB to support collection statistics
-            // For now, return empty stats
-            stats.insert("total_patterns".to_string(), 0);
-            stats.insert("socratic_patterns".to_string(), 0);
-            stats.insert("systematic_patterns".to_string(), 0);
+    pub async fn get_pattern_statistics(&self) -> PatternStatistics {
+        let mut stats = PatternStatistics::default();
+
+        let Some(manager) = &self.chroma_manager else {
+            return stats;
+        };
+
+        let patterns = manager.get_documents(REASONING_PATTERNS_COLLECTION);
+        let mut confidence_sum = 0.0;
+        let mut confidence_count = 0;
+
+        for pattern in &patterns {
+            let additional = &pattern.metadata.additional;
+            let label = |key: &str| additional
+                .get(key)
+                .and_then(|value| value.as_str())
+                .unwrap_or("unknown")
+                .to_string();
+
+            *stats.by_mode.entry(label("analysis_mode")).or_insert(0) += 1;
+            *stats.by_problem_type.entry(label("problem_type")).or_insert(0) += 1;
+
+            // Older patterns stored confidence as a formatted string
+            let confidence = additional.get("avg_confidence").and_then(|value| {
+                value.as_f64().or_else(|| value.as_str().and_then(|text| text.parse().ok()))
+            });
+            if let Some(confidence) = confidence {
+                confidence_sum += confidence;
+                confidence_count += 1;
+            }
         }
-        
+
+        stats.total_patterns = patterns.len();
+        if confidence_count > 0 {
+            stats.average_confidence = (confidence_sum / confidence_count as f64) as f32;
+        }
+
         stats
     }
 }
@@ -525,4 +1119,21 @@ pub fn should_suggest_deep_analysis(prompt: &str) -> bool {
 
     // Suggest deep analysis if multiple complexity indicators or prompt is long
     indicator_count >= 2 || prompt.len() > 200
-}
\ No newline at end of file
+}
+
+/// Pick the analysis mode that best fits a prompt: open-ended debugging and design problems
+/// get Socratic questioning, build-oriented tasks get the Systematic PDCA loop, and short or
+/// simple prompts are answered directly
+pub fn select_analysis_mode(prompt: &str) -> AnalysisMode {
+    let is_complex = should_suggest_deep_analysis(prompt);
+    if !is_complex && prompt.split_whitespace().count() < 5 {
+        return AnalysisMode::Standard;
+    }
+
+    match AnalysisEngine::classify_problem_type(prompt).as_str() {
+        "debugging" | "design" => AnalysisMode::Socratic,
+        "implementation" | "refactoring" | "optimization" => AnalysisMode::Systematic,
+        _ if is_complex => AnalysisMode::Socratic,
+        _ => AnalysisMode::Standard,
+    }
+}
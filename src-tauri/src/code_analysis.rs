@@ -3,12 +3,19 @@ use serde::{Deserialize, Serialize};
 use tower_lsp::lsp_types::DiagnosticSeverity;
 use std::collections::{HashMap, HashSet};
 use futures::{stream, StreamExt};
+use crate::model_aliases::ModelRole;
+
+/// Model used for code analysis when a request doesn't name one: the `code` alias
+pub const DEFAULT_ANALYSIS_MODEL: &str = ModelRole::Code.alias();
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CodeAnalysisRequest {
     pub code: String,
     pub language: String,
     pub file_path: Option<String>,
+    /// Model to analyze with; defaults to `DEFAULT_ANALYSIS_MODEL`
+    #[serde(default)]
+    pub model: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -48,7 +55,224 @@ pub struct Position {
 use std::path::Path;
 use std::sync::Arc;
 use tauri::State;
-use crate::ollama_client::{SharedOllamaClient, ChatMessage};
+use crate::ollama_client::{OllamaClient, SharedOllamaClient, ChatMessage, GenerateOptions};
+use crate::prompt_templates::render_prompt;
+use crate::language_detection::language_from_path;
+use crate::extraction_cache::{ExtractionCache, ExtractionCacheStats};
+use crate::dependency_export::{export_graph, GraphExportFormat};
+use crate::dependency_modules::{aggregate_modules, ModuleGraph};
+use crate::unused_exports::{self, UnusedExportOptions, UnusedExportReport};
+use crate::git_diff::{numbered_chunks, read_diff, render_within_budget, DEFAULT_CONTEXT_LINES};
+use crate::security_scan::{self, SecurityFinding};
+use crate::json_repair;
+use tokio::sync::Mutex;
+
+/// Extracts the symbols a file defines and the imports it makes
+#[async_trait::async_trait]
+pub trait SymbolExtractor: Send + Sync {
+    async fn extract(&self, content: &str, language: &str, file_path: &str) -> (Vec<Symbol>, Vec<Import>);
+}
+
+/// Token budget for asking again when a JSON reply was cut off beyond repair
+const TRUNCATED_JSON_RETRY_MAX_TOKENS: i32 = 8192;
+
+/// Ask `model` for a JSON reply and parse it with `parse`, which repairs replies cut off
+/// mid-value. When a truncated reply still can't be parsed, the request is repeated once
+/// with a larger token budget. Returns the reply that was parsed with the outcome.
+async fn chat_json_parsed<T>(
+    client: &OllamaClient,
+    model: &str,
+    messages: Vec<ChatMessage>,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Result<(ChatMessage, Result<T, String>), String> {
+    let reply = client.chat_json(model, messages.clone()).await.map_err(|e| e.to_string())?;
+    let parsed = parse(&reply.content);
+    if parsed.is_ok() || !json_repair::is_truncated(&reply.content) {
+        return Ok((reply, parsed));
+    }
+    
+    eprintln!("Truncated JSON reply from {}; retrying with num_predict {}", model, TRUNCATED_JSON_RETRY_MAX_TOKENS);
+    let options = GenerateOptions {
+        temperature: None,
+        top_p: None,
+        top_k: None,
+        max_tokens: Some(TRUNCATED_JSON_RETRY_MAX_TOKENS),
+    };
+    let reply = client.chat_json_with_options(model, messages, Some(options)).await.map_err(|e| e.to_string())?;
+    let parsed = parse(&reply.content);
+    Ok((reply, parsed))
+}
+
+fn content_fingerprint(content: &str) -> String {
+    format!("{:x}", md5::compute(content.as_bytes()))
+}
+
+/// Changed files under `repo_path` and their hunks as `CodeChange`s, diffed against
+/// `base_ref`. The staged diff is used when `staged_only`, otherwise staged and
+/// working-tree changes together. Deleted and binary files are left out.
+async fn git_changes(repo_path: &Path, base_ref: &str, staged_only: bool) -> Result<Vec<(String, Vec<CodeChange>)>, git2::Error> {
+    // Without context lines each hunk covers exactly the changed lines
+    let files = read_diff(repo_path, Some(base_ref), staged_only, 0).await?;
+    
+    let changes = files.into_iter()
+        .filter(|file| file.status != "deleted" && file.status != "binary")
+        .map(|file| {
+            let file_changes = file.hunks.iter()
+                .map(|hunk| {
+                    let mut new_text = String::new();
+                    for line in hunk.lines.iter().filter_map(|line| line.strip_prefix('+')) {
+                        new_text.push_str(line);
+                        new_text.push('\n');
+                    }
+                    // Hunk lines are 1-based, except that a pure deletion gives the line before it
+                    let new_lines = hunk.new_lines();
+                    let start = match new_lines {
+                        0 => hunk.new_start,
+                        _ => hunk.new_start - 1,
+                    };
+                    CodeChange {
+                        range: Range {
+                            start: Position { line: start, character: 0 },
+                            end: Position { line: start + new_lines, character: 0 },
+                        },
+                        new_text,
+                        description: Some(hunk.header.trim().to_string()),
+                    }
+                })
+                .collect();
+            (repo_path.join(&file.path).to_string_lossy().to_string(), file_changes)
+        })
+        .collect();
+    Ok(changes)
+}
+
+/// User-facing message for a git error on the repository at `repo_path`
+fn git_error_message(repo_path: &str, e: git2::Error) -> String {
+    match e.code() {
+        git2::ErrorCode::NotFound if e.class() == git2::ErrorClass::Repository => {
+            format!("Not a git repository: {}", repo_path)
+        }
+        _ => format!("Failed to read git diff: {}", e),
+    }
+}
+
+/// Characters of diff text put into a single prompt
+const DIFF_PROMPT_BUDGET: usize = 12_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitMessageStyle {
+    /// `type(scope): summary` titles, e.g. `fix(parser): handle empty input`
+    #[default]
+    Conventional,
+    /// A plain imperative title
+    Plain,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CommitMessage {
+    pub title: String,
+    pub body: String,
+}
+
+/// Title and body of a commit message the model wrote
+fn parse_commit_message(reply: &str) -> Result<CommitMessage, String> {
+    let (message, _) = split_code_reply(reply, "");
+    let mut lines = message.lines().skip_while(|line| line.trim().is_empty());
+    let title = lines.next()
+        .map(|line| {
+            let line = line.trim().trim_start_matches('#').trim();
+            let line = line.strip_prefix("Title:").unwrap_or(line).trim();
+            line.trim_matches(|c| c == '"' || c == '`').to_string()
+        })
+        .filter(|title| !title.is_empty())
+        .ok_or("The model returned an empty commit message")?;
+    let body = lines.collect::<Vec<_>>().join("\n");
+    let body = body.trim();
+    Ok(CommitMessage {
+        title,
+        body: body.strip_prefix("Body:").unwrap_or(body).trim().to_string(),
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReviewComment {
+    /// Path relative to the repository root
+    pub file: String,
+    /// 1-based line in the new version of the file
+    pub line: u32,
+    pub severity: ReviewSeverity,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiffReviewResponse {
+    pub base_ref: String,
+    pub comments: Vec<ReviewComment>,
+    pub reviewed_files: Vec<String>,
+    /// Why parts of the diff went unreviewed
+    pub errors: Vec<String>,
+}
+
+/// Review comments from a model reply, keyed to the diffed `files` they name. Comments
+/// on other files or without a line are dropped.
+fn parse_review_comments(reply: &str, files: &[&str]) -> Result<Vec<ReviewComment>, String> {
+    let json = json_repair::parse_json_reply(reply)?;
+    let entries = match &json {
+        serde_json::Value::Array(entries) => entries,
+        serde_json::Value::Object(object) => object.get("comments")
+            .and_then(|comments| comments.as_array())
+            .ok_or("missing a \"comments\" array")?,
+        _ => return Err("expected a JSON object or array".to_string()),
+    };
+    
+    let field = |entry: &serde_json::Value, names: &[&str]| -> Option<serde_json::Value> {
+        names.iter().find_map(|name| entry.get(*name).cloned())
+    };
+    Ok(entries.iter()
+        .filter_map(|entry| {
+            // Models name files relative to the repository, by their absolute path or by file name
+            let named = field(entry, &["file", "file_path", "path"]).and_then(|file| file.as_str().map(str::to_string));
+            let file = match named {
+                Some(named) => files.iter().find(|file| {
+                    **file == named || file.ends_with(&format!("/{}", named)) || named.ends_with(&format!("/{}", file))
+                })?,
+                None if files.len() == 1 => files.first()?,
+                None => return None,
+            };
+            let line = field(entry, &["line", "line_number"]).and_then(|line| match line {
+                serde_json::Value::Number(line) => line.as_u64(),
+                serde_json::Value::String(line) => line.trim().parse().ok(),
+                _ => None,
+            })?;
+            let message = field(entry, &["message", "comment", "body"])?.as_str()?.trim().to_string();
+            if message.is_empty() {
+                return None;
+            }
+            let severity = field(entry, &["severity"])
+                .and_then(|severity| severity.as_str().map(str::to_lowercase))
+                .map_or(ReviewSeverity::Info, |severity| match severity.as_str() {
+                    "error" | "critical" | "high" | "blocker" => ReviewSeverity::Error,
+                    "warning" | "medium" | "major" => ReviewSeverity::Warning,
+                    _ => ReviewSeverity::Info,
+                });
+            Some(ReviewComment {
+                file: file.to_string(),
+                line: line.max(1) as u32,
+                severity,
+                message,
+            })
+        })
+        .collect())
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RepositoryAnalysisRequest {
@@ -157,6 +381,17 @@ pub enum DependencyStrength {
     Strong,  // Many imports, critical dependency
 }
 
+impl DependencyStrength {
+    /// Numeric weight for summing and visualizing strengths
+    pub fn weight(&self) -> u32 {
+        match self {
+            DependencyStrength::Weak => 1,
+            DependencyStrength::Medium => 2,
+            DependencyStrength::Strong => 3,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DependencyAnalysisRequest {
     pub repo_path: String,
@@ -166,6 +401,37 @@ pub struct DependencyAnalysisRequest {
     pub include_content: Option<bool>,
 }
 
+/// Outcome of refreshing a cached repository dependency graph
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DependencyGraphRefresh {
+    pub files: usize,
+    /// New files and files whose content changed, re-extracted in this refresh
+    pub updated_files: usize,
+    pub removed_files: usize,
+}
+
+/// A single file re-extracted into its cached dependency graph
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileDependencyUpdate {
+    pub file_path: String,
+    /// The file no longer exists and was dropped from the graph
+    pub removed: bool,
+    pub outgoing_edges: Vec<DependencyEdge>,
+    pub incoming_edges: Vec<DependencyEdge>,
+}
+
+/// Upper bound on files in a cached repository dependency graph
+const REPO_GRAPH_MAX_FILES: usize = 500;
+
+/// A repository's dependency graph kept between impact analyses
+struct CachedDependencyGraph {
+    graph: DependencyGraph,
+    /// Content hash of each file when its node was built
+    fingerprints: HashMap<String, String>,
+    /// Files reported changed since the graph was last updated
+    stale: HashSet<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ImpactAnalysisRequest {
     pub file_path: String,
@@ -183,8 +449,126 @@ pub struct CodeChange {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ImpactAnalysisResponse {
     pub affected_files: Vec<AffectedFile>,
+    /// Test files that depend on the changed file, directly or transitively
+    #[serde(default)]
+    pub affected_tests: Vec<AffectedTest>,
+    /// Command that runs `affected_tests`, when their runner is known
+    #[serde(default)]
+    pub test_command: Option<String>,
     pub impact_summary: String,
     pub risk_assessment: RiskLevel,
+    /// Weighted breadth of the impact; see `impact_risk_score`
+    #[serde(default)]
+    pub risk_score: f64,
+}
+
+/// Impact of every file changed relative to a git ref
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitImpactAnalysisResponse {
+    pub base_ref: String,
+    pub changed_files: Vec<String>,
+    pub file_impacts: Vec<FileImpact>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileImpact {
+    pub file_path: String,
+    pub changes: Vec<CodeChange>,
+    pub impact: ImpactAnalysisResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AffectedTest {
+    pub file_path: String,
+    /// Import chain from the changed file to the test, both included
+    pub dependency_path: Vec<String>,
+}
+
+/// Weight of an affected file at each impact level
+fn impact_level_weight(level: &ImpactLevel) -> f64 {
+    match level {
+        ImpactLevel::None => 0.0,
+        ImpactLevel::Low => 1.0,
+        ImpactLevel::Medium => 2.0,
+        ImpactLevel::High => 4.0,
+        ImpactLevel::Critical => 8.0,
+    }
+}
+
+/// Weight of an affected symbol: exported API outweighs internals, and types and
+/// interfaces, which many call sites depend on, outweigh single functions
+fn symbol_weight(symbol: &Symbol) -> f64 {
+    let visibility = if symbol.is_exported { 1.0 } else { 0.5 };
+    let kind = match symbol.kind {
+        SymbolKind::Class | SymbolKind::Interface | SymbolKind::Type | SymbolKind::Namespace | SymbolKind::Module => 1.5,
+        _ => 1.0,
+    };
+    visibility * kind
+}
+
+/// Risk score summing every affected file's level weight and the weights of the
+/// symbols it uses, so impacts with the same maximum level still rank by breadth
+fn impact_risk_score(affected_files: &[AffectedFile]) -> f64 {
+    affected_files.iter()
+        .map(|file| {
+            impact_level_weight(&file.impact_level) + file.affected_symbols.iter().map(symbol_weight).sum::<f64>()
+        })
+        .sum()
+}
+
+/// Directory names that hold tests
+const TEST_DIRECTORIES: [&str; 5] = ["test", "tests", "__tests__", "spec", "specs"];
+
+/// Imports that only test files make
+const TEST_FRAMEWORK_IMPORTS: [&str; 8] = [
+    "jest", "vitest", "mocha", "chai", "@testing-library", "pytest", "unittest", "@jest/globals",
+];
+
+/// Whether a file is a test, from its path or its test-framework imports
+fn is_test_file(node: &DependencyNode) -> bool {
+    let path = Path::new(&node.file_path);
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let stem = file_name.split('.').next().unwrap_or("");
+
+    let by_name = stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_spec")
+        || file_name.contains(".test.")
+        || file_name.contains(".spec.");
+    let by_directory = path.parent().is_some_and(|parent| {
+        parent.components().any(|component| TEST_DIRECTORIES.contains(&component.as_os_str().to_string_lossy().as_ref()))
+    });
+    let by_imports = node.imports.iter().any(|import| {
+        TEST_FRAMEWORK_IMPORTS.iter().any(|framework| {
+            import.source == *framework || import.source.starts_with(&format!("{}/", framework))
+        })
+    });
+
+    by_name || by_directory || by_imports
+}
+
+/// Command running `tests` with their language's usual runner, or None if the tests
+/// span languages or the runner is unknown
+fn test_runner_command(tests: &[&DependencyNode]) -> Option<String> {
+    let language = tests.first()?.language.as_str();
+    if tests.iter().any(|test| test.language != language) {
+        return None;
+    }
+    let paths = tests.iter().map(|test| test.file_path.as_str()).collect::<Vec<_>>().join(" ");
+    match language {
+        "python" => Some(format!("pytest {}", paths)),
+        "javascript" | "typescript" | "jsx" | "tsx" => Some(format!("npx jest {}", paths)),
+        "go" => Some("go test ./...".to_string()),
+        "rust" => Some("cargo test".to_string()),
+        _ => None,
+    }
+}
+
+/// An exported function no test file mentions
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UntestedSymbol {
+    pub file_path: String,
+    pub symbol: Symbol,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -217,9 +601,12 @@ pub struct RefactoringRequest {
     pub file_paths: Vec<String>,
     pub focus_areas: Option<Vec<RefactoringFocusArea>>,
     pub repo_path: String,
+    /// Model to ask; `DEFAULT_ANALYSIS_MODEL` when unset
+    #[serde(default)]
+    pub model: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RefactoringFocusArea {
     Performance,
     Readability,
@@ -228,6 +615,40 @@ pub enum RefactoringFocusArea {
     CodeDuplication,
 }
 
+impl RefactoringFocusArea {
+    pub const ALL: [RefactoringFocusArea; 5] = [
+        RefactoringFocusArea::Performance,
+        RefactoringFocusArea::Readability,
+        RefactoringFocusArea::Maintainability,
+        RefactoringFocusArea::Security,
+        RefactoringFocusArea::CodeDuplication,
+    ];
+    
+    /// Areas considered when a request doesn't name any
+    const DEFAULT: [RefactoringFocusArea; 3] = [
+        RefactoringFocusArea::Readability,
+        RefactoringFocusArea::Maintainability,
+        RefactoringFocusArea::Performance,
+    ];
+    
+    /// What the model should look for in this area
+    fn prompt_guidance(&self) -> &'static str {
+        match self {
+            RefactoringFocusArea::Performance => "redundant work in loops, needless allocations and copies, repeated lookups, blocking calls on hot paths",
+            RefactoringFocusArea::Readability => "unclear names, deep nesting, long functions, clever expressions that hide intent",
+            RefactoringFocusArea::Maintainability => "tight coupling, mixed responsibilities, magic values, missing error handling",
+            RefactoringFocusArea::Security => "injection through unsanitized input, hardcoded secrets, unsafe deserialization, missing validation and authorization checks",
+            RefactoringFocusArea::CodeDuplication => "repeated logic that can be extracted into a shared function or type",
+        }
+    }
+    
+    /// Parse a tag written by the model, ignoring case and separators
+    fn from_tag(tag: &str) -> Option<Self> {
+        let normalized: String = tag.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase();
+        Self::ALL.into_iter().find(|area| format!("{:?}", area).to_lowercase() == normalized)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RefactoringSuggestion {
     pub title: String,
@@ -237,6 +658,246 @@ pub struct RefactoringSuggestion {
     pub affected_files: Vec<String>,
     pub effort_estimate: String,
     pub benefits: Vec<String>,
+    /// Focus areas the suggestion addresses
+    #[serde(default)]
+    pub focus_areas: Vec<RefactoringFocusArea>,
+}
+
+/// Refactoring suggestions, with the reason when there are none
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefactoringResponse {
+    pub suggestions: Vec<RefactoringSuggestion>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Times the model is asked for suggestions before giving up on malformed output
+const REFACTORING_PARSE_ATTEMPTS: usize = 2;
+
+/// A suggestion as the model writes it; missing fields fall back to defaults
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ModelRefactoringSuggestion {
+    title: String,
+    description: String,
+    #[serde(alias = "before")]
+    before_code: String,
+    #[serde(alias = "after")]
+    after_code: String,
+    #[serde(alias = "files")]
+    affected_files: Vec<String>,
+    #[serde(alias = "effort")]
+    effort_estimate: String,
+    benefits: Vec<String>,
+    #[serde(alias = "categories")]
+    focus_areas: Vec<String>,
+}
+
+/// Parse a model reply holding `{"suggestions": [...]}` or a bare array, possibly
+/// wrapped in prose or a code fence. Suggestions without files apply to `file_paths`.
+fn parse_refactoring_suggestions(response: &str, file_paths: &[String]) -> Result<Vec<RefactoringSuggestion>, String> {
+    let json = json_repair::parse_json_reply(response)?;
+    
+    let entries = match &json {
+        serde_json::Value::Array(entries) => entries,
+        serde_json::Value::Object(object) => object.get("suggestions")
+            .and_then(|suggestions| suggestions.as_array())
+            .ok_or("missing a \"suggestions\" array")?,
+        _ => return Err("expected a JSON object or array".to_string()),
+    };
+    
+    entries.iter().enumerate()
+        .map(|(index, entry)| {
+            let suggestion: ModelRefactoringSuggestion = serde_json::from_value(entry.clone())
+                .map_err(|e| format!("suggestion {}: {}", index + 1, e))?;
+            if suggestion.title.trim().is_empty() {
+                return Err(format!("suggestion {} has no title", index + 1));
+            }
+            
+            let effort_estimate = match suggestion.effort_estimate.trim().to_lowercase().as_str() {
+                "easy" | "low" => "Easy".to_string(),
+                "medium" | "moderate" => "Medium".to_string(),
+                "hard" | "high" => "Hard".to_string(),
+                _ => suggestion.effort_estimate.trim().to_string(),
+            };
+            let affected_files = if suggestion.affected_files.is_empty() {
+                file_paths.to_vec()
+            } else {
+                suggestion.affected_files
+            };
+            
+            Ok(RefactoringSuggestion {
+                title: suggestion.title.trim().to_string(),
+                description: suggestion.description,
+                before_code: suggestion.before_code,
+                after_code: suggestion.after_code,
+                affected_files,
+                effort_estimate,
+                benefits: suggestion.benefits,
+                // Unrecognized tags are dropped rather than failing the suggestion
+                focus_areas: suggestion.focus_areas.iter()
+                    .filter_map(|tag| RefactoringFocusArea::from_tag(tag))
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// Suggestions tagged with at least one of `focus_areas`, or all of them when no
+/// areas are requested
+pub fn filter_by_focus_areas(
+    suggestions: Vec<RefactoringSuggestion>,
+    focus_areas: Option<&[RefactoringFocusArea]>,
+) -> Vec<RefactoringSuggestion> {
+    match focus_areas {
+        Some(areas) if !areas.is_empty() => suggestions.into_iter()
+            .filter(|suggestion| suggestion.focus_areas.iter().any(|area| areas.contains(area)))
+            .collect(),
+        _ => suggestions,
+    }
+}
+
+/// A refactoring applied to, or previewed on, a single file
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefactoringEdit {
+    pub file_path: String,
+    pub workspace_edit: tower_lsp::lsp_types::WorkspaceEdit,
+    /// Unified diff of the lines the edit touches
+    pub diff: String,
+    /// False for a dry run, which leaves the file unchanged
+    pub applied: bool,
+}
+
+/// Byte range of the single place `before_code` occurs in `content`, treating any
+/// run of whitespace as equal to any other so indentation and line breaks may differ
+fn locate_fuzzy(content: &str, before_code: &str) -> Result<std::ops::Range<usize>, String> {
+    let tokens: Vec<String> = before_code.split_whitespace().map(regex::escape).collect();
+    if tokens.is_empty() {
+        return Err("The suggestion has no before_code to replace".to_string());
+    }
+    let pattern = regex::Regex::new(&tokens.join(r"\s+")).map_err(|e| e.to_string())?;
+    
+    let mut matches = pattern.find_iter(content);
+    let found = matches.next().ok_or("before_code was not found in the file")?;
+    match matches.count() {
+        0 => Ok(found.range()),
+        others => Err(format!("before_code matches {} places in the file; refusing an ambiguous edit", others + 1)),
+    }
+}
+
+/// `code` with its common indentation removed and `indent` added to every line
+/// after the first, which continues the line it is inserted into
+fn reindent(code: &str, indent: &str) -> String {
+    let common = code.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    code.trim_end_matches('\n')
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            let line = line.get(common..).unwrap_or(line.trim_start());
+            match index {
+                0 => line.to_string(),
+                _ if line.is_empty() => String::new(),
+                _ => format!("{}{}", indent, line),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// LSP position (UTF-16 columns) of a byte offset
+fn lsp_position(content: &str, offset: usize) -> tower_lsp::lsp_types::Position {
+    let line_start = content[..offset].rfind('\n').map_or(0, |index| index + 1);
+    tower_lsp::lsp_types::Position {
+        line: content[..offset].matches('\n').count() as u32,
+        character: content[line_start..offset].encode_utf16().count() as u32,
+    }
+}
+
+/// Unified diff with one hunk per `(old_range, new_range)` pair, each widened to whole lines
+fn unified_diff(file_path: &str, old: &str, new: &str, changes: &[(std::ops::Range<usize>, std::ops::Range<usize>)]) -> String {
+    fn line_span(text: &str, range: std::ops::Range<usize>) -> (usize, Vec<&str>) {
+        let start = text[..range.start].rfind('\n').map_or(0, |index| index + 1);
+        let end = text[range.end..].find('\n').map_or(text.len(), |index| range.end + index);
+        (text[..start].matches('\n').count() + 1, text[start..end].lines().collect())
+    }
+    
+    let path = file_path.trim_start_matches('/');
+    let mut diff = format!("--- a/{}\n+++ b/{}\n", path, path);
+    for (old_range, new_range) in changes {
+        let (old_line, old_lines) = line_span(old, old_range.clone());
+        let (new_line, new_lines) = line_span(new, new_range.clone());
+        diff.push_str(&format!("@@ -{},{} +{},{} @@\n", old_line, old_lines.len(), new_line, new_lines.len()));
+        for line in old_lines {
+            diff.push_str(&format!("-{}\n", line));
+        }
+        for line in new_lines {
+            diff.push_str(&format!("+{}\n", line));
+        }
+    }
+    diff
+}
+
+/// Range in `content` a suggestion replaces and its replacement, indented to fit
+fn plan_replacement(content: &str, suggestion: &RefactoringSuggestion) -> Result<(std::ops::Range<usize>, String), String> {
+    let range = locate_fuzzy(content, &suggestion.before_code)?;
+    let line_start = content[..range.start].rfind('\n').map_or(0, |index| index + 1);
+    let indent: String = content[line_start..range.start].chars().take_while(|c| c.is_whitespace()).collect();
+    Ok((range, reindent(&suggestion.after_code, &indent)))
+}
+
+fn text_edit(content: &str, range: std::ops::Range<usize>, new_text: String) -> tower_lsp::lsp_types::TextEdit {
+    tower_lsp::lsp_types::TextEdit {
+        range: tower_lsp::lsp_types::Range {
+            start: lsp_position(content, range.start),
+            end: lsp_position(content, range.end),
+        },
+        new_text,
+    }
+}
+
+fn workspace_edit(file_path: &str, edits: Vec<tower_lsp::lsp_types::TextEdit>) -> Result<tower_lsp::lsp_types::WorkspaceEdit, String> {
+    let uri = tower_lsp::lsp_types::Url::from_file_path(Path::new(file_path).canonicalize().map_err(|e| e.to_string())?)
+        .map_err(|_| format!("Invalid file path: {}", file_path))?;
+    Ok(tower_lsp::lsp_types::WorkspaceEdit {
+        changes: Some(HashMap::from([(uri, edits)])),
+        ..Default::default()
+    })
+}
+
+/// Outcome of applying several refactoring suggestions to one file. Suggestions are
+/// identified by their index in the request.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefactoringBatchEdit {
+    pub file_path: String,
+    /// Edits of the applied suggestions against the original file
+    pub workspace_edit: tower_lsp::lsp_types::WorkspaceEdit,
+    pub diff: String,
+    /// Applied suggestions, in the order they were applied
+    pub applied: Vec<usize>,
+    pub conflicts: Vec<RefactoringConflict>,
+    pub failed: Vec<RefactoringFailure>,
+    /// True when the file was left unchanged
+    pub dry_run: bool,
+}
+
+/// A suggestion whose edit overlaps one accepted before it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RefactoringConflict {
+    pub suggestion: usize,
+    pub conflicts_with: usize,
+    /// Applied anyway, on top of the earlier edits
+    pub forced: bool,
+}
+
+/// A suggestion whose `before_code` couldn't be located uniquely
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RefactoringFailure {
+    pub suggestion: usize,
+    pub error: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -266,23 +927,263 @@ pub struct CodeGenerationResponse {
     pub explanation: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TestGenerationResponse {
+    pub file_path: String,
+    pub symbol_name: String,
+    pub framework: String,
+    /// Only the code of the model's reply, ready to save as a test file
+    pub test_code: String,
+    /// Prose the model wrote around the code
+    pub explanation: String,
+}
+
+/// Source lines of a symbol's definition. When the location covers only its first
+/// line, the definition runs to its closing brace, or for indentation-scoped languages
+/// to the next line indented no deeper than it.
+fn symbol_source(content: &str, symbol: &Symbol) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let start = (symbol.location.start.line as usize).min(lines.len().saturating_sub(1));
+    let mut end = (symbol.location.end.line as usize).min(lines.len().saturating_sub(1));
+    
+    if end <= start {
+        let indent = |line: &str| line.len() - line.trim_start().len();
+        let mut depth = 0i32;
+        let mut opened = false;
+        end = start;
+        for (index, line) in lines.iter().enumerate().skip(start) {
+            end = index;
+            if !opened && index > start && !line.trim().is_empty() && indent(line) <= indent(lines[start]) {
+                // Indentation-scoped body ended on the previous line
+                end = index - 1;
+                break;
+            }
+            for c in line.chars() {
+                match c {
+                    '{' => { depth += 1; opened = true; }
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+            }
+            if opened && depth <= 0 {
+                break;
+            }
+        }
+        while end > start && lines[end].trim().is_empty() {
+            end -= 1;
+        }
+    }
+    lines.get(start..=end).map(|lines| lines.join("\n")).unwrap_or_default()
+}
+
+/// Split a model reply into its code and the prose around it. The first fenced block
+/// tagged with `language` wins, then the first fenced block; an unfenced reply is all code.
+fn split_code_reply(reply: &str, language: &str) -> (String, String) {
+    let mut blocks = Vec::new();
+    let mut prose = Vec::new();
+    let mut rest = reply;
+    while let Some(open) = rest.find("```") {
+        prose.push(&rest[..open]);
+        let after_fence = &rest[open + 3..];
+        let info_end = after_fence.find('\n').unwrap_or(after_fence.len());
+        let info = after_fence[..info_end].trim();
+        let body = &after_fence[(info_end + 1).min(after_fence.len())..];
+        let Some(close) = body.find("```") else {
+            // Unterminated fence: the rest of the reply is code
+            blocks.push((info, body));
+            rest = "";
+            break;
+        };
+        blocks.push((info, &body[..close]));
+        rest = &body[close + 3..];
+    }
+    prose.push(rest);
+    
+    let code = blocks.iter()
+        .find(|(info, _)| info.eq_ignore_ascii_case(language))
+        .or(blocks.first())
+        .map(|(_, code)| code.trim_end().to_string());
+    match code {
+        Some(code) => (code, prose.iter().map(|text| text.trim()).filter(|text| !text.is_empty()).collect::<Vec<_>>().join("\n\n")),
+        None => (reply.trim().to_string(), String::new()),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DocVerbosity {
+    #[default]
+    Terse,
+    Verbose,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct DocStyle {
+    pub verbosity: DocVerbosity,
+    pub include_examples: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DocGenerationResponse {
+    pub file_path: String,
+    pub symbol_name: String,
+    /// The inserted text, comment markers and indentation included
+    pub doc_comment: String,
+    pub edit: tower_lsp::lsp_types::WorkspaceEdit,
+}
+
+/// Documentation text of a model reply, with any comment markers it added anyway removed
+fn doc_text(reply: &str) -> String {
+    let mut text = reply.trim();
+    // A reply that is one fenced block is the documentation itself
+    if text.starts_with("```") && text.ends_with("```") && text.matches("```").count() == 2 {
+        text = text[3..text.len() - 3].split_once('\n').map_or("", |(_, body)| body);
+    }
+    
+    // Leading asterisks are only markers inside a block comment, elsewhere they're list items
+    let markers: &[&str] = if text.starts_with("/**") {
+        &["/**", "*/", "* ", "*"]
+    } else {
+        &["///", "//!", "\"\"\"", "'''"]
+    };
+    let lines: Vec<&str> = text.lines()
+        .map(|line| {
+            let line = line.trim_end();
+            let trimmed = line.trim_start();
+            for marker in markers {
+                if let Some(rest) = trimmed.strip_prefix(marker) {
+                    return rest.strip_prefix(' ').unwrap_or(rest);
+                }
+            }
+            line
+        })
+        .map(|line| line.trim_end_matches("*/").trim_end_matches("\"\"\"").trim_end())
+        .collect();
+    lines.join("\n").trim_matches('\n').to_string()
+}
+
+/// Where a symbol's documentation goes and the text to insert there: a docstring
+/// opening the body in Python, otherwise a doc comment above the definition and its
+/// attributes or decorators
+fn doc_comment_insertion(content: &str, symbol: &Symbol, language: &str, documentation: &str) -> (usize, String) {
+    let lines: Vec<&str> = content.lines().collect();
+    let line_offset = |line: usize| -> usize { content.split_inclusive('\n').take(line).map(str::len).sum() };
+    let indent_of = |line: &str| line[..line.len() - line.trim_start().len()].to_string();
+    let start = (symbol.location.start.line as usize).min(lines.len().saturating_sub(1));
+    let indent = lines.get(start).map(|line| indent_of(line)).unwrap_or_default();
+    let doc_lines: Vec<&str> = documentation.lines().collect();
+    
+    if language == "python" {
+        // The body starts after the line closing the signature
+        let signature_end = (start..lines.len())
+            .find(|&line| lines[line].trim_end().ends_with(':'))
+            .unwrap_or(start);
+        let body_indent = lines.get(signature_end + 1)
+            .filter(|line| !line.trim().is_empty() && indent_of(line).len() > indent.len())
+            .map(|line| indent_of(line))
+            .unwrap_or_else(|| format!("{}    ", indent));
+        let docstring = match doc_lines.as_slice() {
+            [line] => format!("{}\"\"\"{}\"\"\"\n", body_indent, line),
+            _ => {
+                let body: String = doc_lines.iter()
+                    .map(|line| if line.is_empty() { "\n".to_string() } else { format!("{}{}\n", body_indent, line) })
+                    .collect();
+                format!("{}\"\"\"\n{}{}\"\"\"\n", body_indent, body, body_indent)
+            }
+        };
+        return (line_offset(signature_end + 1).min(content.len()), docstring);
+    }
+    
+    // Attributes and decorators belong below the doc comment
+    let mut above = start;
+    while above > 0 && {
+        let previous = lines[above - 1].trim_start();
+        previous.starts_with("#[") || previous.starts_with('@')
+    } {
+        above -= 1;
+    }
+    let comment_line = |prefix: &str, line: &str| {
+        if line.is_empty() { format!("{}{}\n", indent, prefix.trim_end()) } else { format!("{}{}{}\n", indent, prefix, line) }
+    };
+    let comment: String = match language {
+        "rust" => doc_lines.iter().map(|line| comment_line("/// ", line)).collect(),
+        "go" => doc_lines.iter().map(|line| comment_line("// ", line)).collect(),
+        "ruby" | "shell" | "bash" => doc_lines.iter().map(|line| comment_line("# ", line)).collect(),
+        _ => format!(
+            "{}/**\n{}{} */\n",
+            indent,
+            doc_lines.iter().map(|line| comment_line(" * ", line)).collect::<String>(),
+            indent
+        ),
+    };
+    (line_offset(above), comment)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SecurityScanResponse {
+    pub findings: Vec<SecurityFinding>,
+    /// Why the model review is missing, when only pattern findings are returned
+    pub model_error: Option<String>,
+}
+
 // Code analysis service
 pub struct CodeAnalysisService {
     ollama_client: SharedOllamaClient,
+    /// Replaces AI symbol extraction when set
+    symbol_extractor: Option<Arc<dyn SymbolExtractor>>,
+    /// Dependency graphs by repository path, reused across impact analyses
+    dependency_graphs: Mutex<HashMap<String, CachedDependencyGraph>>,
+    /// Per-file extractions kept across runs
+    extraction_cache: Option<ExtractionCache>,
 }
 
 impl CodeAnalysisService {
     pub fn new(ollama_client: SharedOllamaClient) -> Self {
-        Self { ollama_client }
+        Self {
+            ollama_client,
+            symbol_extractor: None,
+            dependency_graphs: Mutex::new(HashMap::new()),
+            extraction_cache: None,
+        }
+    }
+    
+    pub fn with_extraction_cache(mut self, cache: ExtractionCache) -> Self {
+        self.extraction_cache = Some(cache);
+        self
+    }
+    
+    pub fn extraction_cache_stats(&self) -> Result<ExtractionCacheStats, String> {
+        self.extraction_cache.as_ref()
+            .ok_or_else(|| "Extraction cache is not enabled".to_string())?
+            .stats()
+            .map_err(|e| e.to_string())
+    }
+    
+    /// Remove all cached extractions, returning how many there were
+    pub fn clear_extraction_cache(&self) -> Result<usize, String> {
+        self.extraction_cache.as_ref()
+            .ok_or_else(|| "Extraction cache is not enabled".to_string())?
+            .clear()
+            .map_err(|e| e.to_string())
+    }
+    
+    pub fn extraction_cache(&self) -> Option<&ExtractionCache> {
+        self.extraction_cache.as_ref()
+    }
+    
+    pub fn with_symbol_extractor(mut self, extractor: Arc<dyn SymbolExtractor>) -> Self {
+        self.symbol_extractor = Some(extractor);
+        self
     }
     
     pub async fn analyze_code(&self, request: &CodeAnalysisRequest) -> Result<CodeAnalysisResponse, String> {
         let client = self.ollama_client.lock().await;
         
         // Prepare the prompt for code analysis
-        let prompt = format!(
-            "Analyze the following {} code and provide suggestions and identify errors:\n\n```{}\n{}\n```",
-            request.language, request.language, request.code
+        let prompt = render_prompt(
+            "code.analyze",
+            &[("language", &request.language), ("code", &request.code)],
         );
         
         // Create chat messages
@@ -300,7 +1201,7 @@ impl CodeAnalysisService {
         // Get response from Ollama
         let response = client
             .chat(
-                "llama3:latest", // Use a suitable model
+                request.model.as_deref().unwrap_or(DEFAULT_ANALYSIS_MODEL),
                 messages,
                 None,
                 None::<fn(&str)>,
@@ -345,6 +1246,7 @@ impl CodeAnalysisService {
                             code: content,
                             language: language.clone(),
                             file_path: Some(path_clone.to_string_lossy().to_string()),
+                            model: None,
                         };
                         
                         if let Ok(analysis) = service.analyze_code(&analysis_request).await {
@@ -382,16 +1284,17 @@ impl CodeAnalysisService {
         let client = self.ollama_client.lock().await;
         
         // Prepare the prompt for code fixing
-        let prompt = format!(
-            "Fix the following {} code that has an error: '{}' at line {}:{} to {}:{}:\n\n```{}\n{}\n```",
-            request.language,
-            request.error_message,
-            request.error_range.start.line,
-            request.error_range.start.character,
-            request.error_range.end.line,
-            request.error_range.end.character,
-            request.language,
-            request.code
+        let prompt = render_prompt(
+            "code.fix",
+            &[
+                ("language", &request.language),
+                ("error_message", &request.error_message),
+                ("start_line", &request.error_range.start.line.to_string()),
+                ("start_character", &request.error_range.start.character.to_string()),
+                ("end_line", &request.error_range.end.line.to_string()),
+                ("end_character", &request.error_range.end.character.to_string()),
+                ("code", &request.code),
+            ],
         );
         
         // Create chat messages
@@ -409,7 +1312,7 @@ impl CodeAnalysisService {
         // Get response from Ollama
         let response = client
             .chat(
-                "llama3:latest", // Use a suitable model
+                DEFAULT_ANALYSIS_MODEL,
                 messages,
                 None,
                 None::<fn(&str)>,
@@ -456,7 +1359,7 @@ impl CodeAnalysisService {
         // Get response from Ollama
         let response = client
             .chat(
-                "llama3:latest", // Use a suitable model
+                DEFAULT_ANALYSIS_MODEL,
                 messages,
                 None,
                 None::<fn(&str)>,
@@ -475,21 +1378,166 @@ impl CodeAnalysisService {
         })
     }
     
-    // Helper methods
+    /// Vulnerabilities in `code` from a pattern pass and a model review, merged so each
+    /// issue on a line is reported once. Pattern findings are still returned when the
+    /// model review fails.
+    pub async fn scan_security(&self, code: &str, language: &str, model: Option<&str>) -> Result<SecurityScanResponse, String> {
+        let pattern_findings = security_scan::pattern_findings(code, language);
+        
+        let numbered: String = code.lines().enumerate()
+            .map(|(index, line)| format!("{:>5} {}\n", index + 1, line))
+            .collect();
+        let messages = vec![
+            ChatMessage {
+                role: "system".to_string(),
+                content: "You are an application security expert. Find vulnerabilities such as injection, hard-coded secrets, unsafe deserialization, path traversal, weak cryptography and missing authorization checks. Reply with JSON: {\"findings\": [{\"cwe\": \"CWE-<number>\", \"title\": string, \"severity\": \"low\" | \"medium\" | \"high\" | \"critical\", \"line\": number, \"description\": string, \"remediation\": string}]}, and an empty list when you find none.".to_string(),
+            },
+            ChatMessage {
+                role: "user".to_string(),
+                content: format!("Scan this {} code. Lines are numbered.\n\n```{}\n{}```", language, language, numbered),
+            },
+        ];
+        
+        let client = self.ollama_client.lock().await;
+        let model_findings = chat_json_parsed(&client, model.unwrap_or(DEFAULT_ANALYSIS_MODEL), messages, security_scan::parse_model_findings)
+            .await
+            .and_then(|(_, findings)| findings);
+        let (model_findings, model_error) = match model_findings {
+            Ok(findings) => (findings, None),
+            Err(e) => {
+                eprintln!("Security scan model review failed: {}", e);
+                (Vec::new(), Some(e))
+            }
+        };
+        
+        Ok(SecurityScanResponse {
+            findings: security_scan::merge_findings(pattern_findings, model_findings),
+            model_error,
+        })
+    }
     
-    fn collect_files(
+    /// Unit tests for one function of a file, written by the model in `framework`
+    pub async fn generate_tests(
         &self,
-        repo_path: &Path,
-        file_patterns: &Option<Vec<String>>,
-        exclude_patterns: &Option<Vec<String>>,
-        max_files: Option<usize>,
-    ) -> Result<Vec<std::path::PathBuf>, std::io::Error> {
-        let mut files = Vec::new();
-        let max_count = max_files.unwrap_or(100); // Default to 100 files
+        file_path: &str,
+        symbol_name: &str,
+        framework: &str,
+        model: Option<&str>,
+    ) -> Result<TestGenerationResponse, String> {
+        let (node, symbol, content) = self.symbol_definition(file_path, symbol_name).await?;
+        let source = symbol_source(&content, &symbol);
         
-        let walker = walkdir::WalkDir::new(repo_path)
-            .follow_links(false)
-            .into_iter()
+        let messages = vec![
+            ChatMessage {
+                role: "system".to_string(),
+                content: "You are an expert at writing unit tests. Reply with one code block containing a complete test file.".to_string(),
+            },
+            ChatMessage {
+                role: "user".to_string(),
+                content: format!(
+                    "Write unit tests using {} for the {} function `{}` from {}. Cover normal inputs, edge cases and error handling.\n\n```{}\n{}\n```",
+                    framework, node.language, symbol_name, file_path, node.language, source
+                ),
+            },
+        ];
+        
+        let client = self.ollama_client.lock().await;
+        let response = client
+            .chat(model.unwrap_or(DEFAULT_ANALYSIS_MODEL), messages, None, None::<fn(&str)>)
+            .await
+            .map_err(|e| e.to_string())?;
+        
+        let (test_code, explanation) = split_code_reply(&response.content, &node.language);
+        Ok(TestGenerationResponse {
+            file_path: file_path.to_string(),
+            symbol_name: symbol_name.to_string(),
+            framework: framework.to_string(),
+            test_code,
+            explanation,
+        })
+    }
+    
+    /// Documentation for one symbol of a file, as an edit adding it in the language's
+    /// doc comment syntax at the symbol's location
+    pub async fn generate_docs(
+        &self,
+        file_path: &str,
+        symbol_name: &str,
+        style: &DocStyle,
+        model: Option<&str>,
+    ) -> Result<DocGenerationResponse, String> {
+        let (node, symbol, content) = self.symbol_definition(file_path, symbol_name).await?;
+        let source = symbol_source(&content, &symbol);
+        
+        let mut instructions = match style.verbosity {
+            DocVerbosity::Terse => "Write a one or two sentence summary of what it does.".to_string(),
+            DocVerbosity::Verbose => "Describe what it does, its parameters, its return value and any errors it raises or returns.".to_string(),
+        };
+        if style.include_examples {
+            instructions.push_str(" Include a short usage example in a fenced code block.");
+        }
+        let messages = vec![
+            ChatMessage {
+                role: "system".to_string(),
+                content: "You are an expert technical writer. Reply with only the documentation text, without comment markers such as /// or \"\"\".".to_string(),
+            },
+            ChatMessage {
+                role: "user".to_string(),
+                content: format!(
+                    "Document the {} symbol `{}`. {}\n\n```{}\n{}\n```",
+                    node.language, symbol_name, instructions, node.language, source
+                ),
+            },
+        ];
+        
+        let client = self.ollama_client.lock().await;
+        let response = client
+            .chat(model.unwrap_or(DEFAULT_ANALYSIS_MODEL), messages, None, None::<fn(&str)>)
+            .await
+            .map_err(|e| e.to_string())?;
+        
+        let documentation = doc_text(&response.content);
+        if documentation.is_empty() {
+            return Err(format!("The model returned no documentation for '{}'", symbol_name));
+        }
+        let (offset, doc_comment) = doc_comment_insertion(&content, &symbol, &node.language, &documentation);
+        let edit = workspace_edit(file_path, vec![text_edit(&content, offset..offset, doc_comment.clone())])?;
+        Ok(DocGenerationResponse {
+            file_path: file_path.to_string(),
+            symbol_name: symbol_name.to_string(),
+            doc_comment,
+            edit,
+        })
+    }
+    
+    /// The node of `file_path`, its symbol named `symbol_name` and the file's content
+    async fn symbol_definition(&self, file_path: &str, symbol_name: &str) -> Result<(DependencyNode, Symbol, String), String> {
+        let content = std::fs::read_to_string(file_path)
+            .map_err(|e| format!("Failed to read {}: {}", file_path, e))?;
+        let (node, _) = self.build_node(std::path::PathBuf::from(file_path)).await
+            .ok_or_else(|| format!("Failed to read {}", file_path))?;
+        let symbol = node.symbols.iter()
+            .find(|symbol| symbol.name == symbol_name)
+            .cloned()
+            .ok_or_else(|| format!("Symbol '{}' not found in {}", symbol_name, file_path))?;
+        Ok((node, symbol, content))
+    }
+    
+    // Helper methods
+    
+    fn collect_files(
+        &self,
+        repo_path: &Path,
+        file_patterns: &Option<Vec<String>>,
+        exclude_patterns: &Option<Vec<String>>,
+        max_files: Option<usize>,
+    ) -> Result<Vec<std::path::PathBuf>, std::io::Error> {
+        let mut files = Vec::new();
+        let max_count = max_files.unwrap_or(100); // Default to 100 files
+        
+        let walker = walkdir::WalkDir::new(repo_path)
+            .follow_links(false)
+            .into_iter()
             .filter_map(|e| e.ok());
             
         for entry in walker {
@@ -534,38 +1582,8 @@ impl CodeAnalysisService {
     }
     
     fn detect_language(&self, file_path: &Path) -> String {
-        if let Some(extension) = file_path.extension() {
-            match extension.to_string_lossy().as_ref() {
-                "js" => return "javascript".to_string(),
-                "ts" => return "typescript".to_string(),
-                "jsx" => return "jsx".to_string(),
-                "tsx" => return "tsx".to_string(),
-                "py" => return "python".to_string(),
-                "rs" => return "rust".to_string(),
-                "go" => return "go".to_string(),
-                "java" => return "java".to_string(),
-                "c" => return "c".to_string(),
-                "cpp" | "cc" | "cxx" => return "cpp".to_string(),
-                "cs" => return "csharp".to_string(),
-                "php" => return "php".to_string(),
-                "rb" => return "ruby".to_string(),
-                "swift" => return "swift".to_string(),
-                "kt" | "kts" => return "kotlin".to_string(),
-                "scala" => return "scala".to_string(),
-                "html" => return "html".to_string(),
-                "css" => return "css".to_string(),
-                "json" => return "json".to_string(),
-                "md" => return "markdown".to_string(),
-                "yml" | "yaml" => return "yaml".to_string(),
-                "xml" => return "xml".to_string(),
-                "sh" | "bash" => return "bash".to_string(),
-                "sql" => return "sql".to_string(),
-                _ => {}
-            }
-        }
-        
         // Default to plaintext if we can't determine the language
-        "plaintext".to_string()
+        language_from_path(file_path).unwrap_or("plaintext").to_string()
     }
     
     async fn generate_repository_suggestions(&self, file_analyses: &[FileAnalysis]) -> Result<Vec<RepositorySuggestion>, String> {
@@ -604,98 +1622,298 @@ impl CodeAnalysisService {
         let files = self.collect_files(repo_path, &request.file_patterns, &request.exclude_patterns, request.max_files)
             .map_err(|e| e.to_string())?;
             
-        // Create dependency nodes for each file
-        let mut nodes = HashMap::new();
+        let nodes = self.build_nodes(files).await
+            .into_iter()
+            .map(|(node, _)| (node.file_path.clone(), node))
+            .collect();
         
-        // Process files in parallel using futures::stream
-        let dependency_nodes = stream::iter(files)
-            .map(|file_path| {
-                let path_clone = file_path.clone();
-                let service = self.clone();
-                async move {
-                    if let Ok(content) = std::fs::read_to_string(&path_clone) {
-                        let language = service.detect_language(&path_clone);
-                        let file_path_str = path_clone.to_string_lossy().to_string();
-                        
-                        // Extract symbols and imports
-                        let (symbols, imports) = service.extract_symbols_and_imports(&content, &language, &file_path_str).await;
-                        
-                        // Determine exports (simplified for now - in reality would need language-specific parsing)
-                        let exports = symbols.iter()
-                            .filter(|s| s.is_exported)
-                            .map(|s| s.name.clone())
-                            .collect::<HashSet<String>>();
-                            
-                        Some((file_path_str, DependencyNode {
-                            file_path: file_path_str,
-                            language,
-                            symbols,
-                            imports,
-                            exports,
-                        }))
-                    } else {
-                        None
-                    }
-                }
-            })
+        Ok(self.build_graph(nodes))
+    }
+    
+    /// Dependency nodes and content fingerprints for readable files, built concurrently
+    async fn build_nodes(&self, files: Vec<std::path::PathBuf>) -> Vec<(DependencyNode, String)> {
+        let contents = files.into_iter()
+            .filter_map(|path| std::fs::read_to_string(&path).ok().map(|content| (path, content)))
+            .collect();
+        self.build_nodes_from_contents(contents).await
+    }
+    
+    /// Like `build_nodes`, for files whose content was already read
+    async fn build_nodes_from_contents(&self, files: Vec<(std::path::PathBuf, String)>) -> Vec<(DependencyNode, String)> {
+        stream::iter(files)
+            .map(|(path, content)| self.build_node_from_content(path, content))
             .buffer_unordered(8) // Process up to 8 files concurrently
-            .filter_map(|result| async { result })
             .collect::<Vec<_>>()
-            .await;
-            
-        // Add all nodes to the graph
-        for (path, node) in dependency_nodes {
-            nodes.insert(path, node);
-        }
+            .await
+    }
+    
+    async fn build_node(&self, path: std::path::PathBuf) -> Option<(DependencyNode, String)> {
+        let content = std::fs::read_to_string(&path).ok()?;
+        Some(self.build_node_from_content(path, content).await)
+    }
+    
+    async fn build_node_from_content(&self, path: std::path::PathBuf, content: String) -> (DependencyNode, String) {
+        let language = self.detect_language(&path);
+        let file_path = path.to_string_lossy().to_string();
         
-        // Create edges between nodes based on imports
-        let mut edges = Vec::new();
+        let fingerprint = content_fingerprint(&content);
         
-        for (from_path, node) in &nodes {
-            for import in &node.imports {
-                // Resolve the import path to an absolute file path
-                if let Some(to_path) = self.resolve_import_path(from_path, &import.source, &nodes) {
-                    // Only create an edge if the target file exists in our nodes
-                    if let Some(target_node) = nodes.get(&to_path) {
-                        // Determine which symbols are actually being imported
-                        let imported_symbols = if import.is_all {
-                            target_node.exports.iter().cloned().collect()
-                        } else {
-                            import.symbols.clone()
-                        };
-                        
-                        // Determine dependency strength based on number of imports
-                        let strength = match imported_symbols.len() {
-                            0..=2 => DependencyStrength::Weak,
-                            3..=5 => DependencyStrength::Medium,
-                            _ => DependencyStrength::Strong,
-                        };
-                        
-                        edges.push(DependencyEdge {
-                            from: from_path.clone(),
-                            to: to_path,
-                            symbols: imported_symbols,
-                            strength,
-                        });
+        // Extract symbols and imports, unless this content was extracted before
+        let cached = self.extraction_cache.as_ref().and_then(|cache| cache.get(&file_path, &fingerprint));
+        let (symbols, imports) = match cached {
+            Some(extraction) => extraction,
+            None => {
+                let (symbols, imports) = match &self.symbol_extractor {
+                    Some(extractor) => extractor.extract(&content, &language, &file_path).await,
+                    None => self.extract_symbols_and_imports(&content, &language, &file_path).await,
+                };
+                // Empty results are also what a failed extraction returns, so they aren't cached
+                if let Some(cache) = &self.extraction_cache {
+                    if !symbols.is_empty() || !imports.is_empty() {
+                        if let Err(e) = cache.put(&file_path, &fingerprint, &symbols, &imports) {
+                            eprintln!("Failed to cache extraction for {}: {}", file_path, e);
+                        }
                     }
                 }
+                (symbols, imports)
             }
-        }
+        };
         
-        // Generate summary
-        let summary = format!(
+        // Determine exports (simplified for now - in reality would need language-specific parsing)
+        let exports = symbols.iter()
+            .filter(|s| s.is_exported)
+            .map(|s| s.name.clone())
+            .collect::<HashSet<String>>();
+        
+        (DependencyNode {
+            file_path,
+            language,
+            symbols,
+            imports,
+            exports,
+        }, fingerprint)
+    }
+    
+    /// Connect nodes by their resolved imports
+    fn build_graph(&self, nodes: HashMap<String, DependencyNode>) -> DependencyGraph {
+        // Create edges between nodes based on imports
+        let edges = nodes.iter()
+            .flat_map(|(from_path, node)| self.node_edges(from_path, node, &nodes))
+            .collect();
+        
+        DependencyGraph {
+            summary: Self::graph_summary(&nodes, &edges),
+            nodes,
+            edges,
+        }
+    }
+    
+    fn graph_summary(nodes: &HashMap<String, DependencyNode>, edges: &Vec<DependencyEdge>) -> String {
+        format!(
             "Dependency analysis complete. Found {} files with {} dependencies.",
             nodes.len(),
             edges.len()
-        );
+        )
+    }
+    
+    /// Edges for the imports of one node that resolve to a node in `nodes`
+    fn node_edges(&self, from_path: &str, node: &DependencyNode, nodes: &HashMap<String, DependencyNode>) -> Vec<DependencyEdge> {
+        let mut edges = Vec::new();
+        for import in &node.imports {
+            // Resolve the import path to an absolute file path
+            if let Some(to_path) = self.resolve_import_path(from_path, &import.source, nodes) {
+                // Only create an edge if the target file exists in our nodes
+                if let Some(target_node) = nodes.get(&to_path) {
+                    // Determine which symbols are actually being imported
+                    let imported_symbols = if import.is_all {
+                        target_node.exports.iter().cloned().collect()
+                    } else {
+                        import.symbols.clone()
+                    };
+                    
+                    // Determine dependency strength based on number of imports
+                    let strength = match imported_symbols.len() {
+                        0..=2 => DependencyStrength::Weak,
+                        3..=5 => DependencyStrength::Medium,
+                        _ => DependencyStrength::Strong,
+                    };
+                    
+                    edges.push(DependencyEdge {
+                        from: from_path.to_string(),
+                        to: to_path,
+                        symbols: imported_symbols,
+                        strength,
+                    });
+                }
+            }
+        }
+        edges
+    }
+    
+    /// Re-extract one file and patch its node and the edges into and out of it in the
+    /// cached graph that contains it, leaving every other node and edge untouched
+    pub async fn reanalyze_file_dependencies(&self, file_path: &str) -> Result<FileDependencyUpdate, String> {
+        let contains_file = |repo_path: &String| Path::new(file_path).starts_with(repo_path);
+        let no_graph = || format!("No cached dependency graph contains {}", file_path);
+        if !self.dependency_graphs.lock().await.keys().any(contains_file) {
+            return Err(no_graph());
+        }
         
-        Ok(DependencyGraph {
-            nodes,
-            edges,
-            summary,
+        // Extraction can take a model round trip, so it runs without holding the graphs
+        let built = self.build_node(std::path::PathBuf::from(file_path)).await;
+        
+        let mut graphs = self.dependency_graphs.lock().await;
+        let cached = graphs.iter_mut()
+            .find(|(repo_path, _)| contains_file(*repo_path))
+            .map(|(_, cached)| cached)
+            .ok_or_else(no_graph)?;
+        
+        let graph = &mut cached.graph;
+        graph.edges.retain(|edge| edge.from != file_path && edge.to != file_path);
+        cached.stale.remove(file_path);
+        
+        let Some((node, fingerprint)) = built else {
+            // Deleted or unreadable: drop the file and its edges
+            let removed = graph.nodes.remove(file_path).is_some();
+            cached.fingerprints.remove(file_path);
+            graph.summary = Self::graph_summary(&graph.nodes, &graph.edges);
+            return Ok(FileDependencyUpdate {
+                file_path: file_path.to_string(),
+                removed,
+                outgoing_edges: Vec::new(),
+                incoming_edges: Vec::new(),
+            });
+        };
+        cached.fingerprints.insert(file_path.to_string(), fingerprint);
+        graph.nodes.insert(file_path.to_string(), node);
+        
+        let outgoing_edges = self.node_edges(file_path, &graph.nodes[file_path], &graph.nodes);
+        let incoming_edges: Vec<DependencyEdge> = graph.nodes.iter()
+            .filter(|(from_path, _)| from_path.as_str() != file_path)
+            .flat_map(|(from_path, node)| self.node_edges(from_path, node, &graph.nodes))
+            .filter(|edge| edge.to == file_path)
+            .collect();
+        graph.edges.extend(outgoing_edges.iter().cloned());
+        graph.edges.extend(incoming_edges.iter().cloned());
+        graph.summary = Self::graph_summary(&graph.nodes, &graph.edges);
+        
+        Ok(FileDependencyUpdate {
+            file_path: file_path.to_string(),
+            removed: false,
+            outgoing_edges,
+            incoming_edges,
         })
     }
     
+    /// The cached dependency graph of a repository, built on first use. Files reported
+    /// through `mark_files_changed` since the last call are re-extracted first.
+    pub async fn get_dependency_graph(&self, repo_path: &str) -> Result<DependencyGraph, String> {
+        let mut graphs = self.dependency_graphs.lock().await;
+        let Some(cached) = graphs.get_mut(repo_path) else {
+            drop(graphs);
+            self.refresh_dependency_graph(repo_path).await?;
+            return self.dependency_graphs.lock().await.get(repo_path)
+                .map(|cached| cached.graph.clone())
+                .ok_or_else(|| "Dependency graph was not built".to_string());
+        };
+        if cached.stale.is_empty() {
+            return Ok(cached.graph.clone());
+        }
+        
+        // Re-extract without holding the graphs; files marked meanwhile stay stale
+        let stale: Vec<String> = cached.stale.drain().collect();
+        drop(graphs);
+        let existing = stale.iter().map(std::path::PathBuf::from).filter(|path| path.is_file()).collect();
+        let updated = self.build_nodes(existing).await;
+        
+        let mut graphs = self.dependency_graphs.lock().await;
+        let cached = graphs.get_mut(repo_path)
+            .ok_or_else(|| "Dependency graph was not built".to_string())?;
+        let mut nodes = std::mem::take(&mut cached.graph.nodes);
+        for file_path in &stale {
+            nodes.remove(file_path);
+            cached.fingerprints.remove(file_path);
+        }
+        for (node, fingerprint) in updated {
+            cached.fingerprints.insert(node.file_path.clone(), fingerprint);
+            nodes.insert(node.file_path.clone(), node);
+        }
+        cached.graph = self.build_graph(nodes);
+        
+        Ok(cached.graph.clone())
+    }
+    
+    /// Rescan a repository and update its cached dependency graph, re-extracting only
+    /// new files and files whose content changed
+    pub async fn refresh_dependency_graph(&self, repo_path: &str) -> Result<DependencyGraphRefresh, String> {
+        let root = Path::new(repo_path);
+        if !root.exists() || !root.is_dir() {
+            return Err("Invalid repository path".to_string());
+        }
+        let files = self.collect_files(root, &None, &None, Some(REPO_GRAPH_MAX_FILES))
+            .map_err(|e| e.to_string())?;
+        
+        // Extraction runs against a snapshot so the graphs stay unlocked while it does
+        let (previous_fingerprints, previous_stale) = self.dependency_graphs.lock().await.get(repo_path)
+            .map(|cached| (cached.fingerprints.clone(), cached.stale.clone()))
+            .unwrap_or_default();
+        
+        let changed: Vec<(std::path::PathBuf, String)> = files.iter()
+            .filter_map(|path| {
+                let content = std::fs::read_to_string(path).ok()?;
+                let unchanged = previous_fingerprints.get(path.to_string_lossy().as_ref())
+                    .is_some_and(|fingerprint| *fingerprint == content_fingerprint(&content));
+                (!unchanged).then(|| (path.clone(), content))
+            })
+            .collect();
+        let updated = self.build_nodes_from_contents(changed).await;
+        let updated_files = updated.len();
+        
+        let mut graphs = self.dependency_graphs.lock().await;
+        let previous = graphs.remove(repo_path);
+        let (mut nodes, mut fingerprints, mut stale) = previous
+            .map(|cached| (cached.graph.nodes, cached.fingerprints, cached.stale))
+            .unwrap_or_default();
+        
+        let current: HashSet<String> = files.iter().map(|path| path.to_string_lossy().to_string()).collect();
+        let removed: Vec<String> = nodes.keys().filter(|file_path| !current.contains(*file_path)).cloned().collect();
+        for file_path in &removed {
+            nodes.remove(file_path);
+            fingerprints.remove(file_path);
+        }
+        for (node, fingerprint) in updated {
+            fingerprints.insert(node.file_path.clone(), fingerprint);
+            nodes.insert(node.file_path.clone(), node);
+        }
+        // The rescan covered files marked before it started, but not ones marked during it
+        stale.retain(|file_path| !previous_stale.contains(file_path));
+        
+        let graph = self.build_graph(nodes);
+        let refresh = DependencyGraphRefresh {
+            files: graph.nodes.len(),
+            updated_files,
+            removed_files: removed.len(),
+        };
+        graphs.insert(repo_path.to_string(), CachedDependencyGraph {
+            graph,
+            fingerprints,
+            stale,
+        });
+        Ok(refresh)
+    }
+    
+    /// Record file-change events; affected cached graphs re-extract these files on next use
+    pub async fn mark_files_changed(&self, file_paths: &[String]) {
+        let mut graphs = self.dependency_graphs.lock().await;
+        for (repo_path, cached) in graphs.iter_mut() {
+            for file_path in file_paths {
+                if Path::new(file_path).starts_with(repo_path) {
+                    cached.stale.insert(file_path.clone());
+                }
+            }
+        }
+    }
+    
     // Helper method to extract symbols and imports from file content
     async fn extract_symbols_and_imports(&self, content: &str, language: &str, file_path: &str) -> (Vec<Symbol>, Vec<Import>) {
         let client = self.ollama_client.lock().await;
@@ -722,35 +1940,13 @@ impl CodeAnalysisService {
             },
         ];
         
-        // Get response from Ollama
-        let response = match client
-            .chat(
-                "llama3:latest", // Use a suitable model
-                messages,
-                None,
-                None::<fn(&str)>,
-            )
-            .await {
-                Ok(resp) => resp.content,
-                Err(_) => return (Vec::new(), Vec::new()), // Fallback to empty lists on error
-            };
-            
-        // Try to parse the JSON response
-        // In a real implementation, would need more robust parsing and error handling
-        // This is a simplified approach
-        
-        // Extract JSON from the response (might be surrounded by markdown code blocks or other text)
-        let json_str = if let Some(start) = response.find('{') {
-            if let Some(end) = response.rfind('}') {
-                &response[start..=end]
-            } else {
-                return (Vec::new(), Vec::new());
-            }
-        } else {
-            return (Vec::new(), Vec::new());
+        // Get response from Ollama; a reply cut off by the token limit keeps its complete entries
+        let parsed = match chat_json_parsed(&client, DEFAULT_ANALYSIS_MODEL, messages, json_repair::parse_json_reply).await {
+            Ok((_, parsed)) => parsed,
+            Err(_) => return (Vec::new(), Vec::new()), // Fallback to empty lists on error
         };
         
-        match serde_json::from_str::<serde_json::Value>(json_str) {
+        match parsed {
             Ok(json) => {
                 let mut symbols = Vec::new();
                 let mut imports = Vec::new();
@@ -873,16 +2069,8 @@ impl CodeAnalysisService {
     
     // Impact Analysis Method
     pub async fn analyze_impact(&self, request: &ImpactAnalysisRequest) -> Result<ImpactAnalysisResponse, String> {
-        // First, analyze dependencies to understand the codebase structure
-        let dep_request = DependencyAnalysisRequest {
-            repo_path: request.repo_path.clone(),
-            file_patterns: None,
-            exclude_patterns: None,
-            max_files: Some(500), // Reasonable limit
-            include_content: Some(false),
-        };
-        
-        let dep_graph = self.analyze_dependencies(&dep_request).await?;
+        // Reuse the repository's cached dependency graph, building it on first use
+        let dep_graph = self.get_dependency_graph(&request.repo_path).await?;
         
         // Check if the file being changed exists in our dependency graph
         if !dep_graph.nodes.contains_key(&request.file_path) {
@@ -949,6 +2137,11 @@ impl CodeAnalysisService {
             }
         }
         
+        let affected_tests = self.find_affected_tests(&dep_graph, &request.file_path);
+        let test_command = test_runner_command(
+            &affected_tests.iter().filter_map(|test| dep_graph.nodes.get(&test.file_path)).collect::<Vec<_>>(),
+        );
+        
         // Determine overall risk level
         let risk_level = if affected_files.is_empty() {
             RiskLevel::Low
@@ -967,23 +2160,238 @@ impl CodeAnalysisService {
             }
         };
         
+        let risk_score = impact_risk_score(&affected_files);
+        
         // Generate impact summary
         let impact_summary = format!(
-            "Impact analysis complete. The changes to {} will affect {} other files with a {} risk level.",
+            "Impact analysis complete. The changes to {} will affect {} other files with a {} risk level (score {:.1}).",
             request.file_path,
             affected_files.len(),
-            format!("{:?}", risk_level).to_lowercase()
+            format!("{:?}", risk_level).to_lowercase(),
+            risk_score
         );
         
         Ok(ImpactAnalysisResponse {
             affected_files,
+            affected_tests,
+            test_command,
             impact_summary,
             risk_assessment: risk_level,
+            risk_score,
+        })
+    }
+    
+    /// Impact analysis for each file changed relative to `base_ref` (default `HEAD`),
+    /// with the changes read from the git diff
+    pub async fn analyze_impact_from_git(
+        &self,
+        repo_path: &str,
+        base_ref: Option<&str>,
+        staged_only: bool,
+    ) -> Result<GitImpactAnalysisResponse, String> {
+        let root = Path::new(repo_path).canonicalize()
+            .map_err(|e| format!("Invalid repository path: {}", e))?;
+        let base_ref = base_ref.unwrap_or("HEAD").to_string();
+        let changes = git_changes(&root, &base_ref, staged_only).await.map_err(|e| git_error_message(repo_path, e))?;
+        
+        let repo_path = root.to_string_lossy().to_string();
+        let changed_files: Vec<String> = changes.iter().map(|(file_path, _)| file_path.clone()).collect();
+        self.mark_files_changed(&changed_files).await;
+        
+        let mut file_impacts = Vec::new();
+        for (file_path, file_changes) in changes {
+            let impact = self.analyze_impact(&ImpactAnalysisRequest {
+                file_path: file_path.clone(),
+                changes: file_changes.clone(),
+                repo_path: repo_path.clone(),
+            }).await;
+            match impact {
+                Ok(impact) => file_impacts.push(FileImpact {
+                    file_path,
+                    changes: file_changes,
+                    impact,
+                }),
+                // Files outside the dependency graph, e.g. past its file limit
+                Err(e) => eprintln!("Skipping impact analysis for {}: {}", file_path, e),
+            }
+        }
+        
+        Ok(GitImpactAnalysisResponse {
+            base_ref,
+            changed_files,
+            file_impacts,
+        })
+    }
+    
+    /// A commit message for the staged changes, or for all uncommitted changes unless
+    /// `staged_only`, summarized by the model from the diff
+    pub async fn generate_commit_message(
+        &self,
+        repo_path: &str,
+        staged_only: bool,
+        style: CommitMessageStyle,
+    ) -> Result<CommitMessage, String> {
+        let files = read_diff(Path::new(repo_path), None, staged_only, DEFAULT_CONTEXT_LINES).await.map_err(|e| git_error_message(repo_path, e))?;
+        if files.is_empty() {
+            return Err(match staged_only {
+                true => "Nothing staged to commit".to_string(),
+                false => "No changes to commit".to_string(),
+            });
+        }
+        
+        let format = match style {
+            CommitMessageStyle::Conventional => "Start the title with a Conventional Commits type and optional scope, as in `type(scope): summary`, where type is one of feat, fix, docs, style, refactor, perf, test, build, ci or chore.",
+            CommitMessageStyle::Plain => "Write the title as a plain imperative summary, such as `Add retry to the upload client`.",
+        };
+        let messages = vec![
+            ChatMessage {
+                role: "system".to_string(),
+                content: "You write git commit messages. Reply with only the message: a title of at most 72 characters, a blank line, then a body explaining what changed and why.".to_string(),
+            },
+            ChatMessage {
+                role: "user".to_string(),
+                content: format!(
+                    "Write a commit message for this diff. {}\n\n```diff\n{}```",
+                    format,
+                    render_within_budget(&files, DIFF_PROMPT_BUDGET)
+                ),
+            },
+        ];
+        
+        let client = self.ollama_client.lock().await;
+        let response = client
+            .chat(DEFAULT_ANALYSIS_MODEL, messages, None, None::<fn(&str)>)
+            .await
+            .map_err(|e| e.to_string())?;
+        parse_commit_message(&response.content)
+    }
+    
+    /// Review comments from the model on the uncommitted changes against `base_ref`,
+    /// `HEAD` by default. The diff is reviewed in chunks of whole files that fit the
+    /// prompt budget, and a chunk that fails is reported without failing the review.
+    pub async fn review_diff(
+        &self,
+        repo_path: &str,
+        base_ref: Option<&str>,
+        model: Option<&str>,
+    ) -> Result<DiffReviewResponse, String> {
+        let base_ref = base_ref.unwrap_or("HEAD");
+        let files = read_diff(Path::new(repo_path), Some(base_ref), false, DEFAULT_CONTEXT_LINES).await.map_err(|e| git_error_message(repo_path, e))?;
+        let model = model.unwrap_or(DEFAULT_ANALYSIS_MODEL);
+        
+        let mut comments = Vec::new();
+        let mut reviewed_files = Vec::new();
+        let mut errors = Vec::new();
+        let client = self.ollama_client.lock().await;
+        for (chunk_files, diff) in numbered_chunks(&files, DIFF_PROMPT_BUDGET) {
+            let paths: Vec<&str> = chunk_files.iter().map(|file| file.path.as_str()).collect();
+            let messages = vec![
+                ChatMessage {
+                    role: "system".to_string(),
+                    content: "You are an expert code reviewer. Point out bugs, security issues, and risky or unclear code in the changes. Reply with JSON: {\"comments\": [{\"file\": string, \"line\": number, \"severity\": \"info\" | \"warning\" | \"error\", \"message\": string}]}, and an empty list when the changes look good.".to_string(),
+                },
+                ChatMessage {
+                    role: "user".to_string(),
+                    content: format!(
+                        "Review this diff. Each line that exists in the new file starts with its line number; key comments to those numbers.\n\n{}",
+                        diff
+                    ),
+                },
+            ];
+            let parsed = match chat_json_parsed(&client, model, messages, |reply| parse_review_comments(reply, &paths)).await {
+                Ok((_, parsed)) => parsed,
+                Err(e) => {
+                    errors.push(format!("{}: {}", paths.join(", "), e));
+                    continue;
+                }
+            };
+            match parsed {
+                Ok(chunk_comments) => {
+                    comments.extend(chunk_comments);
+                    reviewed_files.extend(paths.iter().map(|path| path.to_string()));
+                }
+                Err(e) => errors.push(format!("{}: review could not be parsed: {}", paths.join(", "), e)),
+            }
+        }
+        comments.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        
+        Ok(DiffReviewResponse {
+            base_ref: base_ref.to_string(),
+            comments,
+            reviewed_files,
+            errors,
         })
     }
     
+    /// Test files reachable from `file_path` through reverse imports, nearest first.
+    /// The changed file is included when it is itself a test.
+    fn find_affected_tests(&self, graph: &DependencyGraph, file_path: &str) -> Vec<AffectedTest> {
+        let mut importers: HashMap<&str, Vec<&str>> = HashMap::new();
+        for edge in &graph.edges {
+            importers.entry(edge.to.as_str()).or_default().push(edge.from.as_str());
+        }
+        
+        // Breadth-first, remembering how each file was reached
+        let mut reached_from: HashMap<&str, Option<&str>> = HashMap::from([(file_path, None)]);
+        let mut queue = std::collections::VecDeque::from([file_path]);
+        let mut tests = Vec::new();
+        while let Some(current) = queue.pop_front() {
+            if graph.nodes.get(current).is_some_and(is_test_file) {
+                let mut dependency_path = vec![current.to_string()];
+                let mut step = current;
+                while let Some(Some(previous)) = reached_from.get(step) {
+                    dependency_path.push(previous.to_string());
+                    step = previous;
+                }
+                dependency_path.reverse();
+                tests.push(AffectedTest {
+                    file_path: current.to_string(),
+                    dependency_path,
+                });
+            }
+            
+            let mut next: Vec<&str> = importers.get(current).cloned().unwrap_or_default();
+            next.sort_unstable();
+            for importer in next {
+                if !reached_from.contains_key(importer) {
+                    reached_from.insert(importer, Some(current));
+                    queue.push_back(importer);
+                }
+            }
+        }
+        tests
+    }
+    
+    /// Exported functions and methods of non-test files whose name appears in no test
+    /// file of the repository. A mention is any whole-word occurrence, so this is an
+    /// approximation of coverage, not a measurement.
+    pub async fn find_untested_symbols(&self, repo_path: &str) -> Result<Vec<UntestedSymbol>, String> {
+        let graph = self.get_dependency_graph(repo_path).await?;
+        let (tests, sources): (Vec<&DependencyNode>, Vec<&DependencyNode>) = graph.nodes.values().partition(|node| is_test_file(node));
+        
+        let test_contents: Vec<String> = tests.iter()
+            .filter_map(|test| std::fs::read_to_string(&test.file_path).ok())
+            .collect();
+        let is_mentioned = |name: &str| {
+            regex::Regex::new(&format!(r"\b{}\b", regex::escape(name)))
+                .is_ok_and(|pattern| test_contents.iter().any(|content| pattern.is_match(content)))
+        };
+        
+        let mut untested: Vec<UntestedSymbol> = sources.into_iter()
+            .flat_map(|node| node.symbols.iter().map(move |symbol| (node, symbol)))
+            .filter(|(_, symbol)| symbol.is_exported && matches!(symbol.kind, SymbolKind::Function | SymbolKind::Method))
+            .filter(|(_, symbol)| !is_mentioned(&symbol.name))
+            .map(|(node, symbol)| UntestedSymbol {
+                file_path: node.file_path.clone(),
+                symbol: symbol.clone(),
+            })
+            .collect();
+        untested.sort_by(|a, b| (&a.file_path, &a.symbol.name).cmp(&(&b.file_path, &b.symbol.name)));
+        Ok(untested)
+    }
+    
     // Refactoring Suggestions Method
-    pub async fn suggest_refactorings(&self, request: &RefactoringRequest) -> Result<Vec<RefactoringSuggestion>, String> {
+    pub async fn suggest_refactorings(&self, request: &RefactoringRequest) -> Result<RefactoringResponse, String> {
         let client = self.ollama_client.lock().await;
         
         // Collect file contents
@@ -998,25 +2406,32 @@ impl CodeAnalysisService {
             return Err("No readable files provided".to_string());
         }
         
-        // Determine focus areas
-        let focus_areas = if let Some(areas) = &request.focus_areas {
-            areas.iter()
-                .map(|area| format!("{:?}", area))
-                .collect::<Vec<_>>()
-                .join(", ")
-        } else {
-            "Readability, Maintainability, Performance".to_string()
+        // Determine focus areas, each with what to look for
+        let focus_areas = match &request.focus_areas {
+            Some(areas) if !areas.is_empty() => areas.as_slice(),
+            _ => &RefactoringFocusArea::DEFAULT[..],
         };
+        let focus_guidance = focus_areas.iter()
+            .map(|area| format!("- {:?}: {}\n", area, area.prompt_guidance()))
+            .collect::<String>();
+        let tags = RefactoringFocusArea::ALL.iter()
+            .map(|area| format!("{:?}", area))
+            .collect::<Vec<_>>()
+            .join(", ");
         
         // Prepare the prompt for refactoring suggestions
         let mut prompt = format!(
-            "Analyze the following code files and suggest refactorings focused on: {}.\n\
-            For each suggestion, provide:\n\
-            1. A title and description\n\
-            2. Before and after code examples\n\
-            3. Effort estimate (Easy, Medium, Hard)\n\
-            4. Benefits of the refactoring\n\n",
-            focus_areas
+            "Analyze the following code files and suggest refactorings focused on these areas:\n{}\n\
+            Respond with a JSON object of the form {{\"suggestions\": [...]}}, where each suggestion has:\n\
+            - \"title\" and \"description\" strings\n\
+            - \"before_code\": the exact code to replace, copied from the file\n\
+            - \"after_code\": the replacement code\n\
+            - \"affected_files\": paths of the files the suggestion changes\n\
+            - \"effort_estimate\": one of Easy, Medium, Hard\n\
+            - \"benefits\": an array of strings\n\
+            - \"focus_areas\": the areas the suggestion addresses, from: {}\n\
+            Use an empty array when no refactoring is worthwhile.\n\n",
+            focus_guidance, tags
         );
         
         // Add file contents to the prompt (limit to reasonable size)
@@ -1032,10 +2447,10 @@ impl CodeAnalysisService {
         }
         
         // Create chat messages
-        let messages = vec![
+        let mut messages = vec![
             ChatMessage {
                 role: "system".to_string(),
-                content: "You are an expert code refactorer. Analyze code and suggest specific, actionable refactorings with examples.".to_string(),
+                content: "You are an expert code refactorer. Analyze code and suggest specific, actionable refactorings with examples. Output only valid JSON.".to_string(),
             },
             ChatMessage {
                 role: "user".to_string(),
@@ -1043,38 +2458,163 @@ impl CodeAnalysisService {
             },
         ];
         
-        // Get response from Ollama
-        let response = client
-            .chat(
-                "llama3:latest", // Use a suitable model
-                messages,
-                None,
-                None::<fn(&str)>,
-            )
-            .await
-            .map_err(|e| e.to_string())?;
+        // Ask again with the parse error when the reply doesn't fit the schema
+        let model = request.model.as_deref().unwrap_or(DEFAULT_ANALYSIS_MODEL);
+        let mut last_error = String::new();
+        for _ in 0..REFACTORING_PARSE_ATTEMPTS {
+            let (response, parsed) = chat_json_parsed(&client, model, messages.clone(), |reply| {
+                parse_refactoring_suggestions(reply, &request.file_paths)
+            })
+            .await?;
             
-        // Parse the response to extract refactoring suggestions
-        // In a real implementation, would need more robust parsing
-        // For demonstration, we'll create a sample suggestion
-        
-        let suggestions = vec![
-            RefactoringSuggestion {
-                title: "Extract common functionality into helper function".to_string(),
-                description: "Several components duplicate logic for formatting tokens. This could be extracted into a shared helper function.".to_string(),
-                before_code: "function Component1() {\n  const formatted = value > 1000 ? `${(value / 1000).toFixed(1)}k` : value.toString();\n}\n\nfunction Component2() {\n  const formatted = value > 1000 ? `${(value / 1000).toFixed(1)}k` : value.toString();\n}".to_string(),
-                after_code: "function formatValue(value) {\n  return value > 1000 ? `${(value / 1000).toFixed(1)}k` : value.toString();\n}\n\nfunction Component1() {\n  const formatted = formatValue(value);\n}\n\nfunction Component2() {\n  const formatted = formatValue(value);\n}".to_string(),
-                affected_files: request.file_paths.clone(),
-                effort_estimate: "Easy".to_string(),
-                benefits: vec![
-                    "Reduces code duplication".to_string(),
-                    "Improves maintainability".to_string(),
-                    "Makes future changes easier".to_string(),
-                ],
+            match parsed {
+                Ok(suggestions) => {
+                    let suggested = suggestions.len();
+                    let suggestions = filter_by_focus_areas(suggestions, request.focus_areas.as_deref());
+                    let reason = match (suggestions.is_empty(), suggested) {
+                        (false, _) => None,
+                        (true, 0) => Some("The model suggested no refactorings".to_string()),
+                        (true, _) => Some(format!("None of the {} suggestions addressed the requested focus areas", suggested)),
+                    };
+                    return Ok(RefactoringResponse { suggestions, reason });
+                }
+                Err(e) => {
+                    eprintln!("Malformed refactoring suggestions from {}: {}", model, e);
+                    messages.push(response);
+                    messages.push(ChatMessage {
+                        role: "user".to_string(),
+                        content: format!(
+                            "That reply could not be parsed ({}). Reply again with only the JSON object described above.",
+                            e
+                        ),
+                    });
+                    last_error = e;
+                }
             }
-        ];
+        }
         
-        Ok(suggestions)
+        Ok(RefactoringResponse {
+            suggestions: Vec::new(),
+            reason: Some(format!(
+                "The model's suggestions could not be parsed after {} attempts: {}",
+                REFACTORING_PARSE_ATTEMPTS, last_error
+            )),
+        })
+    }
+}
+
+impl CodeAnalysisService {
+    /// Replace the suggestion's `before_code` in `file_path` with its `after_code`,
+    /// refusing when `before_code` isn't found exactly once. A dry run only returns the edit.
+    pub fn apply_refactoring(&self, file_path: &str, suggestion: &RefactoringSuggestion, dry_run: bool) -> Result<RefactoringEdit, String> {
+        let content = std::fs::read_to_string(file_path)
+            .map_err(|e| format!("Failed to read {}: {}", file_path, e))?;
+        let (range, replacement) = plan_replacement(&content, suggestion)?;
+        
+        let mut updated = content.clone();
+        updated.replace_range(range.clone(), &replacement);
+        let diff = unified_diff(file_path, &content, &updated, &[(range.clone(), range.start..range.start + replacement.len())]);
+        let workspace_edit = workspace_edit(file_path, vec![text_edit(&content, range, replacement)])?;
+        
+        if !dry_run {
+            std::fs::write(file_path, &updated).map_err(|e| format!("Failed to write {}: {}", file_path, e))?;
+        }
+        
+        Ok(RefactoringEdit {
+            file_path: file_path.to_string(),
+            workspace_edit,
+            diff,
+            applied: !dry_run,
+        })
+    }
+    
+    /// Apply several suggestions to one file. Edits are located in the original content;
+    /// a suggestion overlapping an earlier accepted one is reported as a conflict and
+    /// skipped, unless `force` applies it afterwards on top of the updated content.
+    pub fn apply_refactorings(
+        &self,
+        file_path: &str,
+        suggestions: &[RefactoringSuggestion],
+        dry_run: bool,
+        force: bool,
+    ) -> Result<RefactoringBatchEdit, String> {
+        let content = std::fs::read_to_string(file_path)
+            .map_err(|e| format!("Failed to read {}: {}", file_path, e))?;
+        
+        let mut accepted: Vec<(usize, std::ops::Range<usize>, String)> = Vec::new();
+        let mut conflicts = Vec::new();
+        let mut failed = Vec::new();
+        for (index, suggestion) in suggestions.iter().enumerate() {
+            match plan_replacement(&content, suggestion) {
+                Ok((range, replacement)) => {
+                    let overlapping = accepted.iter()
+                        .find(|(_, other, _)| range.start < other.end && other.start < range.end);
+                    match overlapping {
+                        Some((other, _, _)) => conflicts.push(RefactoringConflict {
+                            suggestion: index,
+                            conflicts_with: *other,
+                            forced: false,
+                        }),
+                        None => accepted.push((index, range, replacement)),
+                    }
+                }
+                Err(error) => failed.push(RefactoringFailure { suggestion: index, error }),
+            }
+        }
+        
+        // Apply back to front so earlier offsets stay valid
+        accepted.sort_by_key(|(_, range, _)| range.start);
+        let mut updated = content.clone();
+        for (_, range, replacement) in accepted.iter().rev() {
+            updated.replace_range(range.clone(), replacement);
+        }
+        let mut applied: Vec<usize> = accepted.iter().map(|(index, _, _)| *index).collect();
+        
+        let mut shift = 0isize;
+        let mut changes = Vec::new();
+        for (_, range, replacement) in &accepted {
+            let new_start = (range.start as isize + shift) as usize;
+            changes.push((range.clone(), new_start..new_start + replacement.len()));
+            shift += replacement.len() as isize - range.len() as isize;
+        }
+        let mut edits: Vec<_> = accepted.into_iter()
+            .map(|(_, range, replacement)| text_edit(&content, range, replacement))
+            .collect();
+        
+        if force {
+            for conflict in &mut conflicts {
+                match plan_replacement(&updated, &suggestions[conflict.suggestion]) {
+                    Ok((range, replacement)) => {
+                        updated.replace_range(range, &replacement);
+                        conflict.forced = true;
+                        applied.push(conflict.suggestion);
+                    }
+                    Err(error) => failed.push(RefactoringFailure { suggestion: conflict.suggestion, error }),
+                }
+            }
+            // Stacked edits can't be expressed against the original, so replace the whole file
+            if conflicts.iter().any(|conflict| conflict.forced) {
+                edits = vec![text_edit(&content, 0..content.len(), updated.clone())];
+                changes = vec![(0..content.len(), 0..updated.len())];
+            }
+        }
+        
+        let diff = unified_diff(file_path, &content, &updated, &changes);
+        let workspace_edit = workspace_edit(file_path, edits)?;
+        
+        if !dry_run && !applied.is_empty() {
+            std::fs::write(file_path, &updated).map_err(|e| format!("Failed to write {}: {}", file_path, e))?;
+        }
+        
+        Ok(RefactoringBatchEdit {
+            file_path: file_path.to_string(),
+            workspace_edit,
+            diff,
+            applied,
+            conflicts,
+            failed,
+            dry_run,
+        })
     }
 }
 
@@ -1112,6 +2652,41 @@ pub async fn generate_code(
     code_analysis_service.generate_code(&request).await
 }
 
+/// Vulnerability findings for a piece of code, from patterns and a model review
+#[tauri::command]
+pub async fn scan_security(
+    code: String,
+    language: String,
+    model: Option<String>,
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<SecurityScanResponse, String> {
+    code_analysis_service.scan_security(&code, &language, model.as_deref()).await
+}
+
+/// Unit tests for a function, in the given test framework
+#[tauri::command]
+pub async fn generate_tests(
+    file_path: String,
+    symbol_name: String,
+    framework: String,
+    model: Option<String>,
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<TestGenerationResponse, String> {
+    code_analysis_service.generate_tests(&file_path, &symbol_name, &framework, model.as_deref()).await
+}
+
+/// Doc comments for a symbol, as an edit inserting them at its definition
+#[tauri::command]
+pub async fn generate_docs(
+    file_path: String,
+    symbol_name: String,
+    style: Option<DocStyle>,
+    model: Option<String>,
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<DocGenerationResponse, String> {
+    code_analysis_service.generate_docs(&file_path, &symbol_name, &style.unwrap_or_default(), model.as_deref()).await
+}
+
 #[tauri::command]
 pub async fn analyze_dependencies(
     request: DependencyAnalysisRequest,
@@ -1128,10 +2703,1033 @@ pub async fn analyze_impact(
     code_analysis_service.analyze_impact(&request).await
 }
 
+/// Impact analysis of the changes in a git working tree (or only its staged changes)
+/// relative to `base_ref`, `HEAD` by default
+#[tauri::command]
+pub async fn analyze_impact_from_git(
+    repo_path: String,
+    base_ref: Option<String>,
+    staged_only: Option<bool>,
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<GitImpactAnalysisResponse, String> {
+    code_analysis_service
+        .analyze_impact_from_git(&repo_path, base_ref.as_deref(), staged_only.unwrap_or(false))
+        .await
+}
+
+/// Commit message for a repository's staged changes (by default), or all its changes
+#[tauri::command]
+pub async fn generate_commit_message(
+    repo_path: String,
+    staged_only: Option<bool>,
+    style: Option<CommitMessageStyle>,
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<CommitMessage, String> {
+    code_analysis_service
+        .generate_commit_message(&repo_path, staged_only.unwrap_or(true), style.unwrap_or_default())
+        .await
+}
+
+/// Review comments on a repository's uncommitted changes against `base_ref` (`HEAD`)
+#[tauri::command]
+pub async fn review_diff(
+    repo_path: String,
+    base_ref: Option<String>,
+    model: Option<String>,
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<DiffReviewResponse, String> {
+    code_analysis_service.review_diff(&repo_path, base_ref.as_deref(), model.as_deref()).await
+}
+
+/// Module-level view of a repository's cached dependency graph, grouping files
+/// `depth` directories (default 1) below the repository root
+#[tauri::command]
+pub async fn aggregate_dependency_graph(
+    repo_path: String,
+    depth: Option<usize>,
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<ModuleGraph, String> {
+    let graph = code_analysis_service.get_dependency_graph(&repo_path).await?;
+    aggregate_modules(&graph, &repo_path, depth.unwrap_or(1))
+}
+
+/// Exports no analyzed file imports and files nothing imports, from a repository's
+/// cached dependency graph
+#[tauri::command]
+pub async fn find_unused_exports(
+    repo_path: String,
+    options: Option<UnusedExportOptions>,
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<UnusedExportReport, String> {
+    let graph = code_analysis_service.get_dependency_graph(&repo_path).await?;
+    unused_exports::find_unused_exports(&graph, &options.unwrap_or_default())
+}
+
+/// Exported functions of a repository that no test file mentions
+#[tauri::command]
+pub async fn find_untested_symbols(
+    repo_path: String,
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<Vec<UntestedSymbol>, String> {
+    code_analysis_service.find_untested_symbols(&repo_path).await
+}
+
+/// Render a dependency graph as Graphviz DOT or GraphML
+#[tauri::command]
+pub async fn export_dependency_graph(
+    graph: DependencyGraph,
+    format: GraphExportFormat,
+) -> Result<String, String> {
+    Ok(export_graph(&graph, format))
+}
+
+#[tauri::command]
+pub async fn get_extraction_cache_stats(
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<ExtractionCacheStats, String> {
+    code_analysis_service.extraction_cache_stats()
+}
+
+#[tauri::command]
+pub async fn clear_extraction_cache(
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<usize, String> {
+    code_analysis_service.clear_extraction_cache()
+}
+
+/// Rescan a repository, updating its cached dependency graph for changed files only
+#[tauri::command]
+pub async fn refresh_dependency_graph(
+    repo_path: String,
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<DependencyGraphRefresh, String> {
+    code_analysis_service.refresh_dependency_graph(&repo_path).await
+}
+
+/// Re-extract one file into the cached dependency graph containing it
+#[tauri::command]
+pub async fn reanalyze_file_dependencies(
+    file_path: String,
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<FileDependencyUpdate, String> {
+    code_analysis_service.reanalyze_file_dependencies(&file_path).await
+}
+
+/// The cached dependency graph of a repository, built on first request
+#[tauri::command]
+pub async fn get_dependency_graph(
+    repo_path: String,
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<DependencyGraph, String> {
+    code_analysis_service.get_dependency_graph(&repo_path).await
+}
+
 #[tauri::command]
 pub async fn suggest_refactorings(
     request: RefactoringRequest,
     code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
-) -> Result<Vec<RefactoringSuggestion>, String> {
+) -> Result<RefactoringResponse, String> {
     code_analysis_service.suggest_refactorings(&request).await
 }
+
+/// Apply a refactoring suggestion to one file, or preview it with `dry_run`
+#[tauri::command]
+pub async fn apply_refactoring(
+    file_path: String,
+    suggestion: RefactoringSuggestion,
+    dry_run: Option<bool>,
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<RefactoringEdit, String> {
+    code_analysis_service.apply_refactoring(&file_path, &suggestion, dry_run.unwrap_or(false))
+}
+
+/// Apply several refactoring suggestions to one file, reporting overlapping ones as
+/// conflicts; `force` applies those too, and `dry_run` only previews the result
+#[tauri::command]
+pub async fn apply_refactorings(
+    file_path: String,
+    suggestions: Vec<RefactoringSuggestion>,
+    dry_run: Option<bool>,
+    force: Option<bool>,
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<RefactoringBatchEdit, String> {
+    code_analysis_service.apply_refactorings(&file_path, &suggestions, dry_run.unwrap_or(false), force.unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt_guard::{OversizePolicy, PromptLimitConfig};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Parses `import { a } from './x'` and `export function a` lines, counting calls
+    #[derive(Default)]
+    struct CountingExtractor {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl SymbolExtractor for CountingExtractor {
+        async fn extract(&self, content: &str, _language: &str, _file_path: &str) -> (Vec<Symbol>, Vec<Import>) {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let location = Range {
+                start: Position { line: 0, character: 0 },
+                end: Position { line: 0, character: 0 },
+            };
+            let mut symbols = Vec::new();
+            let mut imports = Vec::new();
+            for line in content.lines() {
+                if let Some(name) = line.strip_prefix("export function ").and_then(|rest| rest.split('(').next()) {
+                    symbols.push(Symbol {
+                        name: name.to_string(),
+                        kind: SymbolKind::Function,
+                        location: location.clone(),
+                        documentation: None,
+                        is_exported: true,
+                    });
+                }
+                if let Some((names, source)) = line.strip_prefix("import {").and_then(|rest| rest.split_once("} from ")) {
+                    imports.push(Import {
+                        source: source.trim_matches(|c| c == '\'' || c == ';').to_string(),
+                        symbols: names.split(',').map(|name| name.trim().to_string()).collect(),
+                        is_all: false,
+                        location: location.clone(),
+                    });
+                }
+            }
+            (symbols, imports)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dependency_graph_is_reused_and_updated_incrementally() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("a.js"), "export function add(a, b) {}\n").unwrap();
+        std::fs::write(root.join("b.js"), "import { add } from './a.js';\n").unwrap();
+        std::fs::write(root.join("c.js"), "export function unrelated() {}\n").unwrap();
+
+        let extractor = Arc::new(CountingExtractor::default());
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(None)));
+        let service = CodeAnalysisService::new(ollama).with_symbol_extractor(extractor.clone());
+
+        let repo_path = root.to_string_lossy().to_string();
+        let request = ImpactAnalysisRequest {
+            file_path: root.join("a.js").to_string_lossy().to_string(),
+            changes: vec![CodeChange {
+                range: Range {
+                    start: Position { line: 0, character: 0 },
+                    end: Position { line: 0, character: 0 },
+                },
+                new_text: "export function add(a, b, c) {}".to_string(),
+                description: None,
+            }],
+            repo_path: repo_path.clone(),
+        };
+
+        for _ in 0..2 {
+            let impact = service.analyze_impact(&request).await.unwrap();
+            assert_eq!(impact.affected_files.len(), 1);
+            assert!(impact.affected_files[0].file_path.ends_with("b.js"));
+        }
+        assert_eq!(extractor.calls.load(Ordering::SeqCst), 3);
+
+        // A change event re-extracts only the changed file
+        let c_path = root.join("c.js").to_string_lossy().to_string();
+        std::fs::write(&c_path, "export function renamed() {}\n").unwrap();
+        service.mark_files_changed(&[c_path]).await;
+        service.analyze_impact(&request).await.unwrap();
+        assert_eq!(extractor.calls.load(Ordering::SeqCst), 4);
+
+        // A refresh re-extracts only files whose content changed
+        std::fs::write(root.join("b.js"), "import { add } from './a.js';\nadd(1, 2);\n").unwrap();
+        std::fs::remove_file(root.join("c.js")).unwrap();
+        let refresh = service.refresh_dependency_graph(&repo_path).await.unwrap();
+        assert_eq!(refresh, DependencyGraphRefresh { files: 2, updated_files: 1, removed_files: 1 });
+        assert_eq!(extractor.calls.load(Ordering::SeqCst), 5);
+        assert_eq!(service.get_dependency_graph(&repo_path).await.unwrap().edges.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_impact_analysis_finds_transitively_affected_tests() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("tests")).unwrap();
+        std::fs::write(root.join("a.js"), "export function add(a, b) {}\n").unwrap();
+        std::fs::write(root.join("b.js"), "import { add } from './a.js';\nexport function sum(values) {}\n").unwrap();
+        std::fs::write(root.join("c.js"), "export function unrelated() {}\n").unwrap();
+        std::fs::write(root.join("tests/b.test.js"), "import { sum } from '../b.js';\n").unwrap();
+        std::fs::write(root.join("tests/c.test.js"), "import { unrelated } from '../c.js';\n").unwrap();
+
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(None)));
+        let service = CodeAnalysisService::new(ollama).with_symbol_extractor(Arc::new(CountingExtractor::default()));
+        let path = |name: &str| root.join(name).to_string_lossy().to_string();
+
+        let impact = service.analyze_impact(&ImpactAnalysisRequest {
+            file_path: path("a.js"),
+            changes: Vec::new(),
+            repo_path: root.to_string_lossy().to_string(),
+        }).await.unwrap();
+
+        assert_eq!(impact.affected_tests.len(), 1);
+        assert_eq!(impact.affected_tests[0].file_path, path("tests/b.test.js"));
+        assert_eq!(impact.affected_tests[0].dependency_path, vec![path("a.js"), path("b.js"), path("tests/b.test.js")]);
+        assert_eq!(impact.test_command, Some(format!("npx jest {}", path("tests/b.test.js"))));
+    }
+
+    #[tokio::test]
+    async fn test_find_untested_symbols() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("tests")).unwrap();
+        std::fs::write(root.join("math.js"), "export function add(a, b) {}\nexport function subtract(a, b) {}\n").unwrap();
+        std::fs::write(root.join("tests/math.test.js"), "import { add } from '../math.js';\nexpect(add(1, 2)).toBe(3);\n").unwrap();
+
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(None)));
+        let service = CodeAnalysisService::new(ollama).with_symbol_extractor(Arc::new(CountingExtractor::default()));
+
+        let untested = service.find_untested_symbols(&root.to_string_lossy()).await.unwrap();
+        assert_eq!(untested.len(), 1);
+        assert_eq!(untested[0].symbol.name, "subtract");
+        assert_eq!(untested[0].file_path, root.join("math.js").to_string_lossy());
+    }
+
+    #[test]
+    fn test_is_test_file_conventions() {
+        let node = |file_path: &str, import: Option<&str>| DependencyNode {
+            file_path: file_path.to_string(),
+            language: "python".to_string(),
+            symbols: Vec::new(),
+            imports: import.into_iter().map(|source| Import {
+                source: source.to_string(),
+                symbols: Vec::new(),
+                is_all: true,
+                location: Range {
+                    start: Position { line: 0, character: 0 },
+                    end: Position { line: 0, character: 0 },
+                },
+            }).collect(),
+            exports: HashSet::new(),
+        };
+
+        assert!(is_test_file(&node("/repo/pkg/test_parser.py", None)));
+        assert!(is_test_file(&node("/repo/pkg/parser_test.go", None)));
+        assert!(is_test_file(&node("/repo/src/__tests__/app.js", None)));
+        assert!(is_test_file(&node("/repo/checks.py", Some("pytest"))));
+        assert!(!is_test_file(&node("/repo/src/testing_utils.py", None)));
+        assert!(!is_test_file(&node("/repo/src/contest.py", Some("os"))));
+    }
+
+    #[tokio::test]
+    async fn test_impact_analysis_from_git_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("a.js"), "export function add(a, b) {}\n").unwrap();
+        std::fs::write(root.join("b.js"), "import { add } from './a.js';\n").unwrap();
+
+        let repo = git2::Repository::init(&root).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Base", &tree, &[]).unwrap();
+
+        std::fs::write(root.join("a.js"), "export function add(a, b) {}\nexport function sub(a, b) {}\n").unwrap();
+
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(None)));
+        let service = CodeAnalysisService::new(ollama).with_symbol_extractor(Arc::new(CountingExtractor::default()));
+        let result = service.analyze_impact_from_git(&root.to_string_lossy(), None, false).await.unwrap();
+
+        let a_path = root.join("a.js").to_string_lossy().to_string();
+        assert_eq!(result.changed_files, vec![a_path.clone()]);
+        let file_impact = &result.file_impacts[0];
+        assert_eq!(file_impact.file_path, a_path);
+        assert_eq!(file_impact.changes.len(), 1);
+        assert_eq!(file_impact.changes[0].range.start.line, 1);
+        assert_eq!(file_impact.changes[0].new_text, "export function sub(a, b) {}\n");
+        assert_eq!(file_impact.impact.affected_files.len(), 1);
+        assert!(file_impact.impact.affected_files[0].file_path.ends_with("b.js"));
+
+        // Nothing is staged yet
+        let staged = service.analyze_impact_from_git(&root.to_string_lossy(), None, true).await.unwrap();
+        assert!(staged.changed_files.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_impact_analysis_from_git_outside_a_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(None)));
+        let service = CodeAnalysisService::new(ollama);
+        let error = service.analyze_impact_from_git(&dir.path().to_string_lossy(), None, false).await.unwrap_err();
+        assert!(error.starts_with("Not a git repository"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_generate_commit_message_from_staged_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("math.js"), "export function add(a, b) {}\n").unwrap();
+        let repo = git2::Repository::init(root).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Base", &tree, &[]).unwrap();
+
+        let mut server = mockito::Server::new_async().await;
+        let chat = server.mock("POST", "/api/chat")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex(r"\+export function sub".to_string()),
+                mockito::Matcher::Regex("Conventional Commits".to_string()),
+            ]))
+            .with_body(serde_json::json!({
+                "model": "llama3:latest",
+                "message": {"role": "assistant", "content": "feat(math): add sub\n\nAdds a subtraction helper next to add."},
+                "done": true,
+            }).to_string())
+            .create_async()
+            .await;
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(Some(server.url()))));
+        let service = CodeAnalysisService::new(ollama);
+        let repo_path = root.to_string_lossy().to_string();
+
+        // Unstaged edits alone leave nothing to commit
+        std::fs::write(root.join("math.js"), "export function add(a, b) {}\nexport function sub(a, b) {}\n").unwrap();
+        let error = service.generate_commit_message(&repo_path, true, CommitMessageStyle::Conventional).await.unwrap_err();
+        assert_eq!(error, "Nothing staged to commit");
+
+        index.add_path(Path::new("math.js")).unwrap();
+        index.write().unwrap();
+        let message = service.generate_commit_message(&repo_path, true, CommitMessageStyle::Conventional).await.unwrap();
+        chat.assert_async().await;
+        assert_eq!(message, CommitMessage {
+            title: "feat(math): add sub".to_string(),
+            body: "Adds a subtraction helper next to add.".to_string(),
+        });
+    }
+
+    #[tokio::test]
+    async fn test_review_diff_maps_comments_to_files_and_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join("src")).unwrap();
+        std::fs::write(root.join("src/math.js"), "export function add(a, b) {\n  return a + b;\n}\n").unwrap();
+        let repo = git2::Repository::init(root).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Base", &tree, &[]).unwrap();
+        std::fs::write(root.join("src/math.js"), "export function add(a, b) {\n  return eval(a + b);\n}\nexport function div(a, b) { return a / b; }\n").unwrap();
+
+        let content = r#"{"comments": [
+            {"file": "src/math.js", "line": 2, "severity": "critical", "message": "eval on arguments allows code injection"},
+            {"file": "math.js", "line": "4", "severity": "warning", "message": "Division by zero is not handled"},
+            {"file": "src/other.js", "line": 1, "severity": "info", "message": "Not part of the diff"}
+        ]}"#;
+        let mut server = mockito::Server::new_async().await;
+        let chat = server.mock("POST", "/api/chat")
+            // Added lines are numbered for the model to refer to
+            .match_body(mockito::Matcher::Regex(r"     2 \+  return eval".to_string()))
+            .with_body(serde_json::json!({
+                "model": "llama3:latest",
+                "message": {"role": "assistant", "content": content},
+                "done": true,
+            }).to_string())
+            .create_async()
+            .await;
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(Some(server.url()))));
+        let service = CodeAnalysisService::new(ollama);
+
+        let review = service.review_diff(&root.to_string_lossy(), None, None).await.unwrap();
+        chat.assert_async().await;
+        assert!(review.errors.is_empty());
+        assert_eq!(review.reviewed_files, vec!["src/math.js"]);
+        assert_eq!(review.comments, vec![
+            ReviewComment {
+                file: "src/math.js".to_string(),
+                line: 2,
+                severity: ReviewSeverity::Error,
+                message: "eval on arguments allows code injection".to_string(),
+            },
+            ReviewComment {
+                file: "src/math.js".to_string(),
+                line: 4,
+                severity: ReviewSeverity::Warning,
+                message: "Division by zero is not handled".to_string(),
+            },
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_code_is_trimmed_or_rejected_on_every_path() {
+        let code = "let value = compute(input);\n".repeat(2000);
+        let range = Range {
+            start: Position { line: 0, character: 0 },
+            end: Position { line: 0, character: 5 },
+        };
+        let requests = |service: CodeAnalysisService| {
+            let code = code.clone();
+            let range = range.clone();
+            async move {
+                vec![
+                    service.analyze_code(&CodeAnalysisRequest {
+                        code: code.clone(),
+                        language: "rust".to_string(),
+                        file_path: None,
+                        model: None,
+                    }).await.map(|response| response.analysis),
+                    service.fix_code(&CodeFixRequest {
+                        code: code.clone(),
+                        language: "rust".to_string(),
+                        error_message: "unused variable".to_string(),
+                        error_range: range,
+                    }).await.map(|response| response.explanation),
+                    service.generate_code(&CodeGenerationRequest {
+                        prompt: "Add error handling".to_string(),
+                        language: "rust".to_string(),
+                        context: Some(code),
+                    }).await.map(|response| response.generated_code),
+                ]
+            }
+        };
+        let limit = PromptLimitConfig { max_prompt_tokens: Some(500), ..PromptLimitConfig::default() };
+
+        let mut server = mockito::Server::new_async().await;
+        let chat = server.mock("POST", "/api/chat")
+            .match_body(mockito::Matcher::Regex("words trimmed to fit the context window".to_string()))
+            .with_body(serde_json::json!({
+                "model": "llama3:latest",
+                "message": {"role": "assistant", "content": "Looks fine."},
+                "done": true,
+            }).to_string())
+            .expect(3)
+            .create_async()
+            .await;
+        let client = crate::ollama_client::OllamaClient::new(Some(server.url()));
+        client.set_prompt_limit(limit.clone());
+        let service = CodeAnalysisService::new(Arc::new(Mutex::new(client)));
+        for result in requests(service).await {
+            assert_eq!(result.unwrap(), "Looks fine.");
+        }
+        chat.assert_async().await;
+
+        let mut server = mockito::Server::new_async().await;
+        let chat = server.mock("POST", "/api/chat").expect(0).create_async().await;
+        let client = crate::ollama_client::OllamaClient::new(Some(server.url()));
+        client.set_prompt_limit(PromptLimitConfig { policy: OversizePolicy::Reject, ..limit });
+        let service = CodeAnalysisService::new(Arc::new(Mutex::new(client)));
+        for result in requests(service).await {
+            let error = result.unwrap_err();
+            assert!(error.contains("only 500 fit in its context window"), "{}", error);
+        }
+        chat.assert_async().await;
+    }
+
+    #[test]
+    fn test_broader_impact_scores_higher_at_same_level() {
+        let symbol = |name: &str, kind: SymbolKind, is_exported: bool| Symbol {
+            name: name.to_string(),
+            kind,
+            location: Range {
+                start: Position { line: 0, character: 0 },
+                end: Position { line: 0, character: 0 },
+            },
+            documentation: None,
+            is_exported,
+        };
+        let file = |file_path: &str, affected_symbols: Vec<Symbol>| AffectedFile {
+            file_path: file_path.to_string(),
+            impact_description: String::new(),
+            impact_level: ImpactLevel::Low,
+            affected_symbols,
+        };
+
+        let narrow = vec![file("b.js", vec![symbol("add", SymbolKind::Function, true)])];
+        let broad = vec![
+            file("b.js", vec![symbol("add", SymbolKind::Function, true)]),
+            file("c.js", vec![symbol("add", SymbolKind::Function, true)]),
+            file("d.js", vec![symbol("add", SymbolKind::Function, true)]),
+        ];
+        assert!(impact_risk_score(&broad) > impact_risk_score(&narrow));
+
+        let internal = vec![file("b.js", vec![symbol("helper", SymbolKind::Function, false)])];
+        let public_type = vec![file("b.js", vec![symbol("Config", SymbolKind::Interface, true)])];
+        assert!(impact_risk_score(&narrow) > impact_risk_score(&internal));
+        assert!(impact_risk_score(&public_type) > impact_risk_score(&narrow));
+        assert_eq!(impact_risk_score(&[]), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_generate_tests_returns_only_the_code() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("math.js").to_string_lossy().to_string();
+        std::fs::write(&file_path, "export function add(a, b) {\n  return a + b;\n}\n\nexport function mul(a, b) {\n  return a * b;\n}\n").unwrap();
+
+        let reply = "Here are some tests:\n```javascript\nimport { add } from './math.js';\n\ntest('adds', () => {\n  expect(add(1, 2)).toBe(3);\n});\n```\nThey cover the basic case.";
+        let mut server = mockito::Server::new_async().await;
+        let chat = server.mock("POST", "/api/chat")
+            // The prompt names the framework and carries the function's source
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex("using vitest".to_string()),
+                mockito::Matcher::Regex(r"return a \+ b;\\n\}".to_string()),
+                mockito::Matcher::Regex(r#""model":"codellama""#.to_string()),
+            ]))
+            .with_body(serde_json::json!({
+                "model": "codellama",
+                "message": {"role": "assistant", "content": reply},
+                "done": true,
+            }).to_string())
+            .create_async()
+            .await;
+
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(Some(server.url()))));
+        let service = CodeAnalysisService::new(ollama).with_symbol_extractor(Arc::new(CountingExtractor::default()));
+
+        let tests = service.generate_tests(&file_path, "add", "vitest", Some("codellama")).await.unwrap();
+        chat.assert_async().await;
+        assert!(tests.test_code.starts_with("import { add } from './math.js';"));
+        assert!(tests.test_code.ends_with("});"));
+        assert!(!tests.test_code.contains("```"));
+        assert_eq!(tests.explanation, "Here are some tests:\n\nThey cover the basic case.");
+        assert_eq!(tests.framework, "vitest");
+
+        let missing = service.generate_tests(&file_path, "divide", "vitest", None).await;
+        assert!(missing.unwrap_err().contains("not found"));
+    }
+
+    /// Symbols for the `fn` and `def` definitions of Rust and Python sources, at their line
+    struct DefinitionExtractor;
+
+    #[async_trait::async_trait]
+    impl SymbolExtractor for DefinitionExtractor {
+        async fn extract(&self, content: &str, _language: &str, _file_path: &str) -> (Vec<Symbol>, Vec<Import>) {
+            let pattern = regex::Regex::new(r"^\s*(?:pub\s+)?(?:fn|def)\s+(\w+)").unwrap();
+            let symbols = content.lines().enumerate()
+                .filter_map(|(line, text)| {
+                    let position = Position { line: line as u32, character: 0 };
+                    Some(Symbol {
+                        name: pattern.captures(text)?[1].to_string(),
+                        kind: SymbolKind::Function,
+                        location: Range { start: position.clone(), end: position },
+                        documentation: None,
+                        is_exported: true,
+                    })
+                })
+                .collect();
+            (symbols, Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_docs_inserts_doc_comments_at_the_symbol() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = mockito::Server::new_async().await;
+        let chat = server.mock("POST", "/api/chat")
+            .match_body(mockito::Matcher::Regex("usage example".to_string()))
+            .with_body(serde_json::json!({
+                "model": "llama3:latest",
+                "message": {"role": "assistant", "content": "/// Adds two numbers.\n///\n/// Wraps on overflow."},
+                "done": true,
+            }).to_string())
+            .expect(2)
+            .create_async()
+            .await;
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(Some(server.url()))));
+        let service = CodeAnalysisService::new(ollama).with_symbol_extractor(Arc::new(DefinitionExtractor));
+        let style = DocStyle { verbosity: DocVerbosity::Verbose, include_examples: true };
+        let inserted_at = |response: &DocGenerationResponse| {
+            let edits: Vec<&tower_lsp::lsp_types::TextEdit> = response.edit.changes.as_ref().unwrap().values().flatten().collect();
+            assert_eq!(edits.len(), 1);
+            assert_eq!(edits[0].range.start, edits[0].range.end);
+            assert_eq!(edits[0].new_text, response.doc_comment);
+            edits[0].range.start
+        };
+
+        // Rust: `///` lines above the attribute, at the item's indentation
+        let rust_path = dir.path().join("math.rs").to_string_lossy().to_string();
+        std::fs::write(&rust_path, "impl Math {\n    #[inline]\n    pub fn add(a: u8, b: u8) -> u8 {\n        a.wrapping_add(b)\n    }\n}\n").unwrap();
+        let docs = service.generate_docs(&rust_path, "add", &style, None).await.unwrap();
+        assert_eq!(docs.doc_comment, "    /// Adds two numbers.\n    ///\n    /// Wraps on overflow.\n");
+        assert_eq!(inserted_at(&docs), tower_lsp::lsp_types::Position { line: 1, character: 0 });
+
+        // Python: a docstring opening the function body
+        let python_path = dir.path().join("math.py").to_string_lossy().to_string();
+        std::fs::write(&python_path, "import operator\n\ndef add(a, b):\n    return a + b\n").unwrap();
+        let docs = service.generate_docs(&python_path, "add", &style, None).await.unwrap();
+        assert_eq!(docs.doc_comment, "    \"\"\"\n    Adds two numbers.\n\n    Wraps on overflow.\n    \"\"\"\n");
+        assert_eq!(inserted_at(&docs), tower_lsp::lsp_types::Position { line: 3, character: 0 });
+        chat.assert_async().await;
+    }
+
+    #[test]
+    fn test_parse_refactoring_suggestions() {
+        let files = vec!["src/app.js".to_string()];
+        let response = "Here you go:\n```json\n{\"suggestions\": [{\"title\": \"Inline temporary\", \"description\": \"The variable is used once\", \"before\": \"const x = a + b;\\nreturn x;\", \"after\": \"return a + b;\", \"effort\": \"low\", \"benefits\": [\"Shorter\"]}]}\n```";
+
+        let suggestions = parse_refactoring_suggestions(response, &files).unwrap();
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].title, "Inline temporary");
+        assert_eq!(suggestions[0].before_code, "const x = a + b;\nreturn x;");
+        assert_eq!(suggestions[0].after_code, "return a + b;");
+        assert_eq!(suggestions[0].effort_estimate, "Easy");
+        assert_eq!(suggestions[0].affected_files, files);
+        assert_eq!(suggestions[0].benefits, vec!["Shorter"]);
+
+        assert!(parse_refactoring_suggestions("[]", &files).unwrap().is_empty());
+        assert!(parse_refactoring_suggestions("I would refactor the loop.", &files).is_err());
+        assert!(parse_refactoring_suggestions("{\"suggestions\": [{\"description\": \"untitled\"}]}", &files).is_err());
+    }
+
+    #[test]
+    fn test_truncated_review_keeps_complete_comments() {
+        // Cut off by the token limit partway through the second comment
+        let reply = r#"```json
+{"comments": [{"file": "src/math.js", "line": 2, "severity": "error", "message": "eval allows code injection"}, {"file": "src/math.js", "line": 4, "severity": "warn"#;
+
+        let comments = parse_review_comments(reply, &["src/math.js"]).unwrap();
+        assert_eq!(comments, vec![ReviewComment {
+            file: "src/math.js".to_string(),
+            line: 2,
+            severity: ReviewSeverity::Error,
+            message: "eval allows code injection".to_string(),
+        }]);
+    }
+
+    #[tokio::test]
+    async fn test_unrepairable_truncated_reply_is_retried_with_more_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("app.js").to_string_lossy().to_string();
+        std::fs::write(&file_path, "function f(a, b) {\n  const x = a + b;\n  return x;\n}\n").unwrap();
+
+        let chat_reply = |content: &str| serde_json::json!({
+            "model": "llama3:latest",
+            "message": {"role": "assistant", "content": content},
+            "done": true,
+        }).to_string();
+        let mut server = mockito::Server::new_async().await;
+        let longer = server.mock("POST", "/api/chat")
+            .match_body(mockito::Matcher::PartialJsonString(
+                format!(r#"{{"options": {{"num_predict": {}}}}}"#, TRUNCATED_JSON_RETRY_MAX_TOKENS),
+            ))
+            .with_body(chat_reply(r#"{"suggestions": [{"title": "Inline temporary", "description": "x is used once", "before_code": "const x = a + b;", "after_code": "return a + b;"}]}"#))
+            .expect(1)
+            .create_async()
+            .await;
+        // Repairing this leaves a suggestion without a title, which can't be used
+        let truncated = server.mock("POST", "/api/chat")
+            .with_body(chat_reply(r#"{"suggestions": [{"description": "x is used once", "ti"#))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(Some(server.url()))));
+        let request = RefactoringRequest {
+            file_paths: vec![file_path],
+            focus_areas: None,
+            repo_path: dir.path().to_string_lossy().to_string(),
+            model: None,
+        };
+        let response = CodeAnalysisService::new(ollama).suggest_refactorings(&request).await.unwrap();
+        truncated.assert_async().await;
+        longer.assert_async().await;
+        assert_eq!(response.suggestions.len(), 1);
+        assert_eq!(response.suggestions[0].title, "Inline temporary");
+    }
+
+    #[tokio::test]
+    async fn test_suggest_refactorings_retries_malformed_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("app.js").to_string_lossy().to_string();
+        std::fs::write(&file_path, "function f(a, b) {\n  const x = a + b;\n  return x;\n}\n").unwrap();
+
+        let chat_reply = |content: &str| serde_json::json!({
+            "model": "llama3:latest",
+            "message": {"role": "assistant", "content": content},
+            "done": true,
+        }).to_string();
+        let mut server = mockito::Server::new_async().await;
+        let malformed = server.mock("POST", "/api/chat")
+            .match_body(mockito::Matcher::PartialJsonString(r#"{"format": "json"}"#.to_string()))
+            .with_body(chat_reply("Sure! I'd inline the temporary."))
+            .expect(1)
+            .create_async()
+            .await;
+        let structured = server.mock("POST", "/api/chat")
+            .with_body(chat_reply(r#"{"suggestions": [{"title": "Inline temporary", "description": "x is used once", "before_code": "const x = a + b;\n  return x;", "after_code": "return a + b;", "effort_estimate": "Easy", "benefits": ["Shorter"]}]}"#))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(Some(server.url()))));
+        let service = CodeAnalysisService::new(ollama);
+        let request = RefactoringRequest {
+            file_paths: vec![file_path.clone()],
+            focus_areas: None,
+            repo_path: dir.path().to_string_lossy().to_string(),
+            model: None,
+        };
+
+        let response = service.suggest_refactorings(&request).await.unwrap();
+        malformed.assert_async().await;
+        structured.assert_async().await;
+        assert_eq!(response.reason, None);
+        assert_eq!(response.suggestions.len(), 1);
+        assert_eq!(response.suggestions[0].title, "Inline temporary");
+        assert_eq!(response.suggestions[0].after_code, "return a + b;");
+        assert_eq!(response.suggestions[0].affected_files, vec![file_path]);
+
+        // Output that stays malformed yields no suggestions and the reason
+        let mut server = mockito::Server::new_async().await;
+        server.mock("POST", "/api/chat")
+            .with_body(chat_reply("not json"))
+            .expect(REFACTORING_PARSE_ATTEMPTS)
+            .create_async()
+            .await;
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(Some(server.url()))));
+        let response = CodeAnalysisService::new(ollama).suggest_refactorings(&request).await.unwrap();
+        assert!(response.suggestions.is_empty());
+        assert!(response.reason.unwrap().contains("could not be parsed"));
+    }
+
+    #[tokio::test]
+    async fn test_refactoring_suggestions_are_tagged_and_filtered_by_focus_area() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("db.js").to_string_lossy().to_string();
+        std::fs::write(&file_path, "const rows = db.query(`SELECT * FROM users WHERE id = ${id}`);\n").unwrap();
+
+        let content = r#"{"suggestions": [
+            {"title": "Parameterize the query", "before_code": "${id}", "after_code": "?", "focus_areas": ["security"]},
+            {"title": "Select only needed columns", "before_code": "*", "after_code": "id, name", "focus_areas": ["Performance", "readability"]}
+        ]}"#;
+        let mut server = mockito::Server::new_async().await;
+        let chat = server.mock("POST", "/api/chat")
+            // The prompt spells out what a security review looks for
+            .match_body(mockito::Matcher::Regex("injection".to_string()))
+            .with_body(serde_json::json!({
+                "model": "llama3:latest",
+                "message": {"role": "assistant", "content": content},
+                "done": true,
+            }).to_string())
+            .expect(2)
+            .create_async()
+            .await;
+
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(Some(server.url()))));
+        let service = CodeAnalysisService::new(ollama);
+        let mut request = RefactoringRequest {
+            file_paths: vec![file_path],
+            focus_areas: Some(vec![RefactoringFocusArea::Security]),
+            repo_path: dir.path().to_string_lossy().to_string(),
+            model: None,
+        };
+
+        let response = service.suggest_refactorings(&request).await.unwrap();
+        assert_eq!(response.suggestions.len(), 1);
+        assert_eq!(response.suggestions[0].title, "Parameterize the query");
+        assert_eq!(response.suggestions[0].focus_areas, vec![RefactoringFocusArea::Security]);
+
+        request.focus_areas = Some(vec![RefactoringFocusArea::Security, RefactoringFocusArea::Performance]);
+        let response = service.suggest_refactorings(&request).await.unwrap();
+        assert_eq!(response.suggestions.len(), 2);
+        assert_eq!(
+            response.suggestions[1].focus_areas,
+            vec![RefactoringFocusArea::Performance, RefactoringFocusArea::Readability]
+        );
+        chat.assert_async().await;
+    }
+
+    #[test]
+    fn test_apply_refactoring_with_fuzzy_whitespace() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("math.js").to_string_lossy().to_string();
+        std::fs::write(&file_path, "function total(items) {\n    let sum = 0;\n    for (const item of items) {\n        sum += item.price;\n    }\n    return sum;\n}\n").unwrap();
+
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(None)));
+        let service = CodeAnalysisService::new(ollama);
+        let suggestion = RefactoringSuggestion {
+            title: "Use reduce".to_string(),
+            description: String::new(),
+            // Indentation differs from the file
+            before_code: "let sum = 0;\nfor (const item of items) {\n  sum += item.price;\n}\nreturn sum;".to_string(),
+            after_code: "return items\n  .reduce((sum, item) => sum + item.price, 0);".to_string(),
+            affected_files: vec![file_path.clone()],
+            effort_estimate: "Easy".to_string(),
+            benefits: Vec::new(),
+            focus_areas: Vec::new(),
+        };
+
+        let preview = service.apply_refactoring(&file_path, &suggestion, true).unwrap();
+        assert!(!preview.applied);
+        assert!(std::fs::read_to_string(&file_path).unwrap().contains("let sum = 0;"));
+        let edits = preview.workspace_edit.changes.unwrap().into_values().next().unwrap();
+        assert_eq!(edits[0].range.start, tower_lsp::lsp_types::Position { line: 1, character: 4 });
+        assert_eq!(edits[0].range.end, tower_lsp::lsp_types::Position { line: 5, character: 15 });
+        assert!(preview.diff.contains("@@ -2,5 +2,2 @@\n-    let sum = 0;\n"));
+
+        let edit = service.apply_refactoring(&file_path, &suggestion, false).unwrap();
+        assert!(edit.applied);
+        assert_eq!(
+            std::fs::read_to_string(&file_path).unwrap(),
+            "function total(items) {\n    return items\n      .reduce((sum, item) => sum + item.price, 0);\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_refactoring_rejects_missing_and_ambiguous_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("log.js").to_string_lossy().to_string();
+        let content = "log('start');\nrun();\nlog('start');\n";
+        std::fs::write(&file_path, content).unwrap();
+
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(None)));
+        let service = CodeAnalysisService::new(ollama);
+        let suggestion = |before_code: &str| RefactoringSuggestion {
+            title: "Rename".to_string(),
+            description: String::new(),
+            before_code: before_code.to_string(),
+            after_code: "logger.info('start');".to_string(),
+            affected_files: Vec::new(),
+            effort_estimate: "Easy".to_string(),
+            benefits: Vec::new(),
+            focus_areas: Vec::new(),
+        };
+
+        let ambiguous = service.apply_refactoring(&file_path, &suggestion("log('start');"), false).unwrap_err();
+        assert!(ambiguous.contains("matches 2 places"), "{}", ambiguous);
+        let missing = service.apply_refactoring(&file_path, &suggestion("log('stop');"), false).unwrap_err();
+        assert!(missing.contains("not found"), "{}", missing);
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), content);
+    }
+
+    #[test]
+    fn test_apply_refactorings_detects_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("values.js").to_string_lossy().to_string();
+        let content = "let a = 1;\nlet b = 2;\nlet c = 3;\n";
+        std::fs::write(&file_path, content).unwrap();
+
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(None)));
+        let service = CodeAnalysisService::new(ollama);
+        let suggestion = |before_code: &str, after_code: &str| RefactoringSuggestion {
+            title: "Edit".to_string(),
+            description: String::new(),
+            before_code: before_code.to_string(),
+            after_code: after_code.to_string(),
+            affected_files: Vec::new(),
+            effort_estimate: "Easy".to_string(),
+            benefits: Vec::new(),
+            focus_areas: Vec::new(),
+        };
+
+        // Overlapping: the second rewrites a line the first already replaces
+        let overlapping = vec![
+            suggestion("let a = 1;\nlet b = 2;", "let a = 1;\nlet b = 20;"),
+            suggestion("let a = 1;", "const a = 1;"),
+        ];
+        let batch = service.apply_refactorings(&file_path, &overlapping, false, false).unwrap();
+        assert_eq!(batch.applied, vec![0]);
+        assert_eq!(batch.conflicts, vec![RefactoringConflict { suggestion: 1, conflicts_with: 0, forced: false }]);
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "let a = 1;\nlet b = 20;\nlet c = 3;\n");
+
+        std::fs::write(&file_path, content).unwrap();
+        let forced = service.apply_refactorings(&file_path, &overlapping, true, true).unwrap();
+        assert_eq!(forced.applied, vec![0, 1]);
+        assert!(forced.conflicts[0].forced);
+        assert!(forced.diff.contains("+const a = 1;\n+let b = 20;\n"));
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), content);
+
+        // Non-overlapping edits apply together, against the original positions
+        let separate = vec![
+            suggestion("let c = 3;", "let c = 30;"),
+            suggestion("let a = 1;", "let a = 10;"),
+        ];
+        let batch = service.apply_refactorings(&file_path, &separate, false, false).unwrap();
+        assert!(batch.conflicts.is_empty() && batch.failed.is_empty());
+        assert_eq!(batch.applied, vec![1, 0]);
+        assert_eq!(batch.workspace_edit.changes.unwrap().into_values().next().unwrap().len(), 2);
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "let a = 10;\nlet b = 2;\nlet c = 30;\n");
+    }
+
+    #[tokio::test]
+    async fn test_dependency_analysis_reuses_cached_extractions() {
+        let repo = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let root = repo.path().canonicalize().unwrap();
+        std::fs::write(root.join("a.js"), "export function add(a, b) {}\n").unwrap();
+        std::fs::write(root.join("b.js"), "import { add } from './a.js';\n").unwrap();
+
+        let request = DependencyAnalysisRequest {
+            repo_path: root.to_string_lossy().to_string(),
+            file_patterns: None,
+            exclude_patterns: None,
+            max_files: None,
+            include_content: None,
+        };
+        let extractor = Arc::new(CountingExtractor::default());
+        let service = || {
+            let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(None)));
+            CodeAnalysisService::new(ollama)
+                .with_symbol_extractor(extractor.clone())
+                .with_extraction_cache(ExtractionCache::new(cache_dir.path()))
+        };
+
+        service().analyze_dependencies(&request).await.unwrap();
+        assert_eq!(extractor.calls.load(Ordering::SeqCst), 2);
+
+        // A fresh service finds both extractions on disk
+        let cached = service();
+        let graph = cached.analyze_dependencies(&request).await.unwrap();
+        assert_eq!(extractor.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(cached.extraction_cache_stats().unwrap().hits, 2);
+
+        std::fs::write(root.join("a.js"), "export function add(a, b, c) {}\n").unwrap();
+        cached.analyze_dependencies(&request).await.unwrap();
+        assert_eq!(extractor.calls.load(Ordering::SeqCst), 3);
+
+        assert_eq!(cached.clear_extraction_cache().unwrap(), 2);
+        assert_eq!(cached.extraction_cache_stats().unwrap().entries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_reanalyze_file_patches_only_its_edges() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("a.js"), "export function add(a, b) {}\n").unwrap();
+        std::fs::write(root.join("b.js"), "import { add } from './a.js';\n").unwrap();
+        std::fs::write(root.join("c.js"), "export function mul(a, b) {}\n").unwrap();
+        std::fs::write(root.join("d.js"), "import { mul } from './c.js';\n").unwrap();
+
+        let extractor = Arc::new(CountingExtractor::default());
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(None)));
+        let service = CodeAnalysisService::new(ollama).with_symbol_extractor(extractor.clone());
+        let repo_path = root.to_string_lossy().to_string();
+        let path = |name: &str| root.join(name).to_string_lossy().to_string();
+
+        let before = service.get_dependency_graph(&repo_path).await.unwrap();
+        assert_eq!(extractor.calls.load(Ordering::SeqCst), 4);
+
+        std::fs::write(root.join("b.js"), "import { mul } from './c.js';\n").unwrap();
+        let update = service.reanalyze_file_dependencies(&path("b.js")).await.unwrap();
+        assert_eq!(extractor.calls.load(Ordering::SeqCst), 5);
+        assert_eq!(update.outgoing_edges.len(), 1);
+        assert_eq!(update.outgoing_edges[0].to, path("c.js"));
+        assert!(update.incoming_edges.is_empty());
+
+        let after = service.get_dependency_graph(&repo_path).await.unwrap();
+        assert_eq!(extractor.calls.load(Ordering::SeqCst), 5);
+        for name in ["a.js", "c.js", "d.js"] {
+            assert_eq!(
+                serde_json::to_value(&before.nodes[&path(name)]).unwrap(),
+                serde_json::to_value(&after.nodes[&path(name)]).unwrap(),
+            );
+        }
+        let mut edges: Vec<(String, String)> = after.edges.iter().map(|edge| (edge.from.clone(), edge.to.clone())).collect();
+        edges.sort();
+        assert_eq!(edges, vec![(path("b.js"), path("c.js")), (path("d.js"), path("c.js"))]);
+    }
+}
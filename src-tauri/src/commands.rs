@@ -1,10 +1,19 @@
-use crate::ollama_client::{OllamaClient, ChatMessage, GenerateOptions, HealthStats, HealthConfig};
-use crate::chroma_manager::ChromaManager;
-use crate::operation_manager::{Operation, OperationStatus};
-use crate::analysis_engine::{AnalysisEngine, AnalysisMode, AnalysisConfig, should_suggest_deep_analysis};
+use crate::ollama_client::{OllamaClient, SharedOllamaClient, ChatMessage, GenerateOptions, GenerateDetails, HealthStats, EmbeddingHealthStats, SplitHealthStats, HealthConfig, BufferStats, ModelAvailability, ActiveGenerations, ActiveGeneration};
+use crate::ollama_rate_limit::RateLimitStats;
+use crate::chroma_manager::{AddDocumentsRequest, ChromaManager, ChromaStatus, Citation, CollectionMemoryUsage, CollectionMetadata, CompactionStats, QueryRequest, QueryResult, SearchMode};
+use crate::context_manager::ContextManager;
+use crate::embedding_quantization::EmbeddingQuantization;
+use crate::operation_manager::{Operation, OperationFilter, OperationStatus, OperationSummary, OperationType};
+use crate::thread_pool_manager::TaskPriority;
+use crate::analysis_engine::{AnalysisEngine, AnalysisMode, AnalysisConfig, AnalysisStageOptions, DeepAnalysisResult, select_analysis_mode, should_suggest_deep_analysis};
 use crate::user_errors::{ToUserError, common};
+use crate::settings_store::SettingsStore;
+use crate::searxng_client::SearXNGClient;
+use crate::health_history::HealthHistoryReport;
+use crate::events::{emit_event, AnalysisRound, GenerationDelta};
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, State, Emitter};
+use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::time::Duration;
 
@@ -33,6 +42,30 @@ pub async fn check_ollama_connection(
     }
 }
 
+/// Point Ollama at a new endpoint without restarting; returns whether it is reachable there
+#[tauri::command]
+pub async fn set_ollama_base_url(
+    base_url: String,
+    ollama_client: State<'_, SharedOllamaClient>,
+    settings_store: State<'_, Mutex<SettingsStore>>,
+) -> Result<bool, String> {
+    {
+        let mut store = settings_store.lock().await;
+        let mut settings = store.get().clone();
+        settings.ollama.base_url = base_url.clone();
+        store.update(settings).map_err(|e| format!("Failed to update Ollama base URL: {}", e))?;
+    }
+    
+    // Clones share the base URL, so the managed OllamaClient follows the shared one
+    let client = {
+        let client = ollama_client.lock().await;
+        client.set_base_url(base_url).await;
+        client.clone()
+    };
+    
+    Ok(client.check_connection_with_retry().await.unwrap_or(false))
+}
+
 #[tauri::command]
 pub async fn list_models(
     ollama_client: State<'_, OllamaClient>,
@@ -63,6 +96,19 @@ pub async fn list_models(
     Ok(model_infos)
 }
 
+#[tauri::command]
+pub async fn pull_ollama_model(
+    model: String,
+    ollama_client: State<'_, OllamaClient>,
+) -> Result<(), String> {
+    let client = ollama_client.inner();
+    
+    client.pull_model(&model).await.map_err(|e| {
+        let user_error = e.to_user_error();
+        serde_json::to_string(&user_error).unwrap_or_else(|_| user_error.message)
+    })
+}
+
 #[tauri::command]
 pub async fn generate_completion(
     model: String,
@@ -89,6 +135,30 @@ pub async fn generate_completion(
     }
 }
 
+#[tauri::command]
+pub async fn generate_detailed(
+    model: String,
+    prompt: String,
+    temperature: Option<f32>,
+    max_tokens: Option<i32>,
+    context: Option<Vec<i64>>,
+    ollama_client: State<'_, OllamaClient>,
+) -> Result<GenerateDetails, String> {
+    let client = ollama_client.inner();
+    
+    let options = GenerateOptions {
+        temperature,
+        max_tokens,
+        top_p: None,
+        top_k: None,
+    };
+    
+    client.generate_detailed(&model, &prompt, Some(options), context).await.map_err(|e| {
+        let user_error = e.to_user_error();
+        serde_json::to_string(&user_error).unwrap_or_else(|_| user_error.message)
+    })
+}
+
 #[tauri::command]
 pub async fn generate_stream(
     model: String,
@@ -98,7 +168,7 @@ pub async fn generate_stream(
     app_handle: AppHandle,
     ollama_client: State<'_, OllamaClient>,
 ) -> Result<(), String> {
-    let client = ollama_client.inner();
+    let client = ollama_client.interactive();
     
     let options = GenerateOptions {
         temperature,
@@ -115,11 +185,14 @@ pub async fn generate_stream(
             &prompt,
             Some(options),
             move |token: &str| {
-                let _ = app_handle_clone.emit("ollama-stream", token);
+                emit_event(&app_handle_clone, GenerationDelta::token(token));
             },
         )
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    
+    emit_event(&app_handle, GenerationDelta::done(None));
+    Ok(())
 }
 
 #[tauri::command]
@@ -132,7 +205,7 @@ pub async fn chat(
     app_handle: AppHandle,
     ollama_client: State<'_, OllamaClient>,
 ) -> Result<ChatMessage, String> {
-    let client = ollama_client.inner();
+    let client = ollama_client.interactive();
     
     let options = GenerateOptions {
         temperature,
@@ -156,10 +229,14 @@ pub async fn chat(
             messages,
             Some(options),
             Some(move |token: &str| {
-                let _ = app_handle_clone.emit("ollama-stream", token);
+                emit_event(&app_handle_clone, GenerationDelta::token(token));
             }),
         )
         .await
+        .map(|message| {
+            emit_event(&app_handle, GenerationDelta::done(None));
+            message
+        })
         .map_err(|e| e.to_string())
 }
 
@@ -186,7 +263,7 @@ pub async fn chat_with_ollama(
     temperature: Option<f32>,
     ollama_client: State<'_, OllamaClient>,
 ) -> Result<ChatMessage, String> {
-    let client = ollama_client.inner();
+    let client = ollama_client.interactive();
     
     let options = GenerateOptions {
         temperature,
@@ -209,7 +286,7 @@ pub async fn chat_stream_with_ollama(
     app_handle: AppHandle,
     ollama_client: State<'_, OllamaClient>,
 ) -> Result<(), String> {
-    let client = ollama_client.inner();
+    let client = ollama_client.interactive();
     
     let options = GenerateOptions {
         temperature,
@@ -226,11 +303,11 @@ pub async fn chat_stream_with_ollama(
             messages,
             Some(options),
             Some(move |token: &str| {
-                let _ = app_handle_clone.emit("ollama-stream", token);
+                emit_event(&app_handle_clone, GenerationDelta::token(token));
             }),
         )
         .await
-        .map(|_| ())
+        .map(|_| emit_event(&app_handle, GenerationDelta::done(None)))
         .map_err(|e| e.to_string())
 }
 
@@ -268,17 +345,25 @@ pub async fn generate_stream_with_ollama(
     analysis_mode: Option<String>,
     max_rounds: Option<usize>,
     save_to_rag: Option<bool>,
+    stage_options: Option<AnalysisStageOptions>,
+    request_id: Option<String>,
     app_handle: AppHandle,
     ollama_client: State<'_, OllamaClient>,
     chroma_manager: State<'_, Mutex<ChromaManager>>,
+    settings_store: State<'_, Mutex<SettingsStore>>,
+    active_generations: State<'_, ActiveGenerations>,
 ) -> Result<(), String> {
     let client = ollama_client.inner();
     let use_rag = use_rag.unwrap_or(false);
+    // Buffer stats for `get_generation_buffer_stats` while the answer streams
+    let generation = request_id.map(|request_id| active_generations.register(&request_id));
+    let analysis_settings = settings_store.lock().await.get().analysis.clone();
     
     // Parse analysis mode
     let analysis_mode = match analysis_mode.as_deref() {
         Some("socratic") => AnalysisMode::Socratic,
         Some("systematic") => AnalysisMode::Systematic,
+        Some("auto") => select_analysis_mode(&prompt),
         _ => AnalysisMode::Standard,
     };
     
@@ -352,10 +437,11 @@ pub async fn generate_stream_with_ollama(
         let analysis_engine = AnalysisEngine::new(client.clone(), chroma_manager_clone);
         
         let analysis_config = AnalysisConfig {
-            mode: analysis_mode.clone(),
-            max_rounds: max_rounds.unwrap_or(5),
+            max_rounds: max_rounds.unwrap_or(analysis_settings.max_rounds),
             time_limit: Duration::from_secs(300),
-            save_to_rag: save_to_rag.unwrap_or(true),
+            save_to_rag: save_to_rag.unwrap_or(analysis_settings.save_to_rag),
+            stage_options: stage_options.unwrap_or_default(),
+            ..analysis_settings.to_config(analysis_mode.clone())
         };
         
         // Emit analysis start event
@@ -367,6 +453,11 @@ pub async fn generate_stream_with_ollama(
         
         match analysis_engine.analyze(&enhanced_prompt, &model, analysis_config).await {
             Ok(result) => {
+                let round_session_id = session_id.clone().unwrap_or_default();
+                for chain in &result.reasoning {
+                    emit_event(&app_handle, AnalysisRound::from_chain(&round_session_id, chain));
+                }
+                
                 // Emit reasoning chain for UI display
                 let _ = app_handle.emit("deep-analysis-reasoning", serde_json::json!({
                     "session_id": session_id.as_ref().unwrap_or(&String::new()),
@@ -380,10 +471,7 @@ pub async fn generate_stream_with_ollama(
                 
                 // Stream solution character by character for smooth UX
                 for (i, char) in solution_chars.iter().enumerate() {
-                    let _ = app_handle_clone.emit("ollama-stream", serde_json::json!({
-                        "token": char.to_string(),
-                        "done": false
-                    }));
+                    emit_event(&app_handle_clone, GenerationDelta::token(&char.to_string()));
                     
                     // Small delay to simulate streaming
                     if i % 10 == 0 {
@@ -392,10 +480,7 @@ pub async fn generate_stream_with_ollama(
                 }
                 
                 // Emit completion event
-                let _ = app_handle.emit("ollama-stream", serde_json::json!({
-                    "token": "",
-                    "done": true
-                }));
+                emit_event(&app_handle, GenerationDelta::done(None));
                 
                 // Emit final analysis result
                 let _ = app_handle.emit("deep-analysis-complete", serde_json::json!({
@@ -413,13 +498,342 @@ pub async fn generate_stream_with_ollama(
                 }));
                 
                 // Fall back to standard streaming
-                standard_streaming_generation(client, &model, &enhanced_prompt, temperature, app_handle).await
+                standard_streaming_generation(client, &model, &enhanced_prompt, temperature, app_handle, generation).await
             }
         }
     } else {
         // Standard streaming generation
-        standard_streaming_generation(client, &model, &enhanced_prompt, temperature, app_handle).await
+        standard_streaming_generation(client, &model, &enhanced_prompt, temperature, app_handle, generation).await
+    }
+}
+
+/// Continue a saved deep analysis pattern with new information
+#[tauri::command]
+pub async fn resume_analysis(
+    pattern_id: String,
+    additional_context: String,
+    model: String,
+    max_rounds: Option<usize>,
+    ollama_client: State<'_, OllamaClient>,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+) -> Result<DeepAnalysisResult, String> {
+    let config = AnalysisConfig {
+        max_rounds: max_rounds.unwrap_or(2),
+        ..Default::default()
+    };
+
+    // The Chroma lock is only held to read the pattern and to save it again, not while
+    // the model refines it
+    let saved = AnalysisEngine::read_reasoning_pattern(&*chroma_manager.lock().await, &pattern_id)?;
+    let engine = AnalysisEngine::new(ollama_client.inner().clone(), None);
+    let mut result = engine.continue_analysis(&saved, &additional_context, &model, &config).await?;
+    result.saved_to_rag = AnalysisEngine::save_resumed_pattern(&mut *chroma_manager.lock().await, &saved, &additional_context, &result);
+
+    Ok(result)
+}
+
+/// Run deep analysis over several related prompts, retrieving similar patterns once
+#[tauri::command]
+pub async fn analyze_batch(
+    prompts: Vec<String>,
+    model: String,
+    analysis_mode: Option<String>,
+    max_rounds: Option<usize>,
+    save_to_rag: Option<bool>,
+    ollama_client: State<'_, OllamaClient>,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+    settings_store: State<'_, Mutex<SettingsStore>>,
+) -> Result<Vec<Result<DeepAnalysisResult, String>>, String> {
+    let analysis_settings = settings_store.lock().await.get().analysis.clone();
+    let mode = match analysis_mode.as_deref() {
+        Some("socratic") => AnalysisMode::Socratic,
+        Some("systematic") => AnalysisMode::Systematic,
+        Some("standard") => AnalysisMode::Standard,
+        _ => AnalysisMode::Auto,
+    };
+    let config = AnalysisConfig {
+        max_rounds: max_rounds.unwrap_or(analysis_settings.max_rounds),
+        save_to_rag: save_to_rag.unwrap_or(analysis_settings.save_to_rag),
+        ..analysis_settings.to_config(mode)
+    };
+
+    // Patterns are retrieved and results saved under short locks; the analyses
+    // themselves run without the Chroma manager
+    let patterns = AnalysisEngine::query_patterns(&mut *chroma_manager.lock().await, &prompts.join("\n"), 3);
+    let mut engine = AnalysisEngine::new(ollama_client.inner().clone(), None).with_shared_patterns(patterns);
+    let mut outcomes = engine.analyze_batch(&prompts, &model, config.clone()).await;
+    AnalysisEngine::save_batch_results(&mut *chroma_manager.lock().await, &prompts, &mut outcomes, &config);
+
+    Ok(outcomes)
+}
+
+/// A retrieved document used to ground a RAG answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagSource {
+    pub id: String,
+    pub source: String,
+    pub title: Option<String>,
+    pub file_path: Option<String>,
+    pub url: Option<String>,
+    pub distance: f32,
+    /// How the answer refers to this source, as `[N]` for the Nth source
+    pub citation: Citation,
+}
+
+/// Answer from `generate_with_rag` plus the sources it was grounded on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagGenerationResponse {
+    pub answer: String,
+    pub sources: Vec<RagSource>,
+    pub model: String,
+}
+
+impl From<&QueryResult> for RagSource {
+    fn from(result: &QueryResult) -> Self {
+        Self {
+            id: result.id.clone(),
+            source: result.metadata.source.clone(),
+            title: result.metadata.title.clone(),
+            file_path: result.metadata.file_path.clone(),
+            url: result.metadata.url.clone(),
+            distance: result.distance,
+            citation: result.citation.clone(),
+        }
+    }
+}
+
+/// Format retrieved documents into a prompt with numbered source attributions
+pub fn build_rag_prompt(query: &str, results: &[QueryResult]) -> String {
+    if results.is_empty() {
+        return query.to_string();
+    }
+    
+    let mut prompt = String::from("Based on the following relevant information:\n\n");
+    for (i, result) in results.iter().enumerate() {
+        prompt.push_str(&format!("[{}] {}\n", i + 1, result.citation.label));
+        prompt.push_str(&result.document);
+        prompt.push_str("\n\n");
+    }
+    prompt.push_str("Cite sources as [N] where relevant.\n\n");
+    prompt.push_str(&format!("User Question: {}\n\nAnswer:", query));
+    prompt
+}
+
+/// Query a collection and build the grounded prompt
+pub fn retrieve_rag_context(
+    manager: &mut ChromaManager,
+    query: &str,
+    collection: &str,
+    n_results: usize,
+) -> Result<(String, Vec<RagSource>), String> {
+    let results = manager.query(collection, query, n_results, None)
+        .map_err(|e| format!("Failed to query collection {}: {}", collection, e))?;
+    
+    let sources = results.iter().map(RagSource::from).collect();
+    Ok((build_rag_prompt(query, &results), sources))
+}
+
+/// Retrieve context from Chroma then generate a (non-streaming) answer. The Chroma
+/// lock is released before generation starts.
+pub async fn rag_generate(
+    client: &OllamaClient,
+    chroma_manager: &Mutex<ChromaManager>,
+    query: &str,
+    collection: &str,
+    n_results: usize,
+    model: &str,
+) -> Result<RagGenerationResponse, String> {
+    let (prompt, sources) = {
+        let mut manager = chroma_manager.lock().await;
+        retrieve_rag_context(&mut manager, query, collection, n_results)?
+    };
+    
+    let answer = client.generate_completion(model, &prompt, None)
+        .await
+        .map_err(|e| format!("Failed to generate answer: {}", e))?;
+    
+    Ok(RagGenerationResponse {
+        answer,
+        sources,
+        model: model.to_string(),
+    })
+}
+
+/// Latency percentiles for one RAG stage across the benchmark queries
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageLatency {
+    pub samples: usize,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+}
+
+impl StageLatency {
+    /// Nearest-rank percentiles over `samples`; all zero when empty
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        let mut millis: Vec<f64> = samples.iter().map(|sample| sample.as_secs_f64() * 1000.0).collect();
+        millis.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        
+        let percentile = |p: f64| -> f64 {
+            if millis.is_empty() {
+                return 0.0;
+            }
+            let rank = ((p / 100.0) * millis.len() as f64).ceil() as usize;
+            millis[rank.clamp(1, millis.len()) - 1]
+        };
+        let mean_ms = if millis.is_empty() {
+            0.0
+        } else {
+            millis.iter().sum::<f64>() / millis.len() as f64
+        };
+        
+        Self {
+            samples: millis.len(),
+            mean_ms,
+            p50_ms: percentile(50.0),
+            p95_ms: percentile(95.0),
+            p99_ms: percentile(99.0),
+        }
+    }
+}
+
+/// Per-stage latency of the RAG pipeline, from `benchmark_rag`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagBenchmarkReport {
+    pub collection: String,
+    pub model: String,
+    pub embedding_model: String,
+    pub queries: usize,
+    pub embedding: StageLatency,
+    pub retrieval: StageLatency,
+    pub generation: StageLatency,
+}
+
+/// Time embedding, retrieval and generation for each sample query. Retrieval
+/// bypasses the query cache so repeated queries measure the real search.
+pub async fn run_rag_benchmark(
+    client: &OllamaClient,
+    chroma_manager: &Mutex<ChromaManager>,
+    collection: &str,
+    sample_queries: &[String],
+    model: &str,
+    embedding_model: &str,
+) -> Result<RagBenchmarkReport, String> {
+    if sample_queries.is_empty() {
+        return Err("At least one sample query is required".to_string());
+    }
+    
+    // Queries are embedded the way query_chroma embeds them, through the priority gate
+    let embedder = chroma_manager.lock().await.query_embedder(collection)
+        .map_err(|e| format!("Failed to create embedding: {}", e))?
+        .with_model(embedding_model);
+    let mut embedding = Vec::with_capacity(sample_queries.len());
+    let mut retrieval = Vec::with_capacity(sample_queries.len());
+    let mut generation = Vec::with_capacity(sample_queries.len());
+    
+    for query in sample_queries {
+        let started = std::time::Instant::now();
+        embedder.embed_query(query, TaskPriority::High)
+            .await
+            .map_err(|e| format!("Failed to create embedding: {}", e))?;
+        embedding.push(started.elapsed());
+        
+        let started = std::time::Instant::now();
+        let prompt = {
+            let mut manager = chroma_manager.lock().await;
+            let results = manager.query_without_cache(collection, query, 3, None)
+                .map_err(|e| format!("Failed to query collection {}: {}", collection, e))?;
+            build_rag_prompt(query, &results)
+        };
+        retrieval.push(started.elapsed());
+        
+        let started = std::time::Instant::now();
+        client.generate_completion(model, &prompt, None)
+            .await
+            .map_err(|e| format!("Failed to generate answer: {}", e))?;
+        generation.push(started.elapsed());
+    }
+    
+    Ok(RagBenchmarkReport {
+        collection: collection.to_string(),
+        model: model.to_string(),
+        embedding_model: embedding_model.to_string(),
+        queries: sample_queries.len(),
+        embedding: StageLatency::from_samples(&embedding),
+        retrieval: StageLatency::from_samples(&retrieval),
+        generation: StageLatency::from_samples(&generation),
+    })
+}
+
+/// One-shot end-to-end RAG latency benchmark using the configured embedding model
+#[tauri::command]
+pub async fn benchmark_rag(
+    collection: String,
+    sample_queries: Vec<String>,
+    model: String,
+    ollama_client: State<'_, OllamaClient>,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+    settings_store: State<'_, Mutex<SettingsStore>>,
+) -> Result<RagBenchmarkReport, String> {
+    let embedding_model = settings_store.lock().await.get().ollama.embedding_model.clone();
+    run_rag_benchmark(ollama_client.inner(), &chroma_manager, &collection, &sample_queries, &model, &embedding_model).await
+}
+
+/// Retrieval-augmented generation in one call: query the collection, ground the
+/// prompt on the retrieved documents, generate, and return the answer with its sources.
+/// With `stream`, tokens are also emitted as `ollama-stream` events.
+#[tauri::command]
+pub async fn generate_with_rag(
+    query: String,
+    collection: String,
+    n_results: Option<usize>,
+    model: String,
+    stream: Option<bool>,
+    app_handle: AppHandle,
+    ollama_client: State<'_, OllamaClient>,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+) -> Result<RagGenerationResponse, String> {
+    let client = ollama_client.inner();
+    let n_results = n_results.unwrap_or(3);
+    
+    if !stream.unwrap_or(false) {
+        return rag_generate(client, &chroma_manager, &query, &collection, n_results, &model).await;
     }
+    
+    let (prompt, sources) = {
+        let mut manager = chroma_manager.lock().await;
+        retrieve_rag_context(&mut manager, &query, &collection, n_results)?
+    };
+    
+    let _ = app_handle.emit("rag-context", serde_json::json!({
+        "documents_used": sources.len(),
+        "collection": collection,
+        "sources": sources
+    }));
+    
+    let answer = Arc::new(std::sync::Mutex::new(String::new()));
+    let answer_clone = answer.clone();
+    let app_handle_clone = app_handle.clone();
+    
+    client
+        .generate_stream(&model, &prompt, None, move |token: &str| {
+            if let Ok(mut answer) = answer_clone.lock() {
+                answer.push_str(token);
+            }
+            emit_event(&app_handle_clone, GenerationDelta::token(token));
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    emit_event(&app_handle, GenerationDelta::done(None));
+    
+    let answer = answer.lock().map(|answer| answer.clone()).unwrap_or_default();
+    Ok(RagGenerationResponse {
+        answer,
+        sources,
+        model,
+    })
 }
 
 /// Helper function for standard streaming generation
@@ -429,6 +843,7 @@ async fn standard_streaming_generation(
     prompt: &str,
     temperature: Option<f32>,
     app_handle: AppHandle,
+    generation: Option<ActiveGeneration>,
 ) -> Result<(), String> {
     let options = GenerateOptions {
         temperature,
@@ -438,30 +853,39 @@ async fn standard_streaming_generation(
     };
     
     let app_handle_clone = app_handle.clone();
+    let stats_handle = app_handle.clone();
     
     client
-        .generate_stream(
+        .generate_stream_with_stats(
             model,
             prompt,
             Some(options),
             move |token: &str| {
-                let _ = app_handle_clone.emit("ollama-stream", serde_json::json!({
-                    "token": token,
-                    "done": false
-                }));
+                emit_event(&app_handle_clone, GenerationDelta::token(token));
             },
+            Some(move |stats: BufferStats| {
+                // Live buffer usage plus tokens/sec and time-to-first-token
+                emit_event(&stats_handle, stats);
+            }),
+            generation,
         )
         .await
-        .map(|_| {
-            // Emit completion event
-            let _ = app_handle.emit("ollama-stream", serde_json::json!({
-                "token": "",
-                "done": true
-            }));
+        .map(|summary| {
+            // Emit completion event with the final generation metrics
+            emit_event(&app_handle, GenerationDelta::done(Some(summary)));
         })
         .map_err(|e| e.to_string())
 }
 
+/// Latest buffer and queue usage of a generation still streaming, None once it has finished
+#[tauri::command]
+pub fn get_generation_buffer_stats(
+    request_id: String,
+    active_generations: State<'_, ActiveGenerations>,
+) -> Option<BufferStats> {
+    active_generations.get(&request_id)
+}
+
 #[tauri::command]
 pub async fn enqueue_operation(
     operation: Operation,
@@ -486,6 +910,32 @@ pub fn cancel_operation(
     op_manager.cancel_operation(&operation_id)
 }
 
+/// Queued, running and finished operations matching `filter`
+#[tauri::command]
+pub fn list_operations(
+    filter: Option<OperationFilter>,
+    op_manager: State<'_, crate::operation_manager::OperationManager>,
+) -> Vec<OperationSummary> {
+    op_manager.list_operations(&filter.unwrap_or_default())
+}
+
+/// Cancel queued and running operations of one type; returns how many were cancelled
+#[tauri::command]
+pub fn cancel_operations_by_type(
+    op_type: OperationType,
+    op_manager: State<'_, crate::operation_manager::OperationManager>,
+) -> usize {
+    op_manager.cancel_operations_by_type(&op_type)
+}
+
+/// Cancel all queued and running operations; returns how many were cancelled
+#[tauri::command]
+pub fn cancel_all_operations(
+    op_manager: State<'_, crate::operation_manager::OperationManager>,
+) -> usize {
+    op_manager.cancel_all_operations()
+}
+
 // Health monitoring commands
 
 #[tauri::command]
@@ -496,6 +946,277 @@ pub async fn get_ollama_health_stats(
     Ok(client.get_health_stats().await)
 }
 
+/// Requests in flight and queued for Ollama, and the concurrency cap now in effect
+#[tauri::command]
+pub async fn get_ollama_rate_limit_stats(
+    ollama_client: State<'_, OllamaClient>,
+) -> Result<RateLimitStats, String> {
+    Ok(ollama_client.rate_limit_stats())
+}
+
+/// Whether any models are installed; with none, AI features are unavailable until one is pulled
+#[tauri::command]
+pub async fn check_model_availability(
+    ollama_client: State<'_, OllamaClient>,
+) -> Result<ModelAvailability, String> {
+    match ollama_client.check_model_availability().await {
+        Ok(availability) => Ok(availability),
+        Err(e) => {
+            let user_error = e.to_user_error();
+            Err(serde_json::to_string(&user_error).unwrap_or_else(|_| user_error.message))
+        }
+    }
+}
+
+/// Recent health samples and uptime for "ollama", "chroma" or "searxng"
+#[tauri::command]
+pub async fn get_health_history(
+    service: String,
+    ollama_client: State<'_, OllamaClient>,
+    chroma_status: State<'_, ChromaStatus>,
+    searxng_client: State<'_, SearXNGClient>,
+) -> Result<HealthHistoryReport, String> {
+    match service.as_str() {
+        "ollama" => Ok(ollama_client.get_health_history().await),
+        "chroma" => Ok(chroma_status.get_health_history().await),
+        "searxng" => Ok(searxng_client.get_health_history().await),
+        _ => Err(format!("Unknown service: {}", service)),
+    }
+}
+
+/// Measured memory use across the in-process stores
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryReport {
+    pub collections: Vec<CollectionMemoryUsage>,
+    pub collections_bytes: usize,
+    pub query_cache_bytes: usize,
+    pub context_bytes: usize,
+    pub total_bytes: usize,
+}
+
+#[tauri::command]
+pub async fn get_memory_report(
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+    context_manager: State<'_, ContextManager>,
+) -> Result<MemoryReport, String> {
+    let (collections, query_cache_bytes) = {
+        let manager = chroma_manager.lock().await;
+        (manager.collection_memory_usage(), manager.cache_memory_usage())
+    };
+    let collections_bytes = collections.iter().map(|collection| collection.bytes).sum();
+    let context_bytes = context_manager.memory_usage_bytes().await;
+
+    Ok(MemoryReport {
+        collections,
+        collections_bytes,
+        query_cache_bytes,
+        context_bytes,
+        total_bytes: collections_bytes + query_cache_bytes + context_bytes,
+    })
+}
+
+/// Distinct `document_type` values present in a collection
+#[tauri::command]
+pub async fn list_document_types(
+    collection: String,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+) -> Result<Vec<String>, String> {
+    Ok(chroma_manager.lock().await.list_document_types(&collection))
+}
+
+/// Add documents to a collection, chunking those longer than the chunking settings allow
+#[tauri::command]
+pub async fn add_documents_to_chroma(
+    request: AddDocumentsRequest,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+) -> Result<Vec<String>, String> {
+    ingest_documents(&chroma_manager, request).await
+}
+
+pub async fn ingest_documents(
+    chroma_manager: &Mutex<ChromaManager>,
+    request: AddDocumentsRequest,
+) -> Result<Vec<String>, String> {
+    chroma_manager.lock().await
+        .ingest_documents(&request.collection_name, request.documents, request.metadatas, request.ids)
+        .map_err(|e| format!("Failed to add documents: {}", e))
+}
+
+/// Query a collection in the requested search mode, embedding the query text with
+/// the collection's model for semantic and hybrid searches
+#[tauri::command]
+pub async fn query_chroma(
+    request: QueryRequest,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+) -> Result<Vec<QueryResult>, String> {
+    query_collection(&chroma_manager, request).await
+}
+
+pub async fn query_collection(
+    chroma_manager: &Mutex<ChromaManager>,
+    request: QueryRequest,
+) -> Result<Vec<QueryResult>, String> {
+    let mode = request.search_mode.unwrap_or_default();
+    let query_embedding = if mode == SearchMode::Keyword {
+        None
+    } else {
+        // The embedding waits its turn at the requester's priority without holding the manager
+        let embedder = chroma_manager.lock().await.query_embedder(&request.collection_name)
+            .map_err(|e| format!("Failed to embed query: {}", e))?;
+        let priority = request.priority.as_ref().map_or(TaskPriority::High, TaskPriority::from);
+        let embedding = embedder.embed_query(&request.query_text, priority).await
+            .map_err(|e| format!("Failed to embed query: {}", e))?;
+        Some(embedding)
+    };
+
+    let mut manager = chroma_manager.lock().await;
+    let results = if request.explain.unwrap_or(false) {
+        manager.explain_search(&request.collection_name, &request.query_text, query_embedding.as_deref(), request.n_results, request.filter, mode)
+    } else {
+        manager.search(&request.collection_name, &request.query_text, query_embedding.as_deref(), request.n_results, request.filter, mode)
+    };
+    results.map_err(|e| format!("Failed to query collection: {}", e))
+}
+
+/// Remove duplicate and orphaned documents from a collection
+#[tauri::command]
+pub async fn compact_chroma_collection(
+    collection_name: String,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+) -> Result<CompactionStats, String> {
+    compact_collection(&chroma_manager, &collection_name).await
+}
+
+pub async fn compact_collection(
+    chroma_manager: &Mutex<ChromaManager>,
+    collection_name: &str,
+) -> Result<CompactionStats, String> {
+    chroma_manager.lock().await
+        .compact_collection(collection_name)
+        .await
+        .map_err(|e| format!("Failed to compact collection: {}", e))
+}
+
+/// Embed a collection with `embedding_model` instead of the default model
+#[tauri::command]
+pub async fn set_collection_embedding_model(
+    collection_name: String,
+    embedding_model: Option<String>,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+) -> Result<(), String> {
+    chroma_manager.lock().await.set_collection_embedding_model(&collection_name, embedding_model);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_collection_metadata(
+    collection_name: String,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+) -> Result<CollectionMetadata, String> {
+    collection_metadata(&chroma_manager, &collection_name).await
+}
+
+pub async fn collection_metadata(
+    chroma_manager: &Mutex<ChromaManager>,
+    collection_name: &str,
+) -> Result<CollectionMetadata, String> {
+    chroma_manager.lock().await
+        .get_collection_metadata(collection_name)
+        .ok_or_else(|| format!("Collection {} not found", collection_name))
+}
+
+/// Change how a collection stores its embeddings, converting those already stored
+#[tauri::command]
+pub async fn set_collection_embedding_quantization(
+    collection_name: String,
+    quantization: EmbeddingQuantization,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+) -> Result<(), String> {
+    set_embedding_quantization(&chroma_manager, &collection_name, quantization).await
+}
+
+pub async fn set_embedding_quantization(
+    chroma_manager: &Mutex<ChromaManager>,
+    collection_name: &str,
+    quantization: EmbeddingQuantization,
+) -> Result<(), String> {
+    chroma_manager.lock().await
+        .set_collection_quantization(collection_name, quantization)
+        .map_err(|e| format!("Failed to set embedding quantization: {}", e))
+}
+
+/// Back up every collection and the storage settings to a single file
+#[tauri::command]
+pub async fn create_chroma_snapshot(
+    path: String,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+) -> Result<(), String> {
+    chroma_manager.lock().await
+        .create_snapshot(std::path::Path::new(&path))
+        .map(|_| ())
+        .map_err(|e| format!("Failed to create snapshot: {}", e))
+}
+
+/// Write a collection to an NDJSON file, one document per line; returns the count
+#[tauri::command]
+pub async fn export_collection_ndjson(
+    collection: String,
+    path: String,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+) -> Result<usize, String> {
+    chroma_manager.lock().await
+        .export_collection_ndjson(&collection, std::path::Path::new(&path))
+        .map_err(|e| format!("Failed to export collection: {}", e))
+}
+
+/// Stream an NDJSON export into a collection, keeping its embeddings; returns the count
+#[tauri::command]
+pub async fn import_collection_ndjson(
+    collection: String,
+    path: String,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+) -> Result<usize, String> {
+    chroma_manager.lock().await
+        .import_collection_ndjson(&collection, std::path::Path::new(&path))
+        .map_err(|e| format!("Failed to import collection: {}", e))
+}
+
+/// Replace all collections and storage settings with a snapshot's
+#[tauri::command]
+pub async fn restore_chroma_snapshot(
+    path: String,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+) -> Result<(), String> {
+    chroma_manager.lock().await
+        .restore_snapshot(std::path::Path::new(&path))
+        .map_err(|e| format!("Failed to restore snapshot: {}", e))
+}
+
+/// Probe the embedding model, defaulting to the one configured in settings
+#[tauri::command]
+pub async fn check_ollama_embedding_health(
+    model: Option<String>,
+    expected_dimension: Option<usize>,
+    ollama_client: State<'_, OllamaClient>,
+    settings_store: State<'_, Mutex<SettingsStore>>,
+) -> Result<EmbeddingHealthStats, String> {
+    let model = match model {
+        Some(model) => model,
+        None => settings_store.lock().await.get().ollama.embedding_model.clone(),
+    };
+    
+    let client = ollama_client.inner();
+    client.check_embedding_health(&model, expected_dimension).await;
+    Ok(client.get_embedding_health_stats().await)
+}
+
+#[tauri::command]
+pub async fn get_ollama_split_health(
+    ollama_client: State<'_, OllamaClient>,
+) -> Result<SplitHealthStats, String> {
+    Ok(ollama_client.inner().get_split_health_stats().await)
+}
+
 #[tauri::command]
 pub async fn check_ollama_health(
     ollama_client: State<'_, OllamaClient>,
@@ -3,15 +3,27 @@ use std::collections::HashMap;
 use std::path::Path;
 use tauri::State;
 use tokio::sync::RwLock;
+use crate::float_order;
+use crate::history_manager::{ChatMessage, ChatSession, SharedHistoryManager};
 
 /// Conservative token estimation multiplier for safety margin
 const TOKEN_SAFETY_MULTIPLIER: f32 = 1.2;
 
+/// Section headers `fit_prompt` adds around RAG documents and history
+const RAG_HEADER: &str = "Based on the following relevant information:";
+const HISTORY_HEADER: &str = "Conversation so far:";
+
+fn document_label(number: usize) -> String {
+    format!("[Document {}]", number)
+}
+
 /// Context Manager handles token budget allocation and context building
 #[derive(Debug)]
 pub struct ContextManager {
     pub max_tokens: usize,
     pub reserved_tokens: usize,
+    /// A session whose history needs more tokens than this is represented by its summary
+    pub summary_threshold_tokens: usize,
     pub pinned_files: RwLock<Vec<String>>,
     pub token_cache: RwLock<HashMap<String, usize>>,
 }
@@ -53,12 +65,34 @@ pub struct BuiltContext {
     pub budget: ContextBudget,
 }
 
+/// Prompt sections that can be trimmed to fit a model's context window
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PromptSections {
+    pub system: Option<String>,
+    /// Conversation history, oldest first
+    pub history: Vec<String>,
+    /// Retrieved documents, most relevant first
+    pub rag_documents: Vec<String>,
+    pub user: String,
+}
+
+/// Result of fitting prompt sections into a context window
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AssembledPrompt {
+    pub prompt: String,
+    pub total_tokens: usize,
+    pub dropped_history: usize,
+    pub dropped_documents: usize,
+    pub system_truncated: bool,
+}
+
 impl ContextManager {
     /// Create a new ContextManager with default settings
     pub fn new(max_tokens: usize, reserved_tokens: usize) -> Self {
         Self {
             max_tokens,
             reserved_tokens,
+            summary_threshold_tokens: max_tokens.saturating_sub(reserved_tokens) / 2,
             pinned_files: RwLock::new(Vec::new()),
             token_cache: RwLock::new(HashMap::new()),
         }
@@ -98,13 +132,129 @@ impl ContextManager {
 
     /// Count tokens in text using conservative estimation
     pub fn count_tokens(&self, text: &str) -> usize {
+        Self::estimate_tokens(text)
+    }
+
+    /// Token estimate for callers that don't hold a ContextManager
+    pub fn estimate_tokens(text: &str) -> usize {
         // Simple word-based estimation with safety multiplier
         // In production, this should use the actual model's tokenizer
-        let word_count = text.split_whitespace().count();
+        Self::tokens_for_words(text.split_whitespace().count())
+    }
+
+    fn tokens_for_words(word_count: usize) -> usize {
         let estimated_tokens = (word_count as f32 * 1.3) as usize; // ~1.3 tokens per word average
         (estimated_tokens as f32 * TOKEN_SAFETY_MULTIPLIER) as usize
     }
 
+    /// Assemble system + history + RAG + user sections into a single prompt that fits
+    /// within `max_tokens`, keeping `reserved_tokens` free for the completion.
+    ///
+    /// The user message is never trimmed. The system prompt is truncated if needed, then
+    /// RAG documents are kept in relevance order and history is kept newest first.
+    pub fn fit_prompt(&self, sections: &PromptSections) -> Result<AssembledPrompt, String> {
+        // Budgeted in words of the assembled prompt, headers included, so its estimate
+        // is exactly what the sections were charged
+        let budget = self.max_tokens.saturating_sub(self.reserved_tokens);
+        let words = |text: &str| text.split_whitespace().count();
+        let fits = |word_count: usize| Self::tokens_for_words(word_count) <= budget;
+
+        let user_words = words(&sections.user);
+        if !fits(user_words) {
+            return Err(format!(
+                "User message needs ~{} tokens but only {} are available in the context window",
+                Self::tokens_for_words(user_words), budget
+            ));
+        }
+        let mut used = user_words;
+
+        // System prompt, truncated word-wise if it doesn't fit
+        let mut system_truncated = false;
+        let system = sections.system.as_ref().and_then(|system| {
+            let system_words: Vec<&str> = system.split_whitespace().collect();
+            let mut keep = system_words.len();
+            while keep > 0 && !fits(used + keep) {
+                keep -= 1;
+            }
+            system_truncated = keep < system_words.len();
+            used += keep;
+            if keep == 0 { None } else if system_truncated { Some(system_words[..keep].join(" ")) } else { Some(system.clone()) }
+        });
+
+        // RAG documents in relevance order; the first also pays for the section header
+        let mut documents = Vec::new();
+        for document in &sections.rag_documents {
+            let header = if documents.is_empty() { words(RAG_HEADER) } else { 0 };
+            let needed = header + words(&document_label(documents.len() + 1)) + words(document);
+            if !fits(used + needed) {
+                break;
+            }
+            used += needed;
+            documents.push(document.as_str());
+        }
+
+        // History, newest first, restored to chronological order afterwards
+        let mut history = Vec::new();
+        for message in sections.history.iter().rev() {
+            let header = if history.is_empty() { words(HISTORY_HEADER) } else { 0 };
+            let needed = header + words(message);
+            if !fits(used + needed) {
+                break;
+            }
+            used += needed;
+            history.push(message.as_str());
+        }
+        history.reverse();
+
+        let mut prompt = String::new();
+        if let Some(system) = &system {
+            prompt.push_str(system);
+            prompt.push_str("\n\n");
+        }
+        if !documents.is_empty() {
+            prompt.push_str(RAG_HEADER);
+            prompt.push_str("\n\n");
+            for (i, document) in documents.iter().enumerate() {
+                prompt.push_str(&format!("{}\n{}\n\n", document_label(i + 1), document));
+            }
+        }
+        if !history.is_empty() {
+            prompt.push_str(HISTORY_HEADER);
+            prompt.push('\n');
+            for message in &history {
+                prompt.push_str(message);
+                prompt.push('\n');
+            }
+            prompt.push('\n');
+        }
+        prompt.push_str(&sections.user);
+
+        Ok(AssembledPrompt {
+            prompt,
+            total_tokens: Self::tokens_for_words(used),
+            dropped_history: sections.history.len() - history.len(),
+            dropped_documents: sections.rag_documents.len() - documents.len(),
+            system_truncated,
+        })
+    }
+
+    /// A session's conversation as `role: content` lines. Past `summary_threshold_tokens`
+    /// the session's summary stands in for the messages it covers.
+    pub fn session_conversation(&self, session: &ChatSession) -> String {
+        let line = |message: &ChatMessage| format!("{}: {}\n", message.role, message.content);
+        let full: String = session.messages.iter().map(line).collect();
+        match &session.summary {
+            Some(summary) if self.count_tokens(&full) > self.summary_threshold_tokens => {
+                let mut conversation = format!("Summary of the earlier conversation:\n{}\n\n", summary.content);
+                for message in session.messages.iter().filter(|message| message.sequence > summary.covers_through) {
+                    conversation.push_str(&line(message));
+                }
+                conversation
+            }
+            _ => full,
+        }
+    }
+
     /// Count tokens in a file and cache the result
     pub async fn count_file_tokens(&self, file_path: &str) -> Result<usize, String> {
         // Check cache first
@@ -231,8 +381,8 @@ impl ContextManager {
             }
         }
         
-        // Sort by relevance score (highest first)
-        context_files.sort_by(|a, b| b.relevance_score.partial_cmp(&a.relevance_score).unwrap_or(std::cmp::Ordering::Equal));
+        // Sort by relevance score (highest first, NaN last)
+        context_files.sort_by(|a, b| float_order::descending_nan_last(a.relevance_score, b.relevance_score));
         
         let total_tokens: usize = context_files.iter().map(|f| f.token_count).sum();
         
@@ -253,6 +403,16 @@ impl ContextManager {
     pub async fn get_pinned_files(&self) -> Vec<String> {
         self.pinned_files.read().await.clone()
     }
+
+    /// Bytes held by pinned file paths and the token cache
+    pub async fn memory_usage_bytes(&self) -> usize {
+        let pinned: usize = self.pinned_files.read().await.iter().map(String::len).sum();
+        let cached: usize = self.token_cache.read().await
+            .keys()
+            .map(|path| path.len() + std::mem::size_of::<usize>())
+            .sum();
+        pinned + cached
+    }
 }
 
 impl Default for ContextManager {
@@ -305,14 +465,25 @@ pub async fn calculate_file_relevance(
     Ok(relevance)
 }
 
-/// Tauri command to build context
+/// Tauri command to build context; with a `session_id` the conversation is taken from
+/// that session, summarized when it's long
 #[tauri::command]
 pub async fn build_context(
     manager: State<'_, ContextManager>,
-    conversation_context: String,
+    history_manager: State<'_, SharedHistoryManager>,
+    conversation_context: Option<String>,
+    session_id: Option<String>,
     rag_tokens: Option<usize>,
     suggested_files: Vec<String>,
 ) -> Result<BuiltContext, String> {
+    let conversation_context = match session_id {
+        Some(session_id) => {
+            let session = history_manager.lock().await.get_session(&session_id)
+                .ok_or_else(|| format!("Session not found: {}", session_id))?;
+            manager.session_conversation(&session)
+        }
+        None => conversation_context.unwrap_or_default(),
+    };
     manager.build_context(
         &conversation_context,
         rag_tokens.unwrap_or(0),
@@ -0,0 +1,307 @@
+//! Purging stored user data by scope, on request or by age
+//!
+//! Every purge can be run as a dry run that only counts what would be deleted. A real
+//! purge from the UI has to be confirmed; the maintenance scheduler runs age-based
+//! purges of the scopes configured in `MaintenanceSettings`.
+
+use crate::analysis_engine::REASONING_PATTERNS_COLLECTION;
+use crate::chroma_manager::ChromaManager;
+use crate::code_analysis::CodeAnalysisService;
+use crate::extraction_cache::ExtractionCache;
+use crate::history_manager::{HistoryManager, SharedHistoryManager};
+pub use crate::settings_store::DataScope;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tauri::State;
+use tokio::sync::Mutex;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScopePurge {
+    pub scope: DataScope,
+    /// Sessions, documents, cache entries or log files deleted, or that would be
+    pub items: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PurgeReport {
+    pub dry_run: bool,
+    pub older_than: Option<DateTime<Utc>>,
+    pub scopes: Vec<ScopePurge>,
+}
+
+/// The stores a purge can clear
+pub struct PurgeTargets<'a> {
+    pub history: &'a mut HistoryManager,
+    pub chroma: &'a mut ChromaManager,
+    pub extraction_cache: Option<&'a ExtractionCache>,
+    /// The interaction log; its rotated files `<name>.1`, `<name>.2`, ... go with it
+    pub interaction_log_path: &'a Path,
+}
+
+/// Where the interaction log is written by default, as in `InteractionLogConfig`
+pub fn default_interaction_log_path() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("gerdsenai-socrates")
+        .join("logs")
+        .join("interactions.log")
+}
+
+/// Clear `scopes`, keeping data from `older_than` onwards when given. A dry run
+/// reports the same counts without deleting anything.
+pub fn purge(
+    targets: &mut PurgeTargets,
+    scopes: &[DataScope],
+    older_than: Option<DateTime<Utc>>,
+    dry_run: bool,
+) -> Result<PurgeReport, String> {
+    let mut report = PurgeReport {
+        dry_run,
+        older_than,
+        scopes: Vec::new(),
+    };
+    for &scope in scopes {
+        if report.scopes.iter().any(|purged| purged.scope == scope) {
+            continue;
+        }
+        let items = match scope {
+            DataScope::ChatHistory => targets.history.purge_sessions(older_than, dry_run).map_err(|e| e.to_string())?,
+            DataScope::RagCollections => {
+                let mut items = 0;
+                for name in targets.chroma.list_collections().map_err(|e| e.to_string())? {
+                    if name != REASONING_PATTERNS_COLLECTION {
+                        items += targets.chroma.purge_collection(&name, older_than, dry_run).map_err(|e| e.to_string())?;
+                    }
+                }
+                items
+            }
+            DataScope::ReasoningPatterns => targets.chroma
+                .purge_collection(REASONING_PATTERNS_COLLECTION, older_than, dry_run)
+                .map_err(|e| e.to_string())?,
+            DataScope::Caches => {
+                let mut items = match targets.extraction_cache {
+                    Some(cache) => cache.purge(older_than.map(SystemTime::from), dry_run).map_err(|e| e.to_string())?,
+                    None => 0,
+                };
+                // Cached query results expire on their own, so only a full purge clears them
+                if older_than.is_none() {
+                    items += if dry_run {
+                        targets.chroma.get_cache_stats().total_entries
+                    } else {
+                        targets.chroma.clear_cache()
+                    };
+                }
+                items
+            }
+            DataScope::InteractionLogs => purge_log_files(targets.interaction_log_path, older_than.map(SystemTime::from), dry_run)
+                .map_err(|e| format!("Failed to purge interaction logs: {}", e))?,
+        };
+        report.scopes.push(ScopePurge { scope, items });
+    }
+    Ok(report)
+}
+
+/// Delete the log at `log_path` and its rotated files, only those last written before
+/// `older_than` when given
+fn purge_log_files(log_path: &Path, older_than: Option<SystemTime>, dry_run: bool) -> std::io::Result<usize> {
+    let (Some(dir), Some(name)) = (log_path.parent(), log_path.file_name()) else {
+        return Ok(0);
+    };
+    let name = name.to_string_lossy();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let mut purged = 0;
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let is_log = file_name == name
+            || file_name.strip_prefix(name.as_ref())
+                .and_then(|suffix| suffix.strip_prefix('.'))
+                .is_some_and(|index| index.parse::<usize>().is_ok());
+        if !is_log {
+            continue;
+        }
+        let modified = entry.metadata()?.modified()?;
+        if older_than.map_or(true, |cutoff| modified < cutoff) {
+            if !dry_run {
+                fs::remove_file(entry.path())?;
+            }
+            purged += 1;
+        }
+    }
+    Ok(purged)
+}
+
+/// Cutoff for purging data older than `days`
+pub fn cutoff_for_days(days: u64) -> DateTime<Utc> {
+    Utc::now() - Duration::days(days as i64)
+}
+
+/// Purge the selected scopes. Without `dry_run` the purge must be confirmed; a dry run
+/// returns the counts that confirming would delete.
+#[tauri::command]
+pub async fn purge_data(
+    scopes: Vec<DataScope>,
+    dry_run: Option<bool>,
+    confirm: Option<bool>,
+    older_than_days: Option<u64>,
+    history_manager: State<'_, SharedHistoryManager>,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+    code_analysis_service: State<'_, Arc<CodeAnalysisService>>,
+) -> Result<PurgeReport, String> {
+    let dry_run = dry_run.unwrap_or(false);
+    if scopes.is_empty() {
+        return Err("Select at least one scope to purge".to_string());
+    }
+    if !dry_run && confirm != Some(true) {
+        return Err("Purging permanently deletes data; run a dry run to see what would be removed, then confirm".to_string());
+    }
+
+    let mut history = history_manager.lock().await;
+    let mut chroma = chroma_manager.lock().await;
+    let log_path = default_interaction_log_path();
+    let mut targets = PurgeTargets {
+        history: &mut history,
+        chroma: &mut chroma,
+        extraction_cache: code_analysis_service.extraction_cache(),
+        interaction_log_path: &log_path,
+    };
+    purge(&mut targets, &scopes, older_than_days.map(cutoff_for_days), dry_run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chroma_manager::DocumentMetadata;
+    use crate::code_analysis::{Import, Symbol};
+    use std::collections::HashMap;
+
+    fn metadata(timestamp: &str) -> DocumentMetadata {
+        DocumentMetadata {
+            source: "test".to_string(),
+            document_type: "documentation".to_string(),
+            language: None,
+            timestamp: timestamp.to_string(),
+            file_path: None,
+            url: None,
+            title: None,
+            additional: HashMap::new(),
+        }
+    }
+
+    struct Stores {
+        dir: tempfile::TempDir,
+        history: HistoryManager,
+        chroma: ChromaManager,
+        cache: ExtractionCache,
+    }
+
+    impl Stores {
+        fn new() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let mut history = HistoryManager::load(dir.path().join("history")).unwrap();
+            history.create_session("First", "llama3").unwrap();
+            history.create_session("Second", "llama3").unwrap();
+
+            let mut chroma = ChromaManager::new(dir.path().join("chroma").to_str().unwrap()).unwrap();
+            chroma.add_documents(
+                "docs",
+                vec!["old".to_string(), "new".to_string()],
+                vec![metadata("2020-01-01T00:00:00Z"), metadata(&Utc::now().to_rfc3339())],
+                None,
+            ).unwrap();
+            chroma.add_documents(REASONING_PATTERNS_COLLECTION, vec!["pattern".to_string()], vec![metadata("2020-01-01T00:00:00Z")], None).unwrap();
+
+            let cache = ExtractionCache::new(dir.path().join("extraction_cache"));
+            cache.put("/repo/a.rs", "hash", &Vec::<Symbol>::new(), &Vec::<Import>::new()).unwrap();
+
+            let logs = dir.path().join("logs");
+            fs::create_dir_all(&logs).unwrap();
+            for name in ["interactions.log", "interactions.log.1", "other.log"] {
+                fs::write(logs.join(name), "{}").unwrap();
+            }
+            Self { dir, history, chroma, cache }
+        }
+
+        fn purge(&mut self, scopes: &[DataScope], older_than: Option<DateTime<Utc>>, dry_run: bool) -> PurgeReport {
+            let log_path = self.dir.path().join("logs").join("interactions.log");
+            let mut targets = PurgeTargets {
+                history: &mut self.history,
+                chroma: &mut self.chroma,
+                extraction_cache: Some(&self.cache),
+                interaction_log_path: &log_path,
+            };
+            purge(&mut targets, scopes, older_than, dry_run).unwrap()
+        }
+
+        /// Sessions, RAG documents, reasoning patterns, cache entries and log files left
+        fn remaining(&mut self) -> [usize; 5] {
+            let logs = fs::read_dir(self.dir.path().join("logs")).unwrap().count() - 1;
+            [
+                self.history.list_sessions(true).len(),
+                self.chroma.count("docs").unwrap(),
+                self.chroma.count(REASONING_PATTERNS_COLLECTION).unwrap(),
+                self.cache.stats().unwrap().entries,
+                logs,
+            ]
+        }
+    }
+
+    const ALL_SCOPES: [DataScope; 5] = [
+        DataScope::ChatHistory,
+        DataScope::RagCollections,
+        DataScope::ReasoningPatterns,
+        DataScope::Caches,
+        DataScope::InteractionLogs,
+    ];
+
+    #[test]
+    fn test_dry_run_counts_without_deleting() {
+        let mut stores = Stores::new();
+        let report = stores.purge(&ALL_SCOPES, None, true);
+
+        assert!(report.dry_run);
+        let counts: Vec<(DataScope, usize)> = report.scopes.iter().map(|purged| (purged.scope, purged.items)).collect();
+        assert_eq!(counts, vec![
+            (DataScope::ChatHistory, 2),
+            (DataScope::RagCollections, 2),
+            (DataScope::ReasoningPatterns, 1),
+            (DataScope::Caches, 1),
+            (DataScope::InteractionLogs, 2),
+        ]);
+        assert_eq!(stores.remaining(), [2, 2, 1, 1, 2]);
+        assert_eq!(fs::read_dir(stores.dir.path().join("history")).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_each_scope_is_cleared_independently() {
+        let mut stores = Stores::new();
+        let mut expected = [2, 2, 1, 1, 2];
+        for (index, scope) in ALL_SCOPES.into_iter().enumerate() {
+            let report = stores.purge(&[scope], None, false);
+            assert_eq!(report.scopes, vec![ScopePurge { scope, items: expected[index] }]);
+            expected[index] = 0;
+            assert_eq!(stores.remaining(), expected, "after purging {:?}", scope);
+        }
+        assert!(fs::read_dir(stores.dir.path().join("history")).unwrap().next().is_none());
+        assert!(stores.dir.path().join("logs").join("other.log").exists());
+    }
+
+    #[test]
+    fn test_age_based_purge_keeps_recent_data() {
+        let mut stores = Stores::new();
+        let report = stores.purge(&ALL_SCOPES, Some(cutoff_for_days(30)), false);
+
+        let counts: Vec<usize> = report.scopes.iter().map(|purged| purged.items).collect();
+        assert_eq!(counts, vec![0, 1, 1, 0, 0]);
+        assert_eq!(stores.remaining(), [2, 1, 0, 1, 2]);
+    }
+}
@@ -0,0 +1,229 @@
+//! Export of dependency graphs to Graphviz DOT and GraphML for external visualization
+//!
+//! Nodes are keyed by file path and carry their language; edges carry the imported
+//! symbols and encode `DependencyStrength` as a numeric weight and, in DOT, a color.
+
+use crate::code_analysis::{DependencyEdge, DependencyGraph, DependencyNode, DependencyStrength};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphExportFormat {
+    Dot,
+    #[serde(alias = "graph_ml")]
+    GraphMl,
+}
+
+pub fn export_graph(graph: &DependencyGraph, format: GraphExportFormat) -> String {
+    match format {
+        GraphExportFormat::Dot => to_dot(graph),
+        GraphExportFormat::GraphMl => to_graphml(graph),
+    }
+}
+
+fn strength_color(strength: &DependencyStrength) -> &'static str {
+    match strength {
+        DependencyStrength::Weak => "gray",
+        DependencyStrength::Medium => "orange",
+        DependencyStrength::Strong => "red",
+    }
+}
+
+/// Nodes and edges in a stable order, so exports of the same graph are identical
+fn sorted(graph: &DependencyGraph) -> (Vec<&DependencyNode>, Vec<&DependencyEdge>) {
+    let mut nodes: Vec<&DependencyNode> = graph.nodes.values().collect();
+    nodes.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+    let mut edges: Vec<&DependencyEdge> = graph.edges.iter().collect();
+    edges.sort_by(|a, b| (&a.from, &a.to).cmp(&(&b.from, &b.to)));
+    (nodes, edges)
+}
+
+/// A double-quoted DOT ID
+fn dot_id(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => {}
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+pub fn to_dot(graph: &DependencyGraph) -> String {
+    let (nodes, edges) = sorted(graph);
+    let mut dot = String::from("digraph dependencies {\n    node [shape=box];\n");
+    for node in nodes {
+        let label = Path::new(&node.file_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| node.file_path.clone());
+        dot.push_str(&format!(
+            "    {} [label={}, language={}];\n",
+            dot_id(&node.file_path),
+            dot_id(&label),
+            dot_id(&node.language),
+        ));
+    }
+    for edge in edges {
+        dot.push_str(&format!(
+            "    {} -> {} [weight={}, color={}, label={}];\n",
+            dot_id(&edge.from),
+            dot_id(&edge.to),
+            edge.strength.weight(),
+            strength_color(&edge.strength),
+            dot_id(&edge.symbols.join(", ")),
+        ));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn xml_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn to_graphml(graph: &DependencyGraph) -> String {
+    let (nodes, edges) = sorted(graph);
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+        "  <key id=\"language\" for=\"node\" attr.name=\"language\" attr.type=\"string\"/>\n",
+        "  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"int\"/>\n",
+        "  <key id=\"strength\" for=\"edge\" attr.name=\"strength\" attr.type=\"string\"/>\n",
+        "  <key id=\"symbols\" for=\"edge\" attr.name=\"symbols\" attr.type=\"string\"/>\n",
+        "  <graph id=\"dependencies\" edgedefault=\"directed\">\n",
+    ));
+    for node in nodes {
+        xml.push_str(&format!(
+            "    <node id=\"{}\"><data key=\"language\">{}</data></node>\n",
+            xml_escape(&node.file_path),
+            xml_escape(&node.language),
+        ));
+    }
+    for edge in edges {
+        xml.push_str(&format!(
+            "    <edge source=\"{}\" target=\"{}\"><data key=\"weight\">{}</data><data key=\"strength\">{:?}</data><data key=\"symbols\">{}</data></edge>\n",
+            xml_escape(&edge.from),
+            xml_escape(&edge.to),
+            edge.strength.weight(),
+            edge.strength,
+            xml_escape(&edge.symbols.join(", ")),
+        ));
+    }
+    xml.push_str("  </graph>\n</graphml>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    fn graph() -> DependencyGraph {
+        let node = |file_path: &str, language: &str| DependencyNode {
+            file_path: file_path.to_string(),
+            language: language.to_string(),
+            symbols: Vec::new(),
+            imports: Vec::new(),
+            exports: HashSet::new(),
+        };
+        let tricky = "/repo/my \"quoted\" dir\\a&b.ts";
+        DependencyGraph {
+            nodes: HashMap::from([
+                ("/repo/main.ts".to_string(), node("/repo/main.ts", "typescript")),
+                (tricky.to_string(), node(tricky, "typescript")),
+                ("/repo/util.py".to_string(), node("/repo/util.py", "python")),
+            ]),
+            edges: vec![
+                DependencyEdge {
+                    from: "/repo/main.ts".to_string(),
+                    to: tricky.to_string(),
+                    symbols: vec!["parse".to_string(), "format".to_string()],
+                    strength: DependencyStrength::Weak,
+                },
+                DependencyEdge {
+                    from: "/repo/main.ts".to_string(),
+                    to: "/repo/util.py".to_string(),
+                    symbols: Vec::new(),
+                    strength: DependencyStrength::Strong,
+                },
+            ],
+            summary: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_dot_export_parses_with_expected_nodes_and_edges() {
+        let dot = export_graph(&graph(), GraphExportFormat::Dot);
+        let parsed = layout::gv::DotParser::new(&dot).process().expect("valid DOT");
+
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        for statement in parsed.list.list {
+            match statement {
+                layout::gv::parser::ast::Stmt::Node(node) => {
+                    let language = node.list.list.iter().find(|(key, _)| key == "language").map(|(_, value)| value.clone());
+                    nodes.push((node.id.name, language.unwrap()));
+                }
+                layout::gv::parser::ast::Stmt::Edge(edge) => {
+                    let weight = edge.list.list.iter().find(|(key, _)| key == "weight").map(|(_, value)| value.clone());
+                    edges.push((edge.from.name, edge.to[0].0.name.clone(), weight.unwrap()));
+                }
+                _ => {}
+            }
+        }
+
+        assert_eq!(nodes, vec![
+            ("/repo/main.ts".to_string(), "typescript".to_string()),
+            ("/repo/my \"quoted\" dir\\a&b.ts".to_string(), "typescript".to_string()),
+            ("/repo/util.py".to_string(), "python".to_string()),
+        ]);
+        assert_eq!(edges, vec![
+            ("/repo/main.ts".to_string(), "/repo/my \"quoted\" dir\\a&b.ts".to_string(), "1".to_string()),
+            ("/repo/main.ts".to_string(), "/repo/util.py".to_string(), "3".to_string()),
+        ]);
+        assert!(dot.contains("color=red"));
+    }
+
+    #[test]
+    fn test_graphml_export_is_well_formed() {
+        let xml = export_graph(&graph(), GraphExportFormat::GraphMl);
+        let mut reader = quick_xml::Reader::from_str(&xml);
+        let mut node_ids = Vec::new();
+        let mut edge_count = 0;
+        loop {
+            match reader.read_event().expect("well-formed XML") {
+                quick_xml::events::Event::Start(element) if element.name().as_ref() == b"node" => {
+                    let id = element.try_get_attribute("id").unwrap().unwrap();
+                    node_ids.push(id.unescape_value().unwrap().to_string());
+                }
+                quick_xml::events::Event::Start(element) if element.name().as_ref() == b"edge" => edge_count += 1,
+                quick_xml::events::Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        assert_eq!(node_ids[1], "/repo/my \"quoted\" dir\\a&b.ts");
+        assert_eq!(node_ids.len(), 3);
+        assert_eq!(edge_count, 2);
+        assert!(xml.contains("<data key=\"weight\">3</data><data key=\"strength\">Strong</data>"));
+    }
+}
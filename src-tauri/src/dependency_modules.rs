@@ -0,0 +1,179 @@
+//! Module-level view of a file dependency graph
+//!
+//! Files are grouped by the leading directories of their path below the repository
+//! root, and file edges between groups collapse into one edge per pair of modules.
+
+use crate::code_analysis::DependencyGraph;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Module name for files directly in the repository root
+pub const ROOT_MODULE: &str = ".";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModuleGraph {
+    pub depth: usize,
+    pub modules: Vec<ModuleNode>,
+    pub edges: Vec<ModuleEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModuleNode {
+    /// Directory path relative to the repository root, e.g. `src/components`
+    pub name: String,
+    pub files: Vec<String>,
+    pub languages: Vec<String>,
+    /// File edges between files of this module, which the module graph leaves out
+    pub internal_edges: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModuleEdge {
+    pub from: String,
+    pub to: String,
+    /// File edges collapsed into this one
+    pub file_edges: usize,
+    /// Sum of the collapsed edges' `DependencyStrength` weights
+    pub strength: u32,
+    pub symbols: Vec<String>,
+}
+
+/// Module of `file_path`: up to `depth` leading directories of its path below `root`
+fn module_name(file_path: &str, root: &Path, depth: usize) -> String {
+    let relative = Path::new(file_path).strip_prefix(root).unwrap_or(Path::new(file_path));
+    let directories: Vec<String> = relative
+        .parent()
+        .map(|parent| parent.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect())
+        .unwrap_or_default();
+    match directories.len().min(depth) {
+        0 => ROOT_MODULE.to_string(),
+        kept => directories[..kept].join("/"),
+    }
+}
+
+/// Group the files of `graph` into modules `depth` directories deep below `root`
+pub fn aggregate_modules(graph: &DependencyGraph, root: &str, depth: usize) -> Result<ModuleGraph, String> {
+    if depth == 0 {
+        return Err("Module depth must be at least 1".to_string());
+    }
+    let root = Path::new(root);
+
+    let mut members: BTreeMap<String, (BTreeSet<String>, BTreeSet<String>)> = BTreeMap::new();
+    for node in graph.nodes.values() {
+        let (files, languages) = members.entry(module_name(&node.file_path, root, depth)).or_default();
+        files.insert(node.file_path.clone());
+        languages.insert(node.language.clone());
+    }
+
+    let mut internal_edges: BTreeMap<String, usize> = BTreeMap::new();
+    let mut collapsed: BTreeMap<(String, String), (usize, u32, BTreeSet<String>)> = BTreeMap::new();
+    for edge in &graph.edges {
+        let from = module_name(&edge.from, root, depth);
+        let to = module_name(&edge.to, root, depth);
+        if from == to {
+            *internal_edges.entry(from).or_default() += 1;
+            continue;
+        }
+        let (file_edges, strength, symbols) = collapsed.entry((from, to)).or_default();
+        *file_edges += 1;
+        *strength += edge.strength.weight();
+        symbols.extend(edge.symbols.iter().cloned());
+    }
+
+    Ok(ModuleGraph {
+        depth,
+        modules: members
+            .into_iter()
+            .map(|(name, (files, languages))| ModuleNode {
+                internal_edges: internal_edges.get(&name).copied().unwrap_or(0),
+                name,
+                files: files.into_iter().collect(),
+                languages: languages.into_iter().collect(),
+            })
+            .collect(),
+        edges: collapsed
+            .into_iter()
+            .map(|((from, to), (file_edges, strength, symbols))| ModuleEdge {
+                from,
+                to,
+                file_edges,
+                strength,
+                symbols: symbols.into_iter().collect(),
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code_analysis::{DependencyEdge, DependencyNode, DependencyStrength};
+    use std::collections::{HashMap, HashSet};
+
+    fn graph() -> DependencyGraph {
+        let files = [
+            "/repo/main.ts",
+            "/repo/src/ui/button.ts",
+            "/repo/src/ui/form.ts",
+            "/repo/src/api/client.ts",
+            "/repo/src/api/auth/token.ts",
+        ];
+        let edge = |from: &str, to: &str, symbol: &str, strength: DependencyStrength| DependencyEdge {
+            from: from.to_string(),
+            to: to.to_string(),
+            symbols: vec![symbol.to_string()],
+            strength,
+        };
+        DependencyGraph {
+            nodes: files.iter()
+                .map(|file| (file.to_string(), DependencyNode {
+                    file_path: file.to_string(),
+                    language: "typescript".to_string(),
+                    symbols: Vec::new(),
+                    imports: Vec::new(),
+                    exports: HashSet::new(),
+                }))
+                .collect::<HashMap<_, _>>(),
+            edges: vec![
+                edge("/repo/src/ui/form.ts", "/repo/src/ui/button.ts", "Button", DependencyStrength::Weak),
+                edge("/repo/src/ui/button.ts", "/repo/src/api/client.ts", "get", DependencyStrength::Weak),
+                edge("/repo/src/ui/form.ts", "/repo/src/api/client.ts", "post", DependencyStrength::Medium),
+                edge("/repo/src/ui/form.ts", "/repo/src/api/auth/token.ts", "token", DependencyStrength::Strong),
+                edge("/repo/main.ts", "/repo/src/ui/form.ts", "Form", DependencyStrength::Weak),
+            ],
+            summary: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_files_in_a_directory_collapse_into_one_module() {
+        let modules = aggregate_modules(&graph(), "/repo", 2).unwrap();
+
+        let names: Vec<&str> = modules.modules.iter().map(|module| module.name.as_str()).collect();
+        assert_eq!(names, vec![".", "src/api", "src/ui"]);
+        let ui = &modules.modules[2];
+        assert_eq!(ui.files, vec!["/repo/src/ui/button.ts", "/repo/src/ui/form.ts"]);
+        assert_eq!(ui.internal_edges, 1);
+        // auth/token.ts is deeper than two directories and joins src/api
+        assert_eq!(modules.modules[1].files.len(), 2);
+
+        let ui_to_api = modules.edges.iter().find(|edge| edge.from == "src/ui" && edge.to == "src/api").unwrap();
+        assert_eq!(ui_to_api.file_edges, 3);
+        assert_eq!(ui_to_api.strength, 1 + 2 + 3);
+        assert_eq!(ui_to_api.symbols, vec!["get", "post", "token"]);
+        assert_eq!(modules.edges.len(), 2);
+    }
+
+    #[test]
+    fn test_grouping_depth() {
+        let shallow = aggregate_modules(&graph(), "/repo", 1).unwrap();
+        assert_eq!(shallow.modules.iter().map(|module| module.name.as_str()).collect::<Vec<_>>(), vec![".", "src"]);
+        assert_eq!(shallow.modules[1].internal_edges, 4);
+        assert_eq!(shallow.edges.len(), 1);
+
+        let deep = aggregate_modules(&graph(), "/repo", 3).unwrap();
+        assert!(deep.modules.iter().any(|module| module.name == "src/api/auth"));
+        assert!(aggregate_modules(&graph(), "/repo", 0).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+//! Boundary-aware document chunking for embedding
+//!
+//! Splits documents into chunks no larger than `max_chunk_chars`, preferring
+//! paragraph breaks for prose and top-level item boundaries for code, and
+//! carrying `overlap_chars` of trailing context into the next chunk.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// What kind of boundaries to prefer when splitting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContentKind {
+    Prose,
+    Code,
+}
+
+impl ContentKind {
+    /// Pick a content kind from a document language (e.g. "rust", "python", "markdown")
+    pub fn from_language(language: Option<&str>) -> Self {
+        match language.map(|lang| lang.to_lowercase()) {
+            Some(lang) if !matches!(lang.as_str(), "markdown" | "text" | "plaintext" | "html") => ContentKind::Code,
+            _ => ContentKind::Prose,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkingConfig {
+    pub max_chunk_chars: usize,
+    pub overlap_chars: usize,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            max_chunk_chars: 1500, // Comfortably inside common embedding model windows
+            overlap_chars: 200,
+        }
+    }
+}
+
+impl ChunkingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_chunk_chars == 0 {
+            return Err("Max chunk chars must be at least 1".to_string());
+        }
+        if self.overlap_chars >= self.max_chunk_chars {
+            return Err(format!(
+                "Chunk overlap ({}) must be less than max chunk chars ({})",
+                self.overlap_chars, self.max_chunk_chars
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A chunk of the original text; `start..end` are byte offsets into it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextChunk {
+    pub content: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+fn code_boundary() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^(pub(\(crate\))? |async |fn |impl[ <]|struct |enum |trait |mod |class |def |function |export |interface |type |const |#\[|@)")
+            .expect("valid boundary pattern")
+    })
+}
+
+/// Split `text` into chunks that respect `config` and prefer natural boundaries
+pub fn chunk_text(text: &str, config: &ChunkingConfig, kind: ContentKind) -> Vec<TextChunk> {
+    if text.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let max_chunk = config.max_chunk_chars.max(1);
+    // Overlap must leave room for new content in every chunk
+    let overlap = config.overlap_chars.min(max_chunk / 2);
+    let body_limit = max_chunk - overlap;
+
+    let pieces: Vec<(usize, usize)> = segments(text, kind)
+        .into_iter()
+        .flat_map(|(start, end)| split_oversized(text, start, end, body_limit))
+        .collect();
+
+    let mut chunks = Vec::new();
+    let mut body_start = pieces[0].0;
+    let mut body_end = body_start;
+
+    for (start, end) in pieces {
+        if end - body_start > body_limit && body_end > body_start {
+            chunks.push(make_chunk(text, chunks.last(), body_start, body_end, overlap));
+            body_start = start;
+        }
+        body_end = end;
+    }
+    if body_end > body_start {
+        chunks.push(make_chunk(text, chunks.last(), body_start, body_end, overlap));
+    }
+
+    chunks.retain(|chunk| !chunk.content.trim().is_empty());
+    chunks
+}
+
+fn make_chunk(text: &str, previous: Option<&TextChunk>, body_start: usize, body_end: usize, overlap: usize) -> TextChunk {
+    let start = match previous {
+        Some(previous) if overlap > 0 => overlap_start(text, previous.start, body_start, overlap),
+        _ => body_start,
+    };
+
+    TextChunk {
+        content: text[start..body_end].to_string(),
+        start,
+        end: body_end,
+    }
+}
+
+/// Start of the overlap window before `body_start`, snapped forward to a word start
+fn overlap_start(text: &str, floor: usize, body_start: usize, overlap: usize) -> usize {
+    let mut start = body_start.saturating_sub(overlap).max(floor);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+
+    if start > floor && !text[..start].ends_with(char::is_whitespace) {
+        match text[start..body_start].find(char::is_whitespace) {
+            Some(offset) => start += offset,
+            None => return body_start,
+        }
+    }
+    // Skip the whitespace itself
+    start + text[start..body_start].len() - text[start..body_start].trim_start().len()
+}
+
+/// Contiguous segments ending at paragraph breaks (prose) or before top-level items (code)
+fn segments(text: &str, kind: ContentKind) -> Vec<(usize, usize)> {
+    let mut segments = Vec::new();
+    let mut segment_start = 0;
+    let mut offset = 0;
+    let mut previous_blank = false;
+
+    for line in text.split_inclusive('\n') {
+        let is_blank = line.trim().is_empty();
+        let is_boundary = match kind {
+            ContentKind::Prose => previous_blank && !is_blank,
+            ContentKind::Code => (previous_blank && !is_blank) || (offset > 0 && code_boundary().is_match(line) && !previous_is_attribute(text, offset)),
+        };
+
+        if is_boundary && offset > segment_start {
+            segments.push((segment_start, offset));
+            segment_start = offset;
+        }
+
+        previous_blank = is_blank;
+        offset += line.len();
+    }
+    if offset > segment_start {
+        segments.push((segment_start, offset));
+    }
+
+    segments
+}
+
+/// Keep attributes/decorators attached to the item that follows them
+fn previous_is_attribute(text: &str, offset: usize) -> bool {
+    text[..offset]
+        .trim_end_matches('\n')
+        .rsplit('\n')
+        .next()
+        .map(|line| {
+            let line = line.trim_start();
+            line.starts_with("#[") || line.starts_with('@') || line.starts_with("///") || line.starts_with("//")
+        })
+        .unwrap_or(false)
+}
+
+/// Break a segment larger than `limit` by lines, then words, then characters
+fn split_oversized(text: &str, start: usize, end: usize, limit: usize) -> Vec<(usize, usize)> {
+    if end - start <= limit {
+        return vec![(start, end)];
+    }
+
+    let mut pieces = Vec::new();
+    for (line_start, line_end) in split_inclusive_offsets(text, start, end, '\n') {
+        if line_end - line_start <= limit {
+            pieces.push((line_start, line_end));
+            continue;
+        }
+        for (word_start, word_end) in split_inclusive_offsets(text, line_start, line_end, ' ') {
+            if word_end - word_start <= limit {
+                pieces.push((word_start, word_end));
+                continue;
+            }
+            // A single "word" longer than the limit has to be cut
+            let mut piece_start = word_start;
+            while piece_start < word_end {
+                let mut piece_end = (piece_start + limit).min(word_end);
+                while !text.is_char_boundary(piece_end) {
+                    piece_end -= 1;
+                }
+                if piece_end == piece_start {
+                    piece_end = piece_start + text[piece_start..].chars().next().map(char::len_utf8).unwrap_or(1);
+                }
+                pieces.push((piece_start, piece_end));
+                piece_start = piece_end;
+            }
+        }
+    }
+    pieces
+}
+
+fn split_inclusive_offsets(text: &str, start: usize, end: usize, separator: char) -> Vec<(usize, usize)> {
+    let mut offsets = Vec::new();
+    let mut piece_start = start;
+    for part in text[start..end].split_inclusive(separator) {
+        offsets.push((piece_start, piece_start + part.len()));
+        piece_start += part.len();
+    }
+    offsets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_chunk_chars: usize, overlap_chars: usize) -> ChunkingConfig {
+        ChunkingConfig { max_chunk_chars, overlap_chars }
+    }
+
+    #[test]
+    fn test_chunks_respect_max_size() {
+        let text = "The quick brown fox jumps over the lazy dog. ".repeat(50);
+        let chunks = chunk_text(&text, &config(200, 40), ContentKind::Prose);
+
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.content.len() <= 200, "chunk of {} chars", chunk.content.len());
+            assert_eq!(&text[chunk.start..chunk.end], chunk.content);
+        }
+    }
+
+    #[test]
+    fn test_chunks_overlap_previous_chunk() {
+        let text = "alpha beta gamma delta epsilon zeta eta theta iota kappa lambda mu ".repeat(10);
+        let chunks = chunk_text(&text, &config(120, 30), ContentKind::Prose);
+
+        for pair in chunks.windows(2) {
+            assert!(pair[1].start < pair[0].end, "chunks should overlap");
+            assert!(pair[0].end - pair[1].start <= 30);
+        }
+    }
+
+    #[test]
+    fn test_does_not_split_mid_word() {
+        let text = "lorem ipsum dolor sit amet consectetur adipiscing elit ".repeat(20);
+        let chunks = chunk_text(&text, &config(100, 20), ContentKind::Prose);
+
+        for chunk in &chunks {
+            let first_char_before = text[..chunk.start].chars().last();
+            assert!(first_char_before.map_or(true, char::is_whitespace), "chunk starts mid-word: {:?}", chunk.content);
+            let next_char = text[chunk.end..].chars().next();
+            assert!(
+                next_char.map_or(true, char::is_whitespace) || chunk.content.ends_with(char::is_whitespace),
+                "chunk ends mid-word: {:?}",
+                chunk.content
+            );
+        }
+    }
+
+    #[test]
+    fn test_prose_prefers_paragraph_boundaries() {
+        let paragraph_a = "First paragraph sentence one. Sentence two.\n\n";
+        let paragraph_b = "Second paragraph sentence one. Sentence two.\n\n";
+        let paragraph_c = "Third paragraph sentence one. Sentence two.\n";
+        let text = format!("{}{}{}", paragraph_a, paragraph_b, paragraph_c);
+
+        let chunks = chunk_text(&text, &config(100, 0), ContentKind::Prose);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].content, format!("{}{}", paragraph_a, paragraph_b));
+        assert_eq!(chunks[1].content, paragraph_c);
+    }
+
+    #[test]
+    fn test_code_prefers_function_boundaries() {
+        let text = "fn first() {\n    let a = 1;\n    a + 1\n}\nfn second() {\n    let b = 2;\n    b * 2\n}\n#[test]\nfn third() {\n    assert!(true);\n}\n";
+
+        let chunks = chunk_text(text, &config(60, 0), ContentKind::Code);
+
+        assert!(chunks.iter().all(|chunk| chunk.content.starts_with("fn ") || chunk.content.starts_with("#[test]")));
+        assert!(chunks.iter().any(|chunk| chunk.content.starts_with("#[test]\nfn third()")));
+    }
+
+    #[test]
+    fn test_content_kind_from_language() {
+        assert_eq!(ContentKind::from_language(Some("rust")), ContentKind::Code);
+        assert_eq!(ContentKind::from_language(Some("Markdown")), ContentKind::Prose);
+        assert_eq!(ContentKind::from_language(None), ContentKind::Prose);
+    }
+
+    #[test]
+    fn test_empty_text_has_no_chunks() {
+        assert!(chunk_text("   \n\n", &ChunkingConfig::default(), ContentKind::Prose).is_empty());
+    }
+}
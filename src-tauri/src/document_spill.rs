@@ -0,0 +1,60 @@
+//! On-disk storage for documents evicted from ChromaManager's in-memory collections
+//!
+//! Each document is stored as JSON at `<dir>/<collection>/<id>.json`, with the
+//! collection name and id hex-encoded so any string is a valid file name.
+
+use crate::chroma_manager::Document;
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub struct DocumentSpill {
+    dir: PathBuf,
+}
+
+impl DocumentSpill {
+    /// Spill into `dir`, which is created on first write
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn save(&self, collection_name: &str, document: &Document) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(self.collection_dir(collection_name))?;
+        fs::write(self.document_path(collection_name, &document.id), serde_json::to_vec(document)?)?;
+        Ok(())
+    }
+
+    pub fn load(&self, collection_name: &str, id: &str) -> Result<Document, Box<dyn Error>> {
+        let json = fs::read(self.document_path(collection_name, id))?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    /// Remove a spilled document; a missing file is not an error
+    pub fn remove(&self, collection_name: &str, id: &str) -> Result<(), Box<dyn Error>> {
+        match fs::remove_file(self.document_path(collection_name, id)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn remove_collection(&self, collection_name: &str) -> Result<(), Box<dyn Error>> {
+        match fs::remove_dir_all(self.collection_dir(collection_name)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn collection_dir(&self, collection_name: &str) -> PathBuf {
+        self.dir.join(encode_name(collection_name))
+    }
+
+    fn document_path(&self, collection_name: &str, id: &str) -> PathBuf {
+        self.collection_dir(collection_name).join(format!("{}.json", encode_name(id)))
+    }
+}
+
+fn encode_name(name: &str) -> String {
+    name.bytes().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,89 @@
+//! Normalization and validation of `DocumentMetadata.document_type`
+//!
+//! Types are always case- and separator-normalized ("Source Code" -> "source_code").
+//! When a taxonomy is configured, aliases map onto it and unknown types are either
+//! logged or rejected.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// What to do with a type outside the configured taxonomy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownTypePolicy {
+    /// Keep the normalized type and log a warning
+    #[default]
+    Warn,
+    /// Fail the whole add
+    Reject,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DocumentTypeConfig {
+    /// Allowed types; empty means any type is accepted
+    pub taxonomy: Vec<String>,
+    /// Alternative spellings mapped to a taxonomy type, e.g. "source_code" -> "code"
+    pub aliases: HashMap<String, String>,
+    pub unknown_types: UnknownTypePolicy,
+}
+
+impl DocumentTypeConfig {
+    /// The canonical form of `document_type`, or an error if it is rejected
+    pub fn resolve(&self, document_type: &str) -> Result<String, String> {
+        let normalized = normalize_document_type(document_type);
+        let resolved = self.aliases.iter()
+            .find(|(alias, _)| normalize_document_type(alias) == normalized)
+            .map(|(_, target)| normalize_document_type(target))
+            .unwrap_or(normalized);
+
+        if self.taxonomy.is_empty() || self.taxonomy.iter().any(|known| normalize_document_type(known) == resolved) {
+            return Ok(resolved);
+        }
+        match self.unknown_types {
+            UnknownTypePolicy::Warn => {
+                eprintln!("Unknown document type '{}' is not in the configured taxonomy", resolved);
+                Ok(resolved)
+            }
+            UnknownTypePolicy::Reject => Err(format!("Unknown document type: {}", document_type)),
+        }
+    }
+}
+
+/// Lowercase, trim, and join words with underscores
+pub fn normalize_document_type(document_type: &str) -> String {
+    document_type
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_document_type() {
+        assert_eq!(normalize_document_type("Code"), "code");
+        assert_eq!(normalize_document_type("  Source-Code "), "source_code");
+        assert_eq!(normalize_document_type("API  Reference"), "api_reference");
+    }
+
+    #[test]
+    fn test_resolve_with_taxonomy() {
+        let config = DocumentTypeConfig {
+            taxonomy: vec!["code".to_string(), "documentation".to_string()],
+            aliases: HashMap::from([("Source Code".to_string(), "code".to_string())]),
+            unknown_types: UnknownTypePolicy::Reject,
+        };
+
+        assert_eq!(config.resolve("CODE").unwrap(), "code");
+        assert_eq!(config.resolve("source_code").unwrap(), "code");
+        assert!(config.resolve("recipe").is_err());
+
+        let lenient = DocumentTypeConfig { unknown_types: UnknownTypePolicy::Warn, ..config };
+        assert_eq!(lenient.resolve("Recipe").unwrap(), "recipe");
+    }
+}
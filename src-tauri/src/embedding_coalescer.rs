@@ -0,0 +1,218 @@
+//! Sharing of embedding requests for the same text
+//!
+//! Duplicate chunks across ingests often embed the same text at the same time. Requests
+//! are keyed by model and a hash of the text: while one is in flight, identical requests
+//! wait for its result instead of calling the model again, and completed embeddings are
+//! kept in a bounded cache.
+
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Completed embeddings kept by default
+pub const DEFAULT_CACHED_EMBEDDINGS: usize = 2048;
+
+type EmbeddingKey = (String, [u8; 16]);
+type Waiter = oneshot::Sender<Result<Arc<Vec<f32>>, String>>;
+
+#[derive(Default)]
+struct CompletedEmbeddings {
+    embeddings: HashMap<EmbeddingKey, Arc<Vec<f32>>>,
+    /// Keys oldest first, for eviction
+    order: VecDeque<EmbeddingKey>,
+}
+
+pub struct EmbeddingCoalescer {
+    capacity: usize,
+    in_flight: Mutex<HashMap<EmbeddingKey, Vec<Waiter>>>,
+    completed: Mutex<CompletedEmbeddings>,
+}
+
+/// Removes an abandoned request so its waiters are told rather than left waiting
+struct InFlightGuard<'a> {
+    coalescer: &'a EmbeddingCoalescer,
+    key: Option<EmbeddingKey>,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.coalescer.in_flight.lock().unwrap().remove(&key);
+        }
+    }
+}
+
+impl EmbeddingCoalescer {
+    /// Keep up to `capacity` completed embeddings; none are kept when 0
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            in_flight: Mutex::new(HashMap::new()),
+            completed: Mutex::new(CompletedEmbeddings::default()),
+        }
+    }
+
+    /// The embedding of `text` from the cache, from an identical request in flight, or
+    /// from `embed`. Only the caller running `embed` gets its error as is; waiters get
+    /// its message. Failures aren't cached. When the caller running a shared request is
+    /// cancelled, its waiters try again, and one of them runs its own `embed`.
+    pub async fn embed<F, Fut, E>(&self, model: &str, text: &str, embed: F) -> Result<Vec<f32>, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<f32>, E>>,
+        E: std::fmt::Display + From<String>,
+    {
+        let key = (model.to_string(), md5::compute(text.as_bytes()).0);
+        loop {
+            if let Some(embedding) = self.completed.lock().unwrap().embeddings.get(&key) {
+                return Ok(embedding.as_ref().clone());
+            }
+
+            let waiting = {
+                let mut in_flight = self.in_flight.lock().unwrap();
+                match in_flight.get_mut(&key) {
+                    Some(waiters) => {
+                        let (sender, receiver) = oneshot::channel();
+                        waiters.push(sender);
+                        Some(receiver)
+                    }
+                    None => {
+                        in_flight.insert(key.clone(), Vec::new());
+                        None
+                    }
+                }
+            };
+            let Some(receiver) = waiting else {
+                break;
+            };
+            // The sender is only dropped without a result when its request was cancelled
+            if let Ok(result) = receiver.await {
+                return result.map(|embedding| embedding.as_ref().clone()).map_err(E::from);
+            }
+        }
+
+        let mut guard = InFlightGuard { coalescer: self, key: Some(key) };
+        let result = embed().await;
+        let key = guard.key.take().expect("in-flight key is only taken here");
+        // Cached before the request stops being in flight, so no caller misses both
+        let shared = match &result {
+            Ok(embedding) => {
+                let embedding = Arc::new(embedding.clone());
+                self.cache(key.clone(), embedding.clone());
+                Ok(embedding)
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        let waiters = self.in_flight.lock().unwrap().remove(&key).unwrap_or_default();
+        for waiter in waiters {
+            let _ = waiter.send(shared.clone());
+        }
+        result
+    }
+
+    fn cache(&self, key: EmbeddingKey, embedding: Arc<Vec<f32>>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut completed = self.completed.lock().unwrap();
+        if completed.embeddings.insert(key.clone(), embedding).is_none() {
+            completed.order.push_back(key);
+        }
+        while completed.order.len() > self.capacity {
+            if let Some(oldest) = completed.order.pop_front() {
+                completed.embeddings.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn cached_embeddings(&self) -> usize {
+        self.completed.lock().unwrap().embeddings.len()
+    }
+
+    pub fn clear(&self) -> usize {
+        let mut completed = self.completed.lock().unwrap();
+        completed.order.clear();
+        std::mem::take(&mut completed.embeddings).len()
+    }
+}
+
+impl Default for EmbeddingCoalescer {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHED_EMBEDDINGS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_failures_are_shared_but_not_cached() {
+        let coalescer = EmbeddingCoalescer::new(1);
+        let calls = AtomicUsize::new(0);
+        let failing = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Err::<Vec<f32>, String>("model not found".to_string())
+        };
+        let (first, second) = tokio::join!(
+            coalescer.embed("nomic", "text", failing),
+            coalescer.embed("nomic", "text", failing),
+        );
+        assert_eq!(first.unwrap_err(), "model not found");
+        assert_eq!(second.unwrap_err(), "model not found");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // A later request tries again, and a different model or text is its own request
+        let succeeding = |value: f32| {
+            let calls = &calls;
+            move || async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, String>(vec![value])
+            }
+        };
+        assert_eq!(coalescer.embed("nomic", "text", succeeding(1.0)).await.unwrap(), vec![1.0]);
+        assert_eq!(coalescer.embed("other", "text", succeeding(2.0)).await.unwrap(), vec![2.0]);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // The cache holds one embedding, so the first was evicted
+        assert_eq!(coalescer.cached_embeddings(), 1);
+        assert_eq!(coalescer.embed("nomic", "text", succeeding(3.0)).await.unwrap(), vec![3.0]);
+    }
+
+    #[tokio::test]
+    async fn test_waiter_takes_over_a_cancelled_request() {
+        let coalescer = Arc::new(EmbeddingCoalescer::new(4));
+        let running = tokio::spawn({
+            let coalescer = coalescer.clone();
+            async move {
+                coalescer
+                    .embed("nomic", "text", || async {
+                        tokio::time::sleep(Duration::from_secs(60)).await;
+                        Ok::<_, String>(vec![0.0])
+                    })
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let calls = AtomicUsize::new(0);
+        let (waited, _) = tokio::join!(
+            coalescer.embed("nomic", "text", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, String>(vec![1.0])
+            }),
+            async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                running.abort();
+            },
+        );
+
+        assert_eq!(waited.unwrap(), vec![1.0]);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(coalescer.cached_embeddings(), 1);
+    }
+}
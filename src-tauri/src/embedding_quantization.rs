@@ -0,0 +1,62 @@
+//! Compact in-memory storage for embeddings
+//!
+//! A collection can keep its embeddings as float16 (half the size of f32) or int8
+//! (a quarter, plus one f32 scale per vector) at a small cost in accuracy. Embeddings
+//! are dequantized to f32 for scoring and whenever a document leaves the collection.
+
+use half::f16;
+use serde::{Deserialize, Serialize};
+
+/// How a collection stores the embeddings of its resident documents
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingQuantization {
+    /// Full-precision f32
+    #[default]
+    None,
+    Float16,
+    /// Symmetric per-vector int8: each value is `code * scale`
+    Int8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuantizedEmbedding {
+    Float16(Vec<f16>),
+    Int8 { scale: f32, codes: Vec<i8> },
+}
+
+impl QuantizedEmbedding {
+    /// `embedding` stored at `scheme`, or None when the scheme keeps f32
+    pub fn new(embedding: &[f32], scheme: EmbeddingQuantization) -> Option<Self> {
+        match scheme {
+            EmbeddingQuantization::None => None,
+            EmbeddingQuantization::Float16 => {
+                Some(Self::Float16(embedding.iter().map(|value| f16::from_f32(*value)).collect()))
+            }
+            EmbeddingQuantization::Int8 => {
+                let max = embedding.iter().fold(0.0f32, |max, value| max.max(value.abs()));
+                let scale = if max > 0.0 { max / i8::MAX as f32 } else { 1.0 };
+                let codes = embedding
+                    .iter()
+                    .map(|value| (value / scale).round().clamp(-(i8::MAX as f32), i8::MAX as f32) as i8)
+                    .collect();
+                Some(Self::Int8 { scale, codes })
+            }
+        }
+    }
+
+    pub fn dequantize(&self) -> Vec<f32> {
+        match self {
+            Self::Float16(values) => values.iter().map(|value| value.to_f32()).collect(),
+            Self::Int8 { scale, codes } => codes.iter().map(|code| *code as f32 * scale).collect(),
+        }
+    }
+
+    /// In-memory footprint of the stored values
+    pub fn size_bytes(&self) -> usize {
+        match self {
+            Self::Float16(values) => values.len() * std::mem::size_of::<f16>(),
+            Self::Int8 { codes, .. } => codes.len() + std::mem::size_of::<f32>(),
+        }
+    }
+}
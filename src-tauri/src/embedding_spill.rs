@@ -0,0 +1,234 @@
+//! Memory-mapped storage for embeddings spilled from ChromaManager's resident documents
+//!
+//! Embeddings are appended as little-endian f32s to one file per store and read back
+//! through a memory map, so scoring a spilled embedding is a copy rather than a parse.
+//! Removed and replaced embeddings leave holes; once the holes take up more of the file
+//! than the live embeddings, the file is rewritten without them. The file is deleted
+//! when the store is dropped.
+
+use memmap2::Mmap;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+const F32_SIZE: usize = std::mem::size_of::<f32>();
+
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    offset: usize,
+    dimension: usize,
+}
+
+impl Slot {
+    fn size(&self) -> usize {
+        self.dimension * F32_SIZE
+    }
+}
+
+#[derive(Debug)]
+pub struct EmbeddingSpill {
+    dir: PathBuf,
+    /// Created on first write
+    file: Option<(PathBuf, File)>,
+    map: Option<Mmap>,
+    /// Bytes written to the file, live or not
+    len: usize,
+    /// Bytes of the embeddings still stored
+    live: usize,
+    /// Slot of each spilled embedding, by collection and document id
+    slots: HashMap<String, HashMap<String, Slot>>,
+}
+
+impl EmbeddingSpill {
+    /// Spill into a new file in `dir`, which is created on first write
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            file: None,
+            map: None,
+            len: 0,
+            live: 0,
+            slots: HashMap::new(),
+        }
+    }
+
+    /// Append `embeddings`, replacing any already stored under the same ids
+    pub fn save(&mut self, collection_name: &str, embeddings: &[(&str, &[f32])]) -> Result<(), Box<dyn Error>> {
+        if embeddings.is_empty() {
+            return Ok(());
+        }
+
+        let mut bytes = Vec::with_capacity(embeddings.iter().map(|(_, embedding)| embedding.len() * F32_SIZE).sum());
+        let mut slots = Vec::with_capacity(embeddings.len());
+        for (id, embedding) in embeddings {
+            slots.push((id.to_string(), Slot { offset: self.len + bytes.len(), dimension: embedding.len() }));
+            for value in embedding.iter() {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        let end = self.len as u64;
+        let file = self.file()?;
+        file.seek(SeekFrom::Start(end))?;
+        file.write_all(&bytes)?;
+        // SAFETY: the file is private to this store and only appended to while mapped, so
+        // mapped bytes never change; it is truncated only after the map is dropped
+        let map = unsafe { Mmap::map(&*file)? };
+        self.map = Some(map);
+        self.len += bytes.len();
+        self.live += bytes.len();
+
+        let stored = self.slots.entry(collection_name.to_string()).or_default();
+        for (id, slot) in slots {
+            if let Some(replaced) = stored.insert(id, slot) {
+                self.live -= replaced.size();
+            }
+        }
+        self.compact_if_sparse()
+    }
+
+    pub fn load(&self, collection_name: &str, id: &str) -> Option<Vec<f32>> {
+        let slot = self.slots.get(collection_name)?.get(id)?;
+        let bytes = self.map.as_ref()?.get(slot.offset..slot.offset + slot.size())?;
+        Some(
+            bytes
+                .chunks_exact(F32_SIZE)
+                .map(|value| f32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+                .collect(),
+        )
+    }
+
+    /// Load and remove a spilled embedding
+    pub fn take(&mut self, collection_name: &str, id: &str) -> Result<Option<Vec<f32>>, Box<dyn Error>> {
+        let embedding = self.load(collection_name, id);
+        self.remove(collection_name, id)?;
+        Ok(embedding)
+    }
+
+    /// Remove a spilled embedding; a missing one is not an error
+    pub fn remove(&mut self, collection_name: &str, id: &str) -> Result<(), Box<dyn Error>> {
+        if let Some(slots) = self.slots.get_mut(collection_name) {
+            if let Some(removed) = slots.remove(id) {
+                self.live -= removed.size();
+            }
+            if slots.is_empty() {
+                self.slots.remove(collection_name);
+            }
+        }
+        self.compact_if_sparse()
+    }
+
+    pub fn remove_collection(&mut self, collection_name: &str) -> Result<(), Box<dyn Error>> {
+        if let Some(slots) = self.slots.remove(collection_name) {
+            self.live -= slots.values().map(Slot::size).sum::<usize>();
+        }
+        self.compact_if_sparse()
+    }
+
+    /// Number of embeddings stored
+    pub fn len(&self) -> usize {
+        self.slots.values().map(HashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Size of the file, including holes left by removed embeddings
+    pub fn disk_bytes(&self) -> usize {
+        self.len
+    }
+
+    fn file(&mut self) -> Result<&mut File, Box<dyn Error>> {
+        if self.file.is_none() {
+            fs::create_dir_all(&self.dir)?;
+            let path = self.dir.join(format!("{}.bin", uuid::Uuid::new_v4()));
+            let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+            self.file = Some((path, file));
+        }
+        Ok(&mut self.file.as_mut().unwrap().1)
+    }
+
+    /// Rewrite the file with only the live embeddings once holes outweigh them
+    fn compact_if_sparse(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.len - self.live <= self.live {
+            return Ok(());
+        }
+
+        let mut bytes = Vec::with_capacity(self.live);
+        let mut slots = self.slots.clone();
+        if let Some(map) = &self.map {
+            for slot in slots.values_mut().flat_map(HashMap::values_mut) {
+                let offset = bytes.len();
+                bytes.extend_from_slice(&map[slot.offset..slot.offset + slot.size()]);
+                slot.offset = offset;
+            }
+        }
+
+        self.map = None;
+        self.len = 0;
+        let file = self.file()?;
+        file.set_len(0)?;
+        if !bytes.is_empty() {
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&bytes)?;
+            // SAFETY: as in `save`
+            let map = unsafe { Mmap::map(&*file)? };
+            self.map = Some(map);
+        }
+        self.len = bytes.len();
+        self.slots = slots;
+        Ok(())
+    }
+}
+
+impl Drop for EmbeddingSpill {
+    fn drop(&mut self) {
+        self.map = None;
+        if let Some((path, _)) = self.file.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_and_save_cycles_keep_the_file_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spill = EmbeddingSpill::new(dir.path());
+        let embedding = |seed: usize| -> Vec<f32> { (0..16).map(|value| (seed * 16 + value) as f32).collect() };
+        let ids: Vec<String> = (0..8).map(|index| format!("doc{}", index)).collect();
+        let initial: Vec<Vec<f32>> = (0..ids.len()).map(embedding).collect();
+        let pairs: Vec<(&str, &[f32])> = ids.iter().map(String::as_str).zip(initial.iter().map(Vec::as_slice)).collect();
+        spill.save("docs", &pairs).unwrap();
+        let live_bytes = spill.disk_bytes();
+
+        // Promote and spill documents over and over, as scoring cold documents does
+        for round in 0..200 {
+            let id = &ids[round % ids.len()];
+            let taken = spill.take("docs", id).unwrap().unwrap();
+            assert_eq!(taken, embedding(round % ids.len()));
+            spill.save("docs", &[(id.as_str(), taken.as_slice())]).unwrap();
+            assert!(spill.disk_bytes() <= 2 * live_bytes, "spill file grew to {} bytes", spill.disk_bytes());
+        }
+
+        assert_eq!(spill.len(), ids.len());
+        for (index, id) in ids.iter().enumerate() {
+            assert_eq!(spill.load("docs", id).unwrap(), embedding(index));
+        }
+
+        // Replacing an embedding in place leaves a hole too
+        for _ in 0..20 {
+            spill.save("docs", &[("doc0", embedding(0).as_slice())]).unwrap();
+        }
+        assert!(spill.disk_bytes() <= 2 * live_bytes);
+
+        spill.remove_collection("docs").unwrap();
+        assert_eq!(spill.disk_bytes(), 0);
+    }
+}
@@ -0,0 +1,199 @@
+//! Typed events emitted to the frontend
+//!
+//! Each event has a fixed name and payload shape. Emit them with `emit_event`
+//! instead of calling `AppHandle::emit` with ad hoc JSON.
+
+use crate::analysis_engine::QuestionAnswerChain;
+use crate::ollama_client::{BufferStats, StreamingMetricsSnapshot};
+use crate::operation_manager::OperationProgress;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+/// A payload with the event name it is emitted under
+pub trait AppEvent: Serialize + Clone {
+    const NAME: &'static str;
+}
+
+/// Emit `event` to all windows; failures are logged, not returned
+pub fn emit_event<E: AppEvent>(app_handle: &AppHandle, event: E) {
+    if let Err(e) = app_handle.emit(E::NAME, event) {
+        eprintln!("Failed to emit {} event: {}", E::NAME, e);
+    }
+}
+
+/// A streamed token, or the end of a generation when `done` is set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenerationDelta {
+    pub token: String,
+    pub done: bool,
+    /// Final speed metrics, sent with the `done` delta when available
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<StreamingMetricsSnapshot>,
+}
+
+impl GenerationDelta {
+    pub fn token(token: &str) -> Self {
+        Self {
+            token: token.to_string(),
+            done: false,
+            metrics: None,
+        }
+    }
+
+    pub fn done(metrics: Option<StreamingMetricsSnapshot>) -> Self {
+        Self {
+            token: String::new(),
+            done: true,
+            metrics,
+        }
+    }
+}
+
+impl AppEvent for GenerationDelta {
+    const NAME: &'static str = "ollama-stream";
+}
+
+/// Live buffer usage and speed while a generation streams
+impl AppEvent for BufferStats {
+    const NAME: &'static str = "ollama-stream-stats";
+}
+
+/// One completed question/answer round of a deep analysis
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisRound {
+    pub session_id: String,
+    pub round: usize,
+    pub question: String,
+    pub answer: String,
+    pub confidence: f32,
+}
+
+impl AnalysisRound {
+    pub fn from_chain(session_id: &str, chain: &QuestionAnswerChain) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            round: chain.round,
+            question: chain.question.clone(),
+            answer: chain.answer.clone(),
+            confidence: chain.confidence,
+        }
+    }
+}
+
+impl AppEvent for AnalysisRound {
+    const NAME: &'static str = "analysis-round";
+}
+
+/// Progress of adding documents to a collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IngestProgress {
+    pub collection: String,
+    pub processed: usize,
+    pub total: usize,
+    /// Document currently being processed
+    pub current: Option<String>,
+}
+
+impl AppEvent for IngestProgress {
+    const NAME: &'static str = "ingest-progress";
+}
+
+/// Percent-complete reported by a queued operation
+impl AppEvent for OperationProgress {
+    const NAME: &'static str = "operation-progress";
+}
+
+/// A service moved between healthy and unhealthy
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthChanged {
+    /// "ollama", "chroma" or "searxng"
+    pub service: String,
+    pub healthy: bool,
+    pub error: Option<String>,
+}
+
+impl AppEvent for HealthChanged {
+    const NAME: &'static str = "health-changed";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_generation_delta_shape() {
+        assert_eq!(
+            serde_json::to_value(GenerationDelta::token("Hel")).unwrap(),
+            json!({ "token": "Hel", "done": false })
+        );
+
+        let metrics = StreamingMetricsSnapshot {
+            total_tokens: 12,
+            elapsed_ms: 400,
+            time_to_first_token_ms: Some(50),
+            tokens_per_second: 30.0,
+        };
+        assert_eq!(
+            serde_json::to_value(GenerationDelta::done(Some(metrics))).unwrap(),
+            json!({
+                "token": "",
+                "done": true,
+                "metrics": {
+                    "total_tokens": 12,
+                    "elapsed_ms": 400,
+                    "time_to_first_token_ms": 50,
+                    "tokens_per_second": 30.0
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_analysis_round_shape() {
+        let chain = QuestionAnswerChain {
+            question: "Why?".to_string(),
+            answer: "Because.".to_string(),
+            round: 2,
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+            confidence: 0.5,
+            key_insights: Vec::new(),
+        };
+
+        assert_eq!(AnalysisRound::NAME, "analysis-round");
+        assert_eq!(
+            serde_json::to_value(AnalysisRound::from_chain("session-1", &chain)).unwrap(),
+            json!({
+                "session_id": "session-1",
+                "round": 2,
+                "question": "Why?",
+                "answer": "Because.",
+                "confidence": 0.5
+            })
+        );
+    }
+
+    #[test]
+    fn test_ingest_and_health_shapes() {
+        let progress = IngestProgress {
+            collection: "docs".to_string(),
+            processed: 3,
+            total: 10,
+            current: Some("README.md".to_string()),
+        };
+        assert_eq!(
+            serde_json::to_value(progress).unwrap(),
+            json!({ "collection": "docs", "processed": 3, "total": 10, "current": "README.md" })
+        );
+
+        let changed = HealthChanged {
+            service: "ollama".to_string(),
+            healthy: false,
+            error: None,
+        };
+        assert_eq!(
+            serde_json::to_value(changed).unwrap(),
+            json!({ "service": "ollama", "healthy": false, "error": null })
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! On-disk cache of per-file symbol and import extraction for dependency analysis
+//!
+//! Each entry is stored as JSON at `<dir>/<md5 of file path>.json` together with the
+//! hash of the content it was extracted from, so an edited file misses the cache.
+
+use crate::code_analysis::{Import, Symbol};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedExtraction {
+    file_path: String,
+    content_hash: String,
+    symbols: Vec<Symbol>,
+    imports: Vec<Import>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExtractionCacheStats {
+    pub entries: usize,
+    pub size_bytes: u64,
+    /// Lookups since startup
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug)]
+pub struct ExtractionCache {
+    dir: PathBuf,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ExtractionCache {
+    /// Cache into `dir`, which is created on first write
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// The extraction of `file_path` if it was made from content with `content_hash`
+    pub fn get(&self, file_path: &str, content_hash: &str) -> Option<(Vec<Symbol>, Vec<Import>)> {
+        let entry = fs::read(self.entry_path(file_path))
+            .ok()
+            .and_then(|json| serde_json::from_slice::<CachedExtraction>(&json).ok())
+            .filter(|entry| entry.file_path == file_path && entry.content_hash == content_hash);
+
+        match entry {
+            Some(entry) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some((entry.symbols, entry.imports))
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    pub fn put(&self, file_path: &str, content_hash: &str, symbols: &[Symbol], imports: &[Import]) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(&self.dir)?;
+        let entry = CachedExtraction {
+            file_path: file_path.to_string(),
+            content_hash: content_hash.to_string(),
+            symbols: symbols.to_vec(),
+            imports: imports.to_vec(),
+        };
+        fs::write(self.entry_path(file_path), serde_json::to_vec(&entry)?)?;
+        Ok(())
+    }
+
+    pub fn stats(&self) -> Result<ExtractionCacheStats, Box<dyn Error>> {
+        let mut stats = ExtractionCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            ..Default::default()
+        };
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(stats),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let metadata = entry?.metadata()?;
+            if metadata.is_file() {
+                stats.entries += 1;
+                stats.size_bytes += metadata.len();
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Remove every entry, returning how many there were
+    pub fn clear(&self) -> Result<usize, Box<dyn Error>> {
+        let entries = self.stats()?.entries;
+        match fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(entries),
+        }
+    }
+
+    /// Remove entries written before `older_than`, or all of them, returning how many
+    /// there were. A dry run only counts.
+    pub fn purge(&self, older_than: Option<SystemTime>, dry_run: bool) -> Result<usize, Box<dyn Error>> {
+        let Some(cutoff) = older_than else {
+            return if dry_run { Ok(self.stats()?.entries) } else { self.clear() };
+        };
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let mut purged = 0;
+        for entry in entries {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_file() && metadata.modified()? < cutoff {
+                if !dry_run {
+                    fs::remove_file(entry.path())?;
+                }
+                purged += 1;
+            }
+        }
+        Ok(purged)
+    }
+
+    fn entry_path(&self, file_path: &str) -> PathBuf {
+        self.dir.join(format!("{:x}.json", md5::compute(file_path.as_bytes())))
+    }
+}
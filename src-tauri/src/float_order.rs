@@ -0,0 +1,59 @@
+//! Orderings for scores that may be NaN
+//!
+//! `partial_cmp(..).unwrap_or(Equal)` makes NaN equal to every score, so a single NaN
+//! (say, a cosine against a zero-length embedding) leaves a sort in an arbitrary order.
+//! These orderings rank NaN below every real score instead.
+
+use std::cmp::Ordering;
+
+/// Lowest first, NaN last; for distances
+pub fn ascending_nan_last(a: f32, b: f32) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (false, false) => a.total_cmp(&b),
+        (a_nan, b_nan) => a_nan.cmp(&b_nan),
+    }
+}
+
+/// Highest first, NaN last; for confidences and relevance scores
+pub fn descending_nan_last(a: f32, b: f32) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (false, false) => b.total_cmp(&a),
+        (a_nan, b_nan) => a_nan.cmp(&b_nan),
+    }
+}
+
+/// `value`, or `floor` with a warning naming `what` when it's NaN
+pub fn or_floor(value: f32, floor: f32, what: &str) -> f32 {
+    if value.is_nan() {
+        eprintln!("Warning: {} is NaN; using {} instead", what, floor);
+        floor
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nan_sorts_last_in_both_directions() {
+        let zero = [0.0f32; 3];
+        let norm = zero.iter().map(|x| x * x).sum::<f32>().sqrt();
+        let nan_cosine = zero.iter().sum::<f32>() / (norm * norm);
+        assert!(nan_cosine.is_nan());
+
+        let mut distances = vec![0.4, nan_cosine, 0.1, f32::NAN, 0.7];
+        distances.sort_by(|a, b| ascending_nan_last(*a, *b));
+        assert_eq!(&distances[..3], &[0.1, 0.4, 0.7]);
+        assert!(distances[3..].iter().all(|distance| distance.is_nan()));
+
+        let mut scores = vec![0.4, f32::NAN, 0.9, 0.1];
+        scores.sort_by(|a, b| descending_nan_last(*a, *b));
+        assert_eq!(&scores[..3], &[0.9, 0.4, 0.1]);
+        assert!(scores[3].is_nan());
+
+        assert_eq!(or_floor(f32::NAN, 0.0, "confidence"), 0.0);
+        assert_eq!(or_floor(0.8, 0.0, "confidence"), 0.8);
+    }
+}
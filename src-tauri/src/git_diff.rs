@@ -0,0 +1,346 @@
+//! Git diffs read with `git2` and rendered as unified-diff text for model prompts
+//!
+//! Large diffs are fitted to a character budget by first dropping context lines and
+//! then whole hunks, taking each file's hunks in turn so every changed file keeps its
+//! first hunks rather than the first files keeping all of theirs.
+
+use std::path::Path;
+
+/// Lines of unchanged context git shows around each change by default
+pub const DEFAULT_CONTEXT_LINES: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffHunk {
+    /// e.g. `@@ -3,2 +3,4 @@ fn main() {`
+    pub header: String,
+    /// First line of the hunk in the new file, 1-based
+    pub new_start: u32,
+    /// Lines with their `+`, `-` or ` ` origin prefix
+    pub lines: Vec<String>,
+}
+
+impl DiffHunk {
+    /// Lines the hunk covers in the new file
+    pub fn new_lines(&self) -> u32 {
+        self.lines.iter().filter(|line| !line.starts_with('-')).count() as u32
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDiff {
+    /// Path relative to the repository root, the new path for renames
+    pub path: String,
+    /// `added`, `deleted`, `modified`, `renamed` or `binary`
+    pub status: String,
+    pub hunks: Vec<DiffHunk>,
+}
+
+impl FileDiff {
+    pub fn additions(&self) -> usize {
+        self.changed_lines('+')
+    }
+
+    pub fn deletions(&self) -> usize {
+        self.changed_lines('-')
+    }
+
+    fn changed_lines(&self, origin: char) -> usize {
+        self.hunks.iter().flat_map(|hunk| &hunk.lines).filter(|line| line.starts_with(origin)).count()
+    }
+
+    /// The file's diff as text; without context only the changed lines are kept
+    pub fn patch(&self, with_context: bool) -> String {
+        self.patch_of(self.hunks.iter(), with_context)
+    }
+
+    fn patch_of<'a>(&self, hunks: impl Iterator<Item = &'a DiffHunk>, with_context: bool) -> String {
+        let mut patch = format!("--- {} ({})\n", self.path, self.status);
+        for hunk in hunks {
+            patch.push_str(&hunk_text(hunk, with_context));
+        }
+        patch
+    }
+}
+
+/// The hunk with each line that exists in the new file prefixed by its line number
+fn numbered_hunk_text(hunk: &DiffHunk) -> String {
+    let mut text = format!("{}\n", hunk.header);
+    let mut line_number = hunk.new_start;
+    for line in &hunk.lines {
+        if line.starts_with('-') {
+            text.push_str(&format!("{:>6} {}\n", "", line));
+        } else {
+            text.push_str(&format!("{:>6} {}\n", line_number, line));
+            line_number += 1;
+        }
+    }
+    text
+}
+
+fn hunk_text(hunk: &DiffHunk, with_context: bool) -> String {
+    let mut text = format!("{}\n", hunk.header);
+    for line in hunk.lines.iter().filter(|line| with_context || !line.starts_with(' ')) {
+        text.push_str(line);
+        text.push('\n');
+    }
+    text
+}
+
+/// Diff of the repository at `repo_path` against `base_ref`, `HEAD` by default: the
+/// staged changes when `staged_only`, otherwise staged and working-tree changes together.
+/// git reads the repository on the blocking thread pool.
+pub async fn read_diff(repo_path: &Path, base_ref: Option<&str>, staged_only: bool, context_lines: u32) -> Result<Vec<FileDiff>, git2::Error> {
+    let repo_path = repo_path.to_path_buf();
+    let base_ref = base_ref.map(String::from);
+    tokio::task::spawn_blocking(move || read_diff_blocking(&repo_path, base_ref.as_deref(), staged_only, context_lines))
+        .await
+        .map_err(|e| git2::Error::from_str(&format!("Diff task failed: {}", e)))?
+}
+
+fn read_diff_blocking(repo_path: &Path, base_ref: Option<&str>, staged_only: bool, context_lines: u32) -> Result<Vec<FileDiff>, git2::Error> {
+    let repo = git2::Repository::open(repo_path)?;
+    let base_tree = match base_ref {
+        Some(base_ref) => Some(repo.revparse_single(base_ref)?.peel_to_tree()?),
+        // Before the first commit everything is new
+        None => match repo.head() {
+            Ok(head) => Some(head.peel_to_tree()?),
+            Err(e) if e.code() == git2::ErrorCode::UnbornBranch => None,
+            Err(e) => return Err(e),
+        },
+    };
+    let mut options = git2::DiffOptions::new();
+    options
+        .context_lines(context_lines)
+        .include_untracked(!staged_only)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+    let diff = if staged_only {
+        repo.diff_tree_to_index(base_tree.as_ref(), None, Some(&mut options))?
+    } else {
+        repo.diff_tree_to_workdir_with_index(base_tree.as_ref(), Some(&mut options))?
+    };
+
+    let mut files = Vec::new();
+    for index in 0..diff.deltas().len() {
+        let delta = diff.get_delta(index).expect("delta index in range");
+        let path = delta.new_file().path().or(delta.old_file().path());
+        let Some(path) = path.map(|path| path.to_string_lossy().to_string()) else {
+            continue;
+        };
+        let mut status = match delta.status() {
+            git2::Delta::Added | git2::Delta::Untracked => "added",
+            git2::Delta::Deleted => "deleted",
+            git2::Delta::Renamed => "renamed",
+            _ => "modified",
+        }
+        .to_string();
+
+        let mut hunks = Vec::new();
+        match git2::Patch::from_diff(&diff, index)? {
+            Some(patch) => {
+                for hunk_index in 0..patch.num_hunks() {
+                    let (hunk, line_count) = patch.hunk(hunk_index)?;
+                    let mut lines = Vec::new();
+                    for line_index in 0..line_count {
+                        let line = patch.line_in_hunk(hunk_index, line_index)?;
+                        if matches!(line.origin(), '+' | '-' | ' ') {
+                            let content = String::from_utf8_lossy(line.content());
+                            lines.push(format!("{}{}", line.origin(), content.trim_end_matches(['\n', '\r'])));
+                        }
+                    }
+                    hunks.push(DiffHunk {
+                        header: String::from_utf8_lossy(hunk.header()).trim_end().to_string(),
+                        new_start: hunk.new_start(),
+                        lines,
+                    });
+                }
+            }
+            None => status = "binary".to_string(),
+        }
+        files.push(FileDiff { path, status, hunks });
+    }
+    Ok(files)
+}
+
+/// Files with reviewable content grouped into chunks of at most `budget` characters,
+/// each rendered with new-file line numbers. A file never spans chunks; one too large
+/// for a chunk of its own keeps as many of its hunks as fit.
+pub fn numbered_chunks(files: &[FileDiff], budget: usize) -> Vec<(Vec<&FileDiff>, String)> {
+    let mut chunks: Vec<(Vec<&FileDiff>, String)> = Vec::new();
+    for file in files.iter().filter(|file| file.status != "deleted" && !file.hunks.is_empty()) {
+        let mut text = format!("--- {} ({})\n", file.path, file.status);
+        for hunk in &file.hunks {
+            let hunk_text = numbered_hunk_text(hunk);
+            if text.len() + hunk_text.len() > budget {
+                text.push_str("[remaining hunks omitted]\n");
+                break;
+            }
+            text.push_str(&hunk_text);
+        }
+
+        match chunks.last_mut() {
+            Some((chunk_files, chunk_text)) if chunk_text.len() + text.len() <= budget => {
+                chunk_files.push(file);
+                chunk_text.push_str(&text);
+            }
+            _ => chunks.push((vec![file], text)),
+        }
+    }
+    chunks
+}
+
+/// The diff as text of at most `budget` characters. Context lines go first, then
+/// whole hunks, and an omission note says how many hunks were left out.
+pub fn render_within_budget(files: &[FileDiff], budget: usize) -> String {
+    let full: String = files.iter().map(|file| file.patch(true)).collect();
+    if full.len() <= budget {
+        return full;
+    }
+    let compact: String = files.iter().map(|file| file.patch(false)).collect();
+    if compact.len() <= budget {
+        return compact;
+    }
+
+    let summary: String = files.iter()
+        .map(|file| format!("{} ({}, +{} -{})\n", file.path, file.status, file.additions(), file.deletions()))
+        .collect();
+    let total_hunks: usize = files.iter().map(|file| file.hunks.len()).sum();
+    // Room for the note, whose counts are at most this long
+    let note_room = format!("\n[diff truncated: {} of {} hunks shown]\n", total_hunks, total_hunks).len();
+    let mut used = summary.len() + 1 + note_room;
+
+    // Take hunks round-robin across files while they fit
+    let mut shown: Vec<Vec<&DiffHunk>> = vec![Vec::new(); files.len()];
+    let mut full_files = vec![false; files.len()];
+    let rounds = files.iter().map(|file| file.hunks.len()).max().unwrap_or(0);
+    for round in 0..rounds {
+        for (index, file) in files.iter().enumerate() {
+            let Some(hunk) = file.hunks.get(round).filter(|_| !full_files[index]) else {
+                continue;
+            };
+            let header = if shown[index].is_empty() { file.patch_of(std::iter::empty(), false).len() } else { 0 };
+            let cost = header + hunk_text(hunk, false).len();
+            if used + cost > budget {
+                full_files[index] = true;
+                continue;
+            }
+            used += cost;
+            shown[index].push(hunk);
+        }
+    }
+
+    let mut text = format!("{}\n", summary);
+    for (file, hunks) in files.iter().zip(&shown) {
+        if !hunks.is_empty() {
+            text.push_str(&file.patch_of(hunks.iter().copied(), false));
+        }
+    }
+    let shown_hunks: usize = shown.iter().map(Vec::len).sum();
+    text.push_str(&format!("\n[diff truncated: {} of {} hunks shown]\n", shown_hunks, total_hunks));
+    if text.len() > budget {
+        // Only a summary too long for the budget gets here
+        let mut end = budget;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, hunk_count: usize) -> FileDiff {
+        FileDiff {
+            path: path.to_string(),
+            status: "modified".to_string(),
+            hunks: (0..hunk_count)
+                .map(|index| DiffHunk {
+                    header: format!("@@ -{0},3 +{0},3 @@", index * 100 + 1),
+                    new_start: index as u32 * 100 + 1,
+                    lines: vec![
+                        format!(" context line {} of {}", index, path),
+                        format!("-old line {} of {}", index, path),
+                        format!("+new line {} of {}", index, path),
+                        " more context".repeat(4),
+                    ],
+                })
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_diff_without_context_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("a.txt"), "one\ntwo\nthree\n").unwrap();
+
+        let repo = git2::Repository::init(root).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Base", &tree, &[]).unwrap();
+
+        std::fs::write(root.join("a.txt"), "one\n2\nthree\n").unwrap();
+        std::fs::write(root.join("new.txt"), "fresh\n").unwrap();
+
+        let files = read_diff(root, None, false, 0).await.unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "a.txt");
+        assert_eq!(files[0].hunks[0].lines, vec!["-two", "+2"]);
+        assert_eq!((files[0].hunks[0].new_start, files[0].hunks[0].new_lines()), (2, 1));
+        assert_eq!((files[1].path.as_str(), files[1].status.as_str()), ("new.txt", "added"));
+
+        let with_context = read_diff(root, Some("HEAD"), false, DEFAULT_CONTEXT_LINES).await.unwrap();
+        assert_eq!(with_context[0].hunks[0].lines, vec![" one", "-two", "+2", " three"]);
+        assert!(read_diff(root, None, true, 0).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_large_diffs_drop_context_then_hunks_fairly() {
+        let files = vec![file("src/big.rs", 20), file("src/small.rs", 1)];
+        let full = render_within_budget(&files, usize::MAX);
+        assert!(full.contains(" context line 0"));
+
+        // Dropping context is enough
+        let compact_len: usize = files.iter().map(|file| file.patch(false).len()).sum();
+        let compact = render_within_budget(&files, compact_len);
+        assert!(!compact.contains("context line"));
+        assert!(compact.contains("+new line 19 of src/big.rs"));
+
+        // Hunks are left out, but every file keeps its first hunk
+        let truncated = render_within_budget(&files, 600);
+        assert!(truncated.len() <= 600);
+        assert!(truncated.contains("src/big.rs (modified, +20 -20)"));
+        assert!(truncated.contains("+new line 0 of src/big.rs"));
+        assert!(truncated.contains("+new line 0 of src/small.rs"));
+        assert!(!truncated.contains("+new line 19 of src/big.rs"));
+        assert!(truncated.contains("hunks shown]"));
+    }
+
+    #[test]
+    fn test_numbered_chunks_keep_files_whole() {
+        let mut modified = file("src/a.rs", 1);
+        modified.hunks[0].header = "@@ -10,4 +10,4 @@".to_string();
+        modified.hunks[0].new_start = 10;
+        let files = vec![modified, file("src/b.rs", 1), file("src/big.rs", 30)];
+
+        let chunks = numbered_chunks(&files, 500);
+        let paths: Vec<Vec<&str>> = chunks.iter()
+            .map(|(files, _)| files.iter().map(|file| file.path.as_str()).collect())
+            .collect();
+        assert_eq!(paths, vec![vec!["src/a.rs", "src/b.rs"], vec!["src/big.rs"]]);
+        assert!(chunks.iter().all(|(_, text)| text.len() <= 500 + "[remaining hunks omitted]\n".len()));
+
+        // Context and added lines carry their new-file line numbers, removed lines none
+        let text = &chunks[0].1;
+        assert!(text.contains("    10  context line 0 of src/a.rs"));
+        assert!(text.contains("       -old line 0 of src/a.rs"));
+        assert!(text.contains("    11 +new line 0 of src/a.rs"));
+        assert!(chunks[1].1.ends_with("[remaining hunks omitted]\n"));
+    }
+}
@@ -0,0 +1,149 @@
+//! Bounded history of recent health check results, used for uptime and latency graphs
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Number of samples kept per service
+pub const DEFAULT_HEALTH_HISTORY_SIZE: usize = 120;
+
+/// One recorded health check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthSample {
+    pub timestamp: u64, // Unix timestamp
+    pub success: bool,
+    pub response_time_ms: u64,
+}
+
+/// Ring buffer of the most recent health samples
+#[derive(Debug, Clone)]
+pub struct HealthHistory {
+    samples: VecDeque<HealthSample>,
+    capacity: usize,
+}
+
+impl Default for HealthHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HEALTH_HISTORY_SIZE)
+    }
+}
+
+impl HealthHistory {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Record a check, dropping the oldest sample once full
+    pub fn record(&mut self, timestamp: u64, success: bool, response_time: Duration) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(HealthSample {
+            timestamp,
+            success,
+            response_time_ms: response_time.as_millis() as u64,
+        });
+    }
+
+    /// Samples from oldest to newest
+    pub fn samples(&self) -> Vec<HealthSample> {
+        self.samples.iter().cloned().collect()
+    }
+
+    /// Drop samples recorded before `cutoff` (Unix seconds); returns how many were dropped
+    pub fn trim_before(&mut self, cutoff: u64) -> usize {
+        let before = self.samples.len();
+        self.samples.retain(|sample| sample.timestamp >= cutoff);
+        before - self.samples.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Percentage of successful samples in the window, or None with no samples
+    pub fn uptime_percentage(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let successes = self.samples.iter().filter(|sample| sample.success).count();
+        Some(successes as f64 * 100.0 / self.samples.len() as f64)
+    }
+
+    pub fn report(&self, service: &str) -> HealthHistoryReport {
+        HealthHistoryReport {
+            service: service.to_string(),
+            samples: self.samples(),
+            capacity: self.capacity,
+            uptime_percentage: self.uptime_percentage(),
+        }
+    }
+}
+
+/// Health history as returned to the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthHistoryReport {
+    pub service: String,
+    pub samples: Vec<HealthSample>,
+    pub capacity: usize,
+    pub uptime_percentage: Option<f64>,
+}
+
+/// Unix time `max_age` ago, the cutoff for trimming health history
+pub fn history_cutoff(max_age: Duration) -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        .saturating_sub(max_age.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_caps_at_capacity() {
+        let mut history = HealthHistory::new(3);
+        for timestamp in 0..5 {
+            history.record(timestamp, true, Duration::from_millis(timestamp * 10));
+        }
+
+        let samples = history.samples();
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples.iter().map(|sample| sample.timestamp).collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(samples[2].response_time_ms, 40);
+    }
+
+    #[test]
+    fn test_trim_before_drops_old_samples() {
+        let mut history = HealthHistory::new(10);
+        for timestamp in [100, 200, 300] {
+            history.record(timestamp, true, Duration::from_millis(1));
+        }
+
+        assert_eq!(history.trim_before(200), 1);
+        assert_eq!(history.samples().first().map(|sample| sample.timestamp), Some(200));
+    }
+
+    #[test]
+    fn test_uptime_percentage_over_window() {
+        let mut history = HealthHistory::new(4);
+        assert_eq!(history.uptime_percentage(), None);
+
+        for (timestamp, success) in [true, false, true, true].into_iter().enumerate() {
+            history.record(timestamp as u64, success, Duration::from_millis(5));
+        }
+        assert_eq!(history.uptime_percentage(), Some(75.0));
+
+        // The failure drops out of the window
+        history.record(4, true, Duration::from_millis(5));
+        history.record(5, true, Duration::from_millis(5));
+        assert_eq!(history.uptime_percentage(), Some(100.0));
+        assert_eq!(history.report("ollama").samples.len(), 4);
+    }
+}
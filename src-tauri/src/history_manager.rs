@@ -1,17 +1,44 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tauri::{AppHandle, Manager, State};
 use tokio::sync::Mutex;
 use chrono::{DateTime, Utc};
+use crate::history_storage::{self, HistoryBackend, JsonFileBackend, StorageBackend};
+use crate::model_aliases::ModelRole;
+use crate::ollama_client::{self, OllamaClient, SharedOllamaClient};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
+    /// Supplied by the client or generated; a message appended again with the same id is stored once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Position in the session, counting from 1 without gaps
+    #[serde(default)]
+    pub sequence: u64,
     pub role: String,
     pub content: String,
     pub timestamp: DateTime<Utc>,
+    /// Files and images that were in the context for this message
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+}
+
+/// A file or image referenced in the context of a message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub path: String,
+    pub kind: AttachmentKind,
+    /// Tokens it took up in the context window
+    pub token_count: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentKind {
+    File,
+    Image,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +51,59 @@ pub struct ChatSession {
     pub updated_at: DateTime<Utc>,
     pub tags: Vec<String>,
     pub context: Option<ChatContext>,
+    /// Every branch of the conversation once a message has been edited; empty until then
+    #[serde(default)]
+    pub branches: Vec<ChatBranch>,
+    /// The branch whose messages are in `messages`
+    #[serde(default = "main_branch_id")]
+    pub active_branch: String,
+    /// When the session was deleted; it can be restored until it is removed for good
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Running summary of the active branch, used in place of its earlier messages once
+    /// the history outgrows the context
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<SessionSummary>,
+}
+
+/// Compact memory of a session's messages up to `covers_through`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub content: String,
+    /// Sequence of the last message the summary covers
+    pub covers_through: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Id of the branch a session starts on
+pub const MAIN_BRANCH: &str = "main";
+
+fn main_branch_id() -> String {
+    MAIN_BRANCH.to_string()
+}
+
+/// A line of the conversation, forked from its parent by editing a message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatBranch {
+    pub id: String,
+    /// None for the original conversation
+    pub parent_id: Option<String>,
+    /// Sequence of the edited message this branch starts from
+    pub forked_at: Option<u64>,
+    pub created_at: DateTime<Utc>,
+    /// Held here while another branch is active; the active branch's are the session's `messages`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<ChatMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchSummary {
+    pub id: String,
+    pub parent_id: Option<String>,
+    pub forked_at: Option<u64>,
+    pub created_at: DateTime<Utc>,
+    pub message_count: usize,
+    pub active: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,40 +123,106 @@ pub struct CodeSnippet {
     pub end_line: Option<u32>,
 }
 
+/// Which sessions `filter_sessions` returns; every condition given must hold
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionFilter {
+    /// Sessions carrying all of these tags
+    pub tags: Vec<String>,
+    /// Sessions last updated within this range
+    pub date_range: Option<DateRange>,
+    /// Text in the title, a tag or a message, ignoring case
+    pub query: Option<String>,
+}
+
+/// Bounds are inclusive; an open end is unbounded
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DateRange {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl SessionFilter {
+    fn matches(&self, session: &ChatSession) -> bool {
+        if !self.tags.iter().all(|tag| session.tags.contains(tag)) {
+            return false;
+        }
+        if let Some(range) = &self.date_range {
+            if range.from.is_some_and(|from| session.updated_at < from) || range.to.is_some_and(|to| session.updated_at > to) {
+                return false;
+            }
+        }
+        match &self.query {
+            Some(query) => {
+                let query = query.to_lowercase();
+                session.title.to_lowercase().contains(&query) ||
+                session.tags.iter().any(|tag| tag.to_lowercase().contains(&query)) ||
+                session.messages.iter().any(|msg| msg.content.to_lowercase().contains(&query))
+            }
+            None => true,
+        }
+    }
+}
+
+/// Order of a page of sessions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionSort {
+    /// Most recently updated first
+    #[default]
+    Recent,
+    /// By title, ignoring case
+    Title,
+}
+
+impl SessionSort {
+    fn compare(self, a: &ChatSession, b: &ChatSession) -> std::cmp::Ordering {
+        let order = match self {
+            SessionSort::Recent => b.updated_at.cmp(&a.updated_at),
+            SessionSort::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+        };
+        order.then_with(|| a.id.cmp(&b.id))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionPage {
+    pub sessions: Vec<ChatSession>,
+    /// Sessions matching the filter across all pages
+    pub total: usize,
+}
+
 pub struct HistoryManager {
-    storage_path: PathBuf,
+    storage: Box<dyn StorageBackend>,
     sessions: HashMap<String, ChatSession>,
 }
 
 impl HistoryManager {
-    pub fn new(app_handle: &AppHandle) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(app_handle: &AppHandle, backend: HistoryBackend) -> Result<Self, Box<dyn std::error::Error>> {
         // Get the app data directory
         let app_dir = app_handle
             .path()
             .app_data_dir()
             .map_err(|e| format!("Failed to get app data directory: {}", e))?;
             
-        // Create the history directory if it doesn't exist
-        let history_dir = app_dir.join("history");
-        fs::create_dir_all(&history_dir)?;
-        
-        // Load existing sessions
+        Self::with_backend(history_storage::open_backend(backend, &app_dir.join("history"))?)
+    }
+    
+    /// Load the sessions stored as JSON files in `history_dir`, creating it if it doesn't exist
+    pub fn load(history_dir: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_backend(Box::new(JsonFileBackend::open(history_dir)?))
+    }
+    
+    /// Load the sessions kept in `storage`, which then stores every change
+    pub fn with_backend(storage: Box<dyn StorageBackend>) -> Result<Self, Box<dyn std::error::Error>> {
         let mut sessions = HashMap::new();
-        for entry in fs::read_dir(&history_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if path.is_file() && path.extension().map_or(false, |ext| ext == "json") {
-                if let Ok(content) = fs::read_to_string(&path) {
-                    if let Ok(session) = serde_json::from_str::<ChatSession>(&content) {
-                        sessions.insert(session.id.clone(), session);
-                    }
-                }
-            }
+        for mut session in storage.load_sessions()? {
+            number_messages(&mut session);
+            sessions.insert(session.id.clone(), session);
         }
         
         Ok(Self {
-            storage_path: history_dir,
+            storage,
             sessions,
         })
     }
@@ -85,9 +231,55 @@ impl HistoryManager {
         self.sessions.get(id).cloned()
     }
     
-    pub fn list_sessions(&self) -> Vec<ChatSession> {
-        let mut sessions: Vec<ChatSession> = self.sessions.values().cloned().collect();
-        sessions.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    /// Sessions, most recently updated first; deleted ones only with `include_deleted`
+    pub fn list_sessions(&self, include_deleted: bool) -> Vec<ChatSession> {
+        self.filter_sessions(&SessionFilter::default(), include_deleted)
+    }
+    
+    /// Sessions matching `filter`, most recently updated first
+    pub fn filter_sessions(&self, filter: &SessionFilter, include_deleted: bool) -> Vec<ChatSession> {
+        self.matching_sessions(filter, include_deleted, SessionSort::Recent)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+    
+    /// Up to `limit` of the sessions matching `filter`, starting at `offset`, along with
+    /// how many match in all. Sessions that sort equally are ordered by id, so pages
+    /// neither overlap nor skip sessions while the history is unchanged.
+    pub fn page_sessions(
+        &self,
+        filter: &SessionFilter,
+        include_deleted: bool,
+        sort: SessionSort,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> SessionPage {
+        let sessions = self.matching_sessions(filter, include_deleted, sort);
+        SessionPage {
+            total: sessions.len(),
+            sessions: sessions.into_iter()
+                .skip(offset)
+                .take(limit.unwrap_or(usize::MAX))
+                .cloned()
+                .collect(),
+        }
+    }
+    
+    fn matching_sessions(&self, filter: &SessionFilter, include_deleted: bool, sort: SessionSort) -> Vec<&ChatSession> {
+        // A search index narrows down the sessions whose text has to be checked
+        let candidates = filter.query.as_deref().and_then(|query| {
+            self.storage.search_candidates(query).unwrap_or_else(|e| {
+                eprintln!("Chat history search index unavailable: {}", e);
+                None
+            })
+        });
+        let mut sessions: Vec<&ChatSession> = self.sessions.values()
+            .filter(|session| include_deleted || session.deleted_at.is_none())
+            .filter(|session| candidates.as_ref().map_or(true, |ids| ids.contains(&session.id)))
+            .filter(|session| filter.matches(session))
+            .collect();
+        sessions.sort_by(|a, b| sort.compare(a, b));
         sessions
     }
     
@@ -104,6 +296,10 @@ impl HistoryManager {
             updated_at: now,
             tags: Vec::new(),
             context: None,
+            branches: Vec::new(),
+            active_branch: main_branch_id(),
+            deleted_at: None,
+            summary: None,
         };
         
         self.sessions.insert(id.clone(), session.clone());
@@ -122,25 +318,98 @@ impl HistoryManager {
         Ok(())
     }
     
+    /// Hide a session from listings and searches, keeping it to be restored until
+    /// `purge_deleted_sessions` removes it
     pub fn delete_session(&mut self, id: &str) -> Result<(), Box<dyn std::error::Error>> {
-        if self.sessions.remove(id).is_some() {
-            let file_path = self.storage_path.join(format!("{}.json", id));
-            if file_path.exists() {
-                fs::remove_file(file_path)?;
+        if let Some(session) = self.sessions.get_mut(id) {
+            if session.deleted_at.is_none() {
+                session.deleted_at = Some(Utc::now());
+                let session = session.clone();
+                self.save_session(&session)?;
             }
         }
         
         Ok(())
     }
     
+    pub fn restore_session(&mut self, id: &str) -> Result<ChatSession, Box<dyn std::error::Error>> {
+        let session = self.sessions.get_mut(id)
+            .ok_or_else(|| format!("Session not found: {}", id))?;
+        if session.deleted_at.take().is_none() {
+            return Err(format!("Session is not deleted: {}", id).into());
+        }
+        let session = session.clone();
+        self.save_session(&session)?;
+        
+        Ok(session)
+    }
+    
+    /// Remove sessions deleted before `deleted_before` for good, returning how many
+    pub fn purge_deleted_sessions(&mut self, deleted_before: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error>> {
+        let ids: Vec<String> = self.sessions.values()
+            .filter(|session| session.deleted_at.is_some_and(|deleted_at| deleted_at < deleted_before))
+            .map(|session| session.id.clone())
+            .collect();
+        for id in &ids {
+            self.remove_session(id)?;
+        }
+        Ok(ids.len())
+    }
+    
+    fn remove_session(&mut self, id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if self.sessions.remove(id).is_some() {
+            self.storage.remove_session(id)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Delete sessions last updated before `older_than`, or all of them, returning how
+    /// many there were. A dry run only counts.
+    pub fn purge_sessions(&mut self, older_than: Option<DateTime<Utc>>, dry_run: bool) -> Result<usize, Box<dyn std::error::Error>> {
+        let ids: Vec<String> = self.sessions.values()
+            .filter(|session| older_than.map_or(true, |cutoff| session.updated_at < cutoff))
+            .map(|session| session.id.clone())
+            .collect();
+        if !dry_run {
+            for id in &ids {
+                self.remove_session(id)?;
+            }
+        }
+        Ok(ids.len())
+    }
+    
     pub fn add_message(&mut self, session_id: &str, role: &str, content: &str) -> Result<ChatSession, Box<dyn std::error::Error>> {
+        self.append_message(session_id, role, content, None, Vec::new())
+    }
+    
+    /// Append a message with the next sequence number. Appending a `message_id` the
+    /// session already has returns the session unchanged, so retried appends are safe.
+    /// Callers share the manager behind one lock, which keeps appends to a session in
+    /// a single order both in memory and on disk.
+    pub fn append_message(
+        &mut self,
+        session_id: &str,
+        role: &str,
+        content: &str,
+        message_id: Option<&str>,
+        attachments: Vec<Attachment>,
+    ) -> Result<ChatSession, Box<dyn std::error::Error>> {
         let mut session = self.get_session(session_id)
             .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        if let Some(message_id) = message_id {
+            if session.messages.iter().any(|message| message.id.as_deref() == Some(message_id)) {
+                return Ok(session);
+            }
+        }
             
         let message = ChatMessage {
+            id: Some(message_id.map_or_else(new_message_id, str::to_string)),
+            sequence: session.messages.last().map_or(0, |message| message.sequence) + 1,
             role: role.to_string(),
             content: content.to_string(),
             timestamp: Utc::now(),
+            attachments,
         };
         
         session.messages.push(message);
@@ -152,6 +421,24 @@ impl HistoryManager {
         Ok(session)
     }
     
+    /// Add `text` to the end of a message in the active branch, as when a reply that
+    /// was cut off is continued
+    pub fn extend_message(&mut self, session_id: &str, message_id: &str, text: &str) -> Result<ChatSession, Box<dyn std::error::Error>> {
+        let mut session = self.get_session(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        let message = session.messages.iter_mut()
+            .find(|message| message.id.as_deref() == Some(message_id))
+            .ok_or_else(|| format!("Message not found in the active branch: {}", message_id))?;
+        
+        message.content.push_str(text);
+        session.updated_at = Utc::now();
+        
+        self.sessions.insert(session_id.to_string(), session.clone());
+        self.save_session(&session)?;
+        
+        Ok(session)
+    }
+    
     pub fn update_context(&mut self, session_id: &str, context: ChatContext) -> Result<ChatSession, Box<dyn std::error::Error>> {
         let mut session = self.get_session(session_id)
             .ok_or_else(|| format!("Session not found: {}", session_id))?;
@@ -165,6 +452,23 @@ impl HistoryManager {
         Ok(session)
     }
     
+    /// Store `summary` on the session, unless it covers messages the active branch no
+    /// longer has because the session was edited or switched while it was written
+    pub fn set_summary(&mut self, session_id: &str, summary: SessionSummary) -> Result<ChatSession, Box<dyn std::error::Error>> {
+        let mut session = self.get_session(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        if session.messages.last().map_or(0, |message| message.sequence) < summary.covers_through {
+            return Err(format!("Session {} changed while it was being summarized", session_id).into());
+        }
+
+        session.summary = Some(summary);
+
+        self.sessions.insert(session_id.to_string(), session.clone());
+        self.save_session(&session)?;
+
+        Ok(session)
+    }
+
     pub fn add_tag(&mut self, session_id: &str, tag: &str) -> Result<ChatSession, Box<dyn std::error::Error>> {
         let mut session = self.get_session(session_id)
             .ok_or_else(|| format!("Session not found: {}", session_id))?;
@@ -193,49 +497,280 @@ impl HistoryManager {
         Ok(session)
     }
     
-    pub fn search_sessions(&self, query: &str) -> Vec<ChatSession> {
-        let query = query.to_lowercase();
-        let mut results: Vec<ChatSession> = self.sessions.values()
-            .filter(|session| {
-                session.title.to_lowercase().contains(&query) ||
-                session.tags.iter().any(|tag| tag.to_lowercase().contains(&query)) ||
-                session.messages.iter().any(|msg| msg.content.to_lowercase().contains(&query))
+    /// Fork the conversation at `message_id` into a new branch where the message reads
+    /// `new_content`. The new branch becomes active, ending at the edited message so the
+    /// reply can be regenerated; the original branch keeps every message. A summary that
+    /// reaches the edited message is dropped.
+    pub fn edit_message(&mut self, session_id: &str, message_id: &str, new_content: &str) -> Result<ChatSession, Box<dyn std::error::Error>> {
+        let mut session = self.get_session(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        let index = session.messages.iter()
+            .position(|message| message.id.as_deref() == Some(message_id))
+            .ok_or_else(|| format!("Message not found in the active branch: {}", message_id))?;
+        
+        let now = Utc::now();
+        let edited = ChatMessage {
+            id: Some(new_message_id()),
+            content: new_content.to_string(),
+            timestamp: now,
+            ..session.messages[index].clone()
+        };
+        let mut messages = session.messages[..index].to_vec();
+        messages.push(edited);
+        
+        if session.branches.is_empty() {
+            session.branches.push(ChatBranch {
+                id: session.active_branch.clone(),
+                parent_id: None,
+                forked_at: None,
+                created_at: session.created_at,
+                messages: Vec::new(),
+            });
+        }
+        let branch = ChatBranch {
+            id: format!("branch_{}", uuid::Uuid::new_v4()),
+            parent_id: Some(session.active_branch.clone()),
+            forked_at: Some(session.messages[index].sequence),
+            created_at: now,
+            messages: Vec::new(),
+        };
+        let branch_id = branch.id.clone();
+        session.branches.push(branch);
+        if session.summary.as_ref().is_some_and(|summary| summary.covers_through >= session.messages[index].sequence) {
+            session.summary = None;
+        }
+        stash_active_branch(&mut session, messages, branch_id);
+        session.updated_at = now;
+        
+        self.sessions.insert(session_id.to_string(), session.clone());
+        self.save_session(&session)?;
+        
+        Ok(session)
+    }
+    
+    pub fn list_session_branches(&self, session_id: &str) -> Result<Vec<BranchSummary>, Box<dyn std::error::Error>> {
+        let session = self.sessions.get(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        if session.branches.is_empty() {
+            return Ok(vec![BranchSummary {
+                id: session.active_branch.clone(),
+                parent_id: None,
+                forked_at: None,
+                created_at: session.created_at,
+                message_count: session.messages.len(),
+                active: true,
+            }]);
+        }
+        
+        Ok(session.branches.iter()
+            .map(|branch| {
+                let active = branch.id == session.active_branch;
+                BranchSummary {
+                    id: branch.id.clone(),
+                    parent_id: branch.parent_id.clone(),
+                    forked_at: branch.forked_at,
+                    created_at: branch.created_at,
+                    message_count: if active { session.messages.len() } else { branch.messages.len() },
+                    active,
+                }
             })
-            .cloned()
-            .collect();
-            
-        results.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
-        results
+            .collect())
+    }
+    
+    /// Make `branch_id` the session's active branch, loading its messages. The summary,
+    /// which described the previous branch, is dropped.
+    pub fn switch_branch(&mut self, session_id: &str, branch_id: &str) -> Result<ChatSession, Box<dyn std::error::Error>> {
+        let mut session = self.get_session(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        if session.active_branch == branch_id {
+            return Ok(session);
+        }
+        let target = session.branches.iter_mut()
+            .find(|branch| branch.id == branch_id)
+            .ok_or_else(|| format!("Branch not found: {}", branch_id))?;
+        let messages = std::mem::take(&mut target.messages);
+        stash_active_branch(&mut session, messages, branch_id.to_string());
+        session.summary = None;
+        session.updated_at = Utc::now();
+        
+        self.sessions.insert(session_id.to_string(), session.clone());
+        self.save_session(&session)?;
+        
+        Ok(session)
+    }
+    
+    pub fn search_sessions(&self, query: &str) -> Vec<ChatSession> {
+        let filter = SessionFilter {
+            query: Some(query.to_string()),
+            ..Default::default()
+        };
+        self.filter_sessions(&filter, false)
     }
     
     pub fn filter_sessions_by_tag(&self, tag: &str) -> Vec<ChatSession> {
-        let mut results: Vec<ChatSession> = self.sessions.values()
-            .filter(|session| session.tags.contains(&tag.to_string()))
-            .cloned()
-            .collect();
-            
-        results.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
-        results
+        let filter = SessionFilter {
+            tags: vec![tag.to_string()],
+            ..Default::default()
+        };
+        self.filter_sessions(&filter, false)
     }
     
     fn save_session(&self, session: &ChatSession) -> Result<(), Box<dyn std::error::Error>> {
-        let file_path = self.storage_path.join(format!("{}.json", session.id));
-        let content = serde_json::to_string_pretty(session)?;
-        fs::write(file_path, content)?;
-        Ok(())
+        self.storage.save_session(session)
+    }
+}
+
+/// Move the active branch's messages into its branch record and make `branch_id`,
+/// holding `messages`, the active branch
+fn stash_active_branch(session: &mut ChatSession, messages: Vec<ChatMessage>, branch_id: String) {
+    let previous = std::mem::replace(&mut session.messages, messages);
+    if let Some(branch) = session.branches.iter_mut().find(|branch| branch.id == session.active_branch) {
+        branch.messages = previous;
+    }
+    session.active_branch = branch_id;
+}
+
+fn new_message_id() -> String {
+    format!("message_{}", uuid::Uuid::new_v4())
+}
+
+/// Number messages saved before they had sequence numbers, or out of order, by position,
+/// and give those saved without an id one so they can be edited
+fn number_messages(session: &mut ChatSession) {
+    let numbered = session.messages.iter().zip(1..).all(|(message, sequence)| message.sequence == sequence);
+    for (message, sequence) in session.messages.iter_mut().zip(1..) {
+        if !numbered {
+            message.sequence = sequence;
+        }
+        message.id.get_or_insert_with(new_message_id);
     }
 }
 
 pub type SharedHistoryManager = Arc<Mutex<HistoryManager>>;
 
+const SUMMARY_SYSTEM_PROMPT: &str = "You maintain a compact memory of a conversation between a user and a coding assistant so it can be continued without the full transcript. \
+Keep every decision made, requirement agreed and open question, and the names of files, functions, commands and errors discussed. \
+Drop greetings, repetition and superseded ideas. Reply with the summary only, as short plain-text notes.";
+
+/// Fold the messages since the session's last summary into a new running summary and
+/// store it on the session. The history lock isn't held while the model writes it.
+pub async fn generate_session_summary(
+    history: &SharedHistoryManager,
+    client: &OllamaClient,
+    session_id: &str,
+    model: &str,
+) -> Result<SessionSummary, Box<dyn std::error::Error>> {
+    let session = history.lock().await.get_session(session_id)
+        .ok_or_else(|| format!("Session not found: {}", session_id))?;
+    let covered = session.summary.as_ref().map_or(0, |summary| summary.covers_through);
+    let new_messages: Vec<&ChatMessage> = session.messages.iter()
+        .filter(|message| message.sequence > covered)
+        .collect();
+    let Some(covers_through) = new_messages.last().map(|message| message.sequence) else {
+        return session.summary.ok_or_else(|| format!("Session {} has no messages to summarize", session_id).into());
+    };
+
+    let mut transcript = String::new();
+    if let Some(summary) = &session.summary {
+        transcript.push_str(&format!("Summary so far:\n{}\n\nNew messages:\n", summary.content));
+    }
+    for message in new_messages {
+        transcript.push_str(&format!("{}: {}\n", message.role, message.content));
+    }
+    let messages = vec![
+        ollama_client::ChatMessage { role: "system".to_string(), content: SUMMARY_SYSTEM_PROMPT.to_string() },
+        ollama_client::ChatMessage { role: "user".to_string(), content: transcript },
+    ];
+    let reply = client.chat(model, messages, None, None::<fn(&str)>).await?;
+    let content = reply.content.trim();
+    if content.is_empty() {
+        return Err("The model returned an empty summary".into());
+    }
+
+    let summary = SessionSummary {
+        content: content.to_string(),
+        covers_through,
+        created_at: Utc::now(),
+    };
+    history.lock().await.set_summary(session_id, summary.clone())?;
+    Ok(summary)
+}
+
+const CONTINUE_INSTRUCTION: &str = "Your last reply was cut off. Continue it from exactly where it stopped, \
+without repeating any of it and without an introduction.";
+
+/// Text the model repeats from the end of the partial reply is dropped only when it's
+/// at least this long, so a continuation that merely starts like the reply ends is kept
+const MIN_REPEATED_OVERLAP: usize = 8;
+
+/// Resume an assistant reply that was cut off by the token limit or a cancellation,
+/// appending the rest to the same message. The conversation up to and including the
+/// partial reply is sent back with a request to carry on from where it stopped.
+pub async fn continue_message(
+    history: &SharedHistoryManager,
+    client: &OllamaClient,
+    session_id: &str,
+    message_id: &str,
+    model: Option<&str>,
+) -> Result<ChatSession, Box<dyn std::error::Error>> {
+    let session = history.lock().await.get_session(session_id)
+        .ok_or_else(|| format!("Session not found: {}", session_id))?;
+    let index = session.messages.iter()
+        .position(|message| message.id.as_deref() == Some(message_id))
+        .ok_or_else(|| format!("Message not found in the active branch: {}", message_id))?;
+    let partial = &session.messages[index];
+    if partial.role != "assistant" {
+        return Err(format!("Only assistant replies can be continued; {} is a {} message", message_id, partial.role).into());
+    }
+
+    let mut messages: Vec<ollama_client::ChatMessage> = session.messages[..=index].iter()
+        .map(|message| ollama_client::ChatMessage { role: message.role.clone(), content: message.content.clone() })
+        .collect();
+    messages.push(ollama_client::ChatMessage { role: "user".to_string(), content: CONTINUE_INSTRUCTION.to_string() });
+    let reply = client.chat(model.unwrap_or(&session.model), messages, None, None::<fn(&str)>).await?;
+
+    let continuation = strip_repeated_overlap(&partial.content, &reply.content);
+    if continuation.trim().is_empty() {
+        return Err("The model returned nothing to continue the reply with".into());
+    }
+    history.lock().await.extend_message(session_id, message_id, continuation)
+}
+
+/// `continuation` without any text it repeats from the end of `partial`
+fn strip_repeated_overlap<'a>(partial: &str, continuation: &'a str) -> &'a str {
+    let trimmed = partial.trim_end();
+    let longest = trimmed.len().min(continuation.len());
+    let rest = (MIN_REPEATED_OVERLAP..=longest).rev()
+        .find(|&len| {
+            trimmed.is_char_boundary(trimmed.len() - len) &&
+            continuation.is_char_boundary(len) &&
+            trimmed[trimmed.len() - len..] == continuation[..len]
+        })
+        .map_or(continuation, |len| &continuation[len..]);
+    // Whitespace the partial reply ends in already separates it from the rest
+    if trimmed.len() < partial.len() { rest.trim_start() } else { rest }
+}
+
 // Tauri commands for history management
 
+/// A page of sessions; without a `limit` every session from `offset` on is returned
 #[tauri::command]
 pub async fn list_chat_sessions(
+    filter: Option<SessionFilter>,
+    include_deleted: Option<bool>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    sort: Option<SessionSort>,
     history_manager: State<'_, SharedHistoryManager>,
-) -> Result<Vec<ChatSession>, String> {
+) -> Result<SessionPage, String> {
     let manager = history_manager.lock().await;
-    Ok(manager.list_sessions())
+    Ok(manager.page_sessions(
+        &filter.unwrap_or_default(),
+        include_deleted.unwrap_or(false),
+        sort.unwrap_or_default(),
+        offset.unwrap_or(0),
+        limit,
+    ))
 }
 
 #[tauri::command]
@@ -275,15 +810,90 @@ pub async fn delete_chat_session(
     manager.delete_session(&id).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn restore_chat_session(
+    id: String,
+    history_manager: State<'_, SharedHistoryManager>,
+) -> Result<ChatSession, String> {
+    let mut manager = history_manager.lock().await;
+    manager.restore_session(&id).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn add_chat_message(
     session_id: String,
     role: String,
     content: String,
+    message_id: Option<String>,
+    attachments: Option<Vec<Attachment>>,
+    history_manager: State<'_, SharedHistoryManager>,
+) -> Result<ChatSession, String> {
+    let mut manager = history_manager.lock().await;
+    manager
+        .append_message(&session_id, &role, &content, message_id.as_deref(), attachments.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+/// Edit a message by forking the session into a new, active branch
+#[tauri::command]
+pub async fn edit_chat_message(
+    session_id: String,
+    message_id: String,
+    new_content: String,
+    history_manager: State<'_, SharedHistoryManager>,
+) -> Result<ChatSession, String> {
+    let mut manager = history_manager.lock().await;
+    manager.edit_message(&session_id, &message_id, &new_content).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_session_branches(
+    session_id: String,
+    history_manager: State<'_, SharedHistoryManager>,
+) -> Result<Vec<BranchSummary>, String> {
+    let manager = history_manager.lock().await;
+    manager.list_session_branches(&session_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn switch_session_branch(
+    session_id: String,
+    branch_id: String,
     history_manager: State<'_, SharedHistoryManager>,
 ) -> Result<ChatSession, String> {
     let mut manager = history_manager.lock().await;
-    manager.add_message(&session_id, &role, &content).map_err(|e| e.to_string())
+    manager.switch_branch(&session_id, &branch_id).map_err(|e| e.to_string())
+}
+
+/// Summarize a session for continuing it once its history outgrows the context;
+/// `model` defaults to the chat alias
+#[tauri::command]
+pub async fn summarize_session(
+    session_id: String,
+    model: Option<String>,
+    history_manager: State<'_, SharedHistoryManager>,
+    ollama_client: State<'_, SharedOllamaClient>,
+) -> Result<SessionSummary, String> {
+    let client = ollama_client.lock().await;
+    let model = model.unwrap_or_else(|| ModelRole::Chat.alias().to_string());
+    generate_session_summary(&history_manager, &client, &session_id, &model)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Continue an assistant reply that was cut off; `model` defaults to the session's
+#[tauri::command]
+pub async fn continue_generation(
+    session_id: String,
+    message_id: String,
+    model: Option<String>,
+    history_manager: State<'_, SharedHistoryManager>,
+    ollama_client: State<'_, SharedOllamaClient>,
+) -> Result<ChatSession, String> {
+    let client = ollama_client.lock().await;
+    continue_message(&history_manager, &client, &session_id, &message_id, model.as_deref())
+        .await
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -333,3 +943,429 @@ pub async fn filter_chat_sessions_by_tag(
     let manager = history_manager.lock().await;
     Ok(manager.filter_sessions_by_tag(&tag))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrent_appends_are_ordered_without_gaps() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = HistoryManager::load(dir.path().to_path_buf()).unwrap();
+        let session_id = manager.create_session("Chat", "llama3").unwrap().id;
+        let history: SharedHistoryManager = Arc::new(Mutex::new(manager));
+
+        // Each task appends its messages twice, as a client retrying every append would
+        let tasks: Vec<_> = (0..8)
+            .map(|task| {
+                let history = history.clone();
+                let session_id = session_id.clone();
+                tokio::spawn(async move {
+                    for index in 0..10 {
+                        let id = format!("{}-{}", task, index);
+                        for _ in 0..2 {
+                            history.lock().await.append_message(&session_id, "user", &id, Some(&id), Vec::new()).unwrap();
+                            tokio::task::yield_now().await;
+                        }
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let session = history.lock().await.get_session(&session_id).unwrap();
+        let sequences: Vec<u64> = session.messages.iter().map(|message| message.sequence).collect();
+        assert_eq!(sequences, (1..=80).collect::<Vec<u64>>());
+        for task in 0..8 {
+            let contents: Vec<&str> = session.messages.iter()
+                .map(|message| message.content.as_str())
+                .filter(|content| content.starts_with(&format!("{}-", task)))
+                .collect();
+            let expected: Vec<String> = (0..10).map(|index| format!("{}-{}", task, index)).collect();
+            assert_eq!(contents, expected);
+        }
+
+        // The saved session has the same order
+        let reloaded = HistoryManager::load(dir.path().to_path_buf()).unwrap().get_session(&session_id).unwrap();
+        let reloaded: Vec<(u64, String)> = reloaded.messages.into_iter().map(|message| (message.sequence, message.content)).collect();
+        let expected: Vec<(u64, String)> = session.messages.into_iter().map(|message| (message.sequence, message.content)).collect();
+        assert_eq!(reloaded, expected);
+    }
+
+    fn contents(session: &ChatSession) -> Vec<&str> {
+        session.messages.iter().map(|message| message.content.as_str()).collect()
+    }
+
+    #[test]
+    fn test_editing_a_message_forks_a_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = HistoryManager::load(dir.path().to_path_buf()).unwrap();
+        let session_id = manager.create_session("Chat", "llama3").unwrap().id;
+        for (role, content) in [("user", "What is Rust?"), ("assistant", "A language."), ("user", "Is it fast?"), ("assistant", "Yes.")] {
+            manager.add_message(&session_id, role, content).unwrap();
+        }
+        let original = manager.get_session(&session_id).unwrap();
+        let edited_id = original.messages[2].id.clone().unwrap();
+
+        let edited = manager.edit_message(&session_id, &edited_id, "Is it memory safe?").unwrap();
+        assert_eq!(contents(&edited), vec!["What is Rust?", "A language.", "Is it memory safe?"]);
+        assert_eq!(edited.messages[2].sequence, 3);
+        assert_ne!(edited.active_branch, MAIN_BRANCH);
+
+        let branches = manager.list_session_branches(&session_id).unwrap();
+        assert_eq!(branches.len(), 2);
+        assert_eq!((branches[0].id.as_str(), branches[0].message_count, branches[0].active), (MAIN_BRANCH, 4, false));
+        assert_eq!(branches[1].parent_id.as_deref(), Some(MAIN_BRANCH));
+        assert_eq!((branches[1].forked_at, branches[1].message_count, branches[1].active), (Some(3), 3, true));
+
+        // The reply on the new branch follows the edit
+        let branched = manager.add_message(&session_id, "assistant", "Yes, without a garbage collector.").unwrap();
+        assert_eq!(branched.messages[3].sequence, 4);
+
+        // Switching back restores the original thread, and the branch tree is saved
+        let main = manager.switch_branch(&session_id, MAIN_BRANCH).unwrap();
+        assert_eq!(contents(&main), contents(&original));
+        let mut reloaded = HistoryManager::load(dir.path().to_path_buf()).unwrap();
+        let branch = reloaded.switch_branch(&session_id, &branches[1].id).unwrap();
+        assert_eq!(contents(&branch), vec!["What is Rust?", "A language.", "Is it memory safe?", "Yes, without a garbage collector."]);
+        assert!(reloaded.switch_branch(&session_id, "missing").is_err());
+    }
+
+    #[test]
+    fn test_sessions_filter_by_tags_date_range_and_query() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = HistoryManager::load(dir.path().to_path_buf()).unwrap();
+        let now = Utc::now();
+        let mut ids = Vec::new();
+        for (title, tags, days_ago) in [
+            ("Borrow checker", vec!["rust", "work"], 1),
+            ("Lifetimes", vec!["rust"], 10),
+            ("Standup notes", vec!["work"], 3),
+            ("Recipes", vec![], 40),
+        ] {
+            let id = manager.create_session(title, "llama3").unwrap().id;
+            for tag in tags {
+                manager.add_tag(&id, tag).unwrap();
+            }
+            manager.sessions.get_mut(&id).unwrap().updated_at = now - chrono::Duration::days(days_ago);
+            ids.push(id);
+        }
+        let titles = |filter: SessionFilter| -> Vec<String> {
+            manager.filter_sessions(&filter, false).into_iter().map(|session| session.title).collect()
+        };
+
+        assert_eq!(titles(SessionFilter { tags: vec!["rust".to_string()], ..Default::default() }), vec!["Borrow checker", "Lifetimes"]);
+        assert_eq!(titles(SessionFilter { tags: vec!["rust".to_string(), "work".to_string()], ..Default::default() }), vec!["Borrow checker"]);
+        let last_week = DateRange { from: Some(now - chrono::Duration::days(7)), to: None };
+        assert_eq!(titles(SessionFilter { date_range: Some(last_week.clone()), ..Default::default() }), vec!["Borrow checker", "Standup notes"]);
+        assert_eq!(
+            titles(SessionFilter { tags: vec!["rust".to_string()], date_range: Some(last_week), ..Default::default() }),
+            vec!["Borrow checker"]
+        );
+        let older = DateRange { from: None, to: Some(now - chrono::Duration::days(5)) };
+        assert_eq!(titles(SessionFilter { date_range: Some(older), query: Some("RECIPES".to_string()), ..Default::default() }), vec!["Recipes"]);
+        assert_eq!(titles(SessionFilter::default()).len(), 4);
+
+        // Tags are saved with the session
+        let reloaded = HistoryManager::load(dir.path().to_path_buf()).unwrap();
+        assert_eq!(reloaded.get_session(&ids[0]).unwrap().tags, vec!["rust", "work"]);
+    }
+
+    #[test]
+    fn test_deleted_sessions_can_be_restored_until_purged() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = HistoryManager::load(dir.path().to_path_buf()).unwrap();
+        let kept = manager.create_session("Kept", "llama3").unwrap().id;
+        let deleted = manager.create_session("Deleted", "llama3").unwrap().id;
+        manager.add_tag(&deleted, "rust").unwrap();
+
+        manager.delete_session(&deleted).unwrap();
+        let visible: Vec<String> = manager.list_sessions(false).into_iter().map(|session| session.id).collect();
+        assert_eq!(visible, vec![kept.clone()]);
+        assert_eq!(manager.list_sessions(true).len(), 2);
+        assert!(manager.search_sessions("Deleted").is_empty());
+        assert!(manager.filter_sessions_by_tag("rust").is_empty());
+
+        // Within the grace period the session survives cleanup and a restart, and can be restored
+        let grace_start = Utc::now() - chrono::Duration::days(7);
+        assert_eq!(manager.purge_deleted_sessions(grace_start).unwrap(), 0);
+        let mut manager = HistoryManager::load(dir.path().to_path_buf()).unwrap();
+        assert_eq!(manager.list_sessions(false).len(), 1);
+        let restored = manager.restore_session(&deleted).unwrap();
+        assert!(restored.deleted_at.is_none());
+        assert_eq!(manager.list_sessions(false).len(), 2);
+        assert!(manager.restore_session(&deleted).is_err());
+
+        // Once the grace period has passed it is removed for good
+        manager.delete_session(&deleted).unwrap();
+        assert_eq!(manager.purge_deleted_sessions(Utc::now() + chrono::Duration::seconds(1)).unwrap(), 1);
+        assert!(manager.get_session(&deleted).is_none());
+        assert!(manager.restore_session(&deleted).is_err());
+        assert!(!dir.path().join(format!("{}.json", deleted)).exists());
+        assert!(manager.get_session(&kept).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_summary_folds_new_messages_into_the_running_summary() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = HistoryManager::load(dir.path().to_path_buf()).unwrap();
+        let session_id = manager.create_session("Chat", "llama3").unwrap().id;
+        manager.add_message(&session_id, "user", "Should the cache use Redis?").unwrap();
+        manager.add_message(&session_id, "assistant", "No, an in-process LRU is enough.").unwrap();
+        let history: SharedHistoryManager = Arc::new(Mutex::new(manager));
+
+        let chat_reply = |content: &str| serde_json::json!({
+            "model": "llama3:latest",
+            "message": {"role": "assistant", "content": content},
+            "done": true,
+        }).to_string();
+        let mut server = mockito::Server::new_async().await;
+        let first = server.mock("POST", "/api/chat")
+            .match_body(mockito::Matcher::Regex("user: Should the cache use Redis\\?".to_string()))
+            .with_body(chat_reply("Decided: in-process LRU cache, not Redis."))
+            .expect(1)
+            .create_async()
+            .await;
+        let client = OllamaClient::new(Some(server.url()));
+
+        let summary = generate_session_summary(&history, &client, &session_id, "llama3").await.unwrap();
+        first.assert_async().await;
+        assert_eq!(summary.content, "Decided: in-process LRU cache, not Redis.");
+        assert_eq!(summary.covers_through, 2);
+
+        // The next summary starts from the previous one and covers only the new messages
+        history.lock().await.add_message(&session_id, "user", "Cap it at 500 entries.").unwrap();
+        let second = server.mock("POST", "/api/chat")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex("Summary so far:\\\\nDecided: in-process LRU cache".to_string()),
+                mockito::Matcher::Regex("user: Cap it at 500 entries".to_string()),
+            ]))
+            .with_body(chat_reply("Decided: in-process LRU cache capped at 500 entries, not Redis."))
+            .expect(1)
+            .create_async()
+            .await;
+        let summary = generate_session_summary(&history, &client, &session_id, "llama3").await.unwrap();
+        second.assert_async().await;
+        assert_eq!(summary.covers_through, 3);
+
+        // The summary is saved with the session
+        let reloaded = HistoryManager::load(dir.path().to_path_buf()).unwrap().get_session(&session_id).unwrap();
+        assert_eq!(reloaded.summary.unwrap().content, "Decided: in-process LRU cache capped at 500 entries, not Redis.");
+    }
+
+    #[test]
+    fn test_attachments_round_trip_with_token_counts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = HistoryManager::load(dir.path().to_path_buf()).unwrap();
+        let session_id = manager.create_session("Chat", "llama3").unwrap().id;
+        let attachments = vec![
+            Attachment { path: "src/cache.rs".to_string(), kind: AttachmentKind::File, token_count: 1_843 },
+            Attachment { path: "docs/architecture.png".to_string(), kind: AttachmentKind::Image, token_count: 765 },
+        ];
+
+        manager.append_message(&session_id, "user", "Why does eviction stall?", None, attachments.clone()).unwrap();
+        manager.add_message(&session_id, "assistant", "The lock is held across the write.").unwrap();
+
+        let session = manager.get_session(&session_id).unwrap();
+        assert_eq!(session.messages[0].attachments, attachments);
+        assert!(session.messages[1].attachments.is_empty());
+
+        let reloaded = HistoryManager::load(dir.path().to_path_buf()).unwrap().get_session(&session_id).unwrap();
+        assert_eq!(reloaded.messages[0].attachments, attachments);
+        let tokens: Vec<usize> = reloaded.messages[0].attachments.iter().map(|attachment| attachment.token_count).collect();
+        assert_eq!(tokens, vec![1_843, 765]);
+    }
+
+    /// Runs the session and message API against a manager, reopening it from `reopen`
+    /// to check what was stored
+    fn exercise_session_api(mut manager: HistoryManager, reopen: impl Fn() -> HistoryManager) {
+        let first = manager.create_session("Caching plan", "llama3").unwrap().id;
+        let second = manager.create_session("Standup", "llama3").unwrap().id;
+        manager.add_tag(&first, "work").unwrap();
+        manager.append_message(&first, "user", "Should the cache use Redis?", Some("m1"), Vec::new()).unwrap();
+        manager.append_message(&first, "user", "Should the cache use Redis?", Some("m1"), Vec::new()).unwrap();
+        manager.add_message(&first, "assistant", "An in-process LRU is enough.").unwrap();
+        manager.add_message(&second, "user", "Yesterday I fixed the Ünicode parser").unwrap();
+        let edited = manager.edit_message(&first, "m1", "Should the cache use Memcached?").unwrap();
+
+        let mut reopened = reopen();
+        let session = reopened.get_session(&first).unwrap();
+        assert_eq!(contents(&session), vec!["Should the cache use Memcached?"]);
+        assert_eq!(session.active_branch, edited.active_branch);
+        assert_eq!(session.tags, vec!["work"]);
+        assert_eq!(reopened.list_session_branches(&first).unwrap().len(), 2);
+        let main = reopened.switch_branch(&first, MAIN_BRANCH).unwrap();
+        assert_eq!(contents(&main), vec!["Should the cache use Redis?", "An in-process LRU is enough."]);
+
+        // Search matches titles, tags and messages ignoring case, including short queries
+        let ids = |sessions: Vec<ChatSession>| -> Vec<String> { sessions.into_iter().map(|session| session.id).collect() };
+        assert_eq!(ids(reopened.search_sessions("in-process lru")), vec![first.clone()]);
+        assert_eq!(ids(reopened.search_sessions("ünicode")), vec![second.clone()]);
+        assert_eq!(ids(reopened.search_sessions("WORK")), vec![first.clone()]);
+        assert_eq!(reopened.search_sessions("up").len(), 1);
+        assert!(reopened.search_sessions("Redis%Memcached").is_empty());
+        assert!(reopened.search_sessions("postgres").is_empty());
+
+        reopened.delete_session(&second).unwrap();
+        assert!(reopened.search_sessions("parser").is_empty());
+        assert_eq!(reopened.purge_deleted_sessions(Utc::now() + chrono::Duration::seconds(1)).unwrap(), 1);
+
+        let reopened = reopen();
+        assert_eq!(ids(reopened.list_sessions(true)), vec![first.clone()]);
+        assert_eq!(contents(&reopened.get_session(&first).unwrap()), vec!["Should the cache use Redis?", "An in-process LRU is enough."]);
+        assert!(reopened.search_sessions("parser").is_empty());
+    }
+
+    #[test]
+    fn test_session_api_on_json_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = HistoryManager::load(dir.path().to_path_buf()).unwrap();
+        exercise_session_api(manager, || HistoryManager::load(dir.path().to_path_buf()).unwrap());
+    }
+
+    #[test]
+    fn test_session_api_on_sqlite_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let open = || {
+            let storage = history_storage::open_backend(HistoryBackend::Sqlite, dir.path()).unwrap();
+            HistoryManager::with_backend(storage).unwrap()
+        };
+        exercise_session_api(open(), open);
+        // Nothing is written as JSON
+        let files: Vec<_> = std::fs::read_dir(dir.path()).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn test_json_sessions_migrate_to_sqlite_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut json = HistoryManager::load(dir.path().to_path_buf()).unwrap();
+        let kept = json.create_session("Kept", "llama3").unwrap().id;
+        let dropped = json.create_session("Dropped", "llama3").unwrap().id;
+        json.add_message(&kept, "user", "Migrate me").unwrap();
+        json.add_tag(&kept, "rust").unwrap();
+        json.set_summary(&kept, SessionSummary {
+            content: "Asked to be migrated.".to_string(),
+            covers_through: 1,
+            created_at: Utc::now(),
+        }).unwrap();
+        drop(json);
+
+        let open = || {
+            let storage = history_storage::open_backend(HistoryBackend::Sqlite, dir.path()).unwrap();
+            HistoryManager::with_backend(storage).unwrap()
+        };
+        let mut sqlite = open();
+        assert_eq!(sqlite.list_sessions(true).len(), 2);
+        let session = sqlite.get_session(&kept).unwrap();
+        assert_eq!(contents(&session), vec!["Migrate me"]);
+        assert_eq!(session.messages[0].sequence, 1);
+        assert_eq!(session.tags, vec!["rust"]);
+        assert_eq!(session.summary.unwrap().content, "Asked to be migrated.");
+        assert_eq!(sqlite.search_sessions("migrate").len(), 1);
+
+        // The import runs once; the JSON files left behind don't bring back removed sessions
+        sqlite.purge_sessions(None, false).unwrap();
+        sqlite.create_session("New", "llama3").unwrap();
+        let titles: Vec<String> = open().list_sessions(true).into_iter().map(|session| session.title).collect();
+        assert_eq!(titles, vec!["New"]);
+        assert!(dir.path().join(format!("{}.json", dropped)).exists());
+    }
+
+    #[test]
+    fn test_session_pages_cover_every_session_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = HistoryManager::load(dir.path().to_path_buf()).unwrap();
+        let updated_at = Utc::now();
+        for index in 0..23 {
+            // Several sessions share a title and an update time
+            let id = manager.create_session(&format!("Chat {}", index % 5), "llama3").unwrap().id;
+            manager.sessions.get_mut(&id).unwrap().updated_at = updated_at - chrono::Duration::minutes(index % 4);
+        }
+        let deleted = manager.create_session("Deleted", "llama3").unwrap().id;
+        manager.delete_session(&deleted).unwrap();
+
+        for sort in [SessionSort::Recent, SessionSort::Title] {
+            let mut seen = Vec::new();
+            let mut offset = 0;
+            loop {
+                let page = manager.page_sessions(&SessionFilter::default(), false, sort, offset, Some(5));
+                assert_eq!(page.total, 23);
+                if page.sessions.is_empty() {
+                    break;
+                }
+                assert!(page.sessions.len() <= 5);
+                offset += page.sessions.len();
+                seen.extend(page.sessions);
+            }
+            let ids: std::collections::HashSet<&str> = seen.iter().map(|session| session.id.as_str()).collect();
+            assert_eq!((seen.len(), ids.len()), (23, 23), "{:?}", sort);
+            assert!(seen.windows(2).all(|pair| sort.compare(&pair[0], &pair[1]).is_lt()), "{:?}", sort);
+            // The same page comes back in the same order
+            let again = manager.page_sessions(&SessionFilter::default(), false, sort, 5, Some(5));
+            let again: Vec<&str> = again.sessions.iter().map(|session| session.id.as_str()).collect();
+            let first: Vec<&str> = seen[5..10].iter().map(|session| session.id.as_str()).collect();
+            assert_eq!(again, first);
+        }
+
+        let titled = manager.page_sessions(&SessionFilter::default(), false, SessionSort::Title, 0, Some(3));
+        assert!(titled.sessions.iter().all(|session| session.title == "Chat 0"));
+        let filtered = SessionFilter { query: Some("chat 1".to_string()), ..Default::default() };
+        assert_eq!(manager.page_sessions(&filtered, false, SessionSort::Recent, 0, Some(2)).total, 5);
+        assert_eq!(manager.page_sessions(&SessionFilter::default(), true, SessionSort::Recent, 20, None).sessions.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_continuation_is_appended_to_the_partial_reply() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = HistoryManager::load(dir.path().to_path_buf()).unwrap();
+        let session_id = manager.create_session("Chat", "llama3").unwrap().id;
+        manager.append_message(&session_id, "user", "Write a haiku about autumn", Some("ask"), Vec::new()).unwrap();
+        let partial = "Autumn leaves falling\nOn the quiet";
+        manager.append_message(&session_id, "assistant", partial, Some("reply"), Vec::new()).unwrap();
+        let history: SharedHistoryManager = Arc::new(Mutex::new(manager));
+
+        let mut server = mockito::Server::new_async().await;
+        // The model repeats the end of the partial reply before carrying on
+        let chat = server.mock("POST", "/api/chat")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::PartialJsonString(r#"{"model": "llama3"}"#.to_string()),
+                mockito::Matcher::Regex(r#""role":"assistant","content":"Autumn leaves falling\\nOn the quiet""#.to_string()),
+                mockito::Matcher::Regex("Continue it from exactly where it stopped".to_string()),
+            ]))
+            .with_body(serde_json::json!({
+                "model": "llama3",
+                "message": {"role": "assistant", "content": "On the quiet pond below\nripples drift away"},
+                "done": true,
+            }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+        let client = OllamaClient::new(Some(server.url()));
+
+        let session = continue_message(&history, &client, &session_id, "reply", None).await.unwrap();
+        chat.assert_async().await;
+        assert_eq!(session.messages.len(), 2);
+        let combined = &session.messages[1].content;
+        assert!(combined.starts_with(partial));
+        assert_eq!(combined, "Autumn leaves falling\nOn the quiet pond below\nripples drift away");
+        let reloaded = HistoryManager::load(dir.path().to_path_buf()).unwrap().get_session(&session_id).unwrap();
+        assert_eq!(&reloaded.messages[1].content, combined);
+
+        assert!(continue_message(&history, &client, &session_id, "ask", None).await.unwrap_err().to_string().contains("Only assistant replies"));
+    }
+
+    #[test]
+    fn test_repeated_overlap_is_stripped_only_when_long_enough() {
+        assert_eq!(strip_repeated_overlap("fn main() {\n    let total", "    let total = 0;\n}"), " = 0;\n}");
+        assert_eq!(strip_repeated_overlap("fn main() {\n    let total", "let total = 0;\n}"), " = 0;\n}");
+        assert_eq!(strip_repeated_overlap("The answer is", "is 42."), "is 42.");
+        assert_eq!(strip_repeated_overlap("It works because ", " the lock is held."), "the lock is held.");
+        assert_eq!(strip_repeated_overlap("It works because", " the lock is held."), " the lock is held.");
+    }
+}
@@ -0,0 +1,240 @@
+//! Where chat sessions are persisted
+//!
+//! The JSON backend keeps one file per session, which is simple but slows down with
+//! thousands of sessions: every load reads every file and search scans every message.
+//! The SQLite backend keeps sessions in one database with a full-text index for search
+//! and writes each session in a transaction.
+
+use crate::history_manager::{ChatMessage, ChatSession};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Database file the SQLite backend keeps in the history directory
+pub const SQLITE_FILE: &str = "history.sqlite3";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryBackend {
+    /// One JSON file per session
+    #[default]
+    Json,
+    Sqlite,
+}
+
+pub trait StorageBackend: Send {
+    fn load_sessions(&self) -> Result<Vec<ChatSession>, Box<dyn Error>>;
+
+    /// Write the session, replacing any stored version
+    fn save_session(&self, session: &ChatSession) -> Result<(), Box<dyn Error>>;
+
+    fn remove_session(&self, id: &str) -> Result<(), Box<dyn Error>>;
+
+    /// Ids of sessions that may contain `query` in their title, tags or messages, for
+    /// backends with a search index. None means every session has to be checked.
+    fn search_candidates(&self, _query: &str) -> Result<Option<HashSet<String>>, Box<dyn Error>> {
+        Ok(None)
+    }
+}
+
+/// Open the configured backend for the sessions in `history_dir`
+pub fn open_backend(backend: HistoryBackend, history_dir: &Path) -> Result<Box<dyn StorageBackend>, Box<dyn Error>> {
+    Ok(match backend {
+        HistoryBackend::Json => Box::new(JsonFileBackend::open(history_dir.to_path_buf())?),
+        HistoryBackend::Sqlite => Box::new(SqliteBackend::open(history_dir)?),
+    })
+}
+
+pub struct JsonFileBackend {
+    dir: PathBuf,
+}
+
+impl JsonFileBackend {
+    pub fn open(dir: PathBuf) -> Result<Self, Box<dyn Error>> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn session_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+impl StorageBackend for JsonFileBackend {
+    /// Files that can't be read or parsed are skipped
+    fn load_sessions(&self) -> Result<Vec<ChatSession>, Box<dyn Error>> {
+        let mut sessions = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+                if let Ok(content) = fs::read_to_string(&path) {
+                    if let Ok(session) = serde_json::from_str::<ChatSession>(&content) {
+                        sessions.push(session);
+                    }
+                }
+            }
+        }
+        Ok(sessions)
+    }
+
+    fn save_session(&self, session: &ChatSession) -> Result<(), Box<dyn Error>> {
+        let content = serde_json::to_string_pretty(session)?;
+        fs::write(self.session_path(&session.id), content)?;
+        Ok(())
+    }
+
+    fn remove_session(&self, id: &str) -> Result<(), Box<dyn Error>> {
+        let path = self.session_path(id);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sessions in a SQLite database. Messages are stored a row each and every session's
+/// text is indexed with trigrams, so substring search doesn't read the sessions.
+pub struct SqliteBackend {
+    connection: Connection,
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS sessions (
+        id TEXT PRIMARY KEY,
+        -- The session without its messages
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS messages (
+        session_id TEXT NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (session_id, position)
+    );
+    CREATE VIRTUAL TABLE IF NOT EXISTS session_search USING fts5 (
+        session_id UNINDEXED, title, tags, content, tokenize = 'trigram'
+    );
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+";
+
+/// Set once the JSON sessions in the history directory have been imported
+const JSON_MIGRATED_KEY: &str = "json_migrated";
+
+impl SqliteBackend {
+    /// Open the database in `history_dir`, importing the JSON sessions kept there the
+    /// first time. The JSON files are left in place.
+    pub fn open(history_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        fs::create_dir_all(history_dir)?;
+        let connection = Connection::open(history_dir.join(SQLITE_FILE))?;
+        connection.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")?;
+        connection.execute_batch(SCHEMA)?;
+        let backend = Self { connection };
+
+        let migrated: Option<String> = backend.connection
+            .query_row("SELECT value FROM meta WHERE key = ?1", params![JSON_MIGRATED_KEY], |row| row.get(0))
+            .optional()?;
+        if migrated.is_none() {
+            let imported = backend.import_json(history_dir)?;
+            if imported > 0 {
+                eprintln!("Imported {} chat sessions from JSON into {}", imported, SQLITE_FILE);
+            }
+        }
+        Ok(backend)
+    }
+
+    fn import_json(&self, history_dir: &Path) -> Result<usize, Box<dyn Error>> {
+        let sessions = JsonFileBackend::open(history_dir.to_path_buf())?.load_sessions()?;
+        let transaction = self.connection.unchecked_transaction()?;
+        for session in &sessions {
+            write_session(&transaction, session)?;
+        }
+        transaction.execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)",
+            params![JSON_MIGRATED_KEY, chrono::Utc::now().to_rfc3339()],
+        )?;
+        transaction.commit()?;
+        Ok(sessions.len())
+    }
+}
+
+fn write_session(connection: &Connection, session: &ChatSession) -> Result<(), Box<dyn Error>> {
+    let header = ChatSession { messages: Vec::new(), ..session.clone() };
+    connection.execute(
+        "INSERT INTO sessions (id, data) VALUES (?1, ?2) ON CONFLICT (id) DO UPDATE SET data = excluded.data",
+        params![session.id, serde_json::to_string(&header)?],
+    )?;
+    connection.execute("DELETE FROM messages WHERE session_id = ?1", params![session.id])?;
+    let mut insert = connection.prepare_cached("INSERT INTO messages (session_id, position, data) VALUES (?1, ?2, ?3)")?;
+    for (position, message) in session.messages.iter().enumerate() {
+        insert.execute(params![session.id, position as i64, serde_json::to_string(message)?])?;
+    }
+
+    // Lowercased here because LIKE only ignores the case of ASCII letters
+    connection.execute("DELETE FROM session_search WHERE session_id = ?1", params![session.id])?;
+    let content: Vec<&str> = session.messages.iter().map(|message| message.content.as_str()).collect();
+    connection.execute(
+        "INSERT INTO session_search (session_id, title, tags, content) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            session.id,
+            session.title.to_lowercase(),
+            session.tags.join("\n").to_lowercase(),
+            content.join("\n").to_lowercase(),
+        ],
+    )?;
+    Ok(())
+}
+
+impl StorageBackend for SqliteBackend {
+    fn load_sessions(&self) -> Result<Vec<ChatSession>, Box<dyn Error>> {
+        let mut sessions = Vec::new();
+        let mut select_sessions = self.connection.prepare("SELECT data FROM sessions")?;
+        let mut select_messages = self.connection.prepare("SELECT data FROM messages WHERE session_id = ?1 ORDER BY position")?;
+        let rows = select_sessions.query_map([], |row| row.get::<_, String>(0))?;
+        for data in rows {
+            let mut session: ChatSession = serde_json::from_str(&data?)?;
+            let messages = select_messages.query_map(params![session.id], |row| row.get::<_, String>(0))?;
+            for message in messages {
+                session.messages.push(serde_json::from_str::<ChatMessage>(&message?)?);
+            }
+            sessions.push(session);
+        }
+        Ok(sessions)
+    }
+
+    fn save_session(&self, session: &ChatSession) -> Result<(), Box<dyn Error>> {
+        let transaction = self.connection.unchecked_transaction()?;
+        write_session(&transaction, session)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn remove_session(&self, id: &str) -> Result<(), Box<dyn Error>> {
+        let transaction = self.connection.unchecked_transaction()?;
+        transaction.execute("DELETE FROM sessions WHERE id = ?1", params![id])?;
+        transaction.execute("DELETE FROM session_search WHERE session_id = ?1", params![id])?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Trigrams only index queries of three or more characters; shorter ones are
+    /// checked against every session
+    fn search_candidates(&self, query: &str) -> Result<Option<HashSet<String>>, Box<dyn Error>> {
+        if query.chars().count() < 3 {
+            return Ok(None);
+        }
+        // LIKE wildcards in the query only widen the candidates, which are checked again
+        let pattern = format!("%{}%", query.to_lowercase());
+        let mut statement = self.connection.prepare_cached(
+            "SELECT session_id FROM session_search WHERE title LIKE ?1 OR tags LIKE ?1 OR content LIKE ?1",
+        )?;
+        let ids = statement
+            .query_map(params![pattern], |row| row.get::<_, String>(0))?
+            .collect::<Result<HashSet<_>, _>>()?;
+        Ok(Some(ids))
+    }
+}
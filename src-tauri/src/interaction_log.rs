@@ -0,0 +1,336 @@
+//! Opt-in request/response log for debugging model interactions
+//!
+//! Records each provider request (model, options, prompt) and its response to a
+//! size-capped rotating file. API keys are redacted before anything is written
+//! and prompts can be truncated for privacy.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Configuration for the interaction log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InteractionLogConfig {
+    pub enabled: bool,
+    pub log_path: PathBuf,
+    pub max_file_bytes: u64,
+    pub max_rotated_files: usize,
+    pub redact_api_keys: bool,
+    pub max_prompt_chars: Option<usize>,
+    pub recent_capacity: usize,
+}
+
+impl Default for InteractionLogConfig {
+    fn default() -> Self {
+        let log_dir = dirs::data_local_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("gerdsenai-socrates")
+            .join("logs");
+
+        Self {
+            enabled: false, // Opt-in only
+            log_path: log_dir.join("interactions.log"),
+            max_file_bytes: 5 * 1024 * 1024, // 5MB per file
+            max_rotated_files: 3,
+            redact_api_keys: true,
+            max_prompt_chars: None,
+            recent_capacity: 100,
+        }
+    }
+}
+
+/// A single logged request/response pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InteractionRecord {
+    pub timestamp: String,
+    pub provider: String,
+    pub model: String,
+    pub options: serde_json::Value,
+    pub prompt: String,
+    pub response: Option<String>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+pub struct InteractionLogger {
+    config: Mutex<InteractionLogConfig>,
+    recent: Mutex<VecDeque<InteractionRecord>>,
+    known_secrets: Mutex<Vec<String>>,
+    key_patterns: Vec<Regex>,
+    key_value_pattern: Regex,
+}
+
+impl InteractionLogger {
+    pub fn new(config: InteractionLogConfig) -> Self {
+        let key_patterns = [
+            r"sk-ant-[A-Za-z0-9_\-]{8,}",
+            r"sk-[A-Za-z0-9_\-]{16,}",
+            r"(?i)bearer\s+[A-Za-z0-9_\-\.]{8,}",
+        ]
+        .iter()
+        .map(|pattern| Regex::new(pattern).expect("valid redaction pattern"))
+        .collect();
+
+        Self {
+            config: Mutex::new(config),
+            recent: Mutex::new(VecDeque::new()),
+            known_secrets: Mutex::new(Vec::new()),
+            key_patterns,
+            key_value_pattern: Regex::new(r#"(?i)("?(api[_-]?key|x-api-key|authorization)"?\s*[:=]\s*"?)[^",\s}]+"#)
+                .expect("valid redaction pattern"),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.lock().map(|config| config.enabled).unwrap_or(false)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        if let Ok(mut config) = self.config.lock() {
+            config.enabled = enabled;
+        }
+    }
+
+    pub fn get_config(&self) -> InteractionLogConfig {
+        self.config.lock().map(|config| config.clone()).unwrap_or_default()
+    }
+
+    pub fn update_config(&self, new_config: InteractionLogConfig) {
+        if let Ok(mut config) = self.config.lock() {
+            *config = new_config;
+        }
+    }
+
+    /// Register configured API keys so they are redacted even if they don't match a known pattern
+    pub fn set_known_secrets(&self, secrets: Vec<String>) {
+        if let Ok(mut known) = self.known_secrets.lock() {
+            *known = secrets.into_iter().filter(|secret| !secret.is_empty()).collect();
+        }
+    }
+
+    /// Replace API keys and bearer tokens in `text`
+    pub fn redact(&self, text: &str) -> String {
+        let mut redacted = text.to_string();
+
+        if let Ok(known) = self.known_secrets.lock() {
+            for secret in known.iter() {
+                redacted = redacted.replace(secret.as_str(), REDACTED);
+            }
+        }
+
+        for pattern in &self.key_patterns {
+            redacted = pattern.replace_all(&redacted, REDACTED).into_owned();
+        }
+
+        // Keep the key name, replace only the value
+        redacted = self.key_value_pattern
+            .replace_all(&redacted, format!("${{1}}{}", REDACTED))
+            .into_owned();
+
+        redacted
+    }
+
+    /// Record a request/response pair. Does nothing while logging is disabled.
+    pub fn log(&self, mut record: InteractionRecord) -> Result<(), String> {
+        let config = self.get_config();
+        if !config.enabled {
+            return Ok(());
+        }
+
+        if let Some(max_chars) = config.max_prompt_chars {
+            if record.prompt.chars().count() > max_chars {
+                record.prompt = record.prompt.chars().take(max_chars).collect::<String>() + "…[truncated]";
+            }
+        }
+
+        if config.redact_api_keys {
+            record.prompt = self.redact(&record.prompt);
+            record.response = record.response.map(|response| self.redact(&response));
+            record.error = record.error.map(|error| self.redact(&error));
+            record.options = serde_json::from_str(&self.redact(&record.options.to_string()))
+                .unwrap_or(serde_json::Value::Null);
+        }
+
+        let line = serde_json::to_string(&record)
+            .map_err(|e| format!("Failed to serialize interaction: {}", e))?;
+        self.append_line(&config, &line)?;
+
+        if let Ok(mut recent) = self.recent.lock() {
+            recent.push_back(record);
+            while recent.len() > config.recent_capacity {
+                recent.pop_front();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Most recent interactions, newest last
+    pub fn recent(&self, limit: usize) -> Vec<InteractionRecord> {
+        match self.recent.lock() {
+            Ok(recent) => recent.iter().skip(recent.len().saturating_sub(limit)).cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn append_line(&self, config: &InteractionLogConfig, line: &str) -> Result<(), String> {
+        if let Some(parent) = config.log_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create log directory: {}", e))?;
+        }
+
+        let current_size = fs::metadata(&config.log_path).map(|m| m.len()).unwrap_or(0);
+        if current_size > 0 && current_size + line.len() as u64 + 1 > config.max_file_bytes {
+            Self::rotate(config)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.log_path)
+            .map_err(|e| format!("Failed to open interaction log: {}", e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("Failed to write interaction log: {}", e))
+    }
+
+    /// Shift interactions.log -> interactions.log.1 -> ... dropping the oldest file
+    fn rotate(config: &InteractionLogConfig) -> Result<(), String> {
+        let rotated = |index: usize| {
+            let mut path = config.log_path.clone().into_os_string();
+            path.push(format!(".{}", index));
+            PathBuf::from(path)
+        };
+
+        if config.max_rotated_files == 0 {
+            return fs::remove_file(&config.log_path)
+                .map_err(|e| format!("Failed to rotate interaction log: {}", e));
+        }
+
+        let _ = fs::remove_file(rotated(config.max_rotated_files));
+        for index in (1..config.max_rotated_files).rev() {
+            let from = rotated(index);
+            if from.exists() {
+                fs::rename(&from, rotated(index + 1))
+                    .map_err(|e| format!("Failed to rotate interaction log: {}", e))?;
+            }
+        }
+        fs::rename(&config.log_path, rotated(1))
+            .map_err(|e| format!("Failed to rotate interaction log: {}", e))
+    }
+}
+
+impl Default for InteractionLogger {
+    fn default() -> Self {
+        Self::new(InteractionLogConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_config(dir: &TempDir) -> InteractionLogConfig {
+        InteractionLogConfig {
+            enabled: true,
+            log_path: dir.path().join("interactions.log"),
+            ..Default::default()
+        }
+    }
+
+    fn record(prompt: &str) -> InteractionRecord {
+        InteractionRecord {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            provider: "OpenAI".to_string(),
+            model: "gpt-4".to_string(),
+            options: serde_json::json!({"temperature": 0.3}),
+            prompt: prompt.to_string(),
+            response: Some("done".to_string()),
+            error: None,
+            duration_ms: 42,
+        }
+    }
+
+    #[test]
+    fn test_logs_request_response_pair() {
+        let dir = TempDir::new().unwrap();
+        let logger = InteractionLogger::new(test_config(&dir));
+
+        logger.log(record("Explain closures")).unwrap();
+
+        let contents = fs::read_to_string(dir.path().join("interactions.log")).unwrap();
+        assert!(contents.contains("Explain closures"));
+        assert!(contents.contains("\"response\":\"done\""));
+        assert_eq!(logger.recent(10).len(), 1);
+    }
+
+    #[test]
+    fn test_disabled_logger_writes_nothing() {
+        let dir = TempDir::new().unwrap();
+        let logger = InteractionLogger::new(InteractionLogConfig { enabled: false, ..test_config(&dir) });
+
+        logger.log(record("secret stuff")).unwrap();
+
+        assert!(!dir.path().join("interactions.log").exists());
+        assert!(logger.recent(10).is_empty());
+    }
+
+    #[test]
+    fn test_api_keys_are_redacted() {
+        let dir = TempDir::new().unwrap();
+        let logger = InteractionLogger::new(test_config(&dir));
+        logger.set_known_secrets(vec!["custom-proxy-key-123".to_string()]);
+
+        logger
+            .log(record("key sk-abcdefghijklmnopqrstuv and api_key=hunter2 and custom-proxy-key-123"))
+            .unwrap();
+
+        let contents = fs::read_to_string(dir.path().join("interactions.log")).unwrap();
+        assert!(!contents.contains("sk-abcdefghijklmnopqrstuv"));
+        assert!(!contents.contains("hunter2"));
+        assert!(!contents.contains("custom-proxy-key-123"));
+        assert!(contents.contains(REDACTED));
+    }
+
+    #[test]
+    fn test_prompt_truncation() {
+        let dir = TempDir::new().unwrap();
+        let logger = InteractionLogger::new(InteractionLogConfig {
+            max_prompt_chars: Some(5),
+            ..test_config(&dir)
+        });
+
+        logger.log(record("abcdefghij")).unwrap();
+
+        let logged = logger.recent(1).pop().unwrap();
+        assert!(logged.prompt.starts_with("abcde"));
+        assert!(!logged.prompt.contains("fghij"));
+    }
+
+    #[test]
+    fn test_rotation_caps_file_size() {
+        let dir = TempDir::new().unwrap();
+        let config = InteractionLogConfig {
+            max_file_bytes: 1024,
+            max_rotated_files: 2,
+            ..test_config(&dir)
+        };
+        let logger = InteractionLogger::new(config.clone());
+
+        for i in 0..50 {
+            logger.log(record(&format!("prompt number {} {}", i, "x".repeat(100)))).unwrap();
+        }
+
+        let size = fs::metadata(&config.log_path).unwrap().len();
+        assert!(size <= 1024, "active log grew to {} bytes", size);
+        assert!(dir.path().join("interactions.log.1").exists());
+        assert!(dir.path().join("interactions.log.2").exists());
+        assert!(!dir.path().join("interactions.log.3").exists());
+        assert_eq!(logger.recent(5).len(), 5);
+    }
+}
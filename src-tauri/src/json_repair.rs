@@ -0,0 +1,213 @@
+//! Recovering JSON replies cut off by the token limit
+//!
+//! Structured replies such as symbol lists, review comments and security findings are
+//! sometimes truncated mid-value. Rather than drop the whole reply, the text is cut back
+//! to the last complete value and the strings, arrays and objects left open are closed,
+//! keeping every entry that arrived whole.
+
+/// What the scanner was in the middle of when the text ran out
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expect {
+    Key,
+    Colon,
+    Value,
+    /// A complete value, to be followed by `,` or a closer
+    Next,
+}
+
+struct Frame {
+    closer: char,
+    expect: Expect,
+}
+
+/// Scan state of a JSON prefix
+struct Scan {
+    frames: Vec<Frame>,
+    /// Where the text can be cut and closed, with the closers needed there
+    last_complete: Option<(usize, String)>,
+    /// Start of an unterminated value string, and of an escape left unfinished in it
+    open_value_string: Option<(usize, Option<usize>)>,
+    /// The outermost value was closed; nothing was truncated
+    finished: bool,
+}
+
+impl Scan {
+    fn closers(&self) -> String {
+        self.frames.iter().rev().map(|frame| frame.closer).collect()
+    }
+
+    fn value_completed(&mut self, end: usize) {
+        match self.frames.last_mut() {
+            Some(frame) => {
+                frame.expect = Expect::Next;
+                self.last_complete = Some((end, self.closers()));
+            }
+            None => self.finished = true,
+        }
+    }
+}
+
+fn scan(text: &str) -> Scan {
+    let mut state = Scan {
+        frames: Vec::new(),
+        last_complete: None,
+        open_value_string: None,
+        finished: false,
+    };
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => {
+                let is_key = state.frames.last().is_some_and(|frame| frame.expect == Expect::Key);
+                let mut escape: Option<(usize, usize)> = None;
+                let mut closed = None;
+                for (position, c) in chars.by_ref() {
+                    match escape {
+                        Some((start, 1)) if c == 'u' => escape = Some((start, 4)),
+                        Some((start, remaining)) => escape = (remaining > 1).then_some((start, remaining - 1)),
+                        None if c == '\\' => escape = Some((position, 1)),
+                        None if c == '"' => {
+                            closed = Some(position + 1);
+                            break;
+                        }
+                        None => {}
+                    }
+                }
+                match closed {
+                    Some(_) if is_key => {
+                        if let Some(frame) = state.frames.last_mut() {
+                            frame.expect = Expect::Colon;
+                        }
+                    }
+                    Some(end) => state.value_completed(end),
+                    None => {
+                        if !is_key {
+                            state.open_value_string = Some((index, escape.map(|(start, _)| start)));
+                        }
+                        return state;
+                    }
+                }
+            }
+            '{' | '[' => {
+                let (closer, expect) = if c == '{' { ('}', Expect::Key) } else { (']', Expect::Value) };
+                state.frames.push(Frame { closer, expect });
+                state.last_complete = Some((index + 1, state.closers()));
+            }
+            '}' | ']' => {
+                state.frames.pop();
+                state.value_completed(index + 1);
+            }
+            ':' => {
+                if let Some(frame) = state.frames.last_mut() {
+                    frame.expect = Expect::Value;
+                }
+            }
+            ',' => {
+                if let Some(frame) = state.frames.last_mut() {
+                    frame.expect = if frame.closer == '}' { Expect::Key } else { Expect::Value };
+                }
+            }
+            c if c.is_whitespace() => {}
+            _ => {
+                // A number or literal, complete only if something follows it
+                let mut end = None;
+                while let Some(&(position, c)) = chars.peek() {
+                    if c == ',' || c == '}' || c == ']' || c.is_whitespace() {
+                        end = Some(position);
+                        break;
+                    }
+                    chars.next();
+                }
+                match end {
+                    Some(end) => state.value_completed(end),
+                    None => return state,
+                }
+            }
+        }
+        if state.finished {
+            return state;
+        }
+    }
+    state
+}
+
+/// Whether `json` stops before its outermost object or array is closed
+pub fn is_truncated(json: &str) -> bool {
+    let Some(start) = json.find(['{', '[']) else {
+        return false;
+    };
+    let state = scan(&json[start..]);
+    !state.finished && !state.frames.is_empty()
+}
+
+/// `json` cut off mid-value, closed after its last complete value; a value string
+/// cut off is kept up to where it stops. None when nothing was truncated.
+pub fn repair_truncated(json: &str) -> Option<String> {
+    let state = scan(json);
+    if state.finished || state.frames.is_empty() {
+        return None;
+    }
+    if let Some((start, unfinished_escape)) = state.open_value_string {
+        let end = unfinished_escape.unwrap_or(json.len());
+        return Some(format!("{}\"{}", &json[..end.max(start + 1)], state.closers()));
+    }
+    let (end, closers) = state.last_complete?;
+    Some(format!("{}{}", json[..end].trim_end().trim_end_matches(','), closers))
+}
+
+/// The JSON object or array in a model reply, possibly wrapped in prose or a code
+/// fence. A reply cut off before its end is repaired when possible.
+pub fn parse_json_reply(reply: &str) -> Result<serde_json::Value, String> {
+    let start = reply.find(['{', '[']).ok_or("no JSON in the reply")?;
+    let parsed = match reply.rfind(['}', ']']).filter(|end| *end >= start) {
+        Some(end) => serde_json::from_str(&reply[start..=end]).map_err(|e| format!("invalid JSON: {}", e)),
+        None => Err("no JSON in the reply".to_string()),
+    };
+    parsed.or_else(|error| {
+        repair_truncated(&reply[start..])
+            .and_then(|repaired| serde_json::from_str(&repaired).ok())
+            .ok_or(error)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_truncated_replies_keep_complete_entries() {
+        let cases = [
+            (r#"{"symbols": [{"name": "a", "kind": "function"}, {"name": "b", "ki"#, json!({"symbols": [{"name": "a", "kind": "function"}, {"name": "b"}]})),
+            (r#"{"comments": [{"line": 3, "message": "Unchecked unwr"#, json!({"comments": [{"line": 3, "message": "Unchecked unwr"}]})),
+            (r#"[1, 2, 3"#, json!([1, 2])),
+            (r#"[1, 2, 3, "#, json!([1, 2, 3])),
+            (r#"{"ok": true, "count": 12"#, json!({"ok": true})),
+            (r#"{"ok": tru"#, json!({})),
+            (r#"{"findings": ["#, json!({"findings": []})),
+            (r#"{"message": "line\nbreak \u00e"#, json!({"message": "line\nbreak "})),
+            (r#"{"message": "quote \""#, json!({"message": "quote \""})),
+        ];
+        for (truncated, expected) in cases {
+            let repaired = repair_truncated(truncated).unwrap_or_else(|| panic!("no repair for {}", truncated));
+            let value: serde_json::Value = serde_json::from_str(&repaired).unwrap_or_else(|e| panic!("{} -> {}: {}", truncated, repaired, e));
+            assert_eq!(value, expected, "{}", truncated);
+        }
+    }
+
+    #[test]
+    fn test_reply_parsing_repairs_only_truncated_json() {
+        let complete = "Here you go:\n```json\n{\"findings\": [{\"cwe\": \"CWE-89\"}]}\n```";
+        assert_eq!(parse_json_reply(complete).unwrap(), json!({"findings": [{"cwe": "CWE-89"}]}));
+        assert!(!is_truncated(complete));
+
+        let truncated = "```json\n{\"findings\": [{\"cwe\": \"CWE-89\"}, {\"cwe\": \"CWE";
+        assert!(is_truncated(truncated));
+        assert_eq!(parse_json_reply(truncated).unwrap(), json!({"findings": [{"cwe": "CWE-89"}, {"cwe": "CWE"}]}));
+
+        // Malformed but complete JSON is still an error
+        assert!(repair_truncated(r#"{"a": 1,, "b": 2}"#).is_none());
+        assert!(parse_json_reply(r#"{"a": 1,, "b": 2}"#).unwrap_err().starts_with("invalid JSON"));
+        assert_eq!(parse_json_reply("no json here").unwrap_err(), "no JSON in the reply");
+    }
+}
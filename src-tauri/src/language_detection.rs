@@ -0,0 +1,101 @@
+//! Programming language detection from file extensions and content
+
+use std::path::Path;
+
+/// Language for a file extension, or None if it isn't recognized
+pub fn language_from_path(path: &Path) -> Option<&'static str> {
+    let language = match path.extension()?.to_string_lossy().as_ref() {
+        "js" => "javascript",
+        "ts" => "typescript",
+        "jsx" => "jsx",
+        "tsx" => "tsx",
+        "py" => "python",
+        "rs" => "rust",
+        "go" => "go",
+        "java" => "java",
+        "c" => "c",
+        "cpp" | "cc" | "cxx" => "cpp",
+        "cs" => "csharp",
+        "php" => "php",
+        "rb" => "ruby",
+        "swift" => "swift",
+        "kt" | "kts" => "kotlin",
+        "scala" => "scala",
+        "html" => "html",
+        "css" => "css",
+        "json" => "json",
+        "md" => "markdown",
+        "yml" | "yaml" => "yaml",
+        "xml" => "xml",
+        "sh" | "bash" => "bash",
+        "sql" => "sql",
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// Markers counted when guessing a language from content alone
+const CONTENT_MARKERS: [(&str, &[&str]); 3] = [
+    ("rust", &["fn ", "let mut ", "impl ", "pub fn ", "use std::", "println!", "#[derive", "&self", "::new("]),
+    ("python", &["def ", "import ", "self.", "elif ", "print(", "__init__", "None", "from "]),
+    ("javascript", &["function ", "const ", "=> ", "console.log", "require(", "===", "export ", "var "]),
+];
+
+/// Distinct markers a language needs before content is attributed to it
+const MIN_MARKERS: usize = 2;
+
+/// Guess the language of `content` from a shebang line or, failing that, from
+/// which language's markers appear most. Returns None when there is no clear winner.
+pub fn detect_language(content: &str) -> Option<&'static str> {
+    if let Some(shebang) = content.lines().next().filter(|line| line.starts_with("#!")) {
+        return match () {
+            _ if shebang.contains("python") => Some("python"),
+            _ if shebang.contains("node") => Some("javascript"),
+            _ if shebang.contains("ruby") => Some("ruby"),
+            _ if shebang.contains("bash") || shebang.ends_with("/sh") || shebang.contains("zsh") => Some("bash"),
+            _ => None,
+        };
+    }
+
+    let mut scores: Vec<(&'static str, usize)> = CONTENT_MARKERS.iter()
+        .map(|(language, markers)| (*language, markers.iter().filter(|marker| content.contains(*marker)).count()))
+        .collect();
+    scores.sort_by(|a, b| b.1.cmp(&a.1));
+
+    match scores.as_slice() {
+        [(language, best), (_, runner_up), ..] if *best >= MIN_MARKERS && best > runner_up => Some(language),
+        _ => None,
+    }
+}
+
+/// Language from `file_path` when it has a known extension, otherwise from `content`
+pub fn infer_language(file_path: Option<&str>, content: &str) -> Option<&'static str> {
+    file_path
+        .and_then(|path| language_from_path(Path::new(path)))
+        .or_else(|| detect_language(content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_languages_from_content() {
+        let rust = "use std::collections::HashMap;\n\npub fn main() {\n    let mut map = HashMap::new();\n    println!(\"{:?}\", map);\n}\n";
+        let python = "import os\n\ndef main():\n    if os.path.exists('x'):\n        print('yes')\n    elif True:\n        pass\n";
+        let javascript = "const fs = require('fs');\nfunction main() {\n  fs.readdir('.', (err, files) => console.log(files));\n}\n";
+
+        assert_eq!(detect_language(rust), Some("rust"));
+        assert_eq!(detect_language(python), Some("python"));
+        assert_eq!(detect_language(javascript), Some("javascript"));
+        assert_eq!(detect_language("Just some prose about nothing in particular."), None);
+    }
+
+    #[test]
+    fn test_shebang_and_path_take_precedence() {
+        assert_eq!(detect_language("#!/usr/bin/env python3\nconst x = 1;"), Some("python"));
+        assert_eq!(detect_language("#!/bin/sh\necho hi"), Some("bash"));
+        assert_eq!(infer_language(Some("src/app.ts"), "def main(): import os"), Some("typescript"));
+        assert_eq!(infer_language(Some("notes.unknown"), "def main():\n    import os"), Some("python"));
+    }
+}
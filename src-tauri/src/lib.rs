@@ -7,14 +7,38 @@ pub mod operation_manager;
 pub mod commands;
 pub mod user_errors;
 pub mod ollama_client;
+pub mod model_aliases;
+pub mod ollama_rate_limit;
+pub mod prompt_guard;
+pub mod embedding_coalescer;
 pub mod chroma_manager;
+pub mod document_chunker;
+pub mod document_spill;
+pub mod embedding_spill;
+pub mod embedding_quantization;
+pub mod document_types;
+pub mod language_detection;
 pub mod analysis_engine;
 pub mod thread_pool_manager;
 pub mod ai_providers;
+pub mod secret_redaction;
 pub mod ollama_provider;
 pub mod openai_client;
 pub mod anthropic_client;
 pub mod multi_ai_commands;
+pub mod context_manager;
+pub mod history_manager;
+pub mod history_storage;
+pub mod interaction_log;
+pub mod settings_store;
+pub mod prompt_templates;
+pub mod health_history;
+pub mod events;
+pub mod maintenance;
+pub mod git_diff;
+pub mod security_scan;
+pub mod json_repair;
+pub mod float_order;
 
 #[cfg(test)]
 mod tests;
@@ -25,6 +49,8 @@ fn greet(name: &str) -> String {
 }
 
 use operation_manager::{OperationManager, ResourceLimits};
+use events::emit_event;
+use tokio::sync::broadcast::error::RecvError;
 use multi_ai_commands::MultiAIManager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -37,17 +63,35 @@ pub fn run() {
         io_throttling: true,
     };
     let operation_manager = OperationManager::new(resource_limits);
+    let mut progress_rx = operation_manager.subscribe_progress();
     let multi_ai_manager = MultiAIManager::new();
     
     tauri::Builder::default()
         // .plugin(tauri_plugin_opener::init()) // Commented out - using main.rs setup instead
         .manage(operation_manager)
         .manage(multi_ai_manager)
+        .setup(move |app| {
+            // Forward operation progress to the frontend
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    match progress_rx.recv().await {
+                        Ok(progress) => emit_event(&app_handle, progress),
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             crate::commands::enqueue_operation,
             crate::commands::get_operation_status,
+            crate::commands::list_operations,
             crate::commands::cancel_operation,
+            crate::commands::cancel_operations_by_type,
+            crate::commands::cancel_all_operations,
             crate::multi_ai_commands::initialize_multi_ai,
             crate::multi_ai_commands::get_all_ai_models,
             crate::multi_ai_commands::generate_ai_smart,
@@ -59,6 +103,8 @@ pub fn run() {
             crate::multi_ai_commands::classify_prompt,
             crate::multi_ai_commands::get_model_capabilities,
             crate::multi_ai_commands::get_supported_providers,
+            crate::multi_ai_commands::set_interaction_logging,
+            crate::multi_ai_commands::get_recent_interactions,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -3,27 +3,60 @@ use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 use url::Url;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tauri::State;
 use std::time::{Duration, Instant};
 use crate::code_analysis::{CodeAnalysisService, CodeAnalysisResponse};
+use crate::language_detection::language_from_path;
+use crate::signature_help::{active_call, extract_signatures, signature_from_label, FunctionSignature};
+
+/// Lines longer than this are flagged by the fallback scanner
+const FALLBACK_MAX_LINE_LENGTH: usize = 120;
+
+/// Comment tags flagged by the fallback scanner
+const FALLBACK_TAGS: [(&str, DiagnosticSeverity); 4] = [
+    ("TODO", DiagnosticSeverity::INFORMATION),
+    ("FIXME", DiagnosticSeverity::WARNING),
+    ("XXX", DiagnosticSeverity::WARNING),
+    ("HACK", DiagnosticSeverity::WARNING),
+];
+
+/// Debug output calls worth flagging before commit, by language
+fn debug_print_markers(language: &str) -> &'static [&'static str] {
+    match language {
+        "javascript" | "typescript" | "jsx" | "tsx" => &["console.log"],
+        "rust" => &["println!", "dbg!"],
+        "python" => &["print("],
+        _ => &[],
+    }
+}
 
 // Performance optimization structures
 #[derive(Debug)]
 pub struct DebouncedAnalyzer {
-    delay: Duration,
+    delay_ms: AtomicU64,
     pending_tasks: Arc<Mutex<HashMap<Url, tokio::task::JoinHandle<()>>>>,
 }
 
 impl DebouncedAnalyzer {
     pub fn new(delay_ms: u64) -> Self {
         Self {
-            delay: Duration::from_millis(delay_ms),
+            delay_ms: AtomicU64::new(delay_ms),
             pending_tasks: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay_ms.load(Ordering::Relaxed))
+    }
+
+    /// Applies to analyses scheduled from now on
+    pub fn set_delay(&self, delay_ms: u64) {
+        self.delay_ms.store(delay_ms, Ordering::Relaxed);
+    }
+
     pub async fn schedule_analysis<F, Fut>(&self, uri: Url, analysis_fn: F)
     where
         F: FnOnce() -> Fut + Send + 'static,
@@ -36,7 +69,7 @@ impl DebouncedAnalyzer {
             handle.abort();
         }
 
-        let delay = self.delay;
+        let delay = self.delay();
         let task = tokio::spawn(async move {
             tokio::time::sleep(delay).await;
             analysis_fn().await;
@@ -46,6 +79,153 @@ impl DebouncedAnalyzer {
     }
 }
 
+/// Receives `$/progress` notifications for document analyses
+#[tower_lsp::async_trait]
+pub trait ProgressSink: Send + Sync {
+    /// False when the client couldn't create the token, so nothing was begun
+    async fn begin(&self, token: ProgressToken, title: &str, message: &str) -> bool;
+    async fn report(&self, token: ProgressToken, message: &str);
+    async fn end(&self, token: ProgressToken, message: &str);
+}
+
+#[tower_lsp::async_trait]
+impl ProgressSink for Client {
+    async fn begin(&self, token: ProgressToken, title: &str, message: &str) -> bool {
+        // The client must create a token before progress is reported against it
+        let created = self
+            .send_request::<request::WorkDoneProgressCreate>(WorkDoneProgressCreateParams { token: token.clone() })
+            .await;
+        if created.is_err() {
+            return false;
+        }
+        self.send_notification::<notification::Progress>(ProgressParams {
+            token,
+            value: ProgressParamsValue::WorkDone(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: title.to_string(),
+                cancellable: Some(false),
+                message: Some(message.to_string()),
+                percentage: None,
+            })),
+        })
+        .await;
+        true
+    }
+
+    async fn report(&self, token: ProgressToken, message: &str) {
+        self.send_notification::<notification::Progress>(ProgressParams {
+            token,
+            value: ProgressParamsValue::WorkDone(WorkDoneProgress::Report(WorkDoneProgressReport {
+                cancellable: Some(false),
+                message: Some(message.to_string()),
+                percentage: None,
+            })),
+        })
+        .await;
+    }
+
+    async fn end(&self, token: ProgressToken, message: &str) {
+        self.send_notification::<notification::Progress>(ProgressParams {
+            token,
+            value: ProgressParamsValue::WorkDone(WorkDoneProgress::End(WorkDoneProgressEnd {
+                message: Some(message.to_string()),
+            })),
+        })
+        .await;
+    }
+}
+
+/// Tracks the in-flight analysis progress of each document so a superseded
+/// analysis still ends its progress after its task is aborted
+#[derive(Clone)]
+pub struct AnalysisProgress {
+    sink: Arc<dyn ProgressSink>,
+    active: Arc<Mutex<ActiveProgress>>,
+    next_token: Arc<AtomicU64>,
+    /// Off until the client announces `window.workDoneProgress` support
+    enabled: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+struct ActiveProgress {
+    tokens: HashMap<Url, ProgressToken>,
+    /// Bumped each time the analysis of a document is superseded
+    generations: HashMap<Url, u64>,
+}
+
+impl ActiveProgress {
+    fn generation(&self, uri: &Url) -> u64 {
+        self.generations.get(uri).copied().unwrap_or(0)
+    }
+}
+
+impl AnalysisProgress {
+    pub fn new(sink: Arc<dyn ProgressSink>) -> Self {
+        Self {
+            sink,
+            active: Arc::new(Mutex::new(ActiveProgress::default())),
+            next_token: Arc::new(AtomicU64::new(1)),
+            enabled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Start reporting progress for `uri`; None when progress is disabled
+    pub async fn begin(&self, uri: &Url) -> Option<ProgressToken> {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return None;
+        }
+        let token = ProgressToken::String(format!(
+            "auto-coder/analysis/{}",
+            self.next_token.fetch_add(1, Ordering::Relaxed)
+        ));
+        // The token is created without holding the lock, since that's a client round-trip
+        let generation = self.active.lock().await.generation(uri);
+        if !self.sink.begin(token.clone(), "Auto-Coder", "Analyzing…").await {
+            return None;
+        }
+
+        let mut active = self.active.lock().await;
+        if active.generation(uri) != generation {
+            // Superseded while the client was creating the token
+            drop(active);
+            self.sink.end(token, "Superseded by a newer edit").await;
+            return None;
+        }
+        active.tokens.insert(uri.clone(), token.clone());
+        Some(token)
+    }
+
+    pub async fn report(&self, token: &Option<ProgressToken>, message: &str) {
+        if let Some(token) = token {
+            self.sink.report(token.clone(), message).await;
+        }
+    }
+
+    /// End progress for a finished analysis unless it was already superseded
+    pub async fn finish(&self, uri: &Url, token: Option<ProgressToken>) {
+        let Some(token) = token else {
+            return;
+        };
+        let mut active = self.active.lock().await;
+        if active.tokens.get(uri) == Some(&token) {
+            active.tokens.remove(uri);
+            self.sink.end(token, "Analysis complete").await;
+        }
+    }
+
+    /// End progress for the analysis of `uri` that is about to be replaced
+    pub async fn supersede(&self, uri: &Url) {
+        let mut active = self.active.lock().await;
+        *active.generations.entry(uri.clone()).or_insert(0) += 1;
+        if let Some(token) = active.tokens.remove(uri) {
+            self.sink.end(token, "Superseded by a newer edit").await;
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CachedAnalysisResult {
     pub result: CodeAnalysisResponse,
@@ -55,22 +235,31 @@ pub struct CachedAnalysisResult {
 #[derive(Debug, Clone)]
 pub struct AIResponseCache {
     cache: Arc<Mutex<HashMap<String, CachedAnalysisResult>>>,
-    ttl: Duration,
+    /// Shared by clones so a TTL change reaches in-flight analyses
+    ttl_seconds: Arc<AtomicU64>,
 }
 
 impl AIResponseCache {
     pub fn new(ttl_seconds: u64) -> Self {
         Self {
             cache: Arc::new(Mutex::new(HashMap::new())),
-            ttl: Duration::from_secs(ttl_seconds),
+            ttl_seconds: Arc::new(AtomicU64::new(ttl_seconds)),
         }
     }
 
+    pub fn ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl_seconds.load(Ordering::Relaxed))
+    }
+
+    pub fn set_ttl(&self, ttl_seconds: u64) {
+        self.ttl_seconds.store(ttl_seconds, Ordering::Relaxed);
+    }
+
     pub async fn get(&self, key: &str) -> Option<CodeAnalysisResponse> {
         let mut cache = self.cache.lock().await;
         
         if let Some(cached) = cache.get(key) {
-            if cached.timestamp.elapsed() < self.ttl {
+            if cached.timestamp.elapsed() < self.ttl() {
                 return Some(cached.result.clone());
             } else {
                 cache.remove(key);
@@ -90,27 +279,139 @@ impl AIResponseCache {
 
     pub async fn cleanup_expired(&self) {
         let mut cache = self.cache.lock().await;
-        cache.retain(|_, cached| cached.timestamp.elapsed() < self.ttl);
+        let ttl = self.ttl();
+        cache.retain(|_, cached| cached.timestamp.elapsed() < ttl);
+    }
+}
+
+/// Settings section read from `workspace/didChangeConfiguration` and `workspace/configuration`
+pub const LSP_CONFIG_SECTION: &str = "autoCoder";
+
+/// Least severe diagnostic that is still published
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SeverityThreshold {
+    Error,
+    Warning,
+    Information,
+    #[default]
+    Hint,
+}
+
+impl SeverityThreshold {
+    fn rank(severity: DiagnosticSeverity) -> u8 {
+        match severity {
+            DiagnosticSeverity::ERROR => 1,
+            DiagnosticSeverity::WARNING => 2,
+            DiagnosticSeverity::INFORMATION => 3,
+            _ => 4,
+        }
+    }
+
+    /// Whether a diagnostic of this severity is published; unset severity counts as an error
+    pub fn allows(self, severity: Option<DiagnosticSeverity>) -> bool {
+        let threshold = match self {
+            SeverityThreshold::Error => 1,
+            SeverityThreshold::Warning => 2,
+            SeverityThreshold::Information => 3,
+            SeverityThreshold::Hint => 4,
+        };
+        severity.map_or(1, Self::rank) <= threshold
+    }
+}
+
+/// LSP server configuration; editors send it in camelCase under `autoCoder`
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LspConfig {
+    /// Quiet period after an edit before the document is analyzed
+    pub debounce_ms: u64,
+    pub cache_ttl_seconds: u64,
+    pub severity_threshold: SeverityThreshold,
+    pub diagnostics_enabled: bool,
+    pub hover_enabled: bool,
+    pub completion_enabled: bool,
+    pub signature_help_enabled: bool,
+    /// Analysis model by detected language, e.g. "rust" -> "codellama:34b"; languages
+    /// not listed use the analysis service's default model
+    pub analysis_models: HashMap<String, String>,
+}
+
+impl Default for LspConfig {
+    fn default() -> Self {
+        Self {
+            debounce_ms: 500,
+            cache_ttl_seconds: 300, // 5 minutes
+            severity_threshold: SeverityThreshold::default(),
+            diagnostics_enabled: true,
+            hover_enabled: true,
+            completion_enabled: true,
+            signature_help_enabled: true,
+            analysis_models: HashMap::new(),
+        }
+    }
+}
+
+impl LspConfig {
+    /// Parse client settings, accepting either the whole settings object or just its
+    /// `autoCoder` section; omitted fields take their defaults
+    pub fn from_settings(settings: &serde_json::Value) -> Result<Self, serde_json::Error> {
+        let section = settings.get(LSP_CONFIG_SECTION).unwrap_or(settings);
+        serde_json::from_value(section.clone())
+    }
+
+    pub fn analysis_model(&self, language: &str) -> Option<String> {
+        self.analysis_models.get(language).cloned()
     }
 }
 
 struct Backend {
     client: Client,
+    config: std::sync::RwLock<LspConfig>,
+    /// Client can answer `workspace/configuration` requests
+    supports_configuration_pull: AtomicBool,
+    /// Client accepts a dynamic registration for `workspace/didChangeConfiguration`
+    supports_configuration_registration: AtomicBool,
     document_map: Arc<Mutex<HashMap<Url, String>>>,
     ai_service: Arc<CodeAnalysisService>,
     debounced_analyzer: DebouncedAnalyzer,
     response_cache: AIResponseCache,
+    progress: AnalysisProgress,
+    /// AI-suggested signatures by "language:name", for functions not defined in open documents
+    signature_cache: Arc<Mutex<HashMap<String, FunctionSignature>>>,
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> LspResult<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> LspResult<InitializeResult> {
+        if let Some(workspace) = params.capabilities.workspace {
+            self.supports_configuration_pull
+                .store(workspace.configuration.unwrap_or(false), Ordering::Relaxed);
+            self.supports_configuration_registration.store(
+                workspace.did_change_configuration.and_then(|c| c.dynamic_registration).unwrap_or(false),
+                Ordering::Relaxed,
+            );
+        }
+        self.progress.set_enabled(
+            params.capabilities.window.and_then(|window| window.work_done_progress).unwrap_or(false),
+        );
+        
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
-                text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::FULL,
+                text_document_sync: Some(TextDocumentSyncCapability::Options(
+                    TextDocumentSyncOptions {
+                        open_close: Some(true),
+                        change: Some(TextDocumentSyncKind::FULL),
+                        save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                        ..Default::default()
+                    },
                 )),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
+                signature_help_provider: Some(SignatureHelpOptions {
+                    trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+                    retrigger_characters: None,
+                    work_done_progress_options: Default::default(),
+                }),
                 completion_provider: Some(CompletionOptions {
                     resolve_provider: Some(false),
                     trigger_characters: Some(vec![".".to_string(), ":".to_string()]),
@@ -148,6 +449,36 @@ impl LanguageServer for Backend {
         self.client
             .log_message(MessageType::INFO, "Auto-Coder LSP server initialized!")
             .await;
+        
+        // Configuration changes are only pushed to servers that register for them
+        if self.supports_configuration_registration.load(Ordering::Relaxed) {
+            let registration = Registration {
+                id: "auto-coder-configuration".to_string(),
+                method: "workspace/didChangeConfiguration".to_string(),
+                register_options: Some(serde_json::json!({ "section": LSP_CONFIG_SECTION })),
+            };
+            if let Err(e) = self.client.register_capability(vec![registration]).await {
+                self.client
+                    .log_message(MessageType::WARNING, format!("Failed to register for configuration changes: {}", e))
+                    .await;
+            }
+        }
+        
+        if self.supports_configuration_pull.load(Ordering::Relaxed) {
+            let item = ConfigurationItem {
+                scope_uri: None,
+                section: Some(LSP_CONFIG_SECTION.to_string()),
+            };
+            if let Ok(mut values) = self.client.configuration(vec![item]).await {
+                if let Some(settings) = values.pop().filter(|settings| !settings.is_null()) {
+                    self.update_config(&settings).await;
+                }
+            }
+        }
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        self.update_config(&params.settings).await;
     }
 
     async fn shutdown(&self) -> LspResult<()> {
@@ -181,6 +512,13 @@ impl LanguageServer for Backend {
         }
     }
 
+    async fn did_save(&self, params: DidSaveTextDocumentParams) {
+        // Saved files are re-extracted the next time a cached dependency graph is used
+        if let Ok(path) = params.text_document.uri.to_file_path() {
+            self.ai_service.mark_files_changed(&[path.to_string_lossy().to_string()]).await;
+        }
+    }
+
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
         let mut document_map = self.document_map.lock().await;
         document_map.remove(&params.text_document.uri);
@@ -192,6 +530,9 @@ impl LanguageServer for Backend {
     }
 
     async fn hover(&self, params: HoverParams) -> LspResult<Option<Hover>> {
+        if !self.config().hover_enabled {
+            return Ok(None);
+        }
         let document_map = self.document_map.lock().await;
         let uri = &params.text_document_position_params.text_document.uri;
         
@@ -225,7 +566,50 @@ impl LanguageServer for Backend {
         Ok(None)
     }
 
+    async fn signature_help(&self, params: SignatureHelpParams) -> LspResult<Option<SignatureHelp>> {
+        if !self.config().signature_help_enabled {
+            return Ok(None);
+        }
+        let uri = &params.text_document_position_params.text_document.uri;
+        let language = Self::detect_language_from_uri(uri);
+        
+        let (before_cursor, documents) = {
+            let document_map = self.document_map.lock().await;
+            let Some(document) = document_map.get(uri) else {
+                return Ok(None);
+            };
+            let before_cursor = Self::get_text_before_position(document, params.text_document_position_params.position);
+            // The current document comes first so its own definitions win
+            let mut documents = vec![document.clone()];
+            documents.extend(
+                document_map.iter()
+                    .filter(|(other, _)| *other != uri && Self::detect_language_from_uri(other) == language)
+                    .map(|(_, text)| text.clone()),
+            );
+            (before_cursor, documents)
+        };
+        
+        let Some((name, active_parameter)) = active_call(&before_cursor) else {
+            return Ok(None);
+        };
+        let indexed = documents.iter().find_map(|text| {
+            extract_signatures(text, &language).into_iter().find(|signature| signature.name == name)
+        });
+        let signature = match indexed {
+            Some(signature) => signature,
+            None => match self.get_ai_signature(&name, &before_cursor, &language).await {
+                Some(signature) => signature,
+                None => return Ok(None),
+            },
+        };
+        
+        Ok(Some(Self::to_signature_help(signature, active_parameter)))
+    }
+
     async fn completion(&self, params: CompletionParams) -> LspResult<Option<CompletionResponse>> {
+        if !self.config().completion_enabled {
+            return Ok(None);
+        }
         let document_map = self.document_map.lock().await;
         let uri = &params.text_document_position.text_document.uri;
         
@@ -389,12 +773,59 @@ impl LanguageServer for Backend {
 }
 
 impl Backend {
+    fn new(client: Client, ai_service: Arc<CodeAnalysisService>, config: LspConfig) -> Self {
+        Self {
+            progress: AnalysisProgress::new(Arc::new(client.clone())),
+            client,
+            debounced_analyzer: DebouncedAnalyzer::new(config.debounce_ms),
+            response_cache: AIResponseCache::new(config.cache_ttl_seconds),
+            config: std::sync::RwLock::new(config),
+            supports_configuration_pull: AtomicBool::new(false),
+            supports_configuration_registration: AtomicBool::new(false),
+            document_map: Arc::new(Mutex::new(HashMap::new())),
+            ai_service,
+            signature_cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+    
+    /// Current effective configuration
+    fn config(&self) -> LspConfig {
+        self.config.read().unwrap().clone()
+    }
+    
+    /// Apply client settings; invalid settings are logged and the current ones kept
+    async fn update_config(&self, settings: &serde_json::Value) {
+        match LspConfig::from_settings(settings) {
+            Ok(config) => self.apply_config(config),
+            Err(e) => {
+                self.client
+                    .log_message(MessageType::WARNING, format!("Ignoring invalid Auto-Coder settings: {}", e))
+                    .await;
+            }
+        }
+    }
+    
+    fn apply_config(&self, config: LspConfig) {
+        self.debounced_analyzer.set_delay(config.debounce_ms);
+        self.response_cache.set_ttl(config.cache_ttl_seconds);
+        *self.config.write().unwrap() = config;
+    }
+    
     async fn analyze_document(&self, uri: &Url, text: &str) -> () {
+        let config = self.config();
+        if !config.diagnostics_enabled {
+            return;
+        }
         let uri_clone = uri.clone();
         let text_clone = text.to_string();
         let client = self.client.clone();
         let ai_service = self.ai_service.clone();
         let response_cache = self.response_cache.clone();
+        let threshold = config.severity_threshold;
+        let progress = self.progress.clone();
+        
+        // The pending analysis for this document is aborted when the new one is scheduled
+        self.progress.supersede(uri).await;
         
         // Use debounced analysis to prevent excessive AI calls
         self.debounced_analyzer.schedule_analysis(uri.clone(), move || {
@@ -411,19 +842,13 @@ impl Backend {
                 
                 // Check cache first
                 if let Some(cached_result) = response_cache.get(&cache_key).await {
-                    Self::publish_ai_diagnostics(&client, &uri, &cached_result).await;
+                    Self::publish_ai_diagnostics(&client, &uri, &cached_result, threshold).await;
                     return;
                 }
                 
-                // Detect language from URI
-                let language = Self::detect_language_from_uri(&uri);
-                
-                // Create analysis request
-                let analysis_request = crate::code_analysis::CodeAnalysisRequest {
-                    code: text.clone(),
-                    language: language.clone(),
-                    file_path: Some(uri.to_string()),
-                };
+                let analysis_request = Self::build_analysis_request(&config, &uri, &text);
+                let language = analysis_request.language.clone();
+                let token = progress.begin(&uri).await;
                 
                 // Perform AI analysis
                 match ai_service.analyze_code(&analysis_request).await {
@@ -432,7 +857,7 @@ impl Backend {
                         response_cache.insert(cache_key, analysis_result.clone()).await;
                         
                         // Publish AI-powered diagnostics
-                        Self::publish_ai_diagnostics(&client, &uri, &analysis_result).await;
+                        Self::publish_ai_diagnostics(&client, &uri, &analysis_result, threshold).await;
                     }
                     Err(e) => {
                         // Fall back to basic analysis on AI failure
@@ -441,9 +866,11 @@ impl Backend {
                             &format!("AI analysis failed for {}: {}. Using fallback analysis.", uri, e)
                         ).await;
                         
-                        Self::publish_fallback_diagnostics(&client, &uri, &text).await;
+                        progress.report(&token, "Running fallback analysis").await;
+                        Self::publish_fallback_diagnostics(&client, &uri, &text, &language, threshold).await;
                     }
                 }
+                progress.finish(&uri, token).await;
             }
         }).await;
     }
@@ -452,6 +879,7 @@ impl Backend {
         client: &Client,
         uri: &Url,
         analysis_result: &crate::code_analysis::CodeAnalysisResponse,
+        threshold: SeverityThreshold,
     ) {
         let mut diagnostics = vec![];
         
@@ -504,76 +932,142 @@ impl Backend {
         }
         
         // Publish diagnostics
+        diagnostics.retain(|diagnostic| threshold.allows(diagnostic.severity));
+        client.publish_diagnostics(uri.clone(), diagnostics, None).await;
+    }
+    
+    async fn publish_fallback_diagnostics(
+        client: &Client,
+        uri: &Url,
+        text: &str,
+        language: &str,
+        threshold: SeverityThreshold,
+    ) {
+        let mut diagnostics = Self::fallback_diagnostics(text, language);
+        diagnostics.retain(|diagnostic| threshold.allows(diagnostic.severity));
         client.publish_diagnostics(uri.clone(), diagnostics, None).await;
     }
     
-    async fn publish_fallback_diagnostics(client: &Client, uri: &Url, text: &str) {
+    /// Cheap line-by-line checks used when AI analysis fails: comment tags, trailing
+    /// whitespace, indentation that mixes tabs and spaces, long lines and debug prints.
+    /// Lines are scanned in place; only diagnostics are allocated.
+    fn fallback_diagnostics(text: &str, language: &str) -> Vec<Diagnostic> {
         let mut diagnostics = vec![];
+        let debug_markers = debug_print_markers(language);
+        // The first indented line decides whether the file indents with tabs or spaces
+        let mut indent_char: Option<char> = None;
         
-        // Basic fallback analysis: flag TODO comments and simple issues
         for (i, line) in text.lines().enumerate() {
-            if line.contains("TODO") {
-                diagnostics.push(Diagnostic {
-                    range: tower_lsp::lsp_types::Range {
-                        start: tower_lsp::lsp_types::Position {
-                            line: i as u32,
-                            character: line.find("TODO").unwrap_or(0) as u32,
-                        },
-                        end: tower_lsp::lsp_types::Position {
-                            line: i as u32,
-                            character: (line.find("TODO").unwrap_or(0) + 4) as u32,
-                        },
-                    },
-                    severity: Some(DiagnosticSeverity::INFORMATION),
-                    code: None,
-                    code_description: None,
-                    source: Some("Auto-Coder Fallback".to_string()),
-                    message: "TODO comment found".to_string(),
-                    related_information: None,
-                    tags: Some(vec![DiagnosticTag::UNNECESSARY]),
-                    data: None,
-                });
+            let line_number = i as u32;
+            
+            for (tag, severity) in FALLBACK_TAGS {
+                if let Some(start) = find_word(line, tag) {
+                    let mut diagnostic = Self::fallback_diagnostic(
+                        line, line_number, start, start + tag.len(), severity, format!("{} comment found", tag),
+                    );
+                    diagnostic.tags = Some(vec![DiagnosticTag::UNNECESSARY]);
+                    diagnostics.push(diagnostic);
+                }
+            }
+            
+            let trimmed_len = line.trim_end().len();
+            if trimmed_len < line.len() {
+                diagnostics.push(Self::fallback_diagnostic(
+                    line, line_number, trimmed_len, line.len(), DiagnosticSeverity::HINT, "Trailing whitespace".to_string(),
+                ));
+            }
+            
+            let indent_len = line.len() - line.trim_start_matches([' ', '\t']).len();
+            let indent = &line[..indent_len];
+            if indent.contains(' ') && indent.contains('\t') {
+                diagnostics.push(Self::fallback_diagnostic(
+                    line, line_number, 0, indent_len, DiagnosticSeverity::WARNING,
+                    "Indentation mixes tabs and spaces".to_string(),
+                ));
+            } else if let Some(first) = indent.chars().next().filter(|_| indent_len < line.len()) {
+                match indent_char {
+                    None => indent_char = Some(first),
+                    Some(expected) if expected != first => {
+                        let (used, expected) = if first == '\t' { ("tabs", "spaces") } else { ("spaces", "tabs") };
+                        diagnostics.push(Self::fallback_diagnostic(
+                            line, line_number, 0, indent_len, DiagnosticSeverity::WARNING,
+                            format!("Indentation uses {} but the file is indented with {}", used, expected),
+                        ));
+                    }
+                    Some(_) => {}
+                }
+            }
+            
+            let length = line.chars().count();
+            if length > FALLBACK_MAX_LINE_LENGTH {
+                let start = line.char_indices().nth(FALLBACK_MAX_LINE_LENGTH).map_or(line.len(), |(index, _)| index);
+                diagnostics.push(Self::fallback_diagnostic(
+                    line, line_number, start, line.len(), DiagnosticSeverity::INFORMATION,
+                    format!("Line is {} characters long (max {})", length, FALLBACK_MAX_LINE_LENGTH),
+                ));
+            }
+            
+            for marker in debug_markers {
+                if let Some(start) = find_word(line, marker) {
+                    diagnostics.push(Self::fallback_diagnostic(
+                        line, line_number, start, start + marker.len(), DiagnosticSeverity::WARNING,
+                        format!("Leftover debug output: {}", marker.trim_end_matches('(')),
+                    ));
+                }
             }
         }
         
-        client.publish_diagnostics(uri.clone(), diagnostics, None).await;
+        diagnostics
     }
     
-    fn detect_language_from_uri(uri: &Url) -> String {
-        let path = uri.path();
-        if let Some(extension) = std::path::Path::new(path).extension() {
-            match extension.to_string_lossy().as_ref() {
-                "js" => "javascript".to_string(),
-                "ts" => "typescript".to_string(),
-                "jsx" => "jsx".to_string(),
-                "tsx" => "tsx".to_string(),
-                "py" => "python".to_string(),
-                "rs" => "rust".to_string(),
-                "go" => "go".to_string(),
-                "java" => "java".to_string(),
-                "c" => "c".to_string(),
-                "cpp" | "cc" | "cxx" => "cpp".to_string(),
-                "cs" => "csharp".to_string(),
-                "php" => "php".to_string(),
-                "rb" => "ruby".to_string(),
-                "swift" => "swift".to_string(),
-                "kt" | "kts" => "kotlin".to_string(),
-                "scala" => "scala".to_string(),
-                "html" => "html".to_string(),
-                "css" => "css".to_string(),
-                "json" => "json".to_string(),
-                "md" => "markdown".to_string(),
-                "yml" | "yaml" => "yaml".to_string(),
-                "xml" => "xml".to_string(),
-                "sh" | "bash" => "bash".to_string(),
-                "sql" => "sql".to_string(),
-                _ => "plaintext".to_string(),
-            }
-        } else {
-            "plaintext".to_string()
+    /// A fallback diagnostic spanning byte offsets `start..end` of `line`
+    fn fallback_diagnostic(
+        line: &str,
+        line_number: u32,
+        start: usize,
+        end: usize,
+        severity: DiagnosticSeverity,
+        message: String,
+    ) -> Diagnostic {
+        Diagnostic {
+            range: tower_lsp::lsp_types::Range {
+                start: tower_lsp::lsp_types::Position {
+                    line: line_number,
+                    character: utf16_column(line, start),
+                },
+                end: tower_lsp::lsp_types::Position {
+                    line: line_number,
+                    character: utf16_column(line, end),
+                },
+            },
+            severity: Some(severity),
+            code: None,
+            code_description: None,
+            source: Some("Auto-Coder Fallback".to_string()),
+            message,
+            related_information: None,
+            tags: None,
+            data: None,
         }
     }
     
+    /// Analysis request for a document, using the model configured for its language
+    fn build_analysis_request(config: &LspConfig, uri: &Url, text: &str) -> crate::code_analysis::CodeAnalysisRequest {
+        let language = Self::detect_language_from_uri(uri);
+        crate::code_analysis::CodeAnalysisRequest {
+            code: text.to_string(),
+            model: config.analysis_model(&language),
+            language,
+            file_path: Some(uri.to_string()),
+        }
+    }
+    
+    fn detect_language_from_uri(uri: &Url) -> String {
+        language_from_path(std::path::Path::new(uri.path()))
+            .unwrap_or("plaintext")
+            .to_string()
+    }
+    
     fn get_word_at_position(document: &str, position: tower_lsp::lsp_types::Position) -> Option<String> {
         let lines: Vec<&str> = document.lines().collect();
         
@@ -624,6 +1118,22 @@ impl Backend {
         line[..position.character as usize].to_string()
     }
     
+    /// Document text from the start up to `position`
+    fn get_text_before_position(document: &str, position: tower_lsp::lsp_types::Position) -> String {
+        let mut before = String::new();
+        for (index, line) in document.lines().enumerate() {
+            if index < position.line as usize {
+                before.push_str(line);
+                before.push('\n');
+            } else {
+                let end = line.char_indices().nth(position.character as usize).map_or(line.len(), |(end, _)| end);
+                before.push_str(&line[..end]);
+                break;
+            }
+        }
+        before
+    }
+    
     fn get_context_around_position(document: &str, position: tower_lsp::lsp_types::Position, context_lines: u32) -> String {
         let lines: Vec<&str> = document.lines().collect();
         let line_num = position.line as usize;
@@ -634,6 +1144,58 @@ impl Backend {
         lines[start..end].join("\n")
     }
     
+    /// Ask the AI service for the signature of a function not defined in any open
+    /// document; answers are cached per symbol
+    async fn get_ai_signature(&self, name: &str, before_cursor: &str, language: &str) -> Option<FunctionSignature> {
+        let cache_key = format!("{}:{}", language, name);
+        if let Some(signature) = self.signature_cache.lock().await.get(&cache_key) {
+            return Some(signature.clone());
+        }
+        
+        let context_start = before_cursor.len().saturating_sub(2000);
+        let context_start = (context_start..before_cursor.len())
+            .find(|index| before_cursor.is_char_boundary(*index))
+            .unwrap_or(before_cursor.len());
+        let context = &before_cursor[context_start..];
+        let request = crate::code_analysis::CodeGenerationRequest {
+            prompt: format!(
+                "Give the signature of the {} function '{}' called at the end of this code:\n\n```{}\n{}\n```\n\nReply with only the signature, e.g. `{}(param: Type, other: Type)`, on one line.",
+                language, name, language, context, name
+            ),
+            language: language.to_string(),
+            context: Some(context.to_string()),
+        };
+        
+        let response = self.ai_service.generate_code(&request).await.ok()?;
+        let label = response.generated_code.lines().find(|line| line.contains(name))?;
+        let signature = signature_from_label(name, label, Some(response.explanation))?;
+        self.signature_cache.lock().await.insert(cache_key, signature.clone());
+        Some(signature)
+    }
+    
+    fn to_signature_help(signature: FunctionSignature, active_parameter: u32) -> SignatureHelp {
+        let last_parameter = signature.parameters.len().saturating_sub(1) as u32;
+        let parameters = signature.parameters.into_iter()
+            .map(|parameter| ParameterInformation {
+                label: ParameterLabel::Simple(parameter),
+                documentation: None,
+            })
+            .collect();
+        
+        SignatureHelp {
+            signatures: vec![SignatureInformation {
+                label: signature.label,
+                documentation: signature.documentation.map(|value| {
+                    Documentation::MarkupContent(MarkupContent { kind: MarkupKind::Markdown, value })
+                }),
+                parameters: Some(parameters),
+                active_parameter: None,
+            }],
+            active_signature: Some(0),
+            active_parameter: Some(active_parameter.min(last_parameter)),
+        }
+    }
+    
     async fn get_ai_hover_info(&self, word: &str, context: &str, language: &str) -> std::result::Result<String, String> {
         // Create a specialized prompt for hover information
         let prompt = format!(
@@ -796,17 +1358,32 @@ impl Backend {
     }
 }
 
+/// Byte offset of the first occurrence of `word` in `line` not preceded or followed
+/// by an identifier character
+fn find_word(line: &str, word: &str) -> Option<usize> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    line.match_indices(word).map(|(index, _)| index).find(|&index| {
+        let before = line[..index].chars().next_back();
+        let after = line[index + word.len()..].chars().next();
+        !before.is_some_and(is_ident)
+            && (!word.ends_with(is_ident) || !after.is_some_and(is_ident))
+    })
+}
+
+/// LSP positions count UTF-16 code units
+fn utf16_column(line: &str, byte_offset: usize) -> u32 {
+    line[..byte_offset].encode_utf16().count() as u32
+}
+
 pub async fn start_lsp_server_with_ai(ai_service: Arc<CodeAnalysisService>) {
+    start_lsp_server_with_config(ai_service, LspConfig::default()).await;
+}
+
+pub async fn start_lsp_server_with_config(ai_service: Arc<CodeAnalysisService>, config: LspConfig) {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
     
-    let (service, socket) = LspService::new(|client| Backend {
-        client,
-        document_map: Arc::new(Mutex::new(HashMap::new())),
-        ai_service: ai_service.clone(),
-        debounced_analyzer: DebouncedAnalyzer::new(500), // 500ms delay
-        response_cache: AIResponseCache::new(300), // 5 minute TTL
-    });
+    let (service, socket) = LspService::new(|client| Backend::new(client, ai_service.clone(), config.clone()));
     
     Server::new(stdin, stdout, socket).serve(service).await;
 }
@@ -948,4 +1525,339 @@ mod tests {
         });
         assert_eq!(word, Some("console".to_string()));
     }
+
+    #[test]
+    fn test_analysis_model_follows_detected_language() {
+        let config = LspConfig {
+            analysis_models: HashMap::from([
+                ("rust".to_string(), "codellama:34b".to_string()),
+                ("javascript".to_string(), "qwen2.5-coder:1.5b".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let request = |uri: &str| {
+            Backend::build_analysis_request(&config, &Url::parse(uri).unwrap(), "code")
+        };
+
+        let rust = request("file:///project/src/main.rs");
+        assert_eq!(rust.language, "rust");
+        assert_eq!(rust.model.as_deref(), Some("codellama:34b"));
+        assert_eq!(request("file:///project/app.js").model.as_deref(), Some("qwen2.5-coder:1.5b"));
+
+        // Unlisted languages fall back to the service default
+        let python = request("file:///project/main.py");
+        assert_eq!(python.language, "python");
+        assert_eq!(python.model, None);
+    }
+
+    fn test_backend() -> (LspService<Backend>, tower_lsp::ClientSocket) {
+        let ollama = Arc::new(Mutex::new(crate::ollama_client::OllamaClient::new(None)));
+        let ai_service = Arc::new(CodeAnalysisService::new(ollama));
+        LspService::new(|client| Backend::new(client, ai_service, LspConfig::default()))
+    }
+
+    #[tokio::test]
+    async fn test_did_change_configuration_updates_backend() {
+        let (service, _socket) = test_backend();
+        let backend = service.inner();
+        assert_eq!(backend.debounced_analyzer.delay(), Duration::from_millis(500));
+
+        backend
+            .did_change_configuration(DidChangeConfigurationParams {
+                settings: serde_json::json!({
+                    "autoCoder": {
+                        "debounceMs": 50,
+                        "cacheTtlSeconds": 10,
+                        "severityThreshold": "warning",
+                        "hoverEnabled": false,
+                        "analysisModels": { "rust": "codellama:34b" }
+                    }
+                }),
+            })
+            .await;
+
+        let config = backend.config();
+        assert_eq!(backend.debounced_analyzer.delay(), Duration::from_millis(50));
+        assert_eq!(backend.response_cache.ttl(), Duration::from_secs(10));
+        assert_eq!(config.severity_threshold, SeverityThreshold::Warning);
+        assert!(!config.hover_enabled);
+        assert!(config.completion_enabled);
+        assert_eq!(config.analysis_model("rust").as_deref(), Some("codellama:34b"));
+
+        // Disabled providers answer nothing
+        let uri = Url::parse("file:///project/src/main.rs").unwrap();
+        backend.document_map.lock().await.insert(uri.clone(), "fn main() {}".to_string());
+        let hover = backend
+            .hover(HoverParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri },
+                    position: Position { line: 0, character: 4 },
+                },
+                work_done_progress_params: Default::default(),
+            })
+            .await
+            .unwrap();
+        assert!(hover.is_none());
+
+        // Invalid settings leave the current configuration in place
+        backend
+            .did_change_configuration(DidChangeConfigurationParams {
+                settings: serde_json::json!({ "debounceMs": "soon" }),
+            })
+            .await;
+        assert_eq!(backend.config(), config);
+    }
+
+    #[tokio::test]
+    async fn test_signature_help_for_indexed_function() {
+        let (service, _socket) = test_backend();
+        let backend = service.inner();
+        let main_uri = Url::parse("file:///project/src/main.rs").unwrap();
+        let math_uri = Url::parse("file:///project/src/math.rs").unwrap();
+        let main = "/// Adds two numbers\nfn add(left: i32, right: i32) -> i32 {\n    left + right\n}\n\nfn main() {\n    let total = add(1, scale(2.0, ";
+        {
+            let mut document_map = backend.document_map.lock().await;
+            document_map.insert(main_uri.clone(), main.to_string());
+            document_map.insert(math_uri, "pub fn scale(value: f32, factor: f32) -> f32 {\n    value * factor\n}\n".to_string());
+        }
+        let signature_help = |line: u32, character: u32| {
+            backend.signature_help(SignatureHelpParams {
+                context: None,
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: main_uri.clone() },
+                    position: Position { line, character },
+                },
+                work_done_progress_params: Default::default(),
+            })
+        };
+
+        // Inside `add(1, ` before `scale(` is typed
+        let help = signature_help(6, 23).await.unwrap().unwrap();
+        let signature = &help.signatures[0];
+        assert_eq!(signature.label, "add(left: i32, right: i32) -> i32");
+        let parameters: Vec<_> = signature.parameters.as_ref().unwrap().iter()
+            .map(|parameter| match &parameter.label {
+                ParameterLabel::Simple(label) => label.clone(),
+                ParameterLabel::LabelOffsets(_) => unreachable!(),
+            })
+            .collect();
+        assert_eq!(parameters, vec!["left: i32", "right: i32"]);
+        assert_eq!(help.active_parameter, Some(1));
+        assert!(matches!(&signature.documentation, Some(Documentation::MarkupContent(doc)) if doc.value == "Adds two numbers"));
+
+        // Functions defined in other open documents of the same language are found too
+        let help = signature_help(6, 34).await.unwrap().unwrap();
+        assert_eq!(help.signatures[0].label, "scale(value: f32, factor: f32) -> f32");
+        assert_eq!(help.active_parameter, Some(1));
+
+        // Outside a call there is no signature help
+        assert!(signature_help(6, 4).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_severity_threshold() {
+        assert!(SeverityThreshold::Warning.allows(Some(DiagnosticSeverity::ERROR)));
+        assert!(SeverityThreshold::Warning.allows(Some(DiagnosticSeverity::WARNING)));
+        assert!(!SeverityThreshold::Warning.allows(Some(DiagnosticSeverity::HINT)));
+        assert!(SeverityThreshold::Error.allows(None));
+        assert!(SeverityThreshold::Hint.allows(Some(DiagnosticSeverity::HINT)));
+    }
+
+    #[derive(Default)]
+    struct RecordingSink {
+        events: std::sync::Mutex<Vec<String>>,
+        /// The client refuses to create tokens
+        refuse_tokens: bool,
+        /// Token creation waits until this is notified
+        create_gate: Option<Arc<tokio::sync::Notify>>,
+    }
+
+    impl RecordingSink {
+        fn record(&self, kind: &str, token: ProgressToken, message: &str) {
+            let ProgressToken::String(token) = token else {
+                panic!("analysis progress tokens are strings");
+            };
+            self.events.lock().unwrap().push(format!("{} {} {}", kind, token.rsplit('/').next().unwrap(), message));
+        }
+
+        fn events(&self) -> Vec<String> {
+            self.events.lock().unwrap().clone()
+        }
+    }
+
+    #[tower_lsp::async_trait]
+    impl ProgressSink for RecordingSink {
+        async fn begin(&self, token: ProgressToken, _title: &str, message: &str) -> bool {
+            if let Some(gate) = &self.create_gate {
+                gate.notified().await;
+            }
+            if self.refuse_tokens {
+                return false;
+            }
+            self.record("begin", token, message);
+            true
+        }
+
+        async fn report(&self, token: ProgressToken, message: &str) {
+            self.record("report", token, message);
+        }
+
+        async fn end(&self, token: ProgressToken, message: &str) {
+            self.record("end", token, message);
+        }
+    }
+
+    fn recording_progress() -> (AnalysisProgress, Arc<RecordingSink>) {
+        progress_with_sink(RecordingSink::default())
+    }
+
+    fn progress_with_sink(sink: RecordingSink) -> (AnalysisProgress, Arc<RecordingSink>) {
+        let sink = Arc::new(sink);
+        let progress = AnalysisProgress::new(sink.clone());
+        progress.set_enabled(true);
+        (progress, sink)
+    }
+
+    #[tokio::test]
+    async fn test_refused_token_is_not_tracked() {
+        let (progress, sink) = progress_with_sink(RecordingSink { refuse_tokens: true, ..Default::default() });
+        let uri = Url::parse("file:///project/src/main.rs").unwrap();
+
+        assert!(progress.begin(&uri).await.is_none());
+        progress.supersede(&uri).await;
+
+        assert!(sink.events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_supersede_during_token_creation() {
+        let gate = Arc::new(tokio::sync::Notify::new());
+        let (progress, sink) = progress_with_sink(RecordingSink { create_gate: Some(gate.clone()), ..Default::default() });
+        let uri = Url::parse("file:///project/src/main.rs").unwrap();
+
+        let begin = tokio::spawn({
+            let progress = progress.clone();
+            let uri = uri.clone();
+            async move { progress.begin(&uri).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // Doesn't wait for the client to create the pending token
+        tokio::time::timeout(Duration::from_secs(1), progress.supersede(&uri)).await.unwrap();
+        gate.notify_one();
+
+        assert!(begin.await.unwrap().is_none());
+        assert_eq!(sink.events(), vec!["begin 1 Analyzing…", "end 1 Superseded by a newer edit"]);
+        // The superseded token was never tracked, so there's nothing left to end
+        progress.supersede(&uri).await;
+        assert_eq!(sink.events().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_progress_brackets_analysis() {
+        let (progress, sink) = recording_progress();
+        let uri = Url::parse("file:///project/src/main.rs").unwrap();
+
+        let token = progress.begin(&uri).await;
+        progress.report(&token, "Running fallback analysis").await;
+        progress.finish(&uri, token).await;
+        // Finishing twice doesn't end the progress again
+        progress.finish(&uri, Some(ProgressToken::String("auto-coder/analysis/1".to_string()))).await;
+
+        assert_eq!(
+            sink.events(),
+            vec!["begin 1 Analyzing…", "report 1 Running fallback analysis", "end 1 Analysis complete"]
+        );
+
+        progress.set_enabled(false);
+        assert!(progress.begin(&uri).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_superseded_analysis_ends_its_progress() {
+        let (progress, sink) = recording_progress();
+        let analyzer = DebouncedAnalyzer::new(0);
+        let uri = Url::parse("file:///project/src/main.rs").unwrap();
+
+        let schedule = |delay: Duration| {
+            let progress = progress.clone();
+            let uri = uri.clone();
+            move || async move {
+                let token = progress.begin(&uri).await;
+                tokio::time::sleep(delay).await;
+                progress.finish(&uri, token).await;
+            }
+        };
+
+        analyzer.schedule_analysis(uri.clone(), schedule(Duration::from_secs(60))).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(sink.events(), vec!["begin 1 Analyzing…"]);
+
+        // A newer edit ends the in-flight progress before aborting its task
+        progress.supersede(&uri).await;
+        analyzer.schedule_analysis(uri.clone(), schedule(Duration::ZERO)).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(
+            sink.events(),
+            vec![
+                "begin 1 Analyzing…",
+                "end 1 Superseded by a newer edit",
+                "begin 2 Analyzing…",
+                "end 2 Analysis complete",
+            ]
+        );
+    }
+
+    fn ranges_for(diagnostics: &[Diagnostic], message_prefix: &str) -> Vec<(u32, u32, u32)> {
+        diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.message.starts_with(message_prefix))
+            .map(|diagnostic| (diagnostic.range.start.line, diagnostic.range.start.character, diagnostic.range.end.character))
+            .collect()
+    }
+
+    #[test]
+    fn test_fallback_diagnostics_rules() {
+        let long_line = format!("let s = \"{}\";", "é".repeat(130));
+        let text = [
+            "// TODO: tidy up",
+            "// FIXME broken, XXX and HACK here; HACKATHON is fine",
+            "fn main() {   ",
+            "    let x = 1;",
+            "\tlet y = 2;",
+            " \tlet z = 3;",
+            "    println!(\"{}\", x);",
+            "    eprintln!(\"ok\");",
+            long_line.as_str(),
+        ]
+        .join("\n");
+
+        let diagnostics = Backend::fallback_diagnostics(&text, "rust");
+
+        assert_eq!(ranges_for(&diagnostics, "TODO"), vec![(0, 3, 7)]);
+        assert_eq!(ranges_for(&diagnostics, "FIXME"), vec![(1, 3, 8)]);
+        assert_eq!(ranges_for(&diagnostics, "XXX"), vec![(1, 17, 20)]);
+        assert_eq!(ranges_for(&diagnostics, "HACK"), vec![(1, 25, 29)]);
+        assert_eq!(ranges_for(&diagnostics, "Trailing whitespace"), vec![(2, 11, 14)]);
+        assert_eq!(ranges_for(&diagnostics, "Indentation uses tabs"), vec![(4, 0, 1)]);
+        assert_eq!(ranges_for(&diagnostics, "Indentation mixes"), vec![(5, 0, 2)]);
+        // eprintln! is not flagged as println!
+        assert_eq!(ranges_for(&diagnostics, "Leftover debug output: println!"), vec![(6, 4, 12)]);
+        assert_eq!(ranges_for(&diagnostics, "Leftover debug output").len(), 1);
+        assert_eq!(ranges_for(&diagnostics, "Line is 141 characters"), vec![(8, 120, 141)]);
+    }
+
+    #[test]
+    fn test_fallback_debug_prints_are_gated_by_language() {
+        let text = "console.log(x);\nprint(x)\npprint(x)\nprintln!(\"x\");";
+
+        let javascript = Backend::fallback_diagnostics(text, "javascript");
+        assert_eq!(ranges_for(&javascript, "Leftover debug output"), vec![(0, 0, 11)]);
+
+        let python = Backend::fallback_diagnostics(text, "python");
+        assert_eq!(ranges_for(&python, "Leftover debug output: print"), vec![(1, 0, 6)]);
+
+        assert!(ranges_for(&Backend::fallback_diagnostics(text, "plaintext"), "Leftover").is_empty());
+    }
 }
@@ -1,9 +1,28 @@
 mod commands;
 mod ollama_client;
+mod model_aliases;
+mod ollama_rate_limit;
+mod prompt_guard;
+mod embedding_coalescer;
 mod searxng_client;
 mod searxng_commands;
 mod chroma_manager;
+mod document_chunker;
+mod document_spill;
+mod embedding_spill;
+mod embedding_quantization;
+mod document_types;
+mod dependency_export;
+mod dependency_modules;
+mod unused_exports;
+mod git_diff;
+mod security_scan;
+mod json_repair;
+mod float_order;
+mod extraction_cache;
+mod language_detection;
 mod lsp_server;
+mod signature_help;
 mod code_analysis;
 mod context_manager;
 mod analysis_engine;
@@ -14,19 +33,33 @@ mod user_errors;
 // mod doc_scraper;
 // mod window_manager;
 mod history_manager;
+mod history_storage;
+mod data_purge;
+mod warm_up;
+mod settings_store;
+mod prompt_templates;
+mod health_history;
+mod events;
+mod operation_manager;
+mod maintenance;
 // mod file_watcher;
 
 use tauri::Manager;
 // use window_manager::WindowManager;
-use ollama_client::{OllamaClient, SharedOllamaClient};
+use ollama_client::{ActiveGenerations, OllamaClient, SharedOllamaClient};
 use searxng_client::SearXNGClient;
 use chroma_manager::ChromaManager;
 use code_analysis::CodeAnalysisService;
+use extraction_cache::ExtractionCache;
 use context_manager::ContextManager;
 use mcp_manager::MCPManager;
 use thread_pool_manager::ThreadPoolManager;
 use history_manager::{HistoryManager, SharedHistoryManager};
+use warm_up::{WarmUpConfig, WarmUpState};
+use settings_store::SettingsStore;
+use maintenance::MaintenanceScheduler;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 
 #[cfg(debug_assertions)]
@@ -60,54 +93,209 @@ fn main() {
     
     // Temporarily disable menu for focus on ChromaDB implementation
     // Note: Menu implementation will be added using Tauri 2.0 API in future releases
-    // Initialize ChromaManager with proper error handling
-    let chroma_manager = ChromaManager::new("./chroma_db")
-        .map_err(|e| format!("Failed to initialize ChromaDB: {}", e))
-        .expect("ChromaDB initialization failed");
-    
-    // Initialize OllamaClient for AI services
-    let ollama_client = OllamaClient::new(None);
-    let shared_ollama_client: SharedOllamaClient = Arc::new(Mutex::new(ollama_client.clone()));
-    
-    // Initialize CodeAnalysisService with shared Ollama client
-    let code_analysis_service = Arc::new(CodeAnalysisService::new(shared_ollama_client.clone()));
-
-    // Initialize ContextManager with default settings (128k tokens, 25k reserved)
-    let context_manager = ContextManager::default();
     
     // Initialize MCPManager for Model Context Protocol extensions
     let mcp_manager = MCPManager::new();
     
-    // Initialize ThreadPoolManager for CPU-intensive tasks
-    let thread_pool_manager = ThreadPoolManager::new();
+    // Initialize ThreadPoolManager for CPU-intensive tasks; shared with ChromaManager's embedding batches
+    let thread_pool_manager = Arc::new(ThreadPoolManager::new());
 
     tauri::Builder::default()
         // .manage(WindowManager::new())
-        .manage(ollama_client)
-        .manage(shared_ollama_client)
-        .manage(SearXNGClient::new(None))
-        .manage(Mutex::new(chroma_manager))
-        .manage(code_analysis_service)
-        .manage(context_manager)
         .manage(mcp_manager)
         .manage(thread_pool_manager)
+        .manage(WarmUpState::new())
+        .manage(ActiveGenerations::new())
         .setup(|app| {
-            // Initialize HistoryManager
-            let history_manager = HistoryManager::new(&app.handle())
+            // Load persisted settings; services below are configured from them
+            let app_dir = app
+                .path()
+                .app_data_dir()
+                .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+            let settings_store = SettingsStore::load(&app_dir)
+                .map_err(|e| format!("Failed to load settings: {}", e))?;
+            let settings = settings_store.get().clone();
+            app.manage(Mutex::new(settings_store));
+            
+            // Apply user overrides to the built-in prompt templates
+            prompt_templates::load_overrides(&app_dir.join("prompt_templates"))
+                .map_err(|e| format!("Failed to load prompt templates: {}", e))?;
+            
+            // Initialize OllamaClient for AI services
+            let ollama_client = OllamaClient::new_with_health_config(
+                Some(settings.ollama.base_url.clone()),
+                settings.ollama.health.clone(),
+            )
+            .with_rate_limit(&settings.ollama.rate_limit);
+            ollama_client.set_model_aliases(settings.ollama.model_aliases.clone());
+            ollama_client.set_prompt_limit(settings.ollama.prompt_limit.clone());
+            let shared_ollama_client: SharedOllamaClient = Arc::new(Mutex::new(ollama_client.clone()));
+            
+            // Initialize ChromaManager with proper error handling
+            let mut chroma_manager = ChromaManager::new_with_cache_config("./chroma_db", settings.chroma.cache.clone())
+                .map_err(|e| format!("Failed to initialize ChromaDB: {}", e))?;
+            chroma_manager
+                .set_memory_limit(settings.chroma.memory_limit.clone())
+                .map_err(|e| format!("Failed to apply ChromaDB memory limit: {}", e))?;
+            chroma_manager.set_document_types(settings.chroma.document_types.clone());
+            chroma_manager.set_language_inference(settings.chroma.infer_languages);
+            chroma_manager
+                .set_query_limits(settings.chroma.query_limits.clone())
+                .map_err(|e| format!("Failed to apply ChromaDB query limits: {}", e))?;
+            chroma_manager
+                .set_chunking_config(settings.chroma.chunking.clone())
+                .map_err(|e| format!("Failed to apply ChromaDB chunking settings: {}", e))?;
+            chroma_manager.enable_batch_processing(
+                ollama_client.clone(),
+                app.state::<Arc<ThreadPoolManager>>().inner().clone(),
+                None,
+            );
+            let chroma_status = chroma_manager.status();
+            app.manage(chroma_status.clone());
+            app.manage(Mutex::new(chroma_manager));
+            
+            // Initialize CodeAnalysisService with shared Ollama client
+            app.manage(Arc::new(
+                CodeAnalysisService::new(shared_ollama_client.clone())
+                    .with_extraction_cache(ExtractionCache::new(app_dir.join("extraction_cache"))),
+            ));
+            app.manage(ollama_client.clone());
+            app.manage(shared_ollama_client);
+            
+            let searxng_client = SearXNGClient::new_with_health_config(
+                Some(settings.searxng.base_url.clone()),
+                settings.searxng.health.clone(),
+            );
+            app.manage(searxng_client.clone());
+            
+            // Initialize ContextManager with the configured window (128k tokens, 25k reserved by default)
+            app.manage(ContextManager::new(settings.context.max_tokens, settings.context.reserved_tokens));
+            
+            // Initialize HistoryManager with the configured storage
+            let history_manager = HistoryManager::new(&app.handle(), settings.history.backend)
                 .map_err(|e| format!("Failed to initialize HistoryManager: {}", e))?;
             let shared_history: SharedHistoryManager = Arc::new(Mutex::new(history_manager));
             app.manage(shared_history);
             
+            // Preload models and check services in the background
+            let warm_up_config = WarmUpConfig {
+                chat_model: settings.ollama.default_model.clone(),
+                embedding_model: Some(settings.ollama.embedding_model.clone()),
+                ..Default::default()
+            };
+            warm_up::spawn_warm_up(app.handle().clone(), warm_up_config);
+            
+            // Without any models only non-AI features work; say so rather than failing per request
+            let availability_client = ollama_client.clone();
+            tauri::async_runtime::spawn(async move {
+                let availability = availability_client.check_model_availability().await.map_err(|e| e.to_string());
+                match availability {
+                    Ok(availability) if availability.degraded => eprintln!(
+                        "No Ollama models are installed; running without AI features until a model such as {} is pulled",
+                        availability.suggested_model
+                    ),
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Failed to check installed Ollama models: {}", e),
+                }
+            });
+            
+            // Periodic cache cleanup and health history trimming
+            let scheduler = MaintenanceScheduler::new(settings.maintenance.jitter_fraction);
+            let app_handle = app.handle().clone();
+            scheduler.register(
+                "chroma_cache_cleanup",
+                Duration::from_secs(settings.chroma.cache.cleanup_interval_seconds),
+                move || {
+                    let app_handle = app_handle.clone();
+                    async move {
+                        app_handle.state::<Mutex<ChromaManager>>().lock().await.cleanup_expired_cache();
+                    }
+                },
+            );
+            let max_age = Duration::from_secs(settings.maintenance.health_history_max_age_seconds);
+            scheduler.register(
+                "health_history_trim",
+                Duration::from_secs(settings.maintenance.health_history_trim_interval_seconds),
+                move || {
+                    let ollama_client = ollama_client.clone();
+                    let chroma_status = chroma_status.clone();
+                    let searxng_client = searxng_client.clone();
+                    async move {
+                        ollama_client.trim_health_history(max_age).await;
+                        chroma_status.trim_health_history(max_age).await;
+                        searxng_client.trim_health_history(max_age).await;
+                    }
+                },
+            );
+            let app_handle = app.handle().clone();
+            let grace_days = settings.maintenance.deleted_session_grace_days;
+            scheduler.register(
+                "deleted_session_cleanup",
+                Duration::from_secs(settings.maintenance.deleted_session_cleanup_interval_seconds),
+                move || {
+                    let app_handle = app_handle.clone();
+                    async move {
+                        let history = app_handle.state::<SharedHistoryManager>();
+                        let mut history = history.lock().await;
+                        if let Err(e) = history.purge_deleted_sessions(data_purge::cutoff_for_days(grace_days)) {
+                            eprintln!("Failed to remove deleted chat sessions: {}", e);
+                        }
+                    }
+                },
+            );
+            if let Some(days) = settings.maintenance.auto_purge_after_days {
+                let app_handle = app.handle().clone();
+                let scopes = settings.maintenance.auto_purge_scopes.clone();
+                scheduler.register(
+                    "data_auto_purge",
+                    Duration::from_secs(settings.maintenance.auto_purge_interval_seconds),
+                    move || {
+                        let app_handle = app_handle.clone();
+                        let scopes = scopes.clone();
+                        async move {
+                            let history = app_handle.state::<SharedHistoryManager>();
+                            let chroma = app_handle.state::<Mutex<ChromaManager>>();
+                            let code_analysis_service = app_handle.state::<Arc<CodeAnalysisService>>();
+                            let mut history = history.lock().await;
+                            let mut chroma = chroma.lock().await;
+                            let log_path = data_purge::default_interaction_log_path();
+                            let mut targets = data_purge::PurgeTargets {
+                                history: &mut history,
+                                chroma: &mut chroma,
+                                extraction_cache: code_analysis_service.extraction_cache(),
+                                interaction_log_path: &log_path,
+                            };
+                            if let Err(e) = data_purge::purge(&mut targets, &scopes, Some(data_purge::cutoff_for_days(days)), false) {
+                                eprintln!("Automatic data purge failed: {}", e);
+                            }
+                        }
+                    },
+                );
+            }
+            if settings.maintenance.enabled {
+                scheduler.start();
+            }
+            app.manage(scheduler);
+            
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::check_ollama_connection,
+            commands::set_ollama_base_url,
             commands::list_models,
+            commands::pull_ollama_model,
             commands::chat_with_ollama,
             commands::chat_stream_with_ollama,
             commands::generate_with_ollama,
+            commands::generate_detailed,
             commands::generate_stream_with_ollama,
+            commands::get_generation_buffer_stats,
+            commands::generate_with_rag,
+            commands::benchmark_rag,
+            commands::resume_analysis,
+            commands::analyze_batch,
             searxng_commands::check_searxng_connection,
+            searxng_commands::set_searxng_base_url,
             searxng_commands::search_web,
             searxng_commands::get_available_engines,
             searxng_commands::set_default_engines,
@@ -123,8 +311,6 @@ fn main() {
             chroma_manager::list_chroma_collections,
             chroma_manager::create_chroma_collection,
             chroma_manager::delete_chroma_collection,
-            chroma_manager::add_documents_to_chroma,
-            chroma_manager::query_chroma,
             chroma_manager::get_documents_from_chroma,
             chroma_manager::delete_documents_from_chroma,
             chroma_manager::get_collection_count,
@@ -155,9 +341,26 @@ fn main() {
             code_analysis::analyze_repository,
             code_analysis::fix_code,
             code_analysis::generate_code,
+            code_analysis::scan_security,
+            code_analysis::generate_tests,
+            code_analysis::generate_docs,
             code_analysis::analyze_dependencies,
             code_analysis::analyze_impact,
+            code_analysis::analyze_impact_from_git,
+            code_analysis::generate_commit_message,
+            code_analysis::review_diff,
+            code_analysis::refresh_dependency_graph,
+            code_analysis::get_dependency_graph,
+            code_analysis::reanalyze_file_dependencies,
+            code_analysis::aggregate_dependency_graph,
+            code_analysis::export_dependency_graph,
+            code_analysis::find_unused_exports,
+            code_analysis::find_untested_symbols,
+            code_analysis::get_extraction_cache_stats,
+            code_analysis::clear_extraction_cache,
             code_analysis::suggest_refactorings,
+            code_analysis::apply_refactoring,
+            code_analysis::apply_refactorings,
             context_manager::get_context_budget,
             context_manager::pin_file,
             context_manager::unpin_file,
@@ -188,13 +391,56 @@ fn main() {
             history_manager::create_chat_session,
             history_manager::update_chat_session,
             history_manager::delete_chat_session,
+            history_manager::restore_chat_session,
             history_manager::add_chat_message,
+            history_manager::edit_chat_message,
+            history_manager::list_session_branches,
+            history_manager::switch_session_branch,
+            history_manager::summarize_session,
+            history_manager::continue_generation,
+            history_manager::add_session_tag,
+            history_manager::remove_session_tag,
+            history_manager::search_chat_sessions,
+            history_manager::filter_chat_sessions_by_tag,
             // Health monitoring commands
             commands::get_ollama_health_stats,
+            commands::get_ollama_rate_limit_stats,
+            commands::check_model_availability,
+            commands::get_health_history,
+            commands::get_memory_report,
+            commands::list_document_types,
+            commands::create_chroma_snapshot,
+            commands::restore_chroma_snapshot,
+            commands::add_documents_to_chroma,
+            commands::query_chroma,
+            commands::compact_chroma_collection,
+            commands::set_collection_embedding_model,
+            commands::set_collection_embedding_quantization,
+            commands::get_collection_metadata,
+            commands::export_collection_ndjson,
+            commands::import_collection_ndjson,
             commands::check_ollama_health,
+            commands::check_ollama_embedding_health,
+            commands::get_ollama_split_health,
             commands::start_ollama_health_monitoring,
             commands::stop_ollama_health_monitoring,
             commands::check_ollama_connection_detailed,
+            // Startup warm-up commands
+            warm_up::get_warm_up_report,
+            warm_up::cancel_warm_up,
+            // Settings commands
+            settings_store::get_settings,
+            settings_store::update_settings,
+            thread_pool_manager::get_thread_pool_stats,
+            thread_pool_manager::resize_thread_pool,
+            maintenance::start_maintenance,
+            maintenance::stop_maintenance,
+            maintenance::get_maintenance_status,
+            data_purge::purge_data,
+            // Prompt template commands
+            prompt_templates::list_prompt_templates,
+            prompt_templates::set_prompt_template,
+            prompt_templates::reset_prompt_template,
             // file_watcher::watch_repository,
             // file_watcher::unwatch_repository,
             // file_watcher::list_files,
@@ -0,0 +1,265 @@
+//! Background scheduler for periodic maintenance (cache cleanup, history trimming)
+//!
+//! Tasks are registered with an interval and run on a single background loop. Each
+//! run is followed by a random delay of up to `jitter_fraction` of the interval so
+//! tasks with the same interval don't all fire together.
+
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::State;
+
+pub type MaintenanceFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+pub type MaintenanceTask = Arc<dyn Fn() -> MaintenanceFuture + Send + Sync>;
+
+/// Source of the current time; replaced in tests to control when tasks are due
+pub type Clock = Arc<dyn Fn() -> Instant + Send + Sync>;
+
+/// How often the background loop checks for due tasks
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+struct ScheduledTask {
+    name: String,
+    interval: Duration,
+    next_run: Instant,
+    runs: u64,
+    task: MaintenanceTask,
+}
+
+/// A registered task as shown to the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceTaskStatus {
+    pub name: String,
+    pub interval_seconds: u64,
+    pub runs: u64,
+    pub seconds_until_next_run: u64,
+}
+
+#[derive(Clone)]
+pub struct MaintenanceScheduler {
+    tasks: Arc<Mutex<Vec<ScheduledTask>>>,
+    jitter_fraction: f64,
+    clock: Clock,
+    running: Arc<AtomicBool>,
+    /// Bumped by every start and stop; a loop exits once it no longer matches, so a
+    /// loop stopped and restarted within one tick doesn't keep running alongside the new one
+    generation: Arc<AtomicU64>,
+}
+
+impl MaintenanceScheduler {
+    pub fn new(jitter_fraction: f64) -> Self {
+        Self::with_clock(jitter_fraction, Arc::new(Instant::now))
+    }
+
+    pub fn with_clock(jitter_fraction: f64, clock: Clock) -> Self {
+        Self {
+            tasks: Arc::new(Mutex::new(Vec::new())),
+            jitter_fraction: jitter_fraction.clamp(0.0, 1.0),
+            clock,
+            running: Arc::new(AtomicBool::new(false)),
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Register `task` to run every `interval`, first running one interval from now
+    pub fn register<F, Fut>(&self, name: &str, interval: Duration, task: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let task: MaintenanceTask = Arc::new(move || Box::pin(task()));
+        let next_run = (self.clock)() + interval + self.jitter(interval);
+        self.tasks.lock().unwrap().push(ScheduledTask {
+            name: name.to_string(),
+            interval,
+            next_run,
+            runs: 0,
+            task,
+        });
+    }
+
+    /// Run every task that is due and schedule its next run; returns the names that ran
+    pub async fn run_due(&self) -> Vec<String> {
+        let now = (self.clock)();
+        let due: Vec<(String, MaintenanceTask)> = {
+            let mut tasks = self.tasks.lock().unwrap();
+            tasks
+                .iter_mut()
+                .filter(|scheduled| scheduled.next_run <= now)
+                .map(|scheduled| {
+                    scheduled.next_run = now + scheduled.interval + self.jitter(scheduled.interval);
+                    scheduled.runs += 1;
+                    (scheduled.name.clone(), scheduled.task.clone())
+                })
+                .collect()
+        };
+
+        // Tasks run outside the lock so they can take as long as they need
+        let mut ran = Vec::with_capacity(due.len());
+        for (name, task) in due {
+            task().await;
+            ran.push(name);
+        }
+        ran
+    }
+
+    /// Start the background loop; does nothing if it is already running
+    pub fn start(&self) {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let scheduler = self.clone();
+        tauri::async_runtime::spawn(async move {
+            while scheduler.generation.load(Ordering::SeqCst) == generation {
+                scheduler.run_due().await;
+                tokio::time::sleep(TICK_INTERVAL).await;
+            }
+        });
+    }
+
+    pub fn stop(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.running.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    pub fn status(&self) -> Vec<MaintenanceTaskStatus> {
+        let now = (self.clock)();
+        self.tasks
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|scheduled| MaintenanceTaskStatus {
+                name: scheduled.name.clone(),
+                interval_seconds: scheduled.interval.as_secs(),
+                runs: scheduled.runs,
+                seconds_until_next_run: scheduled.next_run.saturating_duration_since(now).as_secs(),
+            })
+            .collect()
+    }
+
+    fn jitter(&self, interval: Duration) -> Duration {
+        if self.jitter_fraction <= 0.0 {
+            return Duration::ZERO;
+        }
+        interval.mul_f64(rand::random::<f64>() * self.jitter_fraction)
+    }
+}
+
+#[tauri::command]
+pub fn start_maintenance(scheduler: State<'_, MaintenanceScheduler>) -> Result<(), String> {
+    scheduler.start();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_maintenance(scheduler: State<'_, MaintenanceScheduler>) -> Result<(), String> {
+    scheduler.stop();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_maintenance_status(
+    scheduler: State<'_, MaintenanceScheduler>,
+) -> Result<Vec<MaintenanceTaskStatus>, String> {
+    Ok(scheduler.status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Clock that only moves when the test advances it
+    fn manual_clock() -> (Clock, Arc<Mutex<Instant>>) {
+        let now = Arc::new(Mutex::new(Instant::now()));
+        let clock_now = now.clone();
+        (Arc::new(move || *clock_now.lock().unwrap()), now)
+    }
+
+    fn counting_task(scheduler: &MaintenanceScheduler, name: &str, interval_secs: u64) -> Arc<AtomicUsize> {
+        let count = Arc::new(AtomicUsize::new(0));
+        let task_count = count.clone();
+        scheduler.register(name, Duration::from_secs(interval_secs), move || {
+            let count = task_count.clone();
+            async move {
+                count.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        count
+    }
+
+    #[tokio::test]
+    async fn test_tasks_run_at_their_intervals() {
+        let (clock, now) = manual_clock();
+        let scheduler = MaintenanceScheduler::with_clock(0.0, clock);
+        let fast = counting_task(&scheduler, "fast", 10);
+        let slow = counting_task(&scheduler, "slow", 30);
+
+        assert!(scheduler.run_due().await.is_empty());
+
+        for _ in 0..6 {
+            *now.lock().unwrap() += Duration::from_secs(10);
+            scheduler.run_due().await;
+        }
+
+        assert_eq!(fast.load(Ordering::SeqCst), 6);
+        assert_eq!(slow.load(Ordering::SeqCst), 2);
+
+        let status = scheduler.status();
+        assert_eq!(status[0].runs, 6);
+        assert_eq!(status[0].seconds_until_next_run, 10);
+        assert_eq!(status[1].seconds_until_next_run, 30);
+    }
+
+    #[tokio::test]
+    async fn test_jitter_delays_runs_within_bound() {
+        let (clock, now) = manual_clock();
+        let scheduler = MaintenanceScheduler::with_clock(0.5, clock);
+        let count = counting_task(&scheduler, "jittered", 100);
+
+        // Never due before the interval, always due once interval plus maximum jitter has passed
+        *now.lock().unwrap() += Duration::from_secs(99);
+        scheduler.run_due().await;
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+
+        *now.lock().unwrap() += Duration::from_secs(51);
+        scheduler.run_due().await;
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        let next = scheduler.status()[0].seconds_until_next_run;
+        assert!((100..=150).contains(&next), "next run in {}s", next);
+    }
+
+    #[tokio::test]
+    async fn test_restart_within_a_tick_leaves_one_loop() {
+        let scheduler = MaintenanceScheduler::new(0.0);
+        let count = Arc::new(AtomicUsize::new(0));
+        let task_count = count.clone();
+        scheduler.register("every_tick", Duration::ZERO, move || {
+            let count = task_count.clone();
+            async move {
+                count.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        scheduler.start();
+        scheduler.stop();
+        scheduler.start();
+        assert!(scheduler.is_running());
+
+        // One loop runs the task at 0s, 1s and 2s; a second loop would double that
+        tokio::time::sleep(TICK_INTERVAL * 5 / 2).await;
+        scheduler.stop();
+        let runs = count.load(Ordering::SeqCst);
+        assert!((1..=3).contains(&runs), "task ran {} times", runs);
+    }
+}
@@ -0,0 +1,149 @@
+//! Aliases naming models by the role they play
+//!
+//! Features ask for `chat`, `code`, `embed`, `fast` or `reasoning` rather than a
+//! concrete model, and the Ollama client maps the alias to the model the user chose
+//! for that role when the request is sent. Any other name is used as is.
+
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelRole {
+    Chat,
+    Code,
+    Embed,
+    /// Small, quick model for short answers
+    Fast,
+    Reasoning,
+}
+
+impl ModelRole {
+    pub const ALL: [ModelRole; 5] = [
+        ModelRole::Chat,
+        ModelRole::Code,
+        ModelRole::Embed,
+        ModelRole::Fast,
+        ModelRole::Reasoning,
+    ];
+
+    /// The name features pass in place of a model
+    pub const fn alias(self) -> &'static str {
+        match self {
+            ModelRole::Chat => "chat",
+            ModelRole::Code => "code",
+            ModelRole::Embed => "embed",
+            ModelRole::Fast => "fast",
+            ModelRole::Reasoning => "reasoning",
+        }
+    }
+
+    pub fn from_alias(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|role| role.alias() == name)
+    }
+}
+
+/// The model behind each alias
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelAliases {
+    pub chat: String,
+    pub code: String,
+    pub embed: String,
+    pub fast: String,
+    pub reasoning: String,
+}
+
+impl Default for ModelAliases {
+    fn default() -> Self {
+        Self {
+            chat: "llama3:latest".to_string(),
+            code: "llama3:latest".to_string(),
+            embed: "nomic-embed-text".to_string(),
+            fast: "llama3.2".to_string(),
+            reasoning: "llama3:latest".to_string(),
+        }
+    }
+}
+
+impl ModelAliases {
+    pub fn model_for(&self, role: ModelRole) -> &str {
+        match role {
+            ModelRole::Chat => &self.chat,
+            ModelRole::Code => &self.code,
+            ModelRole::Embed => &self.embed,
+            ModelRole::Fast => &self.fast,
+            ModelRole::Reasoning => &self.reasoning,
+        }
+    }
+
+    /// The model `name` stands for, or `name` itself when it isn't an alias
+    pub fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        match ModelRole::from_alias(name) {
+            Some(role) => self.model_for(role),
+            None => name,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for role in ModelRole::ALL {
+            let model = self.model_for(role);
+            if model.trim().is_empty() {
+                return Err(format!("Model alias '{}' must name a model", role.alias()));
+            }
+            if ModelRole::from_alias(model).is_some() {
+                return Err(format!("Model alias '{}' must name a model, not another alias: {}", role.alias(), model));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An alias resolved to a model Ollama doesn't have
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasedModelUnavailable {
+    pub alias: String,
+    pub model: String,
+}
+
+impl std::fmt::Display for AliasedModelUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Model alias '{}' maps to '{}', which was not found; pull it or map the alias to an installed model",
+            self.alias, self.model
+        )
+    }
+}
+
+impl Error for AliasedModelUnavailable {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aliases_resolve_to_configured_models() {
+        let aliases = ModelAliases {
+            code: "qwen2.5-coder:7b".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(aliases.resolve("code"), "qwen2.5-coder:7b");
+        assert_eq!(aliases.resolve("embed"), "nomic-embed-text");
+        // Concrete names pass through, including ones that merely contain an alias
+        assert_eq!(aliases.resolve("codellama:13b"), "codellama:13b");
+        assert_eq!(aliases.resolve("Code"), "Code");
+    }
+
+    #[test]
+    fn test_aliases_must_name_models() {
+        assert!(ModelAliases::default().validate().is_ok());
+
+        let empty = ModelAliases { fast: " ".to_string(), ..Default::default() };
+        assert!(empty.validate().unwrap_err().contains("'fast'"));
+
+        let chained = ModelAliases { reasoning: "chat".to_string(), ..Default::default() };
+        assert!(chained.validate().unwrap_err().contains("another alias"));
+    }
+}
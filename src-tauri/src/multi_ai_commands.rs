@@ -3,6 +3,8 @@ use crate::ollama_provider::OllamaProvider;
 use crate::openai_client::OpenAIClient;
 use crate::anthropic_client::AnthropicClient;
 use crate::ollama_client::OllamaClient;
+use crate::context_manager::{AssembledPrompt, ContextManager, PromptSections};
+use crate::interaction_log::{InteractionLogger, InteractionRecord};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -17,6 +19,16 @@ pub struct MultiAIConfig {
     pub anthropic_api_key: Option<String>,
     pub task_routing: HashMap<String, String>, // capability -> preferred model ID
     pub enabled_providers: Vec<AIProvider>,
+    /// Tokens kept free for the completion when `max_tokens` isn't set on the request
+    #[serde(default = "default_completion_reserve_tokens")]
+    pub completion_reserve_tokens: usize,
+    /// Mask likely secrets in prompts sent to OpenAI and Anthropic
+    #[serde(default)]
+    pub redact_secrets_for_cloud: bool,
+}
+
+fn default_completion_reserve_tokens() -> usize {
+    1024
 }
 
 impl Default for MultiAIConfig {
@@ -27,6 +39,8 @@ impl Default for MultiAIConfig {
             anthropic_api_key: None,
             task_routing: HashMap::new(),
             enabled_providers: vec![AIProvider::Ollama],
+            completion_reserve_tokens: default_completion_reserve_tokens(),
+            redact_secrets_for_cloud: false,
         }
     }
 }
@@ -39,6 +53,14 @@ pub struct AIGenerationRequest {
     pub capability: Option<String>, // For smart routing
     pub options: Option<GenerationOptions>,
     pub stream: Option<bool>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Prior conversation turns, oldest first
+    #[serde(default)]
+    pub history: Vec<String>,
+    /// Retrieved RAG documents, most relevant first
+    #[serde(default)]
+    pub context_documents: Vec<String>,
 }
 
 /// AI generation response
@@ -76,6 +98,7 @@ pub struct ProviderStatus {
 pub struct MultiAIManager {
     client_manager: Arc<Mutex<AIClientManager>>,
     config: Arc<Mutex<MultiAIConfig>>,
+    interaction_logger: Arc<InteractionLogger>,
 }
 
 impl MultiAIManager {
@@ -83,6 +106,7 @@ impl MultiAIManager {
         Self {
             client_manager: Arc::new(Mutex::new(AIClientManager::new())),
             config: Arc::new(Mutex::new(MultiAIConfig::default())),
+            interaction_logger: Arc::new(InteractionLogger::default()),
         }
     }
     
@@ -112,6 +136,16 @@ impl MultiAIManager {
             }
         }
         
+        // Make sure configured keys never reach the interaction log
+        self.interaction_logger.set_known_secrets(
+            config.openai_api_key.iter()
+                .chain(config.anthropic_api_key.iter())
+                .cloned()
+                .collect(),
+        );
+        
+        manager.set_secret_redaction(config.redact_secrets_for_cloud);
+        
         // Set default provider
         manager.set_default_provider(config.default_provider.clone());
         
@@ -160,39 +194,89 @@ pub async fn get_all_ai_models(
     }
 }
 
+/// Trim the request's system/history/RAG/user sections to fit the model's context window
+pub fn assemble_prompt_for_model(
+    model: &AIModel,
+    request: &AIGenerationRequest,
+    default_completion_reserve: usize,
+) -> Result<AssembledPrompt, String> {
+    let completion_reserve = request.options.as_ref()
+        .and_then(|opts| opts.max_tokens)
+        .map(|max_tokens| max_tokens as usize)
+        .unwrap_or(default_completion_reserve);
+    
+    let context_manager = ContextManager::new(model.context_length as usize, completion_reserve);
+    context_manager.fit_prompt(&PromptSections {
+        system: request.system_prompt.clone(),
+        history: request.history.clone(),
+        rag_documents: request.context_documents.clone(),
+        user: request.prompt.clone(),
+    })
+}
+
 /// Generate AI completion with smart model selection
 #[tauri::command]
 pub async fn generate_ai_smart(
     request: AIGenerationRequest,
     state: State<'_, MultiAIManager>,
 ) -> Result<AIGenerationResponse, String> {
+    let completion_reserve = state.config.lock().await.completion_reserve_tokens;
     let manager = state.client_manager.lock().await;
     
-    let result = if let Some(model_id) = request.model_id {
+    let model = if let Some(model_id) = &request.model_id {
         // Use specific model
-        manager.generate_with_model(&model_id, &request.prompt, request.options).await
-    } else if let Some(capability_str) = request.capability {
-        // Use smart routing based on capability
-        if let Ok(capability) = serde_json::from_str::<ModelCapability>(&format!("\"{}\"", capability_str)) {
-            manager.generate_smart(capability, &request.prompt, request.options).await
-        } else {
-            return Err("Invalid capability specified".to_string());
-        }
+        manager.get_model(model_id)
+            .cloned()
+            .ok_or_else(|| format!("Model {} not found", model_id))?
     } else {
-        // Use default model/capability
-        let capability = classify_prompt_capability(&request.prompt);
-        manager.generate_smart(capability, &request.prompt, request.options).await
+        // Use smart routing based on capability, or classify the prompt
+        let capability = match &request.capability {
+            Some(capability_str) => serde_json::from_str::<ModelCapability>(&format!("\"{}\"", capability_str))
+                .map_err(|_| "Invalid capability specified".to_string())?,
+            None => classify_prompt_capability(&request.prompt),
+        };
+        manager.get_best_model_for_task(capability).await
+            .ok_or("No suitable model found for the requested capability")?
     };
     
+    let assembled = assemble_prompt_for_model(&model, &request, completion_reserve)?;
+    let started = std::time::Instant::now();
+    let logged_options = serde_json::to_value(&request.options).unwrap_or(serde_json::Value::Null);
+    let result = manager.generate_with_model(&model.id, &assembled.prompt, request.options).await;
+    
+    if state.interaction_logger.is_enabled() {
+        let (response, error) = match &result {
+            Ok(response) => (Some(response.content.clone()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let _ = state.interaction_logger.log(InteractionRecord {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            provider: format!("{:?}", model.provider),
+            model: model.id.clone(),
+            options: logged_options,
+            prompt: assembled.prompt.clone(),
+            response,
+            error,
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+    }
+    
     match result {
-        Ok(response) => Ok(AIGenerationResponse {
-            content: response.content,
-            model: response.model,
-            provider: format!("{:?}", response.provider),
-            usage: response.usage,
-            finish_reason: response.finish_reason,
-            metadata: response.metadata,
-        }),
+        Ok(response) => {
+            let mut metadata = response.metadata;
+            metadata.insert("prompt_tokens_estimate".to_string(), serde_json::json!(assembled.total_tokens));
+            metadata.insert("dropped_history".to_string(), serde_json::json!(assembled.dropped_history));
+            metadata.insert("dropped_documents".to_string(), serde_json::json!(assembled.dropped_documents));
+            
+            Ok(AIGenerationResponse {
+                content: response.content,
+                model: response.model,
+                provider: format!("{:?}", response.provider),
+                usage: response.usage,
+                finish_reason: response.finish_reason,
+                metadata,
+            })
+        }
         Err(e) => Err(e.to_string()),
     }
 }
@@ -288,6 +372,25 @@ pub async fn get_multi_ai_config(
     Ok(config.clone())
 }
 
+/// Enable or disable the request/response interaction log
+#[tauri::command]
+pub async fn set_interaction_logging(
+    enabled: bool,
+    state: State<'_, MultiAIManager>,
+) -> Result<(), String> {
+    state.interaction_logger.set_enabled(enabled);
+    Ok(())
+}
+
+/// Get the last N logged interactions (already redacted)
+#[tauri::command]
+pub async fn get_recent_interactions(
+    limit: Option<usize>,
+    state: State<'_, MultiAIManager>,
+) -> Result<Vec<InteractionRecord>, String> {
+    Ok(state.interaction_logger.recent(limit.unwrap_or(20)))
+}
+
 /// Classify prompt to determine best capability
 #[tauri::command]
 pub fn classify_prompt(prompt: String) -> Result<String, String> {
@@ -319,4 +422,78 @@ pub fn get_model_capabilities() -> Result<Vec<String>, String> {
 pub fn get_supported_providers() -> Result<Vec<String>, String> {
     let providers = vec!["Ollama", "OpenAI", "Anthropic"];
     Ok(providers.into_iter().map(String::from).collect())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_window_model(context_length: u32) -> AIModel {
+        AIModel {
+            id: "tiny".to_string(),
+            name: "tiny".to_string(),
+            provider: AIProvider::Ollama,
+            capabilities: vec![ModelCapability::GeneralChat],
+            context_length,
+            cost_per_token: None,
+            speed_tokens_per_second: None,
+            is_available: true,
+            description: "Small window test model".to_string(),
+        }
+    }
+
+    fn request_with(prompt: &str, documents: Vec<String>, history: Vec<String>) -> AIGenerationRequest {
+        AIGenerationRequest {
+            prompt: prompt.to_string(),
+            model_id: Some("tiny".to_string()),
+            capability: None,
+            options: None,
+            stream: None,
+            system_prompt: Some("You are a helpful assistant.".to_string()),
+            history,
+            context_documents: documents,
+        }
+    }
+
+    #[test]
+    fn test_over_long_prompt_is_trimmed_to_window() {
+        let model = small_window_model(200);
+        let long_doc = "lorem ipsum ".repeat(40); // ~100 tokens each
+        let request = request_with(
+            "What does the code do?",
+            vec![long_doc.clone(), long_doc.clone(), long_doc],
+            vec!["user: hi".to_string(), "assistant: hello".to_string()],
+        );
+
+        let assembled = assemble_prompt_for_model(&model, &request, 50).unwrap();
+
+        assert!(assembled.total_tokens <= 150);
+        assert!(assembled.dropped_documents >= 2);
+        assert!(assembled.prompt.ends_with("What does the code do?"));
+        assert!(assembled.prompt.starts_with("You are a helpful assistant."));
+    }
+
+    #[test]
+    fn test_request_max_tokens_overrides_reserve() {
+        let model = small_window_model(200);
+        let mut request = request_with("Hi", vec!["word ".repeat(60)], vec![]);
+        request.options = Some(GenerationOptions { max_tokens: Some(190), ..Default::default() });
+
+        let assembled = assemble_prompt_for_model(&model, &request, 10).unwrap();
+
+        assert_eq!(assembled.dropped_documents, 1);
+        assert!(assembled.total_tokens <= 10);
+    }
+
+    #[test]
+    fn test_error_only_when_user_message_does_not_fit() {
+        let model = small_window_model(100);
+
+        let fits = request_with(&"word ".repeat(30), vec!["doc ".repeat(200)], vec![]);
+        assert!(assemble_prompt_for_model(&model, &fits, 50).is_ok());
+
+        let too_long = request_with(&"word ".repeat(60), vec![], vec![]);
+        let err = assemble_prompt_for_model(&model, &too_long, 50).unwrap_err();
+        assert!(err.contains("User message"));
+    }
+}
@@ -2,11 +2,17 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
 use std::error::Error;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 use futures_util::StreamExt;
+use crate::health_history::{history_cutoff, HealthHistory, HealthHistoryReport};
+use crate::embedding_coalescer::EmbeddingCoalescer;
+use crate::model_aliases::{AliasedModelUnavailable, ModelAliases, ModelRole};
+use crate::prompt_guard::{self, PromptLimitConfig, PromptTooLong};
+use crate::ollama_rate_limit::{OllamaRateLimiter, RateLimitConfig, RateLimitStats, RequestPermit};
+use crate::thread_pool_manager::TaskPriority;
 use bytes::Bytes;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -39,9 +45,15 @@ pub struct GenerateRequest {
     pub stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub options: Option<GenerateOptions>,
+    /// "json" or a JSON schema constraining the response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<serde_json::Value>,
+    /// Context returned by a previous generation, continuing it without resending the history
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<Vec<i64>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenerateOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
@@ -49,7 +61,8 @@ pub struct GenerateOptions {
     pub top_p: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_k: Option<i32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Sent as Ollama's `num_predict`; `max_tokens` is still accepted when reading
+    #[serde(rename = "num_predict", alias = "max_tokens", skip_serializing_if = "Option::is_none")]
     pub max_tokens: Option<i32>,
 }
 
@@ -61,6 +74,25 @@ pub struct GenerateResponse {
     pub done: Option<bool>,
 }
 
+/// Final generate response including Ollama's timing and token metadata.
+/// Durations are in nanoseconds; `context` encodes the conversation so far.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GenerateDetails {
+    pub response: String,
+    #[serde(default)]
+    pub total_duration: Option<u64>,
+    #[serde(default)]
+    pub load_duration: Option<u64>,
+    #[serde(default)]
+    pub eval_count: Option<u64>,
+    #[serde(default)]
+    pub eval_duration: Option<u64>,
+    #[serde(default)]
+    pub prompt_eval_count: Option<u64>,
+    #[serde(default)]
+    pub context: Option<Vec<i64>>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatMessage {
     pub role: String,
@@ -74,6 +106,9 @@ pub struct ChatRequest {
     pub stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub options: Option<GenerateOptions>,
+    /// "json" constrains the reply to a JSON object
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -99,13 +134,27 @@ pub struct EmbeddingResponse {
 pub type SharedOllamaClient = Arc<Mutex<OllamaClient>>;
 
 /// Health monitoring configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct HealthConfig {
     pub check_interval_seconds: u64,
     pub timeout_seconds: u64,
     pub max_retry_attempts: u32,
     pub retry_backoff_seconds: u64,
     pub auto_reconnect: bool,
+    /// Consecutive failed checks before a healthy service is marked unhealthy
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// Consecutive successful checks before an unhealthy service is marked healthy again
+    #[serde(default = "default_recovery_threshold")]
+    pub recovery_threshold: u32,
+}
+
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+fn default_recovery_threshold() -> u32 {
+    1
 }
 
 impl Default for HealthConfig {
@@ -116,6 +165,8 @@ impl Default for HealthConfig {
             max_retry_attempts: 3,      // Try 3 times
             retry_backoff_seconds: 2,   // 2 second backoff
             auto_reconnect: true,       // Enable auto-reconnect
+            failure_threshold: default_failure_threshold(),
+            recovery_threshold: default_recovery_threshold(),
         }
     }
 }
@@ -127,6 +178,8 @@ pub struct HealthStats {
     pub last_check_time: Option<u64>, // Unix timestamp
     pub last_successful_check: Option<u64>,
     pub consecutive_failures: u32,
+    #[serde(default)]
+    pub consecutive_successes: u32,
     pub total_checks: u64,
     pub total_failures: u64,
     pub average_response_time_ms: f64,
@@ -139,6 +192,7 @@ impl Default for HealthStats {
             last_check_time: None,
             last_successful_check: None,
             consecutive_failures: 0,
+            consecutive_successes: 0,
             total_checks: 0,
             total_failures: 0,
             average_response_time_ms: 0.0,
@@ -146,10 +200,33 @@ impl Default for HealthStats {
     }
 }
 
+/// Result of the embeddings health probe, tracked apart from chat health
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmbeddingHealthStats {
+    pub is_healthy: bool,
+    pub model: Option<String>,
+    /// Vector length returned by the last successful probe
+    pub dimension: Option<usize>,
+    pub last_check_time: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+/// Chat and embeddings health side by side
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitHealthStats {
+    pub chat: HealthStats,
+    pub embeddings: EmbeddingHealthStats,
+}
+
+/// Text embedded by the embeddings health probe
+const EMBEDDING_PROBE_TEXT: &str = "health check";
+
 /// Health monitoring state
 pub struct HealthMonitor {
     config: HealthConfig,
     stats: Arc<Mutex<HealthStats>>,
+    embedding_stats: Arc<Mutex<EmbeddingHealthStats>>,
+    history: Arc<Mutex<HealthHistory>>,
     is_monitoring: Arc<AtomicBool>,
     check_count: Arc<AtomicU64>,
     failure_count: Arc<AtomicU64>,
@@ -160,6 +237,8 @@ impl HealthMonitor {
         Self {
             config,
             stats: Arc::new(Mutex::new(HealthStats::default())),
+            embedding_stats: Arc::new(Mutex::new(EmbeddingHealthStats::default())),
+            history: Arc::new(Mutex::new(HealthHistory::default())),
             is_monitoring: Arc::new(AtomicBool::new(false)),
             check_count: Arc::new(AtomicU64::new(0)),
             failure_count: Arc::new(AtomicU64::new(0)),
@@ -182,14 +261,21 @@ impl HealthMonitor {
         stats.last_check_time = Some(now);
         stats.total_checks += 1;
 
+        // Only flip state after enough consecutive results to avoid flapping
         if success {
-            stats.is_healthy = true;
             stats.last_successful_check = Some(now);
             stats.consecutive_failures = 0;
+            stats.consecutive_successes += 1;
+            if stats.consecutive_successes >= self.config.recovery_threshold {
+                stats.is_healthy = true;
+            }
         } else {
-            stats.is_healthy = false;
+            stats.consecutive_successes = 0;
             stats.consecutive_failures += 1;
             stats.total_failures += 1;
+            if stats.consecutive_failures >= self.config.failure_threshold {
+                stats.is_healthy = false;
+            }
         }
 
         // Update running average response time
@@ -197,12 +283,23 @@ impl HealthMonitor {
         let new_time = response_time.as_millis() as f64;
         stats.average_response_time_ms = 
             ((stats.average_response_time_ms * (total_checks - 1.0)) + new_time) / total_checks;
+
+        self.history.lock().await.record(now, success, response_time);
+    }
+
+    /// Recent check results with uptime over that window
+    pub async fn get_history(&self) -> HealthHistoryReport {
+        self.history.lock().await.report("ollama")
+    }
+
+    /// Drop history older than `max_age`
+    pub async fn trim_history(&self, max_age: Duration) -> usize {
+        self.history.lock().await.trim_before(history_cutoff(max_age))
     }
 
     /// Check if service should be considered healthy
     pub async fn is_healthy(&self) -> bool {
-        let stats = self.stats.lock().await;
-        stats.is_healthy && stats.consecutive_failures < self.config.max_retry_attempts
+        self.stats.lock().await.is_healthy
     }
 }
 
@@ -303,6 +400,7 @@ impl StreamingBuffer {
             max_queue_size: self.max_queue_size,
             buffer_utilization: (self.buffer.len() as f32 / self.max_buffer_size as f32) * 100.0,
             queue_utilization: (self.total_queued_bytes as f32 / self.max_queue_size as f32) * 100.0,
+            metrics: None,
         }
     }
 
@@ -314,7 +412,7 @@ impl StreamingBuffer {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BufferStats {
     pub buffer_size: usize,
     pub max_buffer_size: usize,
@@ -323,14 +421,215 @@ pub struct BufferStats {
     pub max_queue_size: usize,
     pub buffer_utilization: f32,
     pub queue_utilization: f32,
+    /// Generation speed, filled in while streaming
+    pub metrics: Option<StreamingMetricsSnapshot>,
+}
+
+/// Latest buffer stats of each streaming generation, by request id, so a stall from a
+/// slow consumer can be diagnosed while the generation is still running
+#[derive(Debug, Clone, Default)]
+pub struct ActiveGenerations {
+    stats: Arc<std::sync::Mutex<HashMap<String, BufferStats>>>,
+}
+
+impl ActiveGenerations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start tracking `request_id`. It stays listed until the returned handle is dropped.
+    pub fn register(&self, request_id: &str) -> ActiveGeneration {
+        if let Ok(mut stats) = self.stats.lock() {
+            stats.insert(request_id.to_string(), StreamingBuffer::new().get_stats());
+        }
+        ActiveGeneration {
+            request_id: request_id.to_string(),
+            stats: self.stats.clone(),
+        }
+    }
+
+    pub fn get(&self, request_id: &str) -> Option<BufferStats> {
+        self.stats.lock().ok().and_then(|stats| stats.get(request_id).cloned())
+    }
+
+    pub fn len(&self) -> usize {
+        self.stats.lock().map(|stats| stats.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A generation listed in `ActiveGenerations`; dropping it removes the listing
+#[derive(Debug)]
+pub struct ActiveGeneration {
+    request_id: String,
+    stats: Arc<std::sync::Mutex<HashMap<String, BufferStats>>>,
+}
+
+impl ActiveGeneration {
+    pub fn record(&self, latest: BufferStats) {
+        if let Ok(mut stats) = self.stats.lock() {
+            stats.insert(self.request_id.clone(), latest);
+        }
+    }
+}
+
+impl Drop for ActiveGeneration {
+    fn drop(&mut self) {
+        if let Ok(mut stats) = self.stats.lock() {
+            stats.remove(&self.request_id);
+        }
+    }
+}
+
+/// Tracks generation speed (tokens/sec and time-to-first-token) during streaming.
+/// Each non-empty streamed response fragment counts as one token.
+#[derive(Debug, Clone)]
+pub struct StreamingMetrics {
+    started_at: Instant,
+    first_token_at: Option<Instant>,
+    last_token_at: Option<Instant>,
+    token_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StreamingMetricsSnapshot {
+    pub total_tokens: usize,
+    pub elapsed_ms: u64,
+    pub time_to_first_token_ms: Option<u64>,
+    /// Tokens per second measured from the first token onwards
+    pub tokens_per_second: f64,
+}
+
+impl StreamingMetrics {
+    pub fn new() -> Self {
+        Self::started_at(Instant::now())
+    }
+
+    pub fn started_at(started_at: Instant) -> Self {
+        Self {
+            started_at,
+            first_token_at: None,
+            last_token_at: None,
+            token_count: 0,
+        }
+    }
+
+    pub fn record_token(&mut self) {
+        self.record_token_at(Instant::now());
+    }
+
+    pub fn record_token_at(&mut self, at: Instant) {
+        if self.first_token_at.is_none() {
+            self.first_token_at = Some(at);
+        }
+        self.last_token_at = Some(at);
+        self.token_count += 1;
+    }
+
+    pub fn snapshot(&self) -> StreamingMetricsSnapshot {
+        self.snapshot_at(Instant::now())
+    }
+
+    pub fn snapshot_at(&self, now: Instant) -> StreamingMetricsSnapshot {
+        let time_to_first_token_ms = self.first_token_at
+            .map(|first| first.saturating_duration_since(self.started_at).as_millis() as u64);
+
+        // Rate over the decode window; the first token only marks its start
+        let tokens_per_second = match self.first_token_at {
+            Some(first) if self.token_count > 1 => {
+                let window = now.saturating_duration_since(first).as_secs_f64();
+                if window > 0.0 { (self.token_count - 1) as f64 / window } else { 0.0 }
+            }
+            _ => 0.0,
+        };
+
+        StreamingMetricsSnapshot {
+            total_tokens: self.token_count,
+            elapsed_ms: now.saturating_duration_since(self.started_at).as_millis() as u64,
+            time_to_first_token_ms,
+            tokens_per_second,
+        }
+    }
+
+    /// Final summary, measured up to the last received token
+    pub fn summary(&self) -> StreamingMetricsSnapshot {
+        self.snapshot_at(self.last_token_at.unwrap_or_else(Instant::now))
+    }
+}
+
+impl Default for StreamingMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How long a fetched model list is trusted before `get_model` refreshes it
+const DEFAULT_MODELS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Models from the last `list_models` call and when they were fetched
+#[derive(Default)]
+struct ModelsCache {
+    models: HashMap<String, ModelInfo>,
+    refreshed_at: Option<Instant>,
+}
+
+impl ModelsCache {
+    fn is_fresh(&self, ttl: Duration) -> bool {
+        self.refreshed_at.is_some_and(|refreshed_at| refreshed_at.elapsed() < ttl)
+    }
+
+    fn clear(&mut self) {
+        self.models.clear();
+        self.refreshed_at = None;
+    }
+}
+
+/// Model to offer pulling when none are installed
+pub const SUGGESTED_MODEL: &str = "llama3.2";
+
+/// Returned by model requests while Ollama is running without any models installed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoModelsInstalled;
+
+impl std::fmt::Display for NoModelsInstalled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No Ollama models are installed; pull a model such as {} to use AI features", SUGGESTED_MODEL)
+    }
+}
+
+impl Error for NoModelsInstalled {}
+
+/// Whether AI features can run, as last seen by `list_models`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelAvailability {
+    /// No models are installed, so only non-AI features work
+    pub degraded: bool,
+    pub installed_models: usize,
+    pub suggested_model: String,
 }
 
 #[derive(Clone)]
 pub struct OllamaClient {
-    base_url: String,
+    /// Shared by clones so every handle follows a base URL change
+    base_url: Arc<RwLock<String>>,
     client: Client,
-    models_cache: Arc<Mutex<HashMap<String, ModelInfo>>>,
+    models_cache: Arc<Mutex<ModelsCache>>,
+    models_cache_ttl: Duration,
     health_monitor: Arc<HealthMonitor>,
+    /// Shared by clones so every request counts against the same limits
+    limiter: Arc<OllamaRateLimiter>,
+    priority: TaskPriority,
+    /// Shared by clones so identical embedding requests from anywhere share one call
+    embeddings: Arc<EmbeddingCoalescer>,
+    /// Set when the last model listing was empty; shared by clones
+    no_models: Arc<AtomicBool>,
+    /// Shared by clones so every handle follows alias changes
+    aliases: Arc<RwLock<ModelAliases>>,
+    /// Shared by clones so every request is held to the same prompt limit
+    prompt_limit: Arc<RwLock<PromptLimitConfig>>,
 }
 
 impl OllamaClient {
@@ -343,23 +642,128 @@ impl OllamaClient {
         let health_monitor = Arc::new(HealthMonitor::new(health_config));
         
         Self {
-            base_url,
+            base_url: Arc::new(RwLock::new(base_url)),
             client: Client::new(),
-            models_cache: Arc::new(Mutex::new(HashMap::new())),
+            models_cache: Arc::new(Mutex::new(ModelsCache::default())),
+            models_cache_ttl: DEFAULT_MODELS_CACHE_TTL,
             health_monitor,
+            limiter: Arc::new(OllamaRateLimiter::default()),
+            priority: TaskPriority::Normal,
+            embeddings: Arc::new(EmbeddingCoalescer::default()),
+            no_models: Arc::new(AtomicBool::new(false)),
+            aliases: Arc::new(RwLock::new(ModelAliases::default())),
+            prompt_limit: Arc::new(RwLock::new(PromptLimitConfig::default())),
+        }
+    }
+
+    /// Limit requests with `config`; clones made before this keep the old limits
+    pub fn with_rate_limit(mut self, config: &RateLimitConfig) -> Self {
+        self.limiter = Arc::new(OllamaRateLimiter::new(config));
+        self
+    }
+
+    /// A handle for requests a user is waiting on, which go ahead of queued background requests
+    pub fn interactive(&self) -> Self {
+        Self {
+            priority: TaskPriority::High,
+            ..self.clone()
         }
     }
 
-    pub fn set_base_url(&mut self, base_url: String) {
-        self.base_url = base_url;
+    pub fn rate_limit_stats(&self) -> RateLimitStats {
+        self.limiter.stats()
     }
 
-    pub fn get_base_url(&self) -> &str {
-        &self.base_url
+    /// Send a request once the rate limiter allows it. How quickly the response starts
+    /// tunes adaptive concurrency; the permit should be held until the body is read.
+    async fn send_limited(&self, request: reqwest::RequestBuilder) -> Result<(reqwest::Response, RequestPermit), reqwest::Error> {
+        let permit = self.limiter.acquire(self.priority.clone()).await;
+        let result = request.send().await;
+        permit.record(result.as_ref().is_ok_and(|response| !response.status().is_server_error()));
+        result.map(|response| (response, permit))
+    }
+
+    /// Fail fast while no models are installed rather than with Ollama's 404 for the model
+    fn ensure_models_installed(&self) -> Result<(), NoModelsInstalled> {
+        if self.no_models.load(Ordering::SeqCst) {
+            return Err(NoModelsInstalled);
+        }
+        Ok(())
+    }
+
+    /// List the installed models, putting the client in degraded mode when there are none
+    pub async fn check_model_availability(&self) -> Result<ModelAvailability, Box<dyn Error>> {
+        let installed_models = self.list_models().await?.len();
+        Ok(ModelAvailability {
+            degraded: installed_models == 0,
+            installed_models,
+            suggested_model: SUGGESTED_MODEL.to_string(),
+        })
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.no_models.load(Ordering::SeqCst)
+    }
+
+    pub fn set_model_aliases(&self, aliases: ModelAliases) {
+        *self.aliases.write().unwrap() = aliases;
+    }
+
+    pub fn model_aliases(&self) -> ModelAliases {
+        self.aliases.read().unwrap().clone()
+    }
+
+    /// The model a request for `model` is sent to, resolving aliases such as "code"
+    pub fn resolve_model(&self, model: &str) -> String {
+        self.aliases.read().unwrap().resolve(model).to_string()
+    }
+
+    pub fn set_prompt_limit(&self, config: PromptLimitConfig) {
+        *self.prompt_limit.write().unwrap() = config;
+    }
+
+    pub fn prompt_limit(&self) -> PromptLimitConfig {
+        self.prompt_limit.read().unwrap().clone()
+    }
+
+    /// `prompt` within the limit for `resolved_model`, trimmed or rejected per the policy
+    fn fit_prompt(&self, resolved_model: &str, prompt: &str) -> Result<String, PromptTooLong> {
+        prompt_guard::fit_prompt(&self.prompt_limit.read().unwrap(), resolved_model, prompt)
+    }
+
+    fn fit_messages(&self, resolved_model: &str, messages: Vec<ChatMessage>) -> Result<Vec<ChatMessage>, PromptTooLong> {
+        prompt_guard::fit_messages(&self.prompt_limit.read().unwrap(), resolved_model, messages)
+    }
+
+    /// Name the alias behind a missing model, since the user chose the model by alias
+    fn ensure_aliased_model_found(&self, model: &str, status: reqwest::StatusCode) -> Result<(), AliasedModelUnavailable> {
+        if status == reqwest::StatusCode::NOT_FOUND && ModelRole::from_alias(model).is_some() {
+            return Err(AliasedModelUnavailable {
+                alias: model.to_string(),
+                model: self.resolve_model(model),
+            });
+        }
+        Ok(())
+    }
+
+    /// Point the client at a new endpoint; cached models belong to the old one and are dropped
+    pub async fn set_base_url(&self, base_url: String) {
+        *self.base_url.write().unwrap() = base_url;
+        self.models_cache.lock().await.clear();
+        self.no_models.store(false, Ordering::SeqCst);
+    }
+
+    pub fn get_base_url(&self) -> String {
+        self.base_url.read().unwrap().clone()
+    }
+
+    /// Set how long `get_model` trusts the cached model list before re-querying Ollama
+    pub fn set_models_cache_ttl(&mut self, ttl: Duration) {
+        self.models_cache_ttl = ttl;
     }
 
     pub async fn list_models(&self) -> Result<Vec<ModelInfo>, Box<dyn Error>> {
-        let url = format!("{}/api/tags", self.base_url);
+        let url = format!("{}/api/tags", self.get_base_url());
         let response = self.client.get(&url).send().await?;
         
         if !response.status().is_success() {
@@ -368,21 +772,27 @@ impl OllamaClient {
         
         let model_response: ModelResponse = response.json().await?;
         
-        // Update cache
+        // Replace the cache so models removed from Ollama drop out too
         let mut cache = self.models_cache.lock().await;
-        for model in &model_response.models {
-            cache.insert(model.name.clone(), model.clone());
-        }
+        cache.models = model_response.models.iter()
+            .map(|model| (model.name.clone(), model.clone()))
+            .collect();
+        cache.refreshed_at = Some(Instant::now());
+        self.no_models.store(model_response.models.is_empty(), Ordering::SeqCst);
         
         Ok(model_response.models)
     }
 
     pub async fn get_model(&self, name: &str) -> Result<Option<ModelInfo>, Box<dyn Error>> {
-        // Check cache first
+        let name = self.resolve_model(name);
+        
+        // Check cache first, unless it has gone stale
         {
             let cache = self.models_cache.lock().await;
-            if let Some(model) = cache.get(name) {
-                return Ok(Some(model.clone()));
+            if cache.is_fresh(self.models_cache_ttl) {
+                if let Some(model) = cache.models.get(&name) {
+                    return Ok(Some(model.clone()));
+                }
             }
         }
         
@@ -405,26 +815,74 @@ impl OllamaClient {
         prompt: &str,
         options: Option<GenerateOptions>,
     ) -> Result<String, Box<dyn Error>> {
-        let url = format!("{}/api/generate", self.base_url);
+        self.generate_completion_with_format(model, prompt, options, None).await
+    }
+
+    /// Generate a completion constrained to `format` ("json" or a JSON schema)
+    pub async fn generate_structured(
+        &self,
+        model: &str,
+        prompt: &str,
+        options: Option<GenerateOptions>,
+        format: serde_json::Value,
+    ) -> Result<String, Box<dyn Error>> {
+        self.generate_completion_with_format(model, prompt, options, Some(format)).await
+    }
+
+    async fn generate_completion_with_format(
+        &self,
+        model: &str,
+        prompt: &str,
+        options: Option<GenerateOptions>,
+        format: Option<serde_json::Value>,
+    ) -> Result<String, Box<dyn Error>> {
+        let resolved_model = self.resolve_model(model);
+        let request = GenerateRequest {
+            prompt: self.fit_prompt(&resolved_model, prompt)?,
+            model: resolved_model,
+            stream: false,
+            options,
+            format,
+            context: None,
+        };
         
+        Ok(self.send_generate(model, &request).await?.response)
+    }
+
+    /// Generate a completion, keeping the timing, token counts and context from the final response.
+    /// Pass the `context` from a previous call to continue that conversation.
+    pub async fn generate_detailed(
+        &self,
+        model: &str,
+        prompt: &str,
+        options: Option<GenerateOptions>,
+        context: Option<Vec<i64>>,
+    ) -> Result<GenerateDetails, Box<dyn Error>> {
+        let resolved_model = self.resolve_model(model);
         let request = GenerateRequest {
-            model: model.to_string(),
-            prompt: prompt.to_string(),
+            prompt: self.fit_prompt(&resolved_model, prompt)?,
+            model: resolved_model,
             stream: false,
             options,
+            format: None,
+            context,
         };
         
-        let response = self.client.post(&url)
-            .json(&request)
-            .send()
-            .await?;
+        self.send_generate(model, &request).await
+    }
+
+    async fn send_generate(&self, model: &str, request: &GenerateRequest) -> Result<GenerateDetails, Box<dyn Error>> {
+        let url = format!("{}/api/generate", self.get_base_url());
+        
+        self.ensure_models_installed()?;
+        let (response, _permit) = self.send_limited(self.client.post(&url).json(request)).await?;
+        self.ensure_aliased_model_found(model, response.status())?;
             
         if !response.status().is_success() {
             return Err(format!("Failed to generate completion: {}", response.status()).into());
         }
         
-        let generate_response: GenerateResponse = response.json().await?;
-        Ok(generate_response.response)
+        Ok(response.json().await?)
     }
 
     pub async fn generate_stream<F>(
@@ -437,19 +895,21 @@ impl OllamaClient {
     where
         F: FnMut(&str) + Send + 'static,
     {
-        let url = format!("{}/api/generate", self.base_url);
+        let url = format!("{}/api/generate", self.get_base_url());
         
+        let resolved_model = self.resolve_model(model);
         let request = GenerateRequest {
-            model: model.to_string(),
-            prompt: prompt.to_string(),
+            prompt: self.fit_prompt(&resolved_model, prompt)?,
+            model: resolved_model,
             stream: true,
             options,
+            format: None,
+            context: None,
         };
         
-        let response = self.client.post(&url)
-            .json(&request)
-            .send()
-            .await?;
+        self.ensure_models_installed()?;
+        let (response, _permit) = self.send_limited(self.client.post(&url).json(&request)).await?;
+        self.ensure_aliased_model_found(model, response.status())?;
             
         if !response.status().is_success() {
             return Err(format!("Failed to generate stream: {}", response.status()).into());
@@ -484,7 +944,10 @@ impl OllamaClient {
         Ok(())
     }
 
-    /// Generate stream with buffer statistics callback for monitoring
+    /// Generate stream with buffer statistics callback for monitoring.
+    /// Buffer stats carry live tokens/sec and time-to-first-token; the final
+    /// metrics summary is returned once the stream completes. A `generation` gets the
+    /// stats after every chunk and is unlisted when the stream ends or fails.
     pub async fn generate_stream_with_stats<F, S>(
         &self,
         model: &str,
@@ -492,24 +955,28 @@ impl OllamaClient {
         options: Option<GenerateOptions>,
         mut callback: F,
         mut stats_callback: Option<S>,
-    ) -> Result<(), Box<dyn Error>>
+        generation: Option<ActiveGeneration>,
+    ) -> Result<StreamingMetricsSnapshot, Box<dyn Error>>
     where
         F: FnMut(&str) + Send + 'static,
         S: FnMut(BufferStats) + Send + 'static,
     {
-        let url = format!("{}/api/generate", self.base_url);
+        let url = format!("{}/api/generate", self.get_base_url());
         
+        let resolved_model = self.resolve_model(model);
         let request = GenerateRequest {
-            model: model.to_string(),
-            prompt: prompt.to_string(),
+            prompt: self.fit_prompt(&resolved_model, prompt)?,
+            model: resolved_model,
             stream: true,
             options,
+            format: None,
+            context: None,
         };
         
-        let response = self.client.post(&url)
-            .json(&request)
-            .send()
-            .await?;
+        self.ensure_models_installed()?;
+        let mut metrics = StreamingMetrics::new();
+        let (response, _permit) = self.send_limited(self.client.post(&url).json(&request)).await?;
+        self.ensure_aliased_model_found(model, response.status())?;
             
         if !response.status().is_success() {
             return Err(format!("Failed to generate stream: {}", response.status()).into());
@@ -529,13 +996,18 @@ impl OllamaClient {
             // Report buffer stats every 10 chunks
             if let Some(ref mut stats_cb) = stats_callback {
                 if chunk_count % 10 == 0 {
-                    stats_cb(streaming_buffer.get_stats());
+                    let mut stats = streaming_buffer.get_stats();
+                    stats.metrics = Some(metrics.snapshot());
+                    stats_cb(stats);
                 }
             }
             
             // Process all available complete JSON lines
             let should_continue = streaming_buffer.process_chunks(|line| {
                 if let Ok(response) = serde_json::from_str::<GenerateResponse>(line) {
+                    if !response.response.is_empty() {
+                        metrics.record_token();
+                    }
                     callback(&response.response);
                     
                     if response.done.unwrap_or(false) {
@@ -544,18 +1016,28 @@ impl OllamaClient {
                 }
                 Ok(true) // Continue processing
             })?;
+
+            if let Some(ref generation) = generation {
+                let mut stats = streaming_buffer.get_stats();
+                stats.metrics = Some(metrics.snapshot());
+                generation.record(stats);
+            }
             
             if !should_continue {
                 break;
             }
         }
         
+        let summary = metrics.summary();
+        
         // Final stats report
         if let Some(ref mut stats_cb) = stats_callback {
-            stats_cb(streaming_buffer.get_stats());
+            let mut stats = streaming_buffer.get_stats();
+            stats.metrics = Some(summary.clone());
+            stats_cb(stats);
         }
         
-        Ok(())
+        Ok(summary)
     }
 
     pub async fn chat<F>(
@@ -568,21 +1050,22 @@ impl OllamaClient {
     where
         F: FnMut(&str) + Send + 'static,
     {
-        let url = format!("{}/api/chat", self.base_url);
+        let url = format!("{}/api/chat", self.get_base_url());
         
         let stream = callback.is_some();
         
+        let resolved_model = self.resolve_model(model);
         let request = ChatRequest {
-            model: model.to_string(),
-            messages,
+            messages: self.fit_messages(&resolved_model, messages)?,
+            model: resolved_model,
             stream,
             options,
+            format: None,
         };
         
-        let response = self.client.post(&url)
-            .json(&request)
-            .send()
-            .await?;
+        self.ensure_models_installed()?;
+        let (response, _permit) = self.send_limited(self.client.post(&url).json(&request)).await?;
+        self.ensure_aliased_model_found(model, response.status())?;
             
         if !response.status().is_success() {
             return Err(format!("Failed to chat: {}", response.status()).into());
@@ -631,22 +1114,68 @@ impl OllamaClient {
         })
     }
 
+    /// Non-streaming chat whose reply is constrained to a JSON object
+    pub async fn chat_json(
+        &self,
+        model: &str,
+        messages: Vec<ChatMessage>,
+    ) -> Result<ChatMessage, Box<dyn Error>> {
+        self.chat_json_with_options(model, messages, None).await
+    }
+
+    /// `chat_json` with generation options, such as a larger `max_tokens` for long replies
+    pub async fn chat_json_with_options(
+        &self,
+        model: &str,
+        messages: Vec<ChatMessage>,
+        options: Option<GenerateOptions>,
+    ) -> Result<ChatMessage, Box<dyn Error>> {
+        let url = format!("{}/api/chat", self.get_base_url());
+        let resolved_model = self.resolve_model(model);
+        let request = ChatRequest {
+            messages: self.fit_messages(&resolved_model, messages)?,
+            model: resolved_model,
+            stream: false,
+            options,
+            format: Some("json".to_string()),
+        };
+        
+        self.ensure_models_installed()?;
+        let (response, _permit) = self.send_limited(self.client.post(&url).json(&request)).await?;
+        self.ensure_aliased_model_found(model, response.status())?;
+            
+        if !response.status().is_success() {
+            return Err(format!("Failed to chat: {}", response.status()).into());
+        }
+        
+        let chat_response: ChatResponse = response.json().await?;
+        Ok(chat_response.message)
+    }
+
+    /// Embed `text`, sharing the result with identical requests in flight or recently completed
     pub async fn create_embedding(
         &self,
         model: &str,
         text: &str,
     ) -> Result<Vec<f32>, Box<dyn Error>> {
-        let url = format!("{}/api/embeddings", self.base_url);
+        self.embeddings.embed(&self.resolve_model(model), text, || self.request_embedding(model, text)).await
+    }
+
+    async fn request_embedding(
+        &self,
+        model: &str,
+        text: &str,
+    ) -> Result<Vec<f32>, Box<dyn Error>> {
+        let url = format!("{}/api/embeddings", self.get_base_url());
         
         let request = EmbeddingRequest {
-            model: model.to_string(),
+            model: self.resolve_model(model),
             prompt: text.to_string(),
         };
         
-        let response = self.client.post(&url)
-            .json(&request)
-            .send()
-            .await?;
+        self.ensure_models_installed()?;
+        let (response, _permit) = self.send_limited(self.client.post(&url).json(&request)).await?;
+        self.ensure_aliased_model_found(model, response.status())?;
             
         if !response.status().is_success() {
             return Err(format!("Failed to create embedding: {}", response.status()).into());
@@ -656,6 +1185,50 @@ impl OllamaClient {
         Ok(embedding_response.embedding)
     }
 
+    /// Load a model into memory without generating, so the first real request
+    /// doesn't pay the load cost. Embedding models are loaded via the embeddings API.
+    pub async fn preload_model(&self, model: &str, is_embedding_model: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let (url, body) = if is_embedding_model {
+            (
+                format!("{}/api/embeddings", self.get_base_url()),
+                serde_json::json!({ "model": self.resolve_model(model), "prompt": "" }),
+            )
+        } else {
+            // A generate request without a prompt only loads the model
+            (
+                format!("{}/api/generate", self.get_base_url()),
+                serde_json::json!({ "model": self.resolve_model(model), "stream": false }),
+            )
+        };
+        
+        self.ensure_models_installed()?;
+        let (response, _permit) = self.send_limited(self.client.post(&url).json(&body)).await?;
+        self.ensure_aliased_model_found(model, response.status())?;
+            
+        if !response.status().is_success() {
+            return Err(format!("Failed to preload model {}: {}", model, response.status()).into());
+        }
+        
+        Ok(())
+    }
+
+    /// Download a model from the Ollama library, returning once the pull has finished
+    pub async fn pull_model(&self, model: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let url = format!("{}/api/pull", self.get_base_url());
+        
+        let response = self.client.post(&url)
+            .json(&serde_json::json!({ "model": self.resolve_model(model), "stream": false }))
+            .send()
+            .await?;
+            
+        if !response.status().is_success() {
+            return Err(format!("Failed to pull model {}: {}", model, response.status()).into());
+        }
+        
+        self.no_models.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
     /// Enhanced connection check with health monitoring
     pub async fn check_connection(&self) -> Result<bool, Box<dyn Error>> {
         self.check_connection_with_retry().await
@@ -700,7 +1273,7 @@ impl OllamaClient {
 
     /// Perform actual health check against Ollama API
     async fn perform_health_check(&self) -> Result<bool, Box<dyn Error>> {
-        let url = format!("{}/api/version", self.base_url);
+        let url = format!("{}/api/version", self.get_base_url());
         let timeout_duration = Duration::from_secs(self.health_monitor.config.timeout_seconds);
         
         let response = tokio::time::timeout(
@@ -716,11 +1289,73 @@ impl OllamaClient {
         self.health_monitor.get_stats().await
     }
 
+    /// Get recent health check results
+    pub async fn get_health_history(&self) -> HealthHistoryReport {
+        self.health_monitor.get_history().await
+    }
+
+    pub async fn trim_health_history(&self, max_age: Duration) -> usize {
+        self.health_monitor.trim_history(max_age).await
+    }
+
     /// Check if the service is currently considered healthy
     pub async fn is_healthy(&self) -> bool {
         self.health_monitor.is_healthy().await
     }
 
+    /// Probe embeddings by embedding a short fixed string. The vector must be non-empty and,
+    /// when no dimension is given, match the one last seen for this model.
+    pub async fn check_embedding_health(&self, model: &str, expected_dimension: Option<usize>) -> bool {
+        let previous = self.health_monitor.embedding_stats.lock().await.clone();
+        let expected_dimension = expected_dimension.or_else(|| {
+            previous.dimension.filter(|_| previous.model.as_deref() == Some(model))
+        });
+
+        let outcome = match self.request_embedding(model, EMBEDDING_PROBE_TEXT).await {
+            Ok(embedding) if embedding.is_empty() => Err("Embedding model returned an empty vector".to_string()),
+            Ok(embedding) => match expected_dimension {
+                Some(expected) if embedding.len() != expected => Err(format!(
+                    "Embedding dimension {} does not match expected dimension {}",
+                    embedding.len(), expected
+                )),
+                _ => Ok(embedding.len()),
+            },
+            Err(e) => Err(e.to_string()),
+        };
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut stats = self.health_monitor.embedding_stats.lock().await;
+        stats.model = Some(model.to_string());
+        stats.last_check_time = Some(now);
+        match outcome {
+            Ok(dimension) => {
+                stats.is_healthy = true;
+                stats.dimension = Some(dimension);
+                stats.last_error = None;
+            }
+            Err(error) => {
+                stats.is_healthy = false;
+                stats.last_error = Some(error);
+            }
+        }
+        stats.is_healthy
+    }
+
+    pub async fn get_embedding_health_stats(&self) -> EmbeddingHealthStats {
+        self.health_monitor.embedding_stats.lock().await.clone()
+    }
+
+    /// Chat health (from connection checks) and embeddings health (from the probe)
+    pub async fn get_split_health_stats(&self) -> SplitHealthStats {
+        SplitHealthStats {
+            chat: self.get_health_stats().await,
+            embeddings: self.get_embedding_health_stats().await,
+        }
+    }
+
     /// Start background health monitoring
     pub async fn start_health_monitoring(&self) {
         if self.health_monitor.is_monitoring.swap(true, Ordering::SeqCst) {
@@ -797,7 +1432,7 @@ impl OllamaClient {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use mockito::Server;
+    use mockito::{Matcher, Server};
     use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
     use std::time::Duration;
@@ -833,11 +1468,236 @@ mod tests {
 
     #[tokio::test] 
     async fn test_set_base_url() {
-        let mut client = OllamaClient::new(None);
-        client.set_base_url("http://new-url:9090".to_string());
+        let client = OllamaClient::new(None);
+        client.set_base_url("http://new-url:9090".to_string()).await;
         assert_eq!(client.get_base_url(), "http://new-url:9090");
     }
 
+    #[tokio::test]
+    async fn test_base_url_change_applies_to_next_request() {
+        let mut old_server = Server::new();
+        let mut new_server = Server::new();
+        let old_mock = old_server.mock("GET", "/api/tags").with_status(200).with_body(r#"{"models":[]}"#).expect(1).create();
+        let new_mock = new_server.mock("GET", "/api/tags").with_status(200).with_body(r#"{"models":[]}"#).expect(1).create();
+        
+        let client = OllamaClient::new(Some(old_server.url()));
+        let managed = client.clone();
+        client.list_models().await.unwrap();
+        
+        // Clones share the base URL, so every handle follows the change
+        client.set_base_url(new_server.url()).await;
+        managed.list_models().await.unwrap();
+        
+        old_mock.assert();
+        new_mock.assert();
+        assert_eq!(managed.get_base_url(), new_server.url());
+    }
+
+    #[tokio::test]
+    async fn test_base_url_change_clears_models_cache() {
+        let mut old_server = Server::new();
+        let mut new_server = Server::new();
+        let _old_mock = old_server.mock("GET", "/api/tags").with_status(200)
+            .with_body(r#"{"models":[{"name":"old-model","modified_at":"2025-06-01T12:00:00Z","size":1,"digest":"sha256:old"}]}"#)
+            .create();
+        let new_mock = new_server.mock("GET", "/api/tags").with_status(200)
+            .with_body(r#"{"models":[{"name":"new-model","modified_at":"2025-06-01T12:00:00Z","size":1,"digest":"sha256:new"}]}"#)
+            .expect(1)
+            .create();
+        
+        let client = OllamaClient::new(Some(old_server.url()));
+        client.list_models().await.unwrap();
+        assert!(client.get_model("old-model").await.unwrap().is_some());
+        
+        client.set_base_url(new_server.url()).await;
+        
+        // The cached model from the old endpoint is gone; the lookup refreshes from the new one
+        assert!(client.get_model("old-model").await.unwrap().is_none());
+        new_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_get_model_refreshes_after_cache_ttl() {
+        let mut server = Server::new();
+        let initial = server.mock("GET", "/api/tags").with_status(200)
+            .with_body(r#"{"models":[{"name":"llama3","modified_at":"2025-06-01T12:00:00Z","size":1,"digest":"sha256:a"}]}"#)
+            .expect(1)
+            .create();
+        
+        let mut client = OllamaClient::new(Some(server.url()));
+        client.set_models_cache_ttl(Duration::from_millis(100));
+        client.list_models().await.unwrap();
+        
+        // Within the TTL the cached list answers without a request
+        assert!(client.get_model("llama3").await.unwrap().is_some());
+        initial.assert();
+        initial.remove();
+        
+        let refreshed = server.mock("GET", "/api/tags").with_status(200)
+            .with_body(r#"{"models":[{"name":"llama3","modified_at":"2025-06-01T12:00:00Z","size":1,"digest":"sha256:a"},{"name":"qwen2.5-coder","modified_at":"2025-06-02T12:00:00Z","size":2,"digest":"sha256:b"}]}"#)
+            .expect(1)
+            .create();
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        
+        assert!(client.get_model("llama3").await.unwrap().is_some());
+        refreshed.assert();
+        
+        // The refreshed list includes the model pulled after the first call
+        assert_eq!(client.get_model("qwen2.5-coder").await.unwrap().unwrap().size, 2);
+        refreshed.assert();
+    }
+
+    #[test]
+    fn test_generate_details_parse_final_response() {
+        let body = r#"{"model":"llama3","created_at":"2025-06-01T12:00:00Z","response":"Hello!","done":true,"context":[1,2,3],"total_duration":5043500667,"load_duration":5025959,"prompt_eval_count":26,"prompt_eval_duration":325953000,"eval_count":290,"eval_duration":4709213000}"#;
+        let details: GenerateDetails = serde_json::from_str(body).unwrap();
+        
+        assert_eq!(details.response, "Hello!");
+        assert_eq!(details.total_duration, Some(5043500667));
+        assert_eq!(details.load_duration, Some(5025959));
+        assert_eq!(details.eval_count, Some(290));
+        assert_eq!(details.eval_duration, Some(4709213000));
+        assert_eq!(details.prompt_eval_count, Some(26));
+        assert_eq!(details.context, Some(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn test_generate_details_tolerate_missing_metadata() {
+        let details: GenerateDetails = serde_json::from_str(r#"{"model":"llama3","response":"Hi","done":true}"#).unwrap();
+        
+        assert_eq!(details.response, "Hi");
+        assert!(details.eval_count.is_none());
+        assert!(details.context.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_generate_detailed_returns_metadata() {
+        let mut server = Server::new();
+        let mock = server.mock("POST", "/api/generate")
+            .match_body(Matcher::PartialJsonString(r#"{"model":"llama3","prompt":"Hello","stream":false}"#.to_string()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"model":"llama3","response":"Hi there","done":true,"context":[7,8],"total_duration":1000,"eval_count":3}"#)
+            .create();
+        
+        let client = OllamaClient::new(Some(server.url()));
+        let details = client.generate_detailed("llama3", "Hello", None, None).await.unwrap();
+        
+        mock.assert();
+        assert_eq!(details.response, "Hi there");
+        assert_eq!(details.total_duration, Some(1000));
+        assert_eq!(details.eval_count, Some(3));
+        assert_eq!(details.context, Some(vec![7, 8]));
+    }
+
+    #[tokio::test]
+    async fn test_generate_detailed_continues_from_returned_context() {
+        let mut server = Server::new();
+        let first = server.mock("POST", "/api/generate")
+            .match_body(Matcher::PartialJsonString(r#"{"prompt":"My name is Ada"}"#.to_string()))
+            .with_status(200)
+            .with_body(r#"{"model":"llama3","response":"Nice to meet you","done":true,"context":[101,102,103]}"#)
+            .create();
+        let second = server.mock("POST", "/api/generate")
+            .match_body(Matcher::PartialJsonString(r#"{"prompt":"What is my name?","context":[101,102,103]}"#.to_string()))
+            .with_status(200)
+            .with_body(r#"{"model":"llama3","response":"Ada","done":true,"context":[101,102,103,104]}"#)
+            .create();
+        
+        let client = OllamaClient::new(Some(server.url()));
+        let opening = client.generate_detailed("llama3", "My name is Ada", None, None).await.unwrap();
+        let reply = client.generate_detailed("llama3", "What is my name?", None, opening.context).await.unwrap();
+        
+        first.assert();
+        second.assert();
+        assert_eq!(reply.response, "Ada");
+        assert_eq!(reply.context, Some(vec![101, 102, 103, 104]));
+    }
+
+    #[test]
+    fn test_generate_request_omits_context_when_unset() {
+        let request = GenerateRequest {
+            model: "llama3".to_string(),
+            prompt: "Hello".to_string(),
+            stream: false,
+            options: None,
+            format: None,
+            context: None,
+        };
+        
+        let body = serde_json::to_value(&request).unwrap();
+        assert!(body.get("context").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_health_state_flips_only_after_threshold() {
+        let monitor = HealthMonitor::new(HealthConfig {
+            failure_threshold: 3,
+            recovery_threshold: 2,
+            ..Default::default()
+        });
+        let check = Duration::from_millis(1);
+        
+        // Starting unhealthy, alternating results never reach the recovery threshold
+        for _ in 0..3 {
+            monitor.record_check(true, check).await;
+            monitor.record_check(false, check).await;
+            assert!(!monitor.is_healthy().await);
+        }
+        monitor.record_check(true, check).await;
+        assert!(!monitor.is_healthy().await);
+        monitor.record_check(true, check).await;
+        assert!(monitor.is_healthy().await);
+        
+        // Once healthy, failures interrupted by a success don't reach the failure threshold
+        for _ in 0..3 {
+            monitor.record_check(false, check).await;
+            monitor.record_check(false, check).await;
+            monitor.record_check(true, check).await;
+            assert!(monitor.is_healthy().await);
+        }
+        for _ in 0..2 {
+            monitor.record_check(false, check).await;
+            assert!(monitor.is_healthy().await);
+        }
+        monitor.record_check(false, check).await;
+        assert!(!monitor.is_healthy().await);
+    }
+
+    #[tokio::test]
+    async fn test_embedding_failure_is_reported_separately_from_chat() {
+        let mut server = Server::new();
+        let _version = server.mock("GET", "/api/version").with_status(200).with_body(r#"{"version":"0.5.0"}"#).create();
+        let _embeddings = server.mock("POST", "/api/embeddings").with_status(500).create();
+        
+        let client = OllamaClient::new(Some(server.url()));
+        assert!(client.check_connection_with_retry().await.unwrap());
+        assert!(!client.check_embedding_health("nomic-embed-text", None).await);
+        
+        let health = client.get_split_health_stats().await;
+        assert!(health.chat.is_healthy);
+        assert!(!health.embeddings.is_healthy);
+        assert!(health.embeddings.last_error.unwrap().contains("500"));
+    }
+
+    #[tokio::test]
+    async fn test_embedding_probe_checks_dimension() {
+        let mut server = Server::new();
+        let _embeddings = server.mock("POST", "/api/embeddings")
+            .with_status(200)
+            .with_body(r#"{"embedding":[0.1,0.2,0.3]}"#)
+            .create();
+        
+        let client = OllamaClient::new(Some(server.url()));
+        assert!(client.check_embedding_health("nomic-embed-text", None).await);
+        assert_eq!(client.get_embedding_health_stats().await.dimension, Some(3));
+        
+        assert!(!client.check_embedding_health("nomic-embed-text", Some(768)).await);
+        let stats = client.get_embedding_health_stats().await;
+        assert!(stats.last_error.unwrap().contains("dimension"));
+        assert_eq!(stats.dimension, Some(3));
+    }
+
     #[tokio::test]
     async fn test_list_models_success() {
         let mut server = Server::new();
@@ -981,7 +1841,7 @@ mod tests {
         
         let mock = server
             .mock("POST", "/api/generate")
-            .match_body(mockito::Matcher::JsonString(r#"{"model":"test-model","prompt":"test prompt","stream":false,"options":{"temperature":0.8,"top_p":0.9,"max_tokens":100}}"#.to_string()))
+            .match_body(mockito::Matcher::JsonString(r#"{"model":"test-model","prompt":"test prompt","stream":false,"options":{"temperature":0.8,"top_p":0.9,"num_predict":100}}"#.to_string()))
             .with_status(200)
             .with_header("content-type", "application/json")
             .with_body(r#"{"model":"test-model","response":"Generated response","done":true}"#)
@@ -1177,6 +2037,116 @@ mod tests {
         mock.assert();
     }
 
+    #[tokio::test]
+    async fn test_concurrent_identical_embeddings_share_one_request() {
+        let mut server = Server::new();
+        
+        let mock = server
+            .mock("POST", "/api/embeddings")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"embedding":[0.1, 0.2, 0.3]}"#)
+            .expect(1)
+            .create();
+            
+        let client = OllamaClient::new(Some(server.url()));
+        let requests = (0..8).map(|_| {
+            let client = client.clone();
+            async move { client.create_embedding("test-embedding-model", "duplicate chunk").await.unwrap() }
+        });
+        let embeddings = futures_util::future::join_all(requests).await;
+        
+        assert_eq!(embeddings.len(), 8);
+        assert!(embeddings.iter().all(|embedding| embedding == &vec![0.1, 0.2, 0.3]));
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_no_installed_models_degrades_model_requests() {
+        use crate::user_errors::ToUserError;
+        let mut server = Server::new();
+        
+        let tags = server
+            .mock("GET", "/api/tags")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"models":[]}"#)
+            .expect(2)
+            .create();
+        let generate = server.mock("POST", "/api/generate").expect(0).create();
+        let embeddings = server.mock("POST", "/api/embeddings").expect(0).create();
+        let pull = server
+            .mock("POST", "/api/pull")
+            .with_status(200)
+            .with_body(r#"{"status":"success"}"#)
+            .create();
+            
+        let client = OllamaClient::new(Some(server.url()));
+        let availability = client.check_model_availability().await.unwrap();
+        assert!(availability.degraded);
+        assert_eq!(availability.installed_models, 0);
+        assert_eq!(availability.suggested_model, SUGGESTED_MODEL);
+        assert!(client.is_degraded());
+        
+        // Model requests fail without reaching Ollama; listing models still works
+        let error = client.generate_completion("llama3", "Hello", None).await.unwrap_err();
+        assert_eq!(error.to_user_error().error_code, "NO_MODELS_INSTALLED");
+        let error = client.create_embedding("nomic-embed-text", "text").await.unwrap_err();
+        assert_eq!(error.to_user_error().error_code, "NO_MODELS_INSTALLED");
+        assert!(client.list_models().await.unwrap().is_empty());
+        tags.assert();
+        generate.assert();
+        embeddings.assert();
+        
+        // Pulling a model leaves degraded mode
+        client.pull_model(SUGGESTED_MODEL).await.unwrap();
+        assert!(!client.is_degraded());
+        pull.assert();
+    }
+
+    #[tokio::test]
+    async fn test_model_aliases_resolve_at_call_time() {
+        use crate::user_errors::ToUserError;
+        let mut server = Server::new();
+        
+        let chat = server
+            .mock("POST", "/api/chat")
+            .match_body(Matcher::PartialJsonString(r#"{"model": "qwen2.5-coder:7b"}"#.to_string()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"model":"qwen2.5-coder:7b","message":{"role":"assistant","content":"Done"},"done":true}"#)
+            .create();
+        let missing = server
+            .mock("POST", "/api/generate")
+            .match_body(Matcher::PartialJsonString(r#"{"model": "missing-model"}"#.to_string()))
+            .with_status(404)
+            .create();
+            
+        let client = OllamaClient::new(Some(server.url()));
+        let handle = client.clone();
+        client.set_model_aliases(ModelAliases {
+            code: "qwen2.5-coder:7b".to_string(),
+            reasoning: "missing-model".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(handle.resolve_model("code"), "qwen2.5-coder:7b");
+        assert_eq!(handle.resolve_model("mistral:7b"), "mistral:7b");
+        
+        let messages = vec![ChatMessage { role: "user".to_string(), content: "Fix this".to_string() }];
+        let reply = handle.chat("code", messages, None, None::<fn(&str)>).await.unwrap();
+        assert_eq!(reply.content, "Done");
+        chat.assert();
+        
+        // A missing model behind an alias names both, so the user knows which mapping to fix
+        let error = handle.generate_completion("reasoning", "Why?", None).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Model alias 'reasoning' maps to 'missing-model', which was not found; pull it or map the alias to an installed model"
+        );
+        assert_eq!(error.to_user_error().error_code, "MODEL_NOT_FOUND");
+        missing.assert();
+    }
+
     #[tokio::test]
     async fn test_create_embedding_http_error() {
         let mut server = Server::new();
@@ -1402,4 +2372,125 @@ invalid json line
         let model = client.get_model("recovery-model").await.unwrap();
         assert!(model.is_some());
     }
+
+    #[test]
+    fn test_streaming_metrics_rate_and_ttft() {
+        let start = Instant::now();
+        let mut metrics = StreamingMetrics::started_at(start);
+
+        // First token after 250ms, then one token every 100ms
+        for i in 0..11 {
+            metrics.record_token_at(start + Duration::from_millis(250 + i * 100));
+        }
+
+        let summary = metrics.summary();
+        assert_eq!(summary.total_tokens, 11);
+        assert_eq!(summary.time_to_first_token_ms, Some(250));
+        assert_eq!(summary.elapsed_ms, 1250);
+        assert!((summary.tokens_per_second - 10.0).abs() < 0.5, "rate was {}", summary.tokens_per_second);
+    }
+
+    #[test]
+    fn test_streaming_metrics_without_tokens() {
+        let metrics = StreamingMetrics::new();
+        let snapshot = metrics.snapshot();
+
+        assert_eq!(snapshot.total_tokens, 0);
+        assert_eq!(snapshot.time_to_first_token_ms, None);
+        assert_eq!(snapshot.tokens_per_second, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_generate_stream_with_stats_returns_summary() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("POST", "/api/generate")
+            .with_status(200)
+            .with_header("content-type", "application/x-ndjson")
+            .with_body(concat!(
+                r#"{"model":"test-model","response":"Hello","done":false}"#, "\n",
+                r#"{"model":"test-model","response":" there","done":false}"#, "\n",
+                r#"{"model":"test-model","response":"","done":true}"#, "\n",
+            ))
+            .create();
+
+        let client = OllamaClient::new(Some(server.url()));
+        let last_stats = Arc::new(std::sync::Mutex::new(None));
+        let last_stats_clone = last_stats.clone();
+
+        let summary = client
+            .generate_stream_with_stats(
+                "test-model",
+                "Say hello",
+                None,
+                |_token: &str| {},
+                Some(move |stats: BufferStats| {
+                    *last_stats_clone.lock().unwrap() = Some(stats);
+                }),
+                None,
+            )
+            .await
+            .unwrap();
+
+        mock.assert();
+        assert_eq!(summary.total_tokens, 2);
+        assert!(summary.time_to_first_token_ms.is_some());
+
+        let final_stats = last_stats.lock().unwrap().clone().expect("final stats reported");
+        assert_eq!(final_stats.metrics, Some(summary));
+    }
+
+    #[tokio::test]
+    async fn test_active_generation_stats_follow_the_stream() {
+        use std::io::Write;
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/generate")
+            .with_status(200)
+            .with_header("content-type", "application/x-ndjson")
+            .with_chunked_body(|writer| {
+                for (token, done) in [("Hello", false), (" there", false), ("", true)] {
+                    writeln!(writer, r#"{{"model":"test-model","response":"{}","done":{}}}"#, token, done)?;
+                    writer.flush()?;
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+                Ok(())
+            })
+            .create_async()
+            .await;
+
+        let client = OllamaClient::new(Some(server.url()));
+        let generations = ActiveGenerations::new();
+        let generation = generations.register("request-1");
+        assert_eq!(generations.get("request-1").unwrap().metrics, None);
+
+        // Stats seen from the consumer side as each token arrives
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        let generations_clone = generations.clone();
+
+        client
+            .generate_stream_with_stats(
+                "test-model",
+                "Say hello",
+                None,
+                move |_token: &str| {
+                    let tokens = generations_clone
+                        .get("request-1")
+                        .and_then(|stats| stats.metrics)
+                        .map_or(0, |metrics| metrics.total_tokens);
+                    seen_clone.lock().unwrap().push(tokens);
+                },
+                None::<fn(BufferStats)>,
+                Some(generation),
+            )
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(*seen.lock().unwrap(), vec![0, 1, 2]);
+        assert!(generations.get("request-1").is_none());
+        assert!(generations.is_empty());
+    }
 }
@@ -1,5 +1,6 @@
 use crate::ai_providers::*;
-use crate::ollama_client::{OllamaClient, GenerateOptions as OllamaOptions, ModelInfo, ModelResponse};
+use crate::prompt_guard;
+use crate::ollama_client::{OllamaClient, ChatMessage, GenerateOptions as OllamaOptions, ModelInfo, ModelResponse};
 use async_trait::async_trait;
 use std::collections::HashMap;
 use tokio_stream::{Stream, StreamExt};
@@ -89,38 +90,7 @@ impl OllamaProvider {
     
     /// Estimate context length based on model name
     fn estimate_context_length(model_name: &str) -> u32 {
-        let name_lower = model_name.to_lowercase();
-        
-        // Check for explicit context length in name
-        if name_lower.contains("32k") {
-            32768
-        } else if name_lower.contains("16k") {
-            16384
-        } else if name_lower.contains("8k") {
-            8192
-        } else if name_lower.contains("4k") {
-            4096
-        }
-        // Model-specific defaults
-        else if name_lower.contains("llama2") {
-            4096
-        } else if name_lower.contains("llama3") || name_lower.contains("llama-3") {
-            8192
-        } else if name_lower.contains("codellama") {
-            16384
-        } else if name_lower.contains("mistral") {
-            8192
-        } else if name_lower.contains("qwen") {
-            32768
-        } else if name_lower.contains("deepseek") {
-            16384
-        } else if name_lower.contains("phi") {
-            2048
-        }
-        // Default fallback
-        else {
-            4096
-        }
+        prompt_guard::context_window(model_name) as u32
     }
     
     /// Estimate speed based on model size/type
@@ -212,6 +182,35 @@ impl AIProviderTrait for OllamaProvider {
         }
     }
     
+    async fn generate_chat(
+        &self,
+        model_id: &str,
+        messages: Vec<AIMessage>,
+        options: Option<GenerationOptions>,
+    ) -> Result<AIResponse, Box<dyn std::error::Error + Send + Sync>> {
+        let ollama_options = Self::convert_options(options);
+        
+        // Ollama's chat API accepts system prompts inline as a "system" role message
+        let chat_messages = messages.into_iter()
+            .map(|msg| ChatMessage { role: msg.role, content: msg.content })
+            .collect();
+        
+        let reply = self.client.chat(model_id, chat_messages, ollama_options, None::<fn(&str)>).await
+            .map_err(|e| e.to_string())?;
+        
+        let mut metadata = HashMap::new();
+        metadata.insert("provider".to_string(), serde_json::Value::String("ollama".to_string()));
+        
+        Ok(AIResponse {
+            content: reply.content,
+            model: model_id.to_string(),
+            provider: AIProvider::Ollama,
+            usage: None,
+            finish_reason: Some("stop".to_string()),
+            metadata,
+        })
+    }
+    
     async fn generate_stream(
         &self,
         model_id: &str,
@@ -0,0 +1,439 @@
+//! Limits on requests sent to the local Ollama server
+//!
+//! Embedding batches, analysis, diagnostics and chat all share one Ollama. Every
+//! request takes a slot from a priority gate capping how many run at once, then a
+//! token from an optional rate bucket. Interactive requests wait ahead of queued
+//! background work, so a chat message isn't stuck behind an ingest.
+//!
+//! With adaptive concurrency the cap is tuned between a floor and `max_concurrent` by
+//! an AIMD controller: each quick response raises it a little, and a slow or failed
+//! one halves it.
+
+use crate::thread_pool_manager::{GatePermit, PriorityGate, TaskPriority};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Requests sent to Ollama at once
+    pub max_concurrent: usize,
+    /// Sustained request rate; unlimited when unset
+    pub requests_per_second: Option<f64>,
+    /// Requests that can start back to back before the rate applies
+    pub burst: u32,
+    /// Tune the concurrency cap to observed latency; fixed at `max_concurrent` when unset
+    pub adaptive: Option<AdaptiveConcurrencyConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveConcurrencyConfig {
+    /// The cap never drops below this
+    pub min_concurrent: usize,
+    /// Responses taking longer than this to start count as congestion
+    pub target_latency_ms: u64,
+    /// Multiplier applied to the cap on congestion or a failed request
+    pub decrease_factor: f64,
+}
+
+impl Default for AdaptiveConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            min_concurrent: 1,
+            target_latency_ms: 10_000,
+            decrease_factor: 0.5,
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 4,
+            requests_per_second: None,
+            burst: 4,
+            adaptive: None,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_concurrent == 0 {
+            return Err("Ollama max concurrent requests must be at least 1".to_string());
+        }
+        if self.requests_per_second.is_some_and(|rate| rate.is_nan() || rate <= 0.0) {
+            return Err("Ollama requests per second must be greater than 0 when set".to_string());
+        }
+        if self.burst == 0 {
+            return Err("Ollama request burst must be at least 1".to_string());
+        }
+        if let Some(adaptive) = &self.adaptive {
+            if adaptive.min_concurrent == 0 || adaptive.min_concurrent > self.max_concurrent {
+                return Err(format!(
+                    "Ollama adaptive minimum concurrency must be between 1 and {}",
+                    self.max_concurrent
+                ));
+            }
+            if !(adaptive.decrease_factor > 0.0 && adaptive.decrease_factor < 1.0) {
+                return Err("Ollama adaptive decrease factor must be between 0 and 1".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitStats {
+    pub max_concurrent: usize,
+    /// Concurrency cap in effect, below `max_concurrent` when adaptive concurrency has backed off
+    pub current_limit: usize,
+    pub in_flight: usize,
+    /// Requests waiting for a slot
+    pub waiting: usize,
+    /// Most requests in flight at once since startup
+    pub peak_in_flight: usize,
+}
+
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    /// Take a token, or say how long until one is available
+    fn take(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.refilled_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+        }
+    }
+}
+
+/// Additive-increase/multiplicative-decrease tuning of a concurrency cap
+#[derive(Debug, Clone)]
+pub struct AimdController {
+    config: AdaptiveConcurrencyConfig,
+    ceiling: usize,
+    limit: f64,
+    /// Results still due from requests started before the last decrease, which were
+    /// slowed by the same congestion and mustn't decrease the cap again
+    cooldown: usize,
+}
+
+impl AimdController {
+    pub fn new(config: AdaptiveConcurrencyConfig, ceiling: usize) -> Self {
+        Self {
+            config,
+            ceiling,
+            limit: ceiling as f64,
+            cooldown: 0,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit as usize
+    }
+
+    /// Record how long a request took to respond and whether it succeeded, returning the new cap
+    pub fn record(&mut self, latency: Duration, success: bool) -> usize {
+        let congested = !success || latency > Duration::from_millis(self.config.target_latency_ms);
+        if !congested {
+            // About one more slot per cap's worth of quick responses
+            self.limit = (self.limit + 1.0 / self.limit).min(self.ceiling as f64);
+        } else if self.cooldown == 0 {
+            self.cooldown = self.limit();
+            self.limit = (self.limit * self.config.decrease_factor).max(self.config.min_concurrent as f64);
+        }
+        self.cooldown = self.cooldown.saturating_sub(1);
+        self.limit()
+    }
+}
+
+pub struct OllamaRateLimiter {
+    max_concurrent: usize,
+    gate: Arc<PriorityGate>,
+    bucket: Option<Mutex<TokenBucket>>,
+    controller: Option<Mutex<AimdController>>,
+    current_limit: AtomicUsize,
+    in_flight: AtomicUsize,
+    peak_in_flight: AtomicUsize,
+}
+
+/// Held for the length of a request; frees its slot on drop
+pub struct RequestPermit {
+    _slot: GatePermit,
+    limiter: Arc<OllamaRateLimiter>,
+    started_at: Instant,
+}
+
+impl RequestPermit {
+    /// Report that the request has responded, successfully or not. The time since the
+    /// permit was granted tunes adaptive concurrency.
+    pub fn record(&self, success: bool) {
+        self.limiter.record(self.started_at.elapsed(), success);
+    }
+}
+
+impl Drop for RequestPermit {
+    fn drop(&mut self) {
+        self.limiter.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl OllamaRateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        let bucket = config.requests_per_second.map(|rate| {
+            Mutex::new(TokenBucket {
+                rate,
+                capacity: config.burst as f64,
+                tokens: config.burst as f64,
+                refilled_at: Instant::now(),
+            })
+        });
+        let controller = config.adaptive.clone()
+            .map(|adaptive| Mutex::new(AimdController::new(adaptive, config.max_concurrent)));
+        Self {
+            max_concurrent: config.max_concurrent,
+            gate: PriorityGate::new(config.max_concurrent),
+            bucket,
+            controller,
+            current_limit: AtomicUsize::new(config.max_concurrent),
+            in_flight: AtomicUsize::new(0),
+            peak_in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    /// Wait for a slot, highest priority first, then for the rate limit
+    pub async fn acquire(self: &Arc<Self>, priority: TaskPriority) -> RequestPermit {
+        let slot = self.gate.acquire(priority).await;
+        if let Some(bucket) = &self.bucket {
+            loop {
+                let taken = bucket.lock().unwrap().take(Instant::now());
+                match taken {
+                    Ok(()) => break,
+                    Err(wait) => tokio::time::sleep(wait).await,
+                }
+            }
+        }
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        RequestPermit {
+            _slot: slot,
+            limiter: self.clone(),
+            started_at: Instant::now(),
+        }
+    }
+
+    /// Feed a result to the controller and resize the gate to its new cap. Growing is
+    /// immediate; slots being removed are taken ahead of every waiter as they free up.
+    fn record(&self, latency: Duration, success: bool) {
+        let Some(controller) = &self.controller else {
+            return;
+        };
+        let mut controller = controller.lock().unwrap();
+        let previous = controller.limit();
+        let limit = controller.record(latency, success);
+        self.current_limit.store(limit, Ordering::SeqCst);
+        if limit > previous {
+            self.gate.add_permits(limit - previous);
+        } else if limit < previous {
+            if success {
+                eprintln!("Ollama responded in {:?}, reducing concurrency to {}", latency, limit);
+            } else {
+                eprintln!("Ollama request failed, reducing concurrency to {}", limit);
+            }
+            for _ in limit..previous {
+                let gate = self.gate.clone();
+                tokio::spawn(async move {
+                    gate.acquire(TaskPriority::Critical).await.forget();
+                });
+            }
+        }
+    }
+
+    pub fn stats(&self) -> RateLimitStats {
+        RateLimitStats {
+            max_concurrent: self.max_concurrent,
+            current_limit: self.current_limit.load(Ordering::SeqCst),
+            in_flight: self.in_flight.load(Ordering::SeqCst),
+            waiting: self.gate.waiting(),
+            peak_in_flight: self.peak_in_flight.load(Ordering::SeqCst),
+        }
+    }
+}
+
+impl Default for OllamaRateLimiter {
+    fn default() -> Self {
+        Self::new(&RateLimitConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn wait_for_waiting(limiter: &OllamaRateLimiter, count: usize) {
+        while limiter.stats().waiting < count {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_stays_within_cap() {
+        let limiter = Arc::new(OllamaRateLimiter::new(&RateLimitConfig { max_concurrent: 2, ..Default::default() }));
+        let requests: Vec<_> = (0..8)
+            .map(|index| {
+                let limiter = limiter.clone();
+                let priority = if index % 2 == 0 { TaskPriority::Low } else { TaskPriority::High };
+                tokio::spawn(async move {
+                    let _permit = limiter.acquire(priority).await;
+                    assert!(limiter.stats().in_flight <= 2);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                })
+            })
+            .collect();
+        for request in requests {
+            request.await.unwrap();
+        }
+
+        let stats = limiter.stats();
+        assert_eq!(stats.peak_in_flight, 2);
+        assert_eq!((stats.in_flight, stats.waiting), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_interactive_request_goes_ahead_of_queued_background_work() {
+        let limiter = Arc::new(OllamaRateLimiter::new(&RateLimitConfig { max_concurrent: 1, ..Default::default() }));
+        let running = limiter.acquire(TaskPriority::Low).await;
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        let mut requests = Vec::new();
+        for (index, (name, priority)) in [("embed 1", TaskPriority::Low), ("embed 2", TaskPriority::Low), ("chat", TaskPriority::High)]
+            .into_iter()
+            .enumerate()
+        {
+            let queued = limiter.clone();
+            let sender = sender.clone();
+            requests.push(tokio::spawn(async move {
+                let _permit = queued.acquire(priority).await;
+                sender.send(name).unwrap();
+            }));
+            wait_for_waiting(&limiter, index + 1).await;
+        }
+        drop(running);
+        for request in requests {
+            request.await.unwrap();
+        }
+
+        let order: Vec<&str> = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+        assert_eq!(order, vec!["chat", "embed 1", "embed 2"]);
+    }
+
+    #[tokio::test]
+    async fn test_requests_beyond_the_burst_are_throttled() {
+        let limiter = Arc::new(OllamaRateLimiter::new(&RateLimitConfig {
+            max_concurrent: 4,
+            requests_per_second: Some(50.0),
+            burst: 2,
+            adaptive: None,
+        }));
+        let started = Instant::now();
+        for _ in 0..2 {
+            limiter.acquire(TaskPriority::Low).await;
+        }
+        assert!(started.elapsed() < Duration::from_millis(15));
+
+        // Each further request waits for a token, one every 20ms
+        for _ in 0..3 {
+            limiter.acquire(TaskPriority::Low).await;
+        }
+        assert!(started.elapsed() >= Duration::from_millis(55));
+        assert!(RateLimitConfig { requests_per_second: Some(0.0), ..Default::default() }.validate().is_err());
+    }
+
+    fn controller() -> AimdController {
+        let config = AdaptiveConcurrencyConfig {
+            min_concurrent: 1,
+            target_latency_ms: 100,
+            decrease_factor: 0.5,
+        };
+        AimdController::new(config, 8)
+    }
+
+    #[test]
+    fn test_controller_backs_off_as_latency_rises_and_recovers() {
+        let mut controller = controller();
+        let fast = Duration::from_millis(20);
+        let slow = Duration::from_millis(500);
+        assert_eq!(controller.record(fast, true), 8);
+
+        // The first slow response halves the cap; the rest of that batch doesn't
+        assert_eq!(controller.record(slow, true), 4);
+        let during_cooldown: Vec<usize> = (0..7).map(|_| controller.record(slow, true)).collect();
+        assert_eq!(during_cooldown, vec![4; 7]);
+        assert_eq!(controller.record(slow, true), 2);
+
+        // Sustained congestion bottoms out at the floor
+        let limits: Vec<usize> = (0..20).map(|_| controller.record(slow, true)).collect();
+        assert!(limits.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert_eq!(controller.limit(), 1);
+
+        // Quick responses raise the cap back to the ceiling, one step at a time
+        let limits: Vec<usize> = (0..60).map(|_| controller.record(fast, true)).collect();
+        assert!(limits.windows(2).all(|pair| pair[1] <= pair[0] + 1 && pair[1] >= pair[0]));
+        assert_eq!(controller.limit(), 8);
+
+        // A failure backs off however fast it was
+        assert_eq!(controller.record(fast, false), 4);
+    }
+
+    #[tokio::test]
+    async fn test_slow_responses_reduce_concurrency_and_fast_ones_restore_it() {
+        let limiter = Arc::new(OllamaRateLimiter::new(&RateLimitConfig {
+            max_concurrent: 4,
+            adaptive: Some(AdaptiveConcurrencyConfig {
+                min_concurrent: 1,
+                target_latency_ms: 5,
+                decrease_factor: 0.5,
+            }),
+            ..Default::default()
+        }));
+        let slow = limiter.acquire(TaskPriority::Normal).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        slow.record(true);
+        drop(slow);
+        assert_eq!(limiter.stats().current_limit, 2);
+        // Let the removed slots be taken
+        while limiter.gate.available_permits() > 2 {
+            tokio::task::yield_now().await;
+        }
+
+        let first = limiter.acquire(TaskPriority::Normal).await;
+        let second = limiter.acquire(TaskPriority::Normal).await;
+        let third = tokio::time::timeout(Duration::from_millis(20), limiter.acquire(TaskPriority::High)).await;
+        assert!(third.is_err(), "a third request ran with the cap at 2");
+        drop(second);
+
+        // Quick responses give the slots back
+        while limiter.stats().current_limit < 4 {
+            limiter.acquire(TaskPriority::Normal).await.record(true);
+        }
+        let mut running = vec![first];
+        for _ in 0..3 {
+            let permit = tokio::time::timeout(Duration::from_millis(20), limiter.acquire(TaskPriority::Normal)).await;
+            running.push(permit.expect("the cap is back at 4"));
+        }
+        assert_eq!(limiter.stats().in_flight, 4);
+    }
+}
@@ -185,6 +185,31 @@ impl OpenAIClient {
             description: format!("OpenAI {} model", openai_model.id),
         }
     }
+    
+    /// Convert a complete (non-SSE) chat completion body into a single final chunk
+    fn parse_non_streamed_body(body: &str) -> Result<StreamChunk, Box<dyn std::error::Error + Send + Sync>> {
+        let response: OpenAIResponse = serde_json::from_str(body.trim())
+            .map_err(|e| format!("Failed to parse non-streamed OpenAI response: {}", e))?;
+        
+        let choice = response.choices.first();
+        let content = choice
+            .and_then(|choice| choice.message.as_ref().map(|msg| msg.content.clone()))
+            .or_else(|| choice.and_then(|choice| choice.delta.as_ref()).and_then(|delta| delta.content.clone()))
+            .unwrap_or_default();
+        
+        let mut metadata = HashMap::new();
+        metadata.insert("id".to_string(), serde_json::Value::String(response.id));
+        metadata.insert("non_streamed".to_string(), serde_json::Value::Bool(true));
+        if let Some(reason) = choice.and_then(|choice| choice.finish_reason.clone()) {
+            metadata.insert("finish_reason".to_string(), serde_json::Value::String(reason));
+        }
+        
+        Ok(StreamChunk {
+            content,
+            is_complete: true,
+            metadata,
+        })
+    }
 }
 
 #[async_trait]
@@ -235,6 +260,15 @@ impl AIProviderTrait for OpenAIClient {
         model_id: &str,
         prompt: &str,
         options: Option<GenerationOptions>,
+    ) -> Result<AIResponse, Box<dyn std::error::Error + Send + Sync>> {
+        self.generate_chat(model_id, vec![AIMessage::user(prompt)], options).await
+    }
+    
+    async fn generate_chat(
+        &self,
+        model_id: &str,
+        messages: Vec<AIMessage>,
+        options: Option<GenerationOptions>,
     ) -> Result<AIResponse, Box<dyn std::error::Error + Send + Sync>> {
         if !self.enabled {
             return Err("OpenAI provider is not enabled".into());
@@ -242,12 +276,12 @@ impl AIProviderTrait for OpenAIClient {
         
         let opts = options.unwrap_or_default();
         
+        // OpenAI accepts system prompts inline as a "system" role message
         let request = OpenAIRequest {
             model: model_id.to_string(),
-            messages: vec![OpenAIMessage {
-                role: "user".to_string(),
-                content: prompt.to_string(),
-            }],
+            messages: messages.into_iter()
+                .map(|msg| OpenAIMessage { role: msg.role, content: msg.content })
+                .collect(),
             temperature: opts.temperature,
             max_tokens: opts.max_tokens,
             top_p: opts.top_p,
@@ -330,6 +364,22 @@ impl AIProviderTrait for OpenAIClient {
             return Err(format!("OpenAI API error: {}", error_text).into());
         }
         
+        // Some OpenAI-compatible backends and proxies ignore `stream: true` and
+        // answer with a single JSON body. Emit that as one final delta instead
+        // of trying to parse it as SSE.
+        let is_event_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.starts_with("text/event-stream"))
+            .unwrap_or(false);
+
+        if !is_event_stream {
+            let body = response.text().await?;
+            let chunk = Self::parse_non_streamed_body(&body)?;
+            return Ok(Box::new(stream::iter(vec![Ok(chunk)])));
+        }
+        
         let bytes_stream = response.bytes_stream();
         let text_stream = bytes_stream.map(|chunk_result| {
             chunk_result.map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
@@ -419,4 +469,110 @@ impl AIProviderTrait for OpenAIClient {
         
         Ok(response.status().is_success())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full_completion_body() -> String {
+        serde_json::json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-3.5-turbo",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello from a non-streaming backend"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 6, "total_tokens": 11}
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_generate_stream_falls_back_to_single_delta() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::PartialJsonString(r#"{"stream": true}"#.to_string()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(full_completion_body())
+            .create_async()
+            .await;
+
+        let client = OpenAIClient::new_with_base_url("test-key".to_string(), server.url());
+        let mut stream = client
+            .generate_stream("gpt-3.5-turbo", "Say hello", None)
+            .await
+            .expect("stream should be created");
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            chunks.push(chunk.expect("chunk should parse"));
+        }
+
+        mock.assert_async().await;
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].content, "Hello from a non-streaming backend");
+        assert!(chunks[0].is_complete);
+        assert_eq!(chunks[0].metadata.get("non_streamed"), Some(&serde_json::Value::Bool(true)));
+    }
+
+    #[tokio::test]
+    async fn test_generate_stream_still_parses_sse() {
+        let mut server = mockito::Server::new_async().await;
+        let sse_body = concat!(
+            "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"gpt-3.5-turbo\",",
+            "\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":null}]}\n\n",
+        );
+        let _mock = server
+            .mock("POST", "/chat/completions")
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(sse_body)
+            .create_async()
+            .await;
+
+        let client = OpenAIClient::new_with_base_url("test-key".to_string(), server.url());
+        let mut stream = client
+            .generate_stream("gpt-3.5-turbo", "Say hi", None)
+            .await
+            .expect("stream should be created");
+
+        let first = stream.next().await.expect("one chunk").expect("chunk should parse");
+        assert_eq!(first.content, "Hi");
+        assert!(first.metadata.get("non_streamed").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_generate_chat_keeps_system_message_inline() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"messages": [{"role": "system", "content": "Be terse."}, {"role": "user", "content": "Hi"}]}"#.to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(full_completion_body())
+            .create_async()
+            .await;
+
+        let client = OpenAIClient::new_with_base_url("test-key".to_string(), server.url());
+        let response = client
+            .generate_chat("gpt-3.5-turbo", vec![AIMessage::system("Be terse."), AIMessage::user("Hi")], None)
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(response.content, "Hello from a non-streaming backend");
+    }
+
+    #[test]
+    fn test_parse_non_streamed_body_rejects_garbage() {
+        assert!(OpenAIClient::parse_non_streamed_body("not json").is_err());
+    }
+}
@@ -2,10 +2,14 @@
 
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
-use tokio::sync::{mpsc, Semaphore};
+use tokio::sync::{broadcast, mpsc, Notify, Semaphore};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::collections::HashMap;
+use crate::thread_pool_manager::TaskPriority;
 use tokio::time::Instant;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -27,6 +31,19 @@ pub enum OperationPriority {
     Maintenance = 4,
 }
 
+/// Thread pool tasks spawned by an operation (e.g. its embeddings) inherit its priority,
+/// so interactive work isn't queued behind background ingests
+impl From<&OperationPriority> for TaskPriority {
+    fn from(priority: &OperationPriority) -> Self {
+        match priority {
+            OperationPriority::Critical => TaskPriority::Critical,
+            OperationPriority::High => TaskPriority::High,
+            OperationPriority::Normal => TaskPriority::Normal,
+            OperationPriority::Background | OperationPriority::Maintenance => TaskPriority::Low,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Operation {
     pub id: String,
@@ -39,6 +56,9 @@ pub struct Operation {
     pub payload: serde_json::Value,
     pub completed_at: Option<u64>,
     pub memory_used: Option<u64>,
+    /// Set when the operation is enqueued (Unix seconds)
+    #[serde(default)]
+    pub enqueued_at: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,8 +80,151 @@ pub enum OperationStatus {
     TimedOut,
 }
 
+/// `OperationStatus` without its data, for filtering and display
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+    TimedOut,
+}
+
+impl OperationState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OperationState::Queued => "queued",
+            OperationState::Running => "running",
+            OperationState::Completed => "completed",
+            OperationState::Failed => "failed",
+            OperationState::Cancelled => "cancelled",
+            OperationState::TimedOut => "timed_out",
+        }
+    }
+}
+
+impl From<&OperationStatus> for OperationState {
+    fn from(status: &OperationStatus) -> Self {
+        match status {
+            OperationStatus::Queued => OperationState::Queued,
+            OperationStatus::Running { .. } => OperationState::Running,
+            OperationStatus::Completed { .. } => OperationState::Completed,
+            OperationStatus::Failed { .. } => OperationState::Failed,
+            OperationStatus::Cancelled => OperationState::Cancelled,
+            OperationStatus::TimedOut => OperationState::TimedOut,
+        }
+    }
+}
+
+/// Which operations `list_operations` returns; empty fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OperationFilter {
+    pub states: Option<Vec<OperationState>>,
+    pub op_type: Option<OperationType>,
+}
+
+impl OperationFilter {
+    pub fn matches(&self, operation: &Operation, state: OperationState) -> bool {
+        self.states.as_ref().map_or(true, |states| states.contains(&state))
+            && self.op_type.as_ref().map_or(true, |op_type| *op_type == operation.op_type)
+    }
+}
+
+/// Queue entry as shown to the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationSummary {
+    pub id: String,
+    pub op_type: OperationType,
+    pub priority: OperationPriority,
+    pub state: OperationState,
+    pub status: OperationStatus,
+    pub enqueued_at: Option<u64>,
+    /// Seconds since the operation was enqueued
+    pub age_seconds: u64,
+}
+
+/// Signals an operation to stop; shared between the manager and the running task
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once `cancel` has been called
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+pub type OperationResult = Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Work run for an operation instead of the built-in handler for its type
+pub type OperationTask = Box<
+    dyn FnOnce(ProgressReporter) -> Pin<Box<dyn Future<Output = OperationResult> + Send>> + Send + Sync,
+>;
+
+/// Latest percent-complete reported by a running operation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationProgress {
+    pub operation_id: String,
+    /// Fraction complete, 0.0-1.0
+    pub progress: f32,
+}
+
+/// Handed to an operation's task so it can report how far along it is
+#[derive(Clone)]
+pub struct ProgressReporter {
+    operation_id: String,
+    operations: Arc<DashMap<String, (Operation, OperationStatus)>>,
+    progress_tx: broadcast::Sender<OperationProgress>,
+}
+
+impl ProgressReporter {
+    /// Record `progress` (clamped to 0.0-1.0) while the operation is still running
+    pub fn report(&self, progress: f32) {
+        let progress = progress.clamp(0.0, 1.0);
+        {
+            let Some(mut entry) = self.operations.get_mut(&self.operation_id) else {
+                return;
+            };
+            if !matches!(entry.1, OperationStatus::Running { .. }) {
+                return;
+            }
+            entry.1 = OperationStatus::Running { progress: Some(progress) };
+        }
+
+        // No subscribers is fine
+        let _ = self.progress_tx.send(OperationProgress {
+            operation_id: self.operation_id.clone(),
+            progress,
+        });
+    }
+}
+
 pub struct OperationManager {
-    pub operations: DashMap<String, (Operation, OperationStatus)>,
+    pub operations: Arc<DashMap<String, (Operation, OperationStatus)>>,
+    pub cancellation_tokens: Arc<DashMap<String, CancellationToken>>,
+    pub tasks: Arc<DashMap<String, OperationTask>>,
+    pub progress_tx: broadcast::Sender<OperationProgress>,
     pub queue_tx: mpsc::Sender<Operation>,
     pub resource_limits: ResourceLimits,
     pub semaphore: Arc<Semaphore>,
@@ -156,8 +319,15 @@ impl OperationManager {
 
     pub fn new_with_cleanup(resource_limits: ResourceLimits, cleanup_config: CleanupConfig) -> Self {
         let (queue_tx, mut queue_rx) = mpsc::channel::<Operation>(100);
-        let operations = DashMap::new();
+        // Shared with the processor and cleanup tasks so their status updates are visible here
+        let operations: Arc<DashMap<String, (Operation, OperationStatus)>> = Arc::new(DashMap::new());
         let operations_clone = operations.clone();
+        let cancellation_tokens: Arc<DashMap<String, CancellationToken>> = Arc::new(DashMap::new());
+        let tokens_clone = cancellation_tokens.clone();
+        let tasks: Arc<DashMap<String, OperationTask>> = Arc::new(DashMap::new());
+        let tasks_clone = tasks.clone();
+        let (progress_tx, _) = broadcast::channel(100);
+        let progress_tx_clone = progress_tx.clone();
         let semaphore = Arc::new(Semaphore::new(resource_limits.max_concurrent_operations));
         let semaphore_clone = semaphore.clone();
         let memory_monitor = Arc::new(MemoryMonitor::new());
@@ -176,8 +346,27 @@ impl OperationManager {
                     }
                     let operation = priority_queues[priority_level].remove(0);
                     let op_id = operation.id.clone();
+                    
+                    // Skip operations cancelled while they were queued
+                    let cancelled = operations_clone
+                        .get(&op_id)
+                        .is_some_and(|entry| entry.1 == OperationStatus::Cancelled);
+                    if cancelled {
+                        tokens_clone.remove(&op_id);
+                        tasks_clone.remove(&op_id);
+                        continue;
+                    }
+                    
                     operations_clone.insert(op_id.clone(), (operation.clone(), OperationStatus::Running { progress: None }));
                     let ops_map = operations_clone.clone();
+                    let tokens_task = tokens_clone.clone();
+                    let token = tokens_clone.get(&op_id).map(|entry| entry.value().clone()).unwrap_or_default();
+                    let task = tasks_clone.remove(&op_id).map(|(_, task)| task);
+                    let reporter = ProgressReporter {
+                        operation_id: op_id.clone(),
+                        operations: operations_clone.clone(),
+                        progress_tx: progress_tx_clone.clone(),
+                    };
                     let memory_monitor_task = memory_monitor_clone.clone();
                     let semaphore_task = semaphore_clone.clone();
                     tokio::spawn(async move {
@@ -188,6 +377,7 @@ impl OperationManager {
                                 let mut updated_operation = operation.clone();
                                 updated_operation.completed_at = Some(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs());
                                 ops_map.insert(op_id.clone(), (updated_operation, OperationStatus::Failed { error: "Could not acquire semaphore permit".to_string() }));
+                                tokens_task.remove(&op_id);
                                 return;
                             }
                         };
@@ -197,14 +387,23 @@ impl OperationManager {
                         let estimated_memory = (operation.estimated_resources.memory_mb as u64) * 1024 * 1024;
                         memory_monitor_task.allocate(&op_id, estimated_memory);
                         
-                        // Actual operation execution logic
-                        let result = match operation.op_type {
-                            OperationType::AICompletion => mock_ai_completion(&operation).await,
-                            OperationType::FileAnalysis => mock_file_analysis(&operation).await,
-                            OperationType::DocumentIndexing => mock_document_indexing(&operation).await,
-                            OperationType::CodeGeneration => mock_code_generation(&operation).await,
-                            OperationType::RagQuery => mock_rag_query(&operation).await,
-                            OperationType::ModelLoading => mock_model_loading(&operation).await,
+                        // Actual operation execution logic, abandoned if the operation is cancelled
+                        let execution = async {
+                            if let Some(task) = task {
+                                return task(reporter).await;
+                            }
+                            match operation.op_type {
+                                OperationType::AICompletion => mock_ai_completion(&operation).await,
+                                OperationType::FileAnalysis => mock_file_analysis(&operation).await,
+                                OperationType::DocumentIndexing => mock_document_indexing(&operation).await,
+                                OperationType::CodeGeneration => mock_code_generation(&operation).await,
+                                OperationType::RagQuery => mock_rag_query(&operation).await,
+                                OperationType::ModelLoading => mock_model_loading(&operation).await,
+                            }
+                        };
+                        let result = tokio::select! {
+                            result = execution => result,
+                            _ = token.cancelled() => Err("Operation cancelled".into()),
                         };
                         
                         // Record completion time and memory used
@@ -219,6 +418,9 @@ impl OperationManager {
                         updated_operation.memory_used = Some(estimated_memory);
                         
                         match result {
+                            _ if token.is_cancelled() => {
+                                ops_map.insert(op_id.clone(), (updated_operation, OperationStatus::Cancelled));
+                            },
                             Ok(res) => {
                                 ops_map.insert(op_id.clone(), (updated_operation, OperationStatus::Completed { result: Some(res) }));
                             },
@@ -228,6 +430,7 @@ impl OperationManager {
                         }
                         
                         // Deallocate memory
+                        tokens_task.remove(&op_id);
                         memory_monitor_task.deallocate(&op_id);
                         drop(permit);
                     });
@@ -276,6 +479,9 @@ impl OperationManager {
 
         Self {
             operations,
+            cancellation_tokens,
+            tasks,
+            progress_tx,
             queue_tx,
             resource_limits,
             semaphore,
@@ -290,14 +496,42 @@ impl OperationManager {
         // Initialize fields for new operation
         operation.completed_at = None;
         operation.memory_used = None;
+        operation.enqueued_at = Some(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        );
         
         self.operations.insert(op_id.clone(), (operation.clone(), OperationStatus::Queued));
+        self.cancellation_tokens.insert(op_id.clone(), CancellationToken::default());
         match self.queue_tx.send(operation).await {
             Ok(_) => Ok(op_id),
             Err(e) => Err(format!("Failed to enqueue operation: {}", e))
         }
     }
 
+    /// Enqueue an operation that runs `task`, which receives a reporter for its progress
+    pub async fn enqueue_task<F, Fut>(&self, operation: Operation, task: F) -> Result<String, String>
+    where
+        F: FnOnce(ProgressReporter) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = OperationResult> + Send + 'static,
+    {
+        let op_id = operation.id.clone();
+        self.tasks.insert(op_id.clone(), Box::new(move |reporter| Box::pin(task(reporter))));
+
+        let result = self.enqueue_operation(operation).await;
+        if result.is_err() {
+            self.tasks.remove(&op_id);
+        }
+        result
+    }
+
+    /// Receive progress updates from running operations
+    pub fn subscribe_progress(&self) -> broadcast::Receiver<OperationProgress> {
+        self.progress_tx.subscribe()
+    }
+
     /// Helper method to create a new operation with default values
     pub fn create_operation(
         id: String,
@@ -320,6 +554,7 @@ impl OperationManager {
             payload,
             completed_at: None,
             memory_used: None,
+            enqueued_at: None,
         }
     }
 
@@ -327,11 +562,43 @@ impl OperationManager {
         self.operations.get(operation_id).map(|entry| entry.value().1.clone())
     }
 
+    /// Operations matching `filter`, highest priority first, then oldest first
+    pub fn list_operations(&self, filter: &OperationFilter) -> Vec<OperationSummary> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let mut summaries: Vec<OperationSummary> = self.operations
+            .iter()
+            .filter_map(|entry| {
+                let (operation, status) = entry.value();
+                let state = OperationState::from(status);
+                if !filter.matches(operation, state) {
+                    return None;
+                }
+                Some(OperationSummary {
+                    id: operation.id.clone(),
+                    op_type: operation.op_type.clone(),
+                    priority: operation.priority.clone(),
+                    state,
+                    status: status.clone(),
+                    enqueued_at: operation.enqueued_at,
+                    age_seconds: operation.enqueued_at.map_or(0, |enqueued_at| now.saturating_sub(enqueued_at)),
+                })
+            })
+            .collect();
+
+        summaries.sort_by(|a, b| a.priority.cmp(&b.priority).then(a.enqueued_at.cmp(&b.enqueued_at)));
+        summaries
+    }
+
     pub fn cancel_operation(&self, operation_id: &str) -> Result<(), String> {
         if let Some(mut entry) = self.operations.get_mut(operation_id) {
-            if entry.1 == OperationStatus::Queued || 
-               matches!(entry.1, OperationStatus::Running { .. }) && entry.0.cancellable {
+            if Self::is_cancellable(&entry.0, &entry.1) {
                 entry.1 = OperationStatus::Cancelled;
+                drop(entry);
+                self.trigger_cancellation(operation_id);
                 return Ok(());
             }
             return Err("Operation cannot be cancelled in its current state".to_string());
@@ -339,6 +606,43 @@ impl OperationManager {
         Err("Operation not found".to_string())
     }
 
+    /// Cancel every queued or running operation of `op_type`, returning how many were cancelled
+    pub fn cancel_operations_by_type(&self, op_type: &OperationType) -> usize {
+        self.cancel_matching(|operation| &operation.op_type == op_type)
+    }
+
+    /// Cancel every queued or running operation, returning how many were cancelled
+    pub fn cancel_all_operations(&self) -> usize {
+        self.cancel_matching(|_| true)
+    }
+
+    fn cancel_matching(&self, matches: impl Fn(&Operation) -> bool) -> usize {
+        let mut cancelled = Vec::new();
+        for mut entry in self.operations.iter_mut() {
+            let (operation, status) = entry.value_mut();
+            if matches(operation) && Self::is_cancellable(operation, status) {
+                *status = OperationStatus::Cancelled;
+                cancelled.push(entry.key().clone());
+            }
+        }
+
+        for operation_id in &cancelled {
+            self.trigger_cancellation(operation_id);
+        }
+        cancelled.len()
+    }
+
+    fn is_cancellable(operation: &Operation, status: &OperationStatus) -> bool {
+        *status == OperationStatus::Queued
+            || matches!(status, OperationStatus::Running { .. }) && operation.cancellable
+    }
+
+    fn trigger_cancellation(&self, operation_id: &str) {
+        if let Some(token) = self.cancellation_tokens.get(operation_id) {
+            token.cancel();
+        }
+    }
+
     /// Get memory usage statistics
     pub fn get_memory_stats(&self) -> MemoryStats {
         MemoryStats {
@@ -355,14 +659,7 @@ impl OperationManager {
     pub fn get_operations_by_status(&self) -> HashMap<String, usize> {
         let mut counts = HashMap::new();
         for entry in self.operations.iter() {
-            let status_key = match &entry.1 {
-                OperationStatus::Queued => "queued",
-                OperationStatus::Running { .. } => "running",
-                OperationStatus::Completed { .. } => "completed",
-                OperationStatus::Failed { .. } => "failed",
-                OperationStatus::Cancelled => "cancelled",
-                OperationStatus::TimedOut => "timed_out",
-            };
+            let status_key = OperationState::from(&entry.1).as_str();
             *counts.entry(status_key.to_string()).or_insert(0) += 1;
         }
         counts
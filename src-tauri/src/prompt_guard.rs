@@ -0,0 +1,267 @@
+//! Keeps prompts within the context window of the model they're sent to
+//!
+//! Ollama silently drops the start of a prompt that overflows the model's window, so an
+//! oversized file sent for analysis loses its instructions without any error. Every
+//! generate and chat request goes through `fit_prompt` or `fit_messages` first, which
+//! either trims the prompt to the limit or rejects it, depending on the policy.
+
+use crate::context_manager::ContextManager;
+use crate::ollama_client::ChatMessage;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+/// Window assumed for models whose name gives no hint of theirs
+pub const DEFAULT_CONTEXT_WINDOW: usize = 4096;
+
+/// What to do with a prompt over the limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OversizePolicy {
+    /// Drop the oldest chat turns, then the middle of the longest message
+    #[default]
+    Trim,
+    /// Fail the request without sending it
+    Reject,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptLimitConfig {
+    pub policy: OversizePolicy,
+    /// Upper bound on every prompt; unset leaves each model's own window as the limit
+    pub max_prompt_tokens: Option<usize>,
+    /// Part of the model's window left free for the reply
+    pub reserved_completion_tokens: usize,
+}
+
+impl Default for PromptLimitConfig {
+    fn default() -> Self {
+        Self {
+            policy: OversizePolicy::Trim,
+            max_prompt_tokens: None,
+            reserved_completion_tokens: 512,
+        }
+    }
+}
+
+impl PromptLimitConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_prompt_tokens == Some(0) {
+            return Err("Max prompt tokens must be at least 1 when set".to_string());
+        }
+        if self.reserved_completion_tokens >= DEFAULT_CONTEXT_WINDOW {
+            return Err(format!(
+                "Reserved completion tokens must be below {}, the smallest assumed context window",
+                DEFAULT_CONTEXT_WINDOW
+            ));
+        }
+        Ok(())
+    }
+
+    /// Most tokens a prompt for `model` may take
+    pub fn limit_for(&self, model: &str) -> usize {
+        let window = context_window(model).saturating_sub(self.reserved_completion_tokens);
+        self.max_prompt_tokens.map_or(window, |max| max.min(window))
+    }
+}
+
+/// Name fragments and the context window they indicate; the first match wins
+const CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    // Explicit context length in the name
+    ("32k", 32768),
+    ("16k", 16384),
+    ("8k", 8192),
+    ("4k", 4096),
+    // Model-specific defaults
+    ("llama2", 4096),
+    ("llama3", 8192),
+    ("llama-3", 8192),
+    ("codellama", 16384),
+    ("mistral", 8192),
+    ("qwen", 32768),
+    ("deepseek", 16384),
+    ("phi", 2048),
+];
+
+/// Context window of `model`, estimated from its name
+pub fn context_window(model: &str) -> usize {
+    let name_lower = model.to_lowercase();
+    CONTEXT_WINDOWS
+        .iter()
+        .find(|(fragment, _)| name_lower.contains(fragment))
+        .map_or(DEFAULT_CONTEXT_WINDOW, |(_, window)| *window)
+}
+
+/// A prompt over its model's limit, either under the reject policy or because the
+/// parts that are never trimmed already fill the window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTooLong {
+    pub model: String,
+    pub tokens: usize,
+    pub limit: usize,
+}
+
+impl fmt::Display for PromptTooLong {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Prompt for {} needs ~{} tokens but only {} fit in its context window",
+            self.model, self.tokens, self.limit
+        )
+    }
+}
+
+impl Error for PromptTooLong {}
+
+/// `prompt` within the limit for `model`
+pub fn fit_prompt(config: &PromptLimitConfig, model: &str, prompt: &str) -> Result<String, PromptTooLong> {
+    let limit = config.limit_for(model);
+    let tokens = ContextManager::estimate_tokens(prompt);
+    if tokens <= limit {
+        return Ok(prompt.to_string());
+    }
+    let too_long = PromptTooLong { model: model.to_string(), tokens, limit };
+    if config.policy == OversizePolicy::Reject {
+        return Err(too_long);
+    }
+
+    let trimmed = trim_middle(prompt, limit).ok_or(too_long)?;
+    eprintln!("Trimmed a ~{} token prompt for {} to its limit of {}", tokens, model, limit);
+    Ok(trimmed)
+}
+
+/// `messages` within the limit for `model`. Trimming drops the oldest messages other
+/// than system messages and the last message, then shortens the longest message left.
+pub fn fit_messages(config: &PromptLimitConfig, model: &str, mut messages: Vec<ChatMessage>) -> Result<Vec<ChatMessage>, PromptTooLong> {
+    let limit = config.limit_for(model);
+    let total = |messages: &[ChatMessage]| -> usize {
+        messages.iter().map(|message| ContextManager::estimate_tokens(&message.content)).sum()
+    };
+    let tokens = total(&messages);
+    if tokens <= limit {
+        return Ok(messages);
+    }
+    let too_long = PromptTooLong { model: model.to_string(), tokens, limit };
+    if config.policy == OversizePolicy::Reject {
+        return Err(too_long);
+    }
+
+    while total(&messages) > limit {
+        let last = messages.len().saturating_sub(1);
+        match messages.iter().take(last).position(|message| message.role != "system") {
+            Some(oldest) => {
+                messages.remove(oldest);
+            }
+            None => break,
+        }
+    }
+
+    let remaining = total(&messages);
+    if remaining > limit {
+        let (longest, longest_tokens) = messages
+            .iter()
+            .map(|message| ContextManager::estimate_tokens(&message.content))
+            .enumerate()
+            .max_by_key(|(_, tokens)| *tokens)
+            .ok_or_else(|| too_long.clone())?;
+        let budget = (limit + longest_tokens).checked_sub(remaining).ok_or_else(|| too_long.clone())?;
+        messages[longest].content = trim_middle(&messages[longest].content, budget).ok_or(too_long)?;
+    }
+
+    eprintln!("Trimmed a ~{} token chat for {} to its limit of {}", tokens, model, limit);
+    Ok(messages)
+}
+
+/// `text` cut to `budget` tokens by removing words from the middle, since instructions
+/// tend to come first and the question last. Whitespace in the kept parts is untouched.
+/// None when not even the marker for the cut fits.
+fn trim_middle(text: &str, budget: usize) -> Option<String> {
+    let words: Vec<(usize, &str)> = text
+        .split_whitespace()
+        .map(|word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+        .collect();
+
+    let mut keep = words.len();
+    loop {
+        let head = keep / 2;
+        let tail = keep - head;
+        let head_end = if head == 0 { 0 } else { words[head - 1].0 + words[head - 1].1.len() };
+        let tail_start = if tail == 0 { text.len() } else { words[words.len() - tail].0 };
+        let trimmed = format!(
+            "{}\n[... {} words trimmed to fit the context window ...]\n{}",
+            &text[..head_end],
+            words.len() - keep,
+            &text[tail_start..]
+        );
+        if ContextManager::estimate_tokens(&trimmed) <= budget {
+            return Some(trimmed);
+        }
+        if keep == 0 {
+            return None;
+        }
+        // Step by the overshoot rather than a word at a time for long texts
+        let over = ContextManager::estimate_tokens(&trimmed) - budget;
+        keep = keep.saturating_sub((over / 2).max(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage { role: role.to_string(), content: content.to_string() }
+    }
+
+    fn config(policy: OversizePolicy, max_prompt_tokens: usize) -> PromptLimitConfig {
+        PromptLimitConfig { policy, max_prompt_tokens: Some(max_prompt_tokens), ..PromptLimitConfig::default() }
+    }
+
+    #[test]
+    fn test_limit_follows_the_model_window() {
+        let defaults = PromptLimitConfig::default();
+        assert_eq!(defaults.limit_for("llama3:8b"), 8192 - 512);
+        assert_eq!(defaults.limit_for("phi3"), 2048 - 512);
+        assert_eq!(config(OversizePolicy::Trim, 1000).limit_for("qwen2.5-coder"), 1000);
+        assert_eq!(config(OversizePolicy::Trim, 100_000).limit_for("mistral"), 8192 - 512);
+    }
+
+    #[test]
+    fn test_oversized_prompt_keeps_its_start_and_end() {
+        let prompt = format!("Review this code:\n{}\nWhat is wrong?", "let x = 1;\n".repeat(400));
+        let trimmed = fit_prompt(&config(OversizePolicy::Trim, 200), "llama3", &prompt).unwrap();
+        assert!(ContextManager::estimate_tokens(&trimmed) <= 200);
+        assert!(trimmed.starts_with("Review this code:\nlet x = 1;\n"));
+        assert!(trimmed.ends_with("let x = 1;\n\nWhat is wrong?"));
+        assert!(trimmed.contains("words trimmed to fit the context window"));
+
+        let error = fit_prompt(&config(OversizePolicy::Reject, 200), "llama3", &prompt).unwrap_err();
+        assert_eq!(error.limit, 200);
+        assert!(error.tokens > 200);
+        assert_eq!(fit_prompt(&config(OversizePolicy::Reject, 200), "llama3", "short").unwrap(), "short");
+    }
+
+    #[test]
+    fn test_oversized_chat_drops_old_turns_before_trimming() {
+        let history = vec![
+            message("system", "You are a reviewer."),
+            message("user", &"old question ".repeat(60)),
+            message("assistant", &"old answer ".repeat(60)),
+            message("user", "What does this function return?"),
+        ];
+        let fitted = fit_messages(&config(OversizePolicy::Trim, 100), "llama3", history.clone()).unwrap();
+        let roles: Vec<&str> = fitted.iter().map(|message| message.role.as_str()).collect();
+        assert_eq!(roles, vec!["system", "user"]);
+        assert_eq!(fitted[1].content, "What does this function return?");
+
+        let long_request = vec![message("system", "You are a reviewer."), message("user", &"fn main() {}\n".repeat(300))];
+        let fitted = fit_messages(&config(OversizePolicy::Trim, 100), "llama3", long_request.clone()).unwrap();
+        assert_eq!(fitted[0].content, "You are a reviewer.");
+        let tokens: usize = fitted.iter().map(|message| ContextManager::estimate_tokens(&message.content)).sum();
+        assert!(tokens <= 100);
+
+        assert!(fit_messages(&config(OversizePolicy::Reject, 100), "llama3", history).is_err());
+        assert!(fit_messages(&config(OversizePolicy::Trim, 10), "llama3", vec![message("system", &"rule ".repeat(50))]).is_err());
+    }
+}
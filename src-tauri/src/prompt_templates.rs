@@ -0,0 +1,326 @@
+//! Prompt templates with `{{variable}}` substitution
+//!
+//! Built-in templates can be overridden by `<name>.txt` files in an overrides
+//! directory. Substituted values are inserted verbatim and never re-scanned, and
+//! `\{{` renders a literal `{{`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+/// Built-in templates: name, variables it may use, and the template text
+const BUILTIN_TEMPLATES: &[(&str, &[&str], &str)] = &[
+    (
+        "analysis.question",
+        &["context", "stage"],
+        "Given this problem context: {{context}}\n\nAnd considering this stage of analysis: {{stage}}\n\nAsk one specific, insightful question that will deepen understanding. Be concise and focused:",
+    ),
+    (
+        "analysis.answer",
+        &["context", "question"],
+        "Context: {{context}}\n\nQuestion: {{question}}\n\nProvide a thoughtful, detailed answer:",
+    ),
+    (
+        "analysis.systematic_stage",
+        &["context", "stage_name", "stage_question"],
+        "Problem Context: {{context}}\n\n{{stage_name}} Stage: {{stage_question}}\n\nProvide a structured analysis for this stage:",
+    ),
+    (
+        "analysis.synthesis",
+        &["context"],
+        "Based on all the analysis and reasoning below, provide a clear, actionable solution:\n\n{{context}}\n\nFinal Solution:",
+    ),
+    (
+        "code.analyze",
+        &["language", "code"],
+        "Analyze the following {{language}} code and provide suggestions and identify errors:\n\n```{{language}}\n{{code}}\n```",
+    ),
+    (
+        "code.fix",
+        &["language", "error_message", "start_line", "start_character", "end_line", "end_character", "code"],
+        "Fix the following {{language}} code that has an error: '{{error_message}}' at line {{start_line}}:{{start_character}} to {{end_line}}:{{end_character}}:\n\n```{{language}}\n{{code}}\n```",
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateError {
+    MissingVariable(String),
+    /// Byte offset of a `{{` without a closing `}}`
+    Unclosed(usize),
+    UnknownTemplate(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::MissingVariable(name) => write!(f, "Missing template variable: {}", name),
+            TemplateError::Unclosed(offset) => write!(f, "Unclosed '{{{{' at byte {}", offset),
+            TemplateError::UnknownTemplate(name) => write!(f, "Unknown prompt template: {}", name),
+        }
+    }
+}
+
+impl Error for TemplateError {}
+
+/// Substitute `{{name}}` placeholders in `template` with `variables`
+pub fn render(template: &str, variables: &[(&str, &str)]) -> Result<String, TemplateError> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        if rest[..start].ends_with('\\') {
+            output.push_str(&rest[..start - 1]);
+            output.push_str("{{");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or(TemplateError::Unclosed(template.len() - rest.len() + start))?;
+        let name = after[..end].trim();
+        let value = variables
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| *value)
+            .ok_or_else(|| TemplateError::MissingVariable(name.to_string()))?;
+        output.push_str(value);
+        rest = &after[end + 2..];
+    }
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+/// A template as shown to the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub name: String,
+    pub variables: Vec<String>,
+    pub template: String,
+    pub default_template: String,
+    pub overridden: bool,
+}
+
+/// Built-in templates plus user overrides, optionally persisted to a directory
+#[derive(Debug, Default)]
+pub struct PromptTemplates {
+    overrides_dir: Option<PathBuf>,
+    overrides: HashMap<String, String>,
+}
+
+impl PromptTemplates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load overrides from `dir`; files that don't match a built-in or fail to render are skipped
+    pub fn load(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        fs::create_dir_all(dir)?;
+        let mut templates = Self {
+            overrides_dir: Some(dir.to_path_buf()),
+            overrides: HashMap::new(),
+        };
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("txt") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+
+            let template = fs::read_to_string(&path)?;
+            match validate(name, &template) {
+                Ok(()) => {
+                    templates.overrides.insert(name.to_string(), template);
+                }
+                Err(e) => eprintln!("Ignoring prompt template override {}: {}", path.display(), e),
+            }
+        }
+
+        Ok(templates)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.overrides
+            .get(name)
+            .map(String::as_str)
+            .or_else(|| builtin(name).map(|(_, template)| template))
+    }
+
+    pub fn render(&self, name: &str, variables: &[(&str, &str)]) -> Result<String, TemplateError> {
+        let template = self.get(name).ok_or_else(|| TemplateError::UnknownTemplate(name.to_string()))?;
+        render(template, variables)
+    }
+
+    pub fn list(&self) -> Vec<PromptTemplate> {
+        BUILTIN_TEMPLATES
+            .iter()
+            .map(|(name, variables, default_template)| PromptTemplate {
+                name: name.to_string(),
+                variables: variables.iter().map(|variable| variable.to_string()).collect(),
+                template: self.get(name).unwrap_or(default_template).to_string(),
+                default_template: default_template.to_string(),
+                overridden: self.overrides.contains_key(*name),
+            })
+            .collect()
+    }
+
+    /// Replace a built-in template; the override may only use that template's variables
+    pub fn set_override(&mut self, name: &str, template: &str) -> Result<(), Box<dyn Error>> {
+        validate(name, template)?;
+
+        if let Some(dir) = &self.overrides_dir {
+            fs::write(dir.join(format!("{}.txt", name)), template)?;
+        }
+        self.overrides.insert(name.to_string(), template.to_string());
+
+        Ok(())
+    }
+
+    /// Go back to the built-in template
+    pub fn reset(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+        if builtin(name).is_none() {
+            return Err(TemplateError::UnknownTemplate(name.to_string()).into());
+        }
+
+        if self.overrides.remove(name).is_some() {
+            if let Some(dir) = &self.overrides_dir {
+                fs::remove_file(dir.join(format!("{}.txt", name)))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn builtin(name: &str) -> Option<(&'static [&'static str], &'static str)> {
+    BUILTIN_TEMPLATES
+        .iter()
+        .find(|(builtin_name, _, _)| *builtin_name == name)
+        .map(|(_, variables, template)| (*variables, *template))
+}
+
+/// Check that `template` renders with exactly the variables its built-in provides
+fn validate(name: &str, template: &str) -> Result<(), TemplateError> {
+    let (variables, _) = builtin(name).ok_or_else(|| TemplateError::UnknownTemplate(name.to_string()))?;
+    let placeholders: Vec<(&str, &str)> = variables.iter().map(|variable| (*variable, "")).collect();
+    render(template, &placeholders).map(|_| ())
+}
+
+/// Templates shared by everything that builds prompts
+pub fn templates() -> &'static RwLock<PromptTemplates> {
+    static TEMPLATES: OnceLock<RwLock<PromptTemplates>> = OnceLock::new();
+    TEMPLATES.get_or_init(|| RwLock::new(PromptTemplates::new()))
+}
+
+/// Load overrides from `dir` into the shared templates
+pub fn load_overrides(dir: &Path) -> Result<(), Box<dyn Error>> {
+    let loaded = PromptTemplates::load(dir)?;
+    *templates().write().unwrap() = loaded;
+    Ok(())
+}
+
+/// Render a shared template. Overrides are validated when set, so this only falls
+/// back to the built-in text if something unexpected slipped through.
+pub fn render_prompt(name: &str, variables: &[(&str, &str)]) -> String {
+    let rendered = templates().read().unwrap().render(name, variables);
+    rendered.unwrap_or_else(|e| {
+        eprintln!("Failed to render prompt template {}: {}", name, e);
+        let (_, template) = builtin(name).expect("prompt template is built in");
+        render(template, variables).expect("built-in prompt template renders")
+    })
+}
+
+#[tauri::command]
+pub fn list_prompt_templates() -> Result<Vec<PromptTemplate>, String> {
+    Ok(templates().read().map_err(|e| format!("Failed to read prompt templates: {}", e))?.list())
+}
+
+#[tauri::command]
+pub fn set_prompt_template(name: String, template: String) -> Result<(), String> {
+    templates()
+        .write()
+        .map_err(|e| format!("Failed to update prompt templates: {}", e))?
+        .set_override(&name, &template)
+        .map_err(|e| format!("Failed to set prompt template: {}", e))
+}
+
+#[tauri::command]
+pub fn reset_prompt_template(name: String) -> Result<(), String> {
+    templates()
+        .write()
+        .map_err(|e| format!("Failed to update prompt templates: {}", e))?
+        .reset(&name)
+        .map_err(|e| format!("Failed to reset prompt template: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("prompt_templates_test_{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_render_substitutes_variables() {
+        let rendered = render("Fix {{ language }} code:\n{{code}}", &[("language", "rust"), ("code", "fn main() {}")]).unwrap();
+        assert_eq!(rendered, "Fix rust code:\nfn main() {}");
+    }
+
+    #[test]
+    fn test_render_does_not_rescan_values_and_honours_escapes() {
+        let rendered = render("\\{{literal}} {{value}}", &[("value", "{{code}}")]).unwrap();
+        assert_eq!(rendered, "{{literal}} {{code}}");
+    }
+
+    #[test]
+    fn test_render_reports_missing_and_unclosed_variables() {
+        assert_eq!(render("Hello {{name}}", &[]), Err(TemplateError::MissingVariable("name".to_string())));
+        assert_eq!(render("Hello {{name", &[("name", "x")]), Err(TemplateError::Unclosed(6)));
+    }
+
+    #[test]
+    fn test_override_changes_rendered_prompt() {
+        let mut templates = PromptTemplates::new();
+        let variables = [("context", "ctx"), ("question", "why?")];
+        assert!(templates.render("analysis.answer", &variables).unwrap().starts_with("Context: ctx"));
+
+        templates.set_override("analysis.answer", "Answer {{question}} using {{context}}").unwrap();
+        assert_eq!(templates.render("analysis.answer", &variables).unwrap(), "Answer why? using ctx");
+
+        templates.reset("analysis.answer").unwrap();
+        assert!(templates.render("analysis.answer", &variables).unwrap().starts_with("Context: ctx"));
+    }
+
+    #[test]
+    fn test_override_with_unknown_variable_is_rejected() {
+        let mut templates = PromptTemplates::new();
+
+        assert!(templates.set_override("analysis.answer", "{{unknown}}").is_err());
+        assert!(templates.set_override("not.a.template", "hi").is_err());
+        assert!(!templates.list().iter().any(|template| template.overridden));
+    }
+
+    #[test]
+    fn test_overrides_persist_to_directory() {
+        let dir = temp_dir();
+        let mut templates = PromptTemplates::load(&dir).unwrap();
+        templates.set_override("analysis.synthesis", "Summarize:\n{{context}}").unwrap();
+
+        let reloaded = PromptTemplates::load(&dir).unwrap();
+        assert_eq!(reloaded.render("analysis.synthesis", &[("context", "notes")]).unwrap(), "Summarize:\nnotes");
+        assert!(reloaded.list().iter().any(|template| template.name == "analysis.synthesis" && template.overridden));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
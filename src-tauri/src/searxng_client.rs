@@ -6,6 +6,7 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
+use crate::health_history::{history_cutoff, HealthHistory, HealthHistoryReport};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SearchResult {
@@ -23,7 +24,7 @@ pub struct SearchResponse {
 }
 
 /// Health monitoring configuration for SearXNG
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SearXNGHealthConfig {
     pub check_interval_seconds: u64,
     pub timeout_seconds: u64,
@@ -80,6 +81,7 @@ impl Default for SearXNGHealthStats {
 pub struct SearXNGHealthMonitor {
     config: SearXNGHealthConfig,
     stats: Arc<Mutex<SearXNGHealthStats>>,
+    history: Arc<Mutex<HealthHistory>>,
     is_monitoring: Arc<AtomicBool>,
 }
 
@@ -88,6 +90,7 @@ impl SearXNGHealthMonitor {
         Self {
             config,
             stats: Arc::new(Mutex::new(SearXNGHealthStats::default())),
+            history: Arc::new(Mutex::new(HealthHistory::default())),
             is_monitoring: Arc::new(AtomicBool::new(false)),
         }
     }
@@ -144,6 +147,18 @@ impl SearXNGHealthMonitor {
         let new_time = response_time.as_millis() as f64;
         stats.average_response_time_ms = 
             ((stats.average_response_time_ms * (total_checks - 1.0)) + new_time) / total_checks;
+
+        self.history.lock().await.record(now, success, response_time);
+    }
+
+    /// Recent check results with uptime over that window
+    pub async fn get_history(&self) -> HealthHistoryReport {
+        self.history.lock().await.report("searxng")
+    }
+
+    /// Drop history older than `max_age`
+    pub async fn trim_history(&self, max_age: Duration) -> usize {
+        self.history.lock().await.trim_before(history_cutoff(max_age))
     }
 
     /// Check if service should be considered available (healthy or degraded but functional)
@@ -370,6 +385,15 @@ impl SearXNGClient {
         self.health_monitor.get_stats().await
     }
 
+    /// Get recent health check results
+    pub async fn get_health_history(&self) -> HealthHistoryReport {
+        self.health_monitor.get_history().await
+    }
+
+    pub async fn trim_health_history(&self, max_age: Duration) -> usize {
+        self.health_monitor.trim_history(max_age).await
+    }
+
     /// Check if the service is currently available (healthy or degraded but functional)
     pub async fn is_available(&self) -> bool {
         self.health_monitor.is_available().await
@@ -1,6 +1,8 @@
 use crate::searxng_client::{SearXNGClient, SearchResult, SearXNGHealthStats};
 use crate::user_errors::{ToUserError, common};
+use crate::settings_store::SettingsStore;
 use tauri::State;
+use tokio::sync::Mutex;
 
 #[tauri::command]
 pub async fn check_searxng_connection(
@@ -22,6 +24,26 @@ pub async fn check_searxng_connection(
     }
 }
 
+/// Point SearXNG at a new endpoint without restarting; returns whether it is reachable there
+#[tauri::command]
+pub async fn set_searxng_base_url(
+    base_url: String,
+    searxng_client: State<'_, SearXNGClient>,
+    settings_store: State<'_, Mutex<SettingsStore>>,
+) -> Result<bool, String> {
+    {
+        let mut store = settings_store.lock().await;
+        let mut settings = store.get().clone();
+        settings.searxng.base_url = base_url.clone();
+        store.update(settings).map_err(|e| format!("Failed to update SearXNG base URL: {}", e))?;
+    }
+    
+    let client = searxng_client.inner();
+    client.set_base_url(base_url).await;
+    
+    Ok(client.check_connection().await.unwrap_or(false))
+}
+
 #[tauri::command]
 pub async fn get_available_engines(
     searxng_client: State<'_, SearXNGClient>,
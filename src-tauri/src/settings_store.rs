@@ -0,0 +1,392 @@
+use crate::analysis_engine::{AnalysisConfig, AnalysisMode};
+use crate::chroma_manager::{CacheConfig, ChromaManager, HybridSearchConfig, KeywordConfig, MemoryLimitConfig, QueryLimits};
+use crate::document_chunker::ChunkingConfig;
+use crate::document_types::DocumentTypeConfig;
+use crate::history_storage::HistoryBackend;
+use crate::model_aliases::{ModelAliases, ModelRole};
+use crate::ollama_client::{HealthConfig, SharedOllamaClient};
+use crate::ollama_rate_limit::RateLimitConfig;
+use crate::prompt_guard::PromptLimitConfig;
+use crate::searxng_client::{SearXNGClient, SearXNGHealthConfig};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+use tokio::sync::Mutex;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OllamaSettings {
+    pub base_url: String,
+    pub default_model: Option<String>,
+    pub embedding_model: String,
+    /// Models behind the `chat`, `code`, `embed`, `fast` and `reasoning` aliases
+    pub model_aliases: ModelAliases,
+    pub health: HealthConfig,
+    /// Limits shared by every request to Ollama; read at startup
+    pub rate_limit: RateLimitConfig,
+    /// Cap on prompt size and what happens to prompts over it
+    pub prompt_limit: PromptLimitConfig,
+}
+
+impl Default for OllamaSettings {
+    fn default() -> Self {
+        Self {
+            base_url: "http://localhost:11434".to_string(),
+            default_model: None,
+            embedding_model: ModelRole::Embed.alias().to_string(),
+            model_aliases: ModelAliases::default(),
+            health: HealthConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            prompt_limit: PromptLimitConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChromaSettings {
+    pub cache: CacheConfig,
+    pub memory_limit: MemoryLimitConfig,
+    pub document_types: DocumentTypeConfig,
+    /// Detect the language of documents added without one
+    pub infer_languages: bool,
+    pub query_limits: QueryLimits,
+    /// Stopwords dropped from keyword queries
+    pub keywords: KeywordConfig,
+    pub hybrid_search: HybridSearchConfig,
+    /// How long documents are split when ingested
+    pub chunking: ChunkingConfig,
+}
+
+impl Default for ChromaSettings {
+    fn default() -> Self {
+        Self {
+            cache: CacheConfig::default(),
+            memory_limit: MemoryLimitConfig::default(),
+            document_types: DocumentTypeConfig::default(),
+            infer_languages: true,
+            query_limits: QueryLimits::default(),
+            keywords: KeywordConfig::default(),
+            hybrid_search: HybridSearchConfig::default(),
+            chunking: ChunkingConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearxngSettings {
+    pub base_url: String,
+    pub health: SearXNGHealthConfig,
+}
+
+impl Default for SearxngSettings {
+    fn default() -> Self {
+        Self {
+            base_url: "http://localhost:8080".to_string(),
+            health: SearXNGHealthConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnalysisSettings {
+    pub max_rounds: usize,
+    pub save_to_rag: bool,
+    pub batch_concurrency: usize,
+    pub synthesis_token_budget: usize,
+    pub early_stop_confidence: Option<f32>,
+}
+
+impl Default for AnalysisSettings {
+    fn default() -> Self {
+        let config = AnalysisConfig::default();
+        Self {
+            max_rounds: config.max_rounds,
+            save_to_rag: config.save_to_rag,
+            batch_concurrency: config.batch_concurrency,
+            synthesis_token_budget: config.synthesis_token_budget,
+            early_stop_confidence: config.early_stop_confidence,
+        }
+    }
+}
+
+impl AnalysisSettings {
+    /// Analysis configuration for `mode` using these settings as defaults
+    pub fn to_config(&self, mode: AnalysisMode) -> AnalysisConfig {
+        AnalysisConfig {
+            mode,
+            max_rounds: self.max_rounds,
+            save_to_rag: self.save_to_rag,
+            batch_concurrency: self.batch_concurrency,
+            synthesis_token_budget: self.synthesis_token_budget,
+            early_stop_confidence: self.early_stop_confidence,
+            ..Default::default()
+        }
+    }
+}
+
+/// Context window limits; read when the ContextManager is created at startup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextSettings {
+    pub max_tokens: usize,
+    pub reserved_tokens: usize,
+}
+
+impl Default for ContextSettings {
+    fn default() -> Self {
+        // Matches ContextManager::default()
+        Self {
+            max_tokens: 128_000,
+            reserved_tokens: 25_600,
+        }
+    }
+}
+
+/// A kind of stored user data that can be purged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataScope {
+    ChatHistory,
+    /// Every ChromaDB collection except the reasoning patterns
+    RagCollections,
+    /// The extraction cache and cached query results
+    Caches,
+    InteractionLogs,
+    ReasoningPatterns,
+}
+
+/// Background maintenance; read when the scheduler is created at startup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceSettings {
+    pub enabled: bool,
+    /// Random delay added to each run, as a fraction of the task's interval
+    pub jitter_fraction: f64,
+    pub health_history_trim_interval_seconds: u64,
+    pub health_history_max_age_seconds: u64,
+    /// Purge data older than this many days; nothing is purged automatically when unset
+    pub auto_purge_after_days: Option<u64>,
+    pub auto_purge_scopes: Vec<DataScope>,
+    pub auto_purge_interval_seconds: u64,
+    /// Deleted chat sessions can be restored for this long before they are removed for good
+    pub deleted_session_grace_days: u64,
+    pub deleted_session_cleanup_interval_seconds: u64,
+}
+
+impl Default for MaintenanceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            jitter_fraction: 0.1,
+            health_history_trim_interval_seconds: 600,
+            health_history_max_age_seconds: 86_400,
+            auto_purge_after_days: None,
+            auto_purge_scopes: vec![DataScope::ChatHistory, DataScope::Caches, DataScope::InteractionLogs],
+            auto_purge_interval_seconds: 86_400,
+            deleted_session_grace_days: 7,
+            deleted_session_cleanup_interval_seconds: 3_600,
+        }
+    }
+}
+
+/// Chat history storage; read when the HistoryManager is created at startup
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistorySettings {
+    /// Switching to SQLite imports the existing JSON sessions the first time
+    pub backend: HistoryBackend,
+}
+
+/// Application settings, grouped by the service they configure
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub ollama: OllamaSettings,
+    pub chroma: ChromaSettings,
+    pub searxng: SearxngSettings,
+    pub analysis: AnalysisSettings,
+    pub context: ContextSettings,
+    pub history: HistorySettings,
+    pub maintenance: MaintenanceSettings,
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, url) in [("Ollama", &self.ollama.base_url), ("SearXNG", &self.searxng.base_url)] {
+            if !(url.starts_with("http://") || url.starts_with("https://")) {
+                return Err(format!("{} base URL must start with http:// or https://: {}", name, url));
+            }
+        }
+        if self.ollama.embedding_model.trim().is_empty() {
+            return Err("Embedding model cannot be empty".to_string());
+        }
+        if self.ollama.health.failure_threshold == 0 || self.ollama.health.recovery_threshold == 0 {
+            return Err("Ollama health thresholds must be at least 1".to_string());
+        }
+        self.ollama.model_aliases.validate()?;
+        self.ollama.rate_limit.validate()?;
+        self.ollama.prompt_limit.validate()?;
+        if self.chroma.cache.max_entries == 0 {
+            return Err("Cache max entries must be at least 1".to_string());
+        }
+        let memory_limit = &self.chroma.memory_limit;
+        if [memory_limit.max_documents, memory_limit.max_bytes, memory_limit.max_embedding_bytes].contains(&Some(0)) {
+            return Err("Chroma memory limits must be at least 1 when set".to_string());
+        }
+        self.chroma.query_limits.validate()?;
+        self.chroma.hybrid_search.validate()?;
+        self.chroma.chunking.validate()?;
+        if self.analysis.max_rounds == 0 {
+            return Err("Analysis max rounds must be at least 1".to_string());
+        }
+        if self.analysis.batch_concurrency == 0 {
+            return Err("Analysis batch concurrency must be at least 1".to_string());
+        }
+        if let Some(confidence) = self.analysis.early_stop_confidence {
+            if !(0.0..=1.0).contains(&confidence) {
+                return Err(format!("Early stop confidence must be between 0 and 1: {}", confidence));
+            }
+        }
+        if self.context.reserved_tokens >= self.context.max_tokens {
+            return Err(format!(
+                "Reserved tokens ({}) must be less than max tokens ({})",
+                self.context.reserved_tokens, self.context.max_tokens
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.maintenance.jitter_fraction) {
+            return Err(format!(
+                "Maintenance jitter fraction must be between 0 and 1: {}",
+                self.maintenance.jitter_fraction
+            ));
+        }
+        if self.chroma.cache.cleanup_interval_seconds == 0
+            || self.maintenance.health_history_trim_interval_seconds == 0
+            || self.maintenance.auto_purge_interval_seconds == 0
+            || self.maintenance.deleted_session_cleanup_interval_seconds == 0
+        {
+            return Err("Maintenance intervals must be at least 1 second".to_string());
+        }
+        if self.maintenance.auto_purge_after_days == Some(0) {
+            return Err("Auto-purge age must be at least 1 day when set".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Settings persisted as JSON in the app data directory
+pub struct SettingsStore {
+    path: PathBuf,
+    settings: Settings,
+}
+
+impl SettingsStore {
+    /// Load settings from `dir`, falling back to defaults when none have been saved
+    pub fn load(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(SETTINGS_FILE);
+
+        let settings = if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            serde_json::from_str(&content)?
+        } else {
+            Settings::default()
+        };
+
+        Ok(Self { path, settings })
+    }
+
+    pub fn get(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Validate and persist new settings
+    pub fn update(&mut self, settings: Settings) -> Result<(), Box<dyn Error>> {
+        settings.validate()?;
+
+        let content = serde_json::to_string_pretty(&settings)?;
+        fs::write(&self.path, content)?;
+        self.settings = settings;
+
+        Ok(())
+    }
+}
+
+/// Push settings that can change at runtime into the running services
+pub async fn apply_settings(
+    settings: &Settings,
+    ollama_client: &SharedOllamaClient,
+    searxng_client: &SearXNGClient,
+    chroma_manager: &Mutex<ChromaManager>,
+) {
+    {
+        let client = ollama_client.lock().await;
+        if client.get_base_url() != settings.ollama.base_url {
+            client.set_base_url(settings.ollama.base_url.clone()).await;
+        }
+        client.set_model_aliases(settings.ollama.model_aliases.clone());
+        client.set_prompt_limit(settings.ollama.prompt_limit.clone());
+    }
+
+    if searxng_client.get_base_url().await != settings.searxng.base_url {
+        searxng_client.set_base_url(settings.searxng.base_url.clone()).await;
+    }
+
+    let mut manager = chroma_manager.lock().await;
+    if manager.get_cache_config() != settings.chroma.cache {
+        manager.update_cache_config(settings.chroma.cache.clone());
+    }
+    if manager.get_memory_limit() != settings.chroma.memory_limit {
+        if let Err(e) = manager.set_memory_limit(settings.chroma.memory_limit.clone()) {
+            eprintln!("Failed to apply ChromaDB memory limit: {}", e);
+        }
+    }
+    if manager.get_document_types() != settings.chroma.document_types {
+        manager.set_document_types(settings.chroma.document_types.clone());
+    }
+    manager.set_language_inference(settings.chroma.infer_languages);
+    if let Err(e) = manager.set_query_limits(settings.chroma.query_limits.clone()) {
+        eprintln!("Failed to apply ChromaDB query limits: {}", e);
+    }
+    if let Err(e) = manager.set_hybrid_search_config(settings.chroma.hybrid_search.clone()) {
+        eprintln!("Failed to apply ChromaDB hybrid search settings: {}", e);
+    }
+    if let Err(e) = manager.set_chunking_config(settings.chroma.chunking.clone()) {
+        eprintln!("Failed to apply ChromaDB chunking settings: {}", e);
+    }
+    if manager.get_keyword_config() != settings.chroma.keywords {
+        manager.set_keyword_config(settings.chroma.keywords.clone());
+    }
+}
+
+#[tauri::command]
+pub async fn get_settings(
+    settings_store: State<'_, Mutex<SettingsStore>>,
+) -> Result<Settings, String> {
+    Ok(settings_store.lock().await.get().clone())
+}
+
+#[tauri::command]
+pub async fn update_settings(
+    settings: Settings,
+    settings_store: State<'_, Mutex<SettingsStore>>,
+    ollama_client: State<'_, SharedOllamaClient>,
+    searxng_client: State<'_, SearXNGClient>,
+    chroma_manager: State<'_, Mutex<ChromaManager>>,
+) -> Result<Settings, String> {
+    settings_store
+        .lock()
+        .await
+        .update(settings.clone())
+        .map_err(|e| format!("Failed to update settings: {}", e))?;
+
+    apply_settings(&settings, &ollama_client, &searxng_client, &chroma_manager).await;
+
+    Ok(settings)
+}
@@ -0,0 +1,257 @@
+//! Function signatures for LSP signature help
+//!
+//! Signatures are extracted from source text with lightweight per-language patterns,
+//! and the call surrounding the cursor is found by scanning back to its open paren.
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionSignature {
+    pub name: String,
+    /// e.g. `add(left: i32, right: i32) -> i32`
+    pub label: String,
+    pub parameters: Vec<String>,
+    pub documentation: Option<String>,
+}
+
+/// Languages that share a definition syntax
+fn language_family(language: &str) -> Option<&'static str> {
+    match language {
+        "rust" => Some("rust"),
+        "python" => Some("python"),
+        "javascript" | "typescript" | "jsx" | "tsx" => Some("javascript"),
+        _ => None,
+    }
+}
+
+fn definition_pattern(family: &str) -> &'static Regex {
+    static RUST: OnceLock<Regex> = OnceLock::new();
+    static PYTHON: OnceLock<Regex> = OnceLock::new();
+    static JAVASCRIPT: OnceLock<Regex> = OnceLock::new();
+    match family {
+        "rust" => RUST.get_or_init(|| {
+            Regex::new(r"\bfn\s+([A-Za-z_]\w*)\s*(?:<[^(]*>)?\s*\(").unwrap()
+        }),
+        "python" => PYTHON.get_or_init(|| Regex::new(r"\bdef\s+([A-Za-z_]\w*)\s*\(").unwrap()),
+        _ => JAVASCRIPT.get_or_init(|| {
+            Regex::new(r"\bfunction\s*\*?\s*([A-Za-z_$][\w$]*)\s*(?:<[^(]*>)?\s*\(").unwrap()
+        }),
+    }
+}
+
+/// All function definitions in `text`, in source order
+pub fn extract_signatures(text: &str, language: &str) -> Vec<FunctionSignature> {
+    let Some(family) = language_family(language) else {
+        return Vec::new();
+    };
+
+    definition_pattern(family)
+        .captures_iter(text)
+        .filter_map(|captures| {
+            let name = captures.get(1)?.as_str();
+            let open = captures.get(0)?.end();
+            let close = open + matching_paren(&text[open..])?;
+            let parameters: Vec<String> = split_parameters(&text[open..close])
+                .into_iter()
+                .filter(|parameter| !is_receiver(parameter, family))
+                .collect();
+
+            let mut label = format!("{}({})", name, parameters.join(", "));
+            if let Some(return_type) = return_type(&text[close + 1..]) {
+                label.push_str(" -> ");
+                label.push_str(return_type);
+            }
+
+            Some(FunctionSignature {
+                name: name.to_string(),
+                label,
+                parameters,
+                documentation: documentation(text, captures.get(0)?.start(), close, family),
+            })
+        })
+        .collect()
+}
+
+/// Signature from a label such as `add(left: i32, right: i32) -> i32`, e.g. one
+/// suggested by the AI service
+pub fn signature_from_label(name: &str, label: &str, documentation: Option<String>) -> Option<FunctionSignature> {
+    let label = label.trim().trim_matches('`').trim();
+    let open = label.find('(')? + 1;
+    let close = open + matching_paren(&label[open..])?;
+    Some(FunctionSignature {
+        name: name.to_string(),
+        label: label.to_string(),
+        parameters: split_parameters(&label[open..close]),
+        documentation: documentation.filter(|doc| !doc.trim().is_empty()),
+    })
+}
+
+/// Offset of the `)` closing a list whose `(` was just consumed
+fn matching_paren(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(index),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split a parameter list on top-level commas, ignoring commas inside generics,
+/// tuples, arrays and default values
+fn split_parameters(list: &str) -> Vec<String> {
+    let mut parameters = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (index, c) in list.char_indices() {
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            // `->` inside a closure type is not a closing angle bracket
+            '>' if !list[..index].ends_with('-') => depth -= 1,
+            ',' if depth == 0 => {
+                parameters.push(list[start..index].to_string());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parameters.push(list[start..].to_string());
+    parameters
+        .into_iter()
+        .map(|parameter| parameter.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|parameter| !parameter.is_empty())
+        .collect()
+}
+
+/// `self`-style receivers are not passed inside the call's parentheses
+fn is_receiver(parameter: &str, family: &str) -> bool {
+    match family {
+        "rust" => {
+            let name = parameter.split(':').next().unwrap_or("").trim();
+            name == "self" || name == "&self" || name.ends_with(" self")
+        }
+        "python" => matches!(parameter, "self" | "cls"),
+        _ => false,
+    }
+}
+
+/// Declared return type following the parameter list, if any
+fn return_type(rest: &str) -> Option<&str> {
+    let rest = rest.trim_start().strip_prefix("->")?;
+    let end = rest.find(['{', ';', '\n']).unwrap_or(rest.len());
+    let end = rest[..end].find(" where ").unwrap_or(end);
+    // Python annotations end with the colon that opens the body
+    Some(rest[..end].trim().trim_end_matches(':').trim_end()).filter(|return_type| !return_type.is_empty())
+}
+
+/// Comment lines directly above a definition, or a Python docstring directly below it
+fn documentation(text: &str, definition_start: usize, close: usize, family: &str) -> Option<String> {
+    if family == "python" {
+        let body = &text[close..];
+        let docstring = body.split_once('\n')?.1.trim_start();
+        let docstring = docstring.strip_prefix("\"\"\"").or_else(|| docstring.strip_prefix("'''"))?;
+        let end = docstring.find("\"\"\"").or_else(|| docstring.find("'''"))?;
+        return Some(docstring[..end].trim().to_string()).filter(|doc| !doc.is_empty());
+    }
+
+    let line_start = text[..definition_start].rfind('\n').map_or(0, |index| index + 1);
+    let mut lines: Vec<&str> = text[..line_start]
+        .lines()
+        .rev()
+        .map(str::trim)
+        .take_while(|line| {
+            line.starts_with("//") || line.starts_with("/*") || line.starts_with('*') || line.starts_with("#[")
+        })
+        .filter(|line| !line.starts_with("#["))
+        .map(|line| line.trim_end_matches("*/").trim_start_matches(['/', '*']).trim())
+        .filter(|line| !line.is_empty())
+        .collect();
+    lines.reverse();
+    Some(lines.join("\n")).filter(|doc| !doc.is_empty())
+}
+
+/// Name of the function whose argument list contains the end of `before_cursor`,
+/// and the index of the argument being typed
+pub fn active_call(before_cursor: &str) -> Option<(String, u32)> {
+    // Closing brackets passed over whose opening bracket hasn't been reached yet
+    let mut nesting = 0usize;
+    let mut active_parameter = 0u32;
+
+    for (index, c) in before_cursor.char_indices().rev() {
+        match c {
+            ')' | ']' | '}' => nesting += 1,
+            '(' | '[' | '{' if nesting > 0 => nesting -= 1,
+            '(' => {
+                let name_end = before_cursor[..index].trim_end();
+                let name_start = name_end
+                    .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$' || c == '!'))
+                    .map_or(0, |position| position + 1);
+                let name = name_end[name_start..].trim_end_matches('!');
+                let is_callable = !name.is_empty()
+                    && !name.starts_with(|c: char| c.is_ascii_digit())
+                    && !NON_CALL_KEYWORDS.contains(&name);
+                return is_callable.then(|| (name.to_string(), active_parameter));
+            }
+            ',' if nesting == 0 => active_parameter += 1,
+            // An unclosed array or block, or a statement end, means the cursor isn't in an argument list
+            '[' | '{' | ';' => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Keywords followed by a parenthesized condition rather than arguments
+const NON_CALL_KEYWORDS: [&str; 7] = ["if", "while", "for", "match", "switch", "return", "catch"];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extracts_signatures_with_docs() {
+        let rust = "/// Adds two numbers\n#[inline]\npub fn add(left: i32, right: HashMap<String, i32>) -> i32 {\n    0\n}\n\nimpl Point {\n    fn shift(&mut self, by: f32) {}\n}\n";
+        let signatures = extract_signatures(rust, "rust");
+        assert_eq!(signatures[0].label, "add(left: i32, right: HashMap<String, i32>) -> i32");
+        assert_eq!(signatures[0].parameters, vec!["left: i32", "right: HashMap<String, i32>"]);
+        assert_eq!(signatures[0].documentation.as_deref(), Some("Adds two numbers"));
+        assert_eq!(signatures[1].label, "shift(by: f32)");
+
+        let python = "class A:\n    def greet(self, name, punctuation='!') -> str:\n        \"\"\"Say hello.\"\"\"\n        pass\n";
+        let signature = &extract_signatures(python, "python")[0];
+        assert_eq!(signature.label, "greet(name, punctuation='!') -> str");
+        assert_eq!(signature.parameters, vec!["name", "punctuation='!'"]);
+        assert_eq!(signature.documentation.as_deref(), Some("Say hello."));
+
+        let javascript = "/**\n * Formats a value\n */\nfunction format(value, options = {a: 1, b: 2}) {}\n";
+        let signature = &extract_signatures(javascript, "typescript")[0];
+        assert_eq!(signature.parameters, vec!["value", "options = {a: 1, b: 2}"]);
+        assert_eq!(signature.documentation.as_deref(), Some("Formats a value"));
+    }
+
+    #[test]
+    fn test_active_call() {
+        assert_eq!(active_call("let x = add("), Some(("add".to_string(), 0)));
+        assert_eq!(active_call("let x = add(1, "), Some(("add".to_string(), 1)));
+        assert_eq!(active_call("add(compute(1, 2), [3, 4], "), Some(("add".to_string(), 2)));
+        assert_eq!(active_call("obj.method(a"), Some(("method".to_string(), 0)));
+        assert_eq!(active_call("println!(\"{}\", "), Some(("println".to_string(), 1)));
+        assert_eq!(active_call("add(1, 2);\nlet y = "), None);
+        assert_eq!(active_call("let y = (1 + "), None);
+        assert_eq!(active_call("if (ready && "), None);
+        assert_eq!(active_call("add(1, vec![2, "), None);
+    }
+
+    #[test]
+    fn test_signature_from_label() {
+        let signature = signature_from_label("fetch", "`fetch(url: &str, retries: u32) -> Result<String>`", None).unwrap();
+        assert_eq!(signature.label, "fetch(url: &str, retries: u32) -> Result<String>");
+        assert_eq!(signature.parameters, vec!["url: &str", "retries: u32"]);
+        assert!(signature_from_label("fetch", "no signature here", None).is_none());
+    }
+}
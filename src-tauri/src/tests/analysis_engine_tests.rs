@@ -0,0 +1,803 @@
+use crate::analysis_engine::*;
+use crate::context_manager::ContextManager;
+use crate::ollama_client::{GenerateOptions, OllamaClient};
+use mockito::{Matcher, Server};
+use tokio::time::Duration;
+
+/// Helper function to mock a generate call for one stage, identified by its options
+async fn mock_stage(server: &mut Server, options_json: &str, response: &str) -> mockito::Mock {
+    server
+        .mock("POST", "/api/generate")
+        .match_body(Matcher::PartialJsonString(format!(r#"{{"options": {}}}"#, options_json)))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(format!(r#"{{"model":"llama3","response":"{}","done":true}}"#, response))
+        .expect(1)
+        .create_async()
+        .await
+}
+
+fn config(mode: AnalysisMode, stage_options: AnalysisStageOptions) -> AnalysisConfig {
+    AnalysisConfig {
+        mode,
+        max_rounds: 1,
+        time_limit: Duration::from_secs(10),
+        save_to_rag: false,
+        stage_options,
+        ..Default::default()
+    }
+}
+
+fn temperature(temperature: f32) -> Option<GenerateOptions> {
+    Some(GenerateOptions {
+        temperature: Some(temperature),
+        max_tokens: None,
+        top_p: None,
+        top_k: None,
+    })
+}
+
+#[tokio::test]
+async fn test_question_override_temperature_is_sent() {
+    let mut server = Server::new_async().await;
+    let question = mock_stage(&mut server, r#"{"temperature": 0.9, "num_predict": 100}"#, "What is assumed?").await;
+    let answer = mock_stage(&mut server, r#"{"temperature": 0.3, "num_predict": 500}"#, "An answer").await;
+    let synthesis = mock_stage(&mut server, r#"{"temperature": 0.2, "num_predict": 800}"#, "A solution").await;
+
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), None);
+    let stage_options = AnalysisStageOptions {
+        question: temperature(0.9),
+        ..Default::default()
+    };
+
+    let result = engine
+        .analyze("Why is my build slow?", "llama3", config(AnalysisMode::Socratic, stage_options))
+        .await
+        .unwrap();
+
+    question.assert_async().await;
+    answer.assert_async().await;
+    synthesis.assert_async().await;
+    assert_eq!(result.solution, "A solution");
+}
+
+#[tokio::test]
+async fn test_answer_and_synthesis_overrides_are_sent() {
+    let mut server = Server::new_async().await;
+    let answer = mock_stage(&mut server, r#"{"temperature": 0.1, "num_predict": 500}"#, "Plan analysis").await;
+    let synthesis = mock_stage(&mut server, r#"{"temperature": 0.5, "num_predict": 1200}"#, "A solution").await;
+
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), None);
+    let stage_options = AnalysisStageOptions {
+        answer: temperature(0.1),
+        synthesis: Some(GenerateOptions {
+            temperature: Some(0.5),
+            max_tokens: Some(1200),
+            top_p: None,
+            top_k: None,
+        }),
+        ..Default::default()
+    };
+
+    engine
+        .analyze("Design a cache layer", "llama3", config(AnalysisMode::Systematic, stage_options))
+        .await
+        .unwrap();
+
+    answer.assert_async().await;
+    synthesis.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_default_stage_options_are_unchanged() {
+    let mut server = Server::new_async().await;
+    let question = mock_stage(&mut server, r#"{"temperature": 0.7, "num_predict": 100}"#, "What is assumed?").await;
+    let answer = mock_stage(&mut server, r#"{"temperature": 0.3, "num_predict": 500}"#, "An answer").await;
+    let synthesis = mock_stage(&mut server, r#"{"temperature": 0.2, "num_predict": 800}"#, "A solution").await;
+
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), None);
+
+    engine
+        .analyze("Why is my build slow?", "llama3", config(AnalysisMode::Socratic, AnalysisStageOptions::default()))
+        .await
+        .unwrap();
+
+    question.assert_async().await;
+    answer.assert_async().await;
+    synthesis.assert_async().await;
+}
+
+/// Helper function to mock every generate call with the same response
+async fn mock_any_generate(server: &mut Server, response: &str) -> mockito::Mock {
+    server
+        .mock("POST", "/api/generate")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(format!(r#"{{"model":"llama3","response":"{}","done":true}}"#, response))
+        .create_async()
+        .await
+}
+
+fn saving_config(mode: AnalysisMode) -> AnalysisConfig {
+    AnalysisConfig {
+        save_to_rag: true,
+        ..config(mode, AnalysisStageOptions::default())
+    }
+}
+
+#[tokio::test]
+async fn test_pattern_statistics_count_saved_patterns() {
+    let mut server = Server::new_async().await;
+    let _generate = mock_any_generate(&mut server, "Consider the evidence because it matters").await;
+
+    let chroma = crate::chroma_manager::ChromaManager::new("test_db").unwrap();
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), Some(chroma));
+
+    let socratic = engine
+        .analyze("Why does this error happen when I fix the config?", "llama3", saving_config(AnalysisMode::Socratic))
+        .await
+        .unwrap();
+    let systematic = engine
+        .analyze("Design the architecture for a plugin system", "llama3", saving_config(AnalysisMode::Systematic))
+        .await
+        .unwrap();
+    assert!(socratic.saved_to_rag && systematic.saved_to_rag);
+
+    let stats = engine.get_pattern_statistics().await;
+
+    assert_eq!(stats.total_patterns, 2);
+    assert_eq!(stats.by_mode.get("socratic"), Some(&1));
+    assert_eq!(stats.by_mode.get("systematic"), Some(&1));
+    assert_eq!(stats.by_problem_type.get("debugging"), Some(&1));
+    assert_eq!(stats.by_problem_type.get("design"), Some(&1));
+    let expected_confidence = (socratic.confidence + systematic.confidence) / 2.0;
+    assert!((stats.average_confidence - expected_confidence).abs() < 0.01);
+}
+
+#[tokio::test]
+async fn test_pattern_statistics_without_chroma_are_empty() {
+    let engine = AnalysisEngine::new(OllamaClient::new(None), None);
+
+    let stats = engine.get_pattern_statistics().await;
+
+    assert_eq!(stats.total_patterns, 0);
+    assert!(stats.by_mode.is_empty());
+    assert_eq!(stats.average_confidence, 0.0);
+}
+
+const WEAK_ANSWER: &str = "ok";
+const STRONG_ANSWER: &str = "Consider the alternative because the evidence shows the assumption is wrong, therefore the implication and consequence of this choice matter. We should consider every alternative and weigh the evidence before we commit to the final approach here.";
+
+async fn analyze_with_answer(server: &mut Server, engine: &mut AnalysisEngine, prompt: &str, answer: &str) -> DeepAnalysisResult {
+    server.reset_async().await;
+    let _generate = mock_any_generate(server, answer).await;
+    engine
+        .analyze(prompt, "llama3", saving_config(AnalysisMode::Socratic))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_repeated_analysis_does_not_duplicate_patterns() {
+    let mut server = Server::new_async().await;
+    let chroma = crate::chroma_manager::ChromaManager::new("test_db").unwrap();
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), Some(chroma));
+
+    for _ in 0..3 {
+        analyze_with_answer(&mut server, &mut engine, "Why does my build fail?", STRONG_ANSWER).await;
+    }
+
+    assert_eq!(engine.get_pattern_statistics().await.total_patterns, 1);
+}
+
+#[tokio::test]
+async fn test_higher_confidence_pattern_replaces_weaker_duplicate() {
+    let mut server = Server::new_async().await;
+    let chroma = crate::chroma_manager::ChromaManager::new("test_db").unwrap();
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), Some(chroma));
+
+    let weak = analyze_with_answer(&mut server, &mut engine, "Why does my build fail?", WEAK_ANSWER).await;
+    let strong = analyze_with_answer(&mut server, &mut engine, "Why does my build fail?", STRONG_ANSWER).await;
+    assert!(strong.confidence > weak.confidence);
+    assert!(strong.saved_to_rag);
+
+    let stats = engine.get_pattern_statistics().await;
+    assert_eq!(stats.total_patterns, 1);
+    assert!((stats.average_confidence - strong.confidence).abs() < 0.01);
+}
+
+#[tokio::test]
+async fn test_lower_confidence_duplicate_is_skipped() {
+    let mut server = Server::new_async().await;
+    let chroma = crate::chroma_manager::ChromaManager::new("test_db").unwrap();
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), Some(chroma));
+
+    let strong = analyze_with_answer(&mut server, &mut engine, "Why does my build fail?", STRONG_ANSWER).await;
+    let weak = analyze_with_answer(&mut server, &mut engine, "why does my build FAIL", WEAK_ANSWER).await;
+    assert!(!weak.saved_to_rag);
+
+    let stats = engine.get_pattern_statistics().await;
+    assert_eq!(stats.total_patterns, 1);
+    assert!((stats.average_confidence - strong.confidence).abs() < 0.01);
+}
+
+#[tokio::test]
+async fn test_different_prompts_are_saved_separately() {
+    let mut server = Server::new_async().await;
+    let chroma = crate::chroma_manager::ChromaManager::new("test_db").unwrap();
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), Some(chroma));
+
+    analyze_with_answer(&mut server, &mut engine, "Why does my build fail?", STRONG_ANSWER).await;
+    analyze_with_answer(&mut server, &mut engine, "How should I design a plugin architecture?", STRONG_ANSWER).await;
+
+    assert_eq!(engine.get_pattern_statistics().await.total_patterns, 2);
+}
+
+fn round(round: usize, confidence: f32, marker: &str) -> QuestionAnswerChain {
+    QuestionAnswerChain {
+        question: format!("Question {}", marker),
+        answer: format!("Answer {} {}", marker, "with supporting detail ".repeat(10)),
+        round,
+        timestamp: "2025-06-01T12:00:00Z".to_string(),
+        confidence,
+        key_insights: Vec::new(),
+    }
+}
+
+fn varied_chain() -> Vec<QuestionAnswerChain> {
+    vec![round(1, 0.4, "weak"), round(2, 0.9, "strong"), round(3, 0.6, "middling")]
+}
+
+#[test]
+fn test_synthesis_context_orders_rounds_by_confidence() {
+    let context = build_synthesis_context("Why is the build slow?", &varied_chain(), 10_000);
+
+    let strong = context.find("Answer strong").unwrap();
+    let middling = context.find("Answer middling").unwrap();
+    let weak = context.find("Answer weak").unwrap();
+    assert!(strong < middling && middling < weak);
+    assert!(context.starts_with("Why is the build slow?"));
+}
+
+#[test]
+fn test_synthesis_context_ranks_nan_confidence_last() {
+    let mut chain = varied_chain();
+    chain.insert(0, round(0, f32::NAN, "unscored"));
+    let context = build_synthesis_context("Why is the build slow?", &chain, 10_000);
+
+    let positions: Vec<usize> = ["Answer strong", "Answer middling", "Answer weak", "Answer unscored"]
+        .iter()
+        .map(|marker| context.find(marker).unwrap())
+        .collect();
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_synthesis_context_drops_low_confidence_rounds_first() {
+    let full = build_synthesis_context("Why is the build slow?", &varied_chain(), 10_000);
+    let weak_only = build_synthesis_context("Why is the build slow?", &[round(1, 0.4, "weak")], 10_000);
+    let base = build_synthesis_context("Why is the build slow?", &[], 10_000);
+    let round_tokens = ContextManager::estimate_tokens(&weak_only) - ContextManager::estimate_tokens(&base);
+
+    // Room for the problem and two rounds, but not all three
+    let tight_budget = ContextManager::estimate_tokens(&full) - round_tokens / 2;
+    let context = build_synthesis_context("Why is the build slow?", &varied_chain(), tight_budget);
+    assert!(context.contains("Answer strong"));
+    assert!(context.contains("Answer middling"));
+    assert!(!context.contains("Answer weak"));
+
+    // Room for only one round keeps the strongest
+    let tighter_budget = ContextManager::estimate_tokens(&base) + round_tokens + 2;
+    let context = build_synthesis_context("Why is the build slow?", &varied_chain(), tighter_budget);
+    assert!(context.contains("Answer strong"));
+    assert!(!context.contains("Answer middling"));
+    assert!(!context.contains("Answer weak"));
+}
+
+#[test]
+fn test_synthesis_context_keeps_problem_when_budget_is_exhausted() {
+    let context = build_synthesis_context("Why is the build slow?", &varied_chain(), 0);
+
+    assert!(context.starts_with("Why is the build slow?"));
+    assert!(!context.contains("Insight from Round"));
+}
+
+#[test]
+fn test_select_analysis_mode_for_representative_prompts() {
+    let cases = [
+        ("What is a closure?", "Standard"),
+        ("Explain how async runtimes schedule tasks", "Standard"),
+        ("Debug why my login handler returns an error after the session expires", "Socratic"),
+        ("Design the architecture for a multi-tenant billing service", "Socratic"),
+        ("Implement a REST endpoint that creates new user accounts", "Systematic"),
+        ("Refactor the payment module to use the strategy pattern", "Systematic"),
+    ];
+
+    for (prompt, expected) in cases {
+        assert_eq!(format!("{:?}", select_analysis_mode(prompt)), expected, "prompt: {}", prompt);
+    }
+}
+
+#[tokio::test]
+async fn test_auto_mode_resolves_before_analysis() {
+    let mut server = Server::new_async().await;
+    let generate = server
+        .mock("POST", "/api/generate")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"model":"llama3","response":"A closure captures its environment","done":true}"#)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), None);
+    let result = engine
+        .analyze("What is a closure?", "llama3", config(AnalysisMode::Auto, AnalysisStageOptions::default()))
+        .await
+        .unwrap();
+
+    generate.assert_async().await;
+    assert!(matches!(result.mode_used, AnalysisMode::Standard));
+}
+
+fn early_stop_config(early_stop_confidence: Option<f32>, stop_on_confidence_plateau: bool) -> AnalysisConfig {
+    AnalysisConfig {
+        max_rounds: 4,
+        early_stop_confidence,
+        stop_on_confidence_plateau,
+        ..config(AnalysisMode::Socratic, AnalysisStageOptions::default())
+    }
+}
+
+async fn mock_generate_calls(server: &mut Server, response: &str, calls: usize) -> mockito::Mock {
+    server
+        .mock("POST", "/api/generate")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(format!(r#"{{"model":"llama3","response":"{}","done":true}}"#, response))
+        .expect(calls)
+        .create_async()
+        .await
+}
+
+#[tokio::test]
+async fn test_socratic_stops_when_confidence_is_high() {
+    let mut server = Server::new_async().await;
+    // One question, one answer, then synthesis
+    let generate = mock_generate_calls(&mut server, STRONG_ANSWER, 3).await;
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), None);
+
+    let result = engine
+        .analyze("Why does my build fail?", "llama3", early_stop_config(Some(0.9), false))
+        .await
+        .unwrap();
+
+    generate.assert_async().await;
+    assert!(result.stopped_early);
+    assert_eq!(result.reasoning.len(), 1);
+}
+
+#[tokio::test]
+async fn test_socratic_stops_when_confidence_plateaus() {
+    let mut server = Server::new_async().await;
+    // Two rounds at the confidence ceiling, then synthesis
+    let generate = mock_generate_calls(&mut server, STRONG_ANSWER, 5).await;
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), None);
+
+    let result = engine
+        .analyze("Why does my build fail?", "llama3", early_stop_config(None, true))
+        .await
+        .unwrap();
+
+    generate.assert_async().await;
+    assert!(result.stopped_early);
+    assert_eq!(result.reasoning.len(), 2);
+}
+
+#[tokio::test]
+async fn test_socratic_runs_all_rounds_without_early_stop() {
+    let mut server = Server::new_async().await;
+    let generate = mock_generate_calls(&mut server, STRONG_ANSWER, 9).await;
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), None);
+
+    let result = engine
+        .analyze("Why does my build fail?", "llama3", early_stop_config(None, false))
+        .await
+        .unwrap();
+
+    generate.assert_async().await;
+    assert!(!result.stopped_early);
+    assert_eq!(result.reasoning.len(), 4);
+}
+
+async fn mock_structured_rounds(server: &mut Server, round_json: &str, calls: usize) -> mockito::Mock {
+    let body = serde_json::json!({ "model": "llama3", "response": round_json, "done": true });
+    server
+        .mock("POST", "/api/generate")
+        .match_body(Matcher::PartialJsonString(r#"{"format": {"type": "object"}}"#.to_string()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(body.to_string())
+        .expect(calls)
+        .create_async()
+        .await
+}
+
+fn structured_config(mode: AnalysisMode) -> AnalysisConfig {
+    AnalysisConfig {
+        max_rounds: 2,
+        structured_output: true,
+        early_stop_confidence: None,
+        stop_on_confidence_plateau: false,
+        ..config(mode, AnalysisStageOptions::default())
+    }
+}
+
+#[tokio::test]
+async fn test_structured_socratic_rounds_populate_chain() {
+    let mut server = Server::new_async().await;
+    let rounds = mock_structured_rounds(
+        &mut server,
+        r#"{"question": "Which step is slow?", "answer": "Linking dominates", "confidence": 0.82, "key_insights": ["Linking is the bottleneck", "Try a faster linker"]}"#,
+        2,
+    )
+    .await;
+    let synthesis = mock_stage(&mut server, r#"{"temperature": 0.2, "num_predict": 800}"#, "Use mold").await;
+
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), None);
+    let result = engine
+        .analyze("Why is my build slow?", "llama3", structured_config(AnalysisMode::Socratic))
+        .await
+        .unwrap();
+
+    rounds.assert_async().await;
+    synthesis.assert_async().await;
+    assert_eq!(result.reasoning.len(), 2);
+    let first = &result.reasoning[0];
+    assert_eq!(first.question, "Which step is slow?");
+    assert_eq!(first.answer, "Linking dominates");
+    assert!((first.confidence - 0.82).abs() < f32::EPSILON);
+    assert_eq!(first.key_insights, vec!["Linking is the bottleneck", "Try a faster linker"]);
+    assert_eq!(result.solution, "Use mold");
+}
+
+#[tokio::test]
+async fn test_structured_systematic_rounds_keep_stage_questions() {
+    let mut server = Server::new_async().await;
+    let _rounds = mock_structured_rounds(
+        &mut server,
+        r#"{"question": "ignored", "answer": "Scope the plugin API", "confidence": 1.7, "key_insights": ["Start small"]}"#,
+        2,
+    )
+    .await;
+    let _synthesis = mock_stage(&mut server, r#"{"temperature": 0.2, "num_predict": 800}"#, "Plan done").await;
+
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), None);
+    let result = engine
+        .analyze("Design a plugin system", "llama3", structured_config(AnalysisMode::Systematic))
+        .await
+        .unwrap();
+
+    assert!(result.reasoning[0].question.starts_with("Plan Stage:"));
+    assert_eq!(result.reasoning[0].answer, "Scope the plugin API");
+    assert_eq!(result.reasoning[0].key_insights, vec!["Start small"]);
+    // Out-of-range confidence from the model is clamped
+    assert_eq!(result.reasoning[0].confidence, 1.0);
+}
+
+#[tokio::test]
+async fn test_structured_round_falls_back_to_raw_text() {
+    let mut server = Server::new_async().await;
+    let _rounds = mock_structured_rounds(&mut server, "Linking dominates the build", 1).await;
+    let _synthesis = mock_stage(&mut server, r#"{"temperature": 0.2, "num_predict": 800}"#, "Use mold").await;
+
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), None);
+    let result = engine
+        .analyze("Why is my build slow?", "llama3", AnalysisConfig { max_rounds: 1, ..structured_config(AnalysisMode::Socratic) })
+        .await
+        .unwrap();
+
+    let round = &result.reasoning[0];
+    assert_eq!(round.answer, "Linking dominates the build");
+    assert!(round.key_insights.is_empty());
+    assert!(round.question.starts_with("What assumptions"));
+}
+
+#[tokio::test]
+async fn test_resume_analysis_extends_saved_chain() {
+    let mut server = Server::new_async().await;
+    let _generate = mock_any_generate(&mut server, STRONG_ANSWER).await;
+
+    let chroma = crate::chroma_manager::ChromaManager::new("test_db").unwrap();
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), Some(chroma));
+    let original_config = AnalysisConfig {
+        max_rounds: 2,
+        save_to_rag: true,
+        early_stop_confidence: None,
+        stop_on_confidence_plateau: false,
+        ..config(AnalysisMode::Socratic, AnalysisStageOptions::default())
+    };
+    let original = engine.analyze("Why does my build fail?", "llama3", original_config).await.unwrap();
+    assert!(original.saved_to_rag);
+
+    let chroma = engine.into_chroma_manager().unwrap();
+    let pattern_id = chroma.get_documents(REASONING_PATTERNS_COLLECTION)[0].id.clone();
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), Some(chroma));
+
+    let resume_config = AnalysisConfig { max_rounds: 2, ..Default::default() };
+    let resumed = engine
+        .resume_analysis(&pattern_id, "The failure only happens on CI runners", "llama3", &resume_config)
+        .await
+        .unwrap();
+
+    assert_eq!(resumed.reasoning.len(), 4);
+    assert_eq!(resumed.reasoning[0].question, original.reasoning[0].question);
+    assert_eq!(resumed.reasoning[1].answer, original.reasoning[1].answer);
+    assert_eq!(resumed.reasoning.iter().map(|qa| qa.round).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    assert!(resumed.saved_to_rag);
+
+    // The extended chain replaces the saved pattern rather than adding another
+    let saved = engine.load_reasoning_pattern(&pattern_id).unwrap();
+    assert_eq!(saved.reasoning.len(), 4);
+    assert_eq!(saved.original_prompt, "Why does my build fail?");
+    assert_eq!(engine.get_pattern_statistics().await.total_patterns, 1);
+}
+
+#[tokio::test]
+async fn test_resume_unknown_pattern_fails() {
+    let chroma = crate::chroma_manager::ChromaManager::new("test_db").unwrap();
+    let mut engine = AnalysisEngine::new(OllamaClient::new(None), Some(chroma));
+
+    let result = engine
+        .resume_analysis("deep_analysis_missing", "more info", "llama3", &AnalysisConfig::default())
+        .await;
+
+    assert!(result.unwrap_err().contains("not found"));
+}
+
+#[tokio::test]
+async fn test_resume_without_lending_the_manager() {
+    let mut server = Server::new_async().await;
+    let _generate = mock_any_generate(&mut server, STRONG_ANSWER).await;
+
+    let chroma = crate::chroma_manager::ChromaManager::new("test_db").unwrap();
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), Some(chroma));
+    let original_config = AnalysisConfig {
+        max_rounds: 1,
+        save_to_rag: true,
+        ..config(AnalysisMode::Systematic, AnalysisStageOptions::default())
+    };
+    engine.analyze("Why does my build fail?", "llama3", original_config).await.unwrap();
+    let mut chroma = engine.into_chroma_manager().unwrap();
+    let pattern_id = chroma.get_documents(REASONING_PATTERNS_COLLECTION)[0].id.clone();
+
+    let saved = AnalysisEngine::read_reasoning_pattern(&chroma, &pattern_id).unwrap();
+    let engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), None);
+    let resume_config = AnalysisConfig { max_rounds: 1, ..Default::default() };
+    let resumed = engine.continue_analysis(&saved, "Only on CI", "llama3", &resume_config).await.unwrap();
+    assert!(!resumed.saved_to_rag);
+    assert!(matches!(resumed.mode_used, AnalysisMode::Systematic));
+
+    assert!(AnalysisEngine::save_resumed_pattern(&mut chroma, &saved, "Only on CI", &resumed));
+    let reloaded = AnalysisEngine::read_reasoning_pattern(&chroma, &pattern_id).unwrap();
+    assert_eq!(reloaded.reasoning.len(), 2);
+    assert_eq!(chroma.get_documents(REASONING_PATTERNS_COLLECTION).len(), 1);
+}
+
+fn batch_prompts() -> Vec<String> {
+    vec![
+        "Why does my build fail?".to_string(),
+        "Why do my tests hang on CI?".to_string(),
+        "Why is the release binary slower?".to_string(),
+    ]
+}
+
+#[tokio::test]
+async fn test_batch_returns_one_result_per_prompt() {
+    let mut server = Server::new_async().await;
+    let _generate = mock_any_generate(&mut server, STRONG_ANSWER).await;
+
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), None);
+    let results = engine
+        .analyze_batch(&batch_prompts(), "llama3", config(AnalysisMode::Socratic, AnalysisStageOptions::default()))
+        .await;
+
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|result| matches!(result.as_ref().unwrap().mode_used, AnalysisMode::Socratic)));
+}
+
+#[tokio::test]
+async fn test_batch_retrieves_patterns_once() {
+    let mut server = Server::new_async().await;
+    let _generate = mock_any_generate(&mut server, STRONG_ANSWER).await;
+
+    let chroma = crate::chroma_manager::ChromaManager::new("test_db").unwrap();
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), Some(chroma));
+    let results = engine
+        .analyze_batch(&batch_prompts(), "llama3", config(AnalysisMode::Socratic, AnalysisStageOptions::default()))
+        .await;
+    assert_eq!(results.len(), 3);
+
+    let stats = engine.into_chroma_manager().unwrap().get_cache_stats();
+    assert_eq!(stats.total_hits + stats.total_misses, 1);
+}
+
+#[tokio::test]
+async fn test_batch_saves_each_result_after_analysis() {
+    let mut server = Server::new_async().await;
+    let _generate = mock_any_generate(&mut server, STRONG_ANSWER).await;
+
+    let chroma = crate::chroma_manager::ChromaManager::new("test_db").unwrap();
+    let mut engine = AnalysisEngine::new(OllamaClient::new(Some(server.url())), Some(chroma));
+    let results = engine
+        .analyze_batch(&batch_prompts(), "llama3", saving_config(AnalysisMode::Socratic))
+        .await;
+
+    assert!(results.iter().all(|result| result.as_ref().unwrap().saved_to_rag));
+    assert_eq!(engine.get_pattern_statistics().await.total_patterns, 3);
+}
+
+/// Provider that returns queued answers in order and records every prompt it was sent
+#[derive(Default)]
+struct ScriptedProvider {
+    answers: std::sync::Mutex<std::collections::VecDeque<String>>,
+    prompts: std::sync::Mutex<Vec<String>>,
+}
+
+impl ScriptedProvider {
+    fn new(answers: &[&str]) -> std::sync::Arc<Self> {
+        std::sync::Arc::new(Self {
+            answers: std::sync::Mutex::new(answers.iter().map(|answer| answer.to_string()).collect()),
+            prompts: std::sync::Mutex::new(Vec::new()),
+        })
+    }
+
+    fn next(&self, prompt: &str) -> Result<String, String> {
+        self.prompts.lock().unwrap().push(prompt.to_string());
+        self.answers.lock().unwrap().pop_front().ok_or_else(|| "Script ran out of answers".to_string())
+    }
+
+    fn calls(&self) -> usize {
+        self.prompts.lock().unwrap().len()
+    }
+}
+
+#[async_trait::async_trait]
+impl CompletionProvider for ScriptedProvider {
+    async fn complete(&self, _model: &str, prompt: &str, _options: Option<GenerateOptions>) -> Result<String, String> {
+        self.next(prompt)
+    }
+
+    async fn complete_structured(
+        &self,
+        _model: &str,
+        prompt: &str,
+        _options: Option<GenerateOptions>,
+        _format: serde_json::Value,
+    ) -> Result<String, String> {
+        self.next(prompt)
+    }
+}
+
+fn scripted_round(confidence: f32) -> String {
+    serde_json::json!({
+        "question": "What is slow?",
+        "answer": "Linking dominates",
+        "confidence": confidence,
+        "key_insights": ["Linking is the bottleneck"],
+    })
+    .to_string()
+}
+
+#[tokio::test]
+async fn test_scripted_rounds_stop_early_and_average_confidence() {
+    let rounds: Vec<String> = [0.5, 0.7, 0.95].iter().map(|&confidence| scripted_round(confidence)).collect();
+    let provider = ScriptedProvider::new(&[&rounds[0], &rounds[1], &rounds[2], "Use mold"]);
+    let mut engine = AnalysisEngine::with_provider(provider.clone(), None);
+
+    let result = engine
+        .analyze(
+            "Why is my build slow?",
+            "llama3",
+            AnalysisConfig { max_rounds: 5, early_stop_confidence: Some(0.9), ..structured_config(AnalysisMode::Socratic) },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(result.reasoning.len(), 3);
+    assert!(result.stopped_early);
+    assert_eq!(provider.calls(), 4);
+    assert_eq!(result.solution, "Use mold");
+    assert!((result.confidence - (0.5 + 0.7 + 0.95) / 3.0).abs() < 1e-6);
+    assert!(!result.saved_to_rag);
+}
+
+#[tokio::test]
+async fn test_scripted_rounds_respect_max_rounds_and_plateau() {
+    let rounds: Vec<String> = [0.4, 0.6, 0.8].iter().map(|&confidence| scripted_round(confidence)).collect();
+    let provider = ScriptedProvider::new(&[&rounds[0], &rounds[1], "Capped"]);
+    let mut engine = AnalysisEngine::with_provider(provider.clone(), None);
+
+    let capped = engine
+        .analyze("Why is my build slow?", "llama3", structured_config(AnalysisMode::Socratic))
+        .await
+        .unwrap();
+    assert_eq!(capped.reasoning.len(), 2);
+    assert!(!capped.stopped_early);
+    assert_eq!(provider.calls(), 3);
+
+    let rounds: Vec<String> = [0.6, 0.6, 0.8].iter().map(|&confidence| scripted_round(confidence)).collect();
+    let provider = ScriptedProvider::new(&[&rounds[0], &rounds[1], "Plateaued"]);
+    let mut engine = AnalysisEngine::with_provider(provider.clone(), None);
+    let plateaued = engine
+        .analyze(
+            "Why is my build slow?",
+            "llama3",
+            AnalysisConfig { max_rounds: 3, stop_on_confidence_plateau: true, ..structured_config(AnalysisMode::Socratic) },
+        )
+        .await
+        .unwrap();
+    assert_eq!(plateaued.reasoning.len(), 2);
+    assert!(plateaued.stopped_early);
+    assert_eq!(plateaued.solution, "Plateaued");
+}
+
+#[tokio::test]
+async fn test_scripted_analysis_is_saved_as_pattern() {
+    let round = scripted_round(0.8);
+    let provider = ScriptedProvider::new(&[&round, &round, "Use mold"]);
+    let chroma = crate::chroma_manager::ChromaManager::new("test_db").unwrap();
+    let mut engine = AnalysisEngine::with_provider(provider.clone(), Some(chroma));
+
+    let result = engine
+        .analyze(
+            "Why does my build fail?",
+            "llama3",
+            AnalysisConfig { save_to_rag: true, ..structured_config(AnalysisMode::Socratic) },
+        )
+        .await
+        .unwrap();
+
+    assert!(result.saved_to_rag);
+    assert!(provider.prompts.lock().unwrap()[0].contains("Why does my build fail?"));
+    let stats = engine.get_pattern_statistics().await;
+    assert_eq!(stats.total_patterns, 1);
+    assert_eq!(stats.by_mode.get("socratic"), Some(&1));
+    assert!((stats.average_confidence - 0.8).abs() < 0.01);
+}
+
+#[tokio::test]
+async fn test_batch_keeps_results_of_prompts_before_a_failure() {
+    let provider = ScriptedProvider::new(&["Cache the dependencies", "Raise the timeout"]);
+    let mut engine = AnalysisEngine::with_provider(provider, None);
+
+    let results = engine
+        .analyze_batch(
+            &batch_prompts(),
+            "llama3",
+            AnalysisConfig { batch_concurrency: 1, ..config(AnalysisMode::Standard, AnalysisStageOptions::default()) },
+        )
+        .await;
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap().solution, "Cache the dependencies");
+    assert_eq!(results[1].as_ref().unwrap().solution, "Raise the timeout");
+    assert!(results[2].as_ref().unwrap_err().contains("prompt 3"));
+}
+
+#[tokio::test]
+async fn test_zero_rounds_report_zero_confidence() {
+    for mode in [AnalysisMode::Socratic, AnalysisMode::Systematic] {
+        let provider = ScriptedProvider::new(&["Just ship it"]);
+        let mut engine = AnalysisEngine::with_provider(provider, None);
+
+        let result = engine
+            .analyze("Why is my build slow?", "llama3", AnalysisConfig { max_rounds: 0, ..config(mode, AnalysisStageOptions::default()) })
+            .await
+            .unwrap();
+
+        assert!(result.reasoning.is_empty());
+        assert_eq!(result.solution, "Just ship it");
+        assert_eq!(result.confidence, 0.0);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::chroma_manager::*;
+use crate::document_chunker::ChunkingConfig;
+use crate::commands::*;
+use crate::embedding_quantization::EmbeddingQuantization;
+use crate::ollama_client::OllamaClient;
+use crate::thread_pool_manager::ThreadPoolManager;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+fn metadata() -> DocumentMetadata {
+    DocumentMetadata {
+        source: "notes".to_string(),
+        document_type: "documentation".to_string(),
+        language: None,
+        timestamp: "2025-06-01T12:00:00Z".to_string(),
+        file_path: None,
+        url: None,
+        title: None,
+        additional: HashMap::new(),
+    }
+}
+
+/// The manager as the app manages it
+fn managed_manager() -> Mutex<ChromaManager> {
+    Mutex::new(ChromaManager::new("test_db").unwrap())
+}
+
+#[tokio::test]
+async fn test_set_embedding_quantization_on_managed_manager() {
+    let manager = managed_manager();
+
+    set_embedding_quantization(&manager, "docs", EmbeddingQuantization::Int8).await.unwrap();
+
+    let metadata = collection_metadata(&manager, "docs").await.unwrap();
+    assert_eq!(metadata.embedding_quantization, EmbeddingQuantization::Int8);
+}
+
+#[tokio::test]
+async fn test_collection_metadata_on_managed_manager() {
+    let manager = managed_manager();
+    assert!(collection_metadata(&manager, "docs").await.unwrap_err().contains("not found"));
+
+    manager.lock().await.set_collection_embedding_model("docs", Some("nomic-embed-text".to_string()));
+
+    let metadata = collection_metadata(&manager, "docs").await.unwrap();
+    assert_eq!(metadata.embedding_model.as_deref(), Some("nomic-embed-text"));
+}
+
+#[tokio::test]
+async fn test_compact_collection_on_managed_manager() {
+    let manager = managed_manager();
+    assert!(compact_collection(&manager, "docs").await.unwrap_err().contains("not found"));
+
+    manager.lock().await
+        .add_documents(
+            "docs",
+            vec!["same text".to_string(), "same text".to_string()],
+            vec![metadata(), metadata()],
+            Some(vec!["first".to_string(), "second".to_string()]),
+        )
+        .unwrap();
+
+    let stats = compact_collection(&manager, "docs").await.unwrap();
+    assert_eq!(stats.duplicates_removed, 1);
+    assert_eq!(stats.documents_remaining, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_query_collection_explains_hybrid_search() {
+    let mut server = mockito::Server::new_async().await;
+    let embeddings = server
+        .mock("POST", "/api/embeddings")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"embedding": [0.1, 0.2, 0.3]}"#)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let manager = managed_manager();
+    manager.lock().await.enable_batch_processing(OllamaClient::new(Some(server.url())), Arc::new(ThreadPoolManager::new()), None);
+    manager.lock().await
+        .add_documents(
+            "docs",
+            vec!["Borrow checker errors".to_string(), "Gardening tips".to_string()],
+            vec![metadata(), metadata()],
+            Some(vec!["borrow".to_string(), "garden".to_string()]),
+        )
+        .unwrap();
+
+    let request = QueryRequest {
+        collection_name: "docs".to_string(),
+        query_text: "borrow checker".to_string(),
+        n_results: 5,
+        filter: None,
+        explain: Some(true),
+        search_mode: Some(SearchMode::Hybrid),
+        priority: None,
+    };
+    let results = query_collection(&manager, request).await.unwrap();
+
+    embeddings.assert_async().await;
+    // The query was embedded through the batch processor's priority gate
+    assert_eq!(manager.lock().await.get_batch_stats().unwrap().total_batches_processed, 1);
+    assert_eq!(results.len(), 1);
+    let explain = results[0].explain.as_ref().unwrap();
+    assert_eq!(explain.rank, 1);
+    assert_eq!(explain.total_keywords, 2);
+    // Documents added without embeddings only match on keywords
+    assert_eq!(explain.cosine_score, None);
+}
+
+#[tokio::test]
+async fn test_ingest_documents_chunks_long_documents() {
+    let manager = managed_manager();
+    manager.lock().await
+        .set_chunking_config(ChunkingConfig { max_chunk_chars: 60, overlap_chars: 0 })
+        .unwrap();
+
+    let long = "First paragraph about the borrow checker.\n\nSecond paragraph about lifetimes and references.".to_string();
+    let request = AddDocumentsRequest {
+        collection_name: "docs".to_string(),
+        documents: vec![long, "A short note".to_string()],
+        metadatas: vec![metadata(), metadata()],
+        ids: Some(vec!["guide".to_string(), "note".to_string()]),
+    };
+    let ids = ingest_documents(&manager, request).await.unwrap();
+
+    assert_eq!(ids, vec!["guide#chunk0", "guide#chunk1", "note"]);
+    let manager = manager.lock().await;
+    let chunk = manager.get_documents("docs").into_iter().find(|document| document.id == "guide#chunk1").unwrap();
+    assert_eq!(chunk.metadata.additional["parent_id"], "guide");
+    assert_eq!(chunk.metadata.additional["chunk_count"], 2);
+    assert!(manager.get_documents("docs").iter().all(|document| document.id != "guide"));
+}
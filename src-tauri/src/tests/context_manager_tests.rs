@@ -578,4 +578,107 @@ async fn test_context_file_serialization() {
     assert_eq!(context_file.relevance_score, deserialized.relevance_score);
     assert_eq!(context_file.is_pinned, deserialized.is_pinned);
     assert_eq!(context_file.file_type, deserialized.file_type);
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_fit_prompt_keeps_newest_history() {
+    let manager = ContextManager::new(40, 10);
+    let sections = PromptSections {
+        system: None,
+        history: vec![
+            "oldest message ".repeat(5),
+            "middle message ".repeat(5),
+            "newest message".to_string(),
+        ],
+        rag_documents: vec![],
+        user: "question".to_string(),
+    };
+
+    let assembled = manager.fit_prompt(&sections).unwrap();
+
+    assert!(assembled.total_tokens <= 30);
+    assert!(assembled.prompt.contains("newest message"));
+    assert!(!assembled.prompt.contains("oldest message"));
+    assert!(assembled.dropped_history >= 1);
+}
+
+#[tokio::test]
+async fn test_fit_prompt_truncates_system_prompt() {
+    let manager = ContextManager::new(20, 5);
+    let sections = PromptSections {
+        system: Some("instruction ".repeat(50)),
+        user: "question".to_string(),
+        ..Default::default()
+    };
+
+    let assembled = manager.fit_prompt(&sections).unwrap();
+
+    assert!(assembled.system_truncated);
+    assert!(assembled.total_tokens <= 15);
+    assert!(assembled.prompt.ends_with("question"));
+}
+
+#[tokio::test]
+async fn test_fit_prompt_counts_section_headers() {
+    let manager = ContextManager::new(40, 10);
+    let sections = PromptSections {
+        history: vec!["earlier question".to_string(), "earlier answer".to_string()],
+        rag_documents: (0..6).map(|index| format!("relevant document number {} text", index)).collect(),
+        user: "question".to_string(),
+        ..Default::default()
+    };
+
+    let assembled = manager.fit_prompt(&sections).unwrap();
+
+    // The whole prompt, headers and labels included, stays within the budget
+    assert!(assembled.prompt.contains("[Document 1]"));
+    assert!(assembled.dropped_documents > 0);
+    assert_eq!(manager.count_tokens(&assembled.prompt), assembled.total_tokens);
+    assert!(assembled.total_tokens <= 30);
+}
+
+#[tokio::test]
+async fn test_fit_prompt_rejects_oversized_user_message() {
+    let manager = ContextManager::new(20, 5);
+    let sections = PromptSections {
+        user: "word ".repeat(100),
+        ..Default::default()
+    };
+
+    assert!(manager.fit_prompt(&sections).is_err());
+}
+
+#[tokio::test]
+async fn test_build_context_substitutes_summary_for_long_history() {
+    use crate::history_manager::{HistoryManager, SessionSummary};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut history = HistoryManager::load(dir.path().to_path_buf()).unwrap();
+    let session_id = history.create_session("Chat", "llama3").unwrap().id;
+    for index in 0..20 {
+        history.add_message(&session_id, "user", &format!("question {} {}", index, "about the cache ".repeat(10))).unwrap();
+    }
+    history.set_summary(&session_id, SessionSummary {
+        content: "Decided: in-process LRU cache, not Redis.".to_string(),
+        covers_through: 18,
+        created_at: chrono::Utc::now(),
+    }).unwrap();
+    let session = history.get_session(&session_id).unwrap();
+
+    // Below the threshold the full history is kept
+    let mut manager = create_test_manager();
+    manager.summary_threshold_tokens = 5000;
+    let full = manager.session_conversation(&session);
+    assert!(full.contains("user: question 0 "));
+    assert!(!full.contains("Decided"));
+
+    // Above it the summary replaces the messages it covers
+    manager.summary_threshold_tokens = 100;
+    let summarized = manager.session_conversation(&session);
+    assert!(summarized.contains("Decided: in-process LRU cache, not Redis."));
+    assert!(!summarized.contains("question 17 "));
+    assert!(summarized.contains("user: question 18 ") && summarized.contains("user: question 19 "));
+
+    let full_context = manager.build_context(&full, 0, vec![]).await.unwrap();
+    let summarized_context = manager.build_context(&summarized, 0, vec![]).await.unwrap();
+    assert!(summarized_context.budget.breakdown.conversation < full_context.budget.breakdown.conversation / 5);
+}
@@ -2,4 +2,8 @@ pub mod searxng_integration_tests;
 pub mod searxng_performance_tests;
 pub mod searxng_health_tests;
 pub mod operation_manager_tests;
-pub mod context_manager_tests;
\ No newline at end of file
+pub mod context_manager_tests;
+pub mod rag_generation_tests;
+pub mod analysis_engine_tests;
+pub mod settings_store_tests;
+pub mod chroma_commands_tests;
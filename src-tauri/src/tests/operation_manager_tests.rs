@@ -27,6 +27,9 @@ fn create_test_operation(
         timeout_ms: Some(5000),
         cancellable,
         payload: serde_json::json!({"test": "data"}),
+        completed_at: None,
+        memory_used: None,
+        enqueued_at: None,
     }
 }
 
@@ -435,6 +438,9 @@ async fn test_operation_timeout_handling() {
         timeout_ms: Some(100), // Very short timeout
         cancellable: true,
         payload: serde_json::json!({"test": "timeout"}),
+        completed_at: None,
+        memory_used: None,
+        enqueued_at: None,
     };
     
     assert_eq!(operation.timeout_ms, Some(100));
@@ -504,4 +510,202 @@ async fn test_serialization_deserialization() {
         OperationStatus::Running { progress } => assert_eq!(progress, Some(0.75)),
         _ => panic!("Deserialization failed"),
     }
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_cancel_operations_by_type() {
+    let resource_limits = ResourceLimits {
+        max_concurrent_operations: 4,
+        max_memory_usage: 1024,
+        max_cpu_usage: 80.0,
+        io_throttling: false,
+    };
+    let manager = OperationManager::new(resource_limits);
+    
+    let operations = vec![
+        create_test_operation("embed-1", OperationType::DocumentIndexing, OperationPriority::Normal, true),
+        create_test_operation("embed-2", OperationType::DocumentIndexing, OperationPriority::Normal, true),
+        create_test_operation("rag-1", OperationType::RagQuery, OperationPriority::Normal, true),
+        create_test_operation("embed-3", OperationType::DocumentIndexing, OperationPriority::Normal, true),
+    ];
+    for op in operations {
+        manager.enqueue_operation(op).await.unwrap();
+    }
+    
+    // Let some of them start running
+    time::sleep(Duration::from_millis(20)).await;
+    
+    assert_eq!(manager.cancel_operations_by_type(&OperationType::DocumentIndexing), 3);
+    for id in ["embed-1", "embed-2", "embed-3"] {
+        assert_eq!(manager.get_operation_status(id), Some(OperationStatus::Cancelled));
+    }
+    assert_ne!(manager.get_operation_status("rag-1"), Some(OperationStatus::Cancelled));
+    
+    // Nothing left of that type to cancel
+    assert_eq!(manager.cancel_operations_by_type(&OperationType::DocumentIndexing), 0);
+    
+    // Cancelled operations stay cancelled after the processor reaches them
+    time::sleep(Duration::from_millis(600)).await;
+    assert_eq!(manager.get_operation_status("embed-1"), Some(OperationStatus::Cancelled));
+    assert_eq!(manager.get_operation_status("embed-3"), Some(OperationStatus::Cancelled));
+}
+
+#[tokio::test]
+async fn test_cancel_all_operations() {
+    let resource_limits = ResourceLimits {
+        max_concurrent_operations: 1,
+        max_memory_usage: 1024,
+        max_cpu_usage: 80.0,
+        io_throttling: false,
+    };
+    let manager = OperationManager::new(resource_limits);
+    
+    let operations = vec![
+        create_test_operation("all-1", OperationType::AICompletion, OperationPriority::Normal, true),
+        create_test_operation("all-2", OperationType::FileAnalysis, OperationPriority::Normal, true),
+        create_test_operation("all-3", OperationType::ModelLoading, OperationPriority::Normal, false),
+        create_test_operation("all-4", OperationType::CodeGeneration, OperationPriority::Normal, true),
+    ];
+    for op in operations {
+        manager.enqueue_operation(op).await.unwrap();
+    }
+    
+    // Queued operations are cancellable even when not marked cancellable
+    assert_eq!(manager.cancel_all_operations(), 4);
+    assert_eq!(manager.cancel_all_operations(), 0);
+    
+    let counts = manager.get_operations_by_status();
+    assert_eq!(counts.get("cancelled"), Some(&4));
+}
+
+#[tokio::test]
+async fn test_list_operations_reflects_states() {
+    let resource_limits = ResourceLimits {
+        max_concurrent_operations: 4,
+        max_memory_usage: 1024,
+        max_cpu_usage: 80.0,
+        io_throttling: false,
+    };
+    let manager = OperationManager::new(resource_limits);
+    
+    manager.enqueue_operation(create_test_operation("list-running", OperationType::ModelLoading, OperationPriority::Normal, true)).await.unwrap();
+    time::sleep(Duration::from_millis(50)).await;
+    
+    manager.enqueue_operation(create_test_operation("list-background", OperationType::FileAnalysis, OperationPriority::Background, true)).await.unwrap();
+    manager.enqueue_operation(create_test_operation("list-critical", OperationType::RagQuery, OperationPriority::Critical, true)).await.unwrap();
+    manager.enqueue_operation(create_test_operation("list-cancelled", OperationType::AICompletion, OperationPriority::Normal, true)).await.unwrap();
+    manager.cancel_operation("list-cancelled").unwrap();
+    
+    let all = manager.list_operations(&OperationFilter::default());
+    assert_eq!(all.len(), 4);
+    assert_eq!(all[0].id, "list-critical");
+    assert_eq!(all[3].id, "list-background");
+    assert!(all.iter().all(|summary| summary.enqueued_at.is_some()));
+    
+    let running = all.iter().find(|summary| summary.id == "list-running").unwrap();
+    assert_eq!(running.state, OperationState::Running);
+    assert_eq!(running.op_type, OperationType::ModelLoading);
+    
+    let cancelled = all.iter().find(|summary| summary.id == "list-cancelled").unwrap();
+    assert_eq!(cancelled.state, OperationState::Cancelled);
+    assert_eq!(cancelled.status, OperationStatus::Cancelled);
+}
+
+#[tokio::test]
+async fn test_list_operations_filters_by_state() {
+    let manager = OperationManager::new(create_test_resource_limits());
+    
+    manager.enqueue_operation(create_test_operation("filter-1", OperationType::AICompletion, OperationPriority::Normal, true)).await.unwrap();
+    manager.enqueue_operation(create_test_operation("filter-2", OperationType::DocumentIndexing, OperationPriority::Normal, true)).await.unwrap();
+    manager.enqueue_operation(create_test_operation("filter-3", OperationType::DocumentIndexing, OperationPriority::Normal, true)).await.unwrap();
+    manager.cancel_operation("filter-2").unwrap();
+    
+    let cancelled = manager.list_operations(&OperationFilter {
+        states: Some(vec![OperationState::Cancelled]),
+        op_type: None,
+    });
+    assert_eq!(cancelled.iter().map(|summary| summary.id.as_str()).collect::<Vec<_>>(), vec!["filter-2"]);
+    
+    let active_indexing = manager.list_operations(&OperationFilter {
+        states: Some(vec![OperationState::Queued, OperationState::Running]),
+        op_type: Some(OperationType::DocumentIndexing),
+    });
+    assert_eq!(active_indexing.iter().map(|summary| summary.id.as_str()).collect::<Vec<_>>(), vec!["filter-3"]);
+    
+    // Once everything finishes, nothing is pending or running
+    time::sleep(Duration::from_millis(600)).await;
+    let active = manager.list_operations(&OperationFilter {
+        states: Some(vec![OperationState::Queued, OperationState::Running]),
+        op_type: None,
+    });
+    assert!(active.is_empty());
+}
+
+#[tokio::test]
+async fn test_operation_reports_progress() {
+    let manager = OperationManager::new(create_test_resource_limits());
+    let mut progress_rx = manager.subscribe_progress();
+    
+    let operation = create_test_operation("progress-op", OperationType::DocumentIndexing, OperationPriority::Normal, true);
+    manager.enqueue_task(operation, |progress| async move {
+        progress.report(0.25);
+        time::sleep(Duration::from_millis(50)).await;
+        progress.report(0.5);
+        time::sleep(Duration::from_millis(200)).await;
+        progress.report(1.0);
+        Ok(serde_json::json!({"result": "indexed with progress"}))
+    }).await.unwrap();
+    
+    time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(
+        manager.get_operation_status("progress-op"),
+        Some(OperationStatus::Running { progress: Some(0.5) })
+    );
+    
+    let first = progress_rx.recv().await.unwrap();
+    assert_eq!(first, OperationProgress { operation_id: "progress-op".to_string(), progress: 0.25 });
+    assert_eq!(progress_rx.recv().await.unwrap().progress, 0.5);
+    
+    time::sleep(Duration::from_millis(250)).await;
+    match manager.get_operation_status("progress-op") {
+        Some(OperationStatus::Completed { result }) => {
+            assert_eq!(result.unwrap()["result"], "indexed with progress");
+        }
+        other => panic!("Expected Completed status, got {:?}", other),
+    }
+    assert_eq!(progress_rx.recv().await.unwrap().progress, 1.0);
+}
+
+#[tokio::test]
+async fn test_progress_is_clamped_and_ignored_after_cancel() {
+    let manager = OperationManager::new(create_test_resource_limits());
+    
+    let operation = create_test_operation("clamped-op", OperationType::FileAnalysis, OperationPriority::Normal, true);
+    manager.enqueue_task(operation, |progress| async move {
+        progress.report(1.5);
+        time::sleep(Duration::from_millis(100)).await;
+        progress.report(0.75);
+        time::sleep(Duration::from_millis(200)).await;
+        Ok(serde_json::json!({}))
+    }).await.unwrap();
+    
+    time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(
+        manager.get_operation_status("clamped-op"),
+        Some(OperationStatus::Running { progress: Some(1.0) })
+    );
+    
+    manager.cancel_operation("clamped-op").unwrap();
+    time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(manager.get_operation_status("clamped-op"), Some(OperationStatus::Cancelled));
+}
+
+#[test]
+fn test_operation_priority_is_inherited_by_pool_tasks() {
+    use crate::thread_pool_manager::TaskPriority;
+
+    assert_eq!(TaskPriority::from(&OperationPriority::Critical), TaskPriority::Critical);
+    assert_eq!(TaskPriority::from(&OperationPriority::High), TaskPriority::High);
+    assert_eq!(TaskPriority::from(&OperationPriority::Normal), TaskPriority::Normal);
+    assert_eq!(TaskPriority::from(&OperationPriority::Background), TaskPriority::Low);
+    assert_eq!(TaskPriority::from(&OperationPriority::Maintenance), TaskPriority::Low);
+}
@@ -0,0 +1,205 @@
+use crate::chroma_manager::*;
+use crate::commands::*;
+use crate::ollama_client::OllamaClient;
+use crate::thread_pool_manager::ThreadPoolManager;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Helper function to create document metadata with a title
+fn create_metadata(source: &str, title: &str) -> DocumentMetadata {
+    DocumentMetadata {
+        source: source.to_string(),
+        document_type: "documentation".to_string(),
+        language: Some("rust".to_string()),
+        timestamp: "2025-06-01T12:00:00Z".to_string(),
+        file_path: None,
+        url: None,
+        title: Some(title.to_string()),
+        additional: HashMap::new(),
+    }
+}
+
+/// Helper function to create a seeded collection
+fn create_seeded_manager() -> ChromaManager {
+    let mut manager = ChromaManager::new("test_db").unwrap();
+    manager
+        .add_documents(
+            "docs",
+            vec![
+                "Tokio spawns tasks onto a multi-threaded runtime".to_string(),
+                "Serde derives Serialize and Deserialize".to_string(),
+            ],
+            vec![
+                create_metadata("tokio-docs", "Tokio runtime"),
+                create_metadata("serde-docs", "Serde derive"),
+            ],
+            Some(vec!["doc-tokio".to_string(), "doc-serde".to_string()]),
+        )
+        .unwrap();
+    manager
+}
+
+#[test]
+fn test_build_rag_prompt_includes_attributions() {
+    let mut manager = create_seeded_manager();
+    let (prompt, sources) = retrieve_rag_context(&mut manager, "tokio runtime tasks", "docs", 3).unwrap();
+
+    assert_eq!(sources.len(), 1);
+    assert_eq!(sources[0].id, "doc-tokio");
+    assert!(prompt.contains("[1] Tokio runtime\n"));
+    assert!(prompt.contains("Tokio spawns tasks onto a multi-threaded runtime"));
+    assert!(prompt.ends_with("User Question: tokio runtime tasks\n\nAnswer:"));
+}
+
+#[test]
+fn test_build_rag_prompt_without_results_is_plain_query() {
+    assert_eq!(build_rag_prompt("hello", &[]), "hello");
+}
+
+#[tokio::test]
+async fn test_rag_generate_grounds_prompt_and_returns_sources() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/api/generate")
+        .match_body(mockito::Matcher::Regex(
+            "Tokio spawns tasks onto a multi-threaded runtime".to_string(),
+        ))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"model":"test-model","response":"Use tokio::spawn [1]","done":true}"#)
+        .create_async()
+        .await;
+
+    let client = OllamaClient::new(Some(server.url()));
+    let manager = tokio::sync::Mutex::new(create_seeded_manager());
+
+    let response = rag_generate(&client, &manager, "How do tokio tasks work?", "docs", 3, "test-model")
+        .await
+        .unwrap();
+
+    mock.assert_async().await;
+    assert_eq!(response.answer, "Use tokio::spawn [1]");
+    assert_eq!(response.model, "test-model");
+    assert!(response.sources.iter().any(|source| source.id == "doc-tokio" && source.source == "tokio-docs"));
+}
+
+#[test]
+fn test_citations_come_from_metadata_with_fallbacks() {
+    let mut manager = ChromaManager::new("test_db").unwrap();
+    let with = |source: &str, title: Option<&str>, file_path: Option<&str>, url: Option<&str>| DocumentMetadata {
+        title: title.map(String::from),
+        file_path: file_path.map(String::from),
+        url: url.map(String::from),
+        ..create_metadata(source, "")
+    };
+    manager
+        .add_documents(
+            "docs",
+            vec![
+                "borrow checker rules for references".to_string(),
+                "borrow checker errors explained".to_string(),
+                "borrow checker and lifetimes".to_string(),
+                "borrow checker in loops".to_string(),
+            ],
+            vec![
+                with("rust-book", Some("References and Borrowing"), None, Some("https://doc.rust-lang.org/book/ch04-02.html")),
+                with("error-index", None, Some("docs/errors/E0502.md"), None),
+                with("blog", Some("  "), None, Some("https://blog.example.com/lifetimes")),
+                with("notes", None, None, None),
+            ],
+            Some(vec![
+                "doc-book-borrowing".to_string(),
+                "doc-e0502".to_string(),
+                "doc-blog".to_string(),
+                "doc-notes".to_string(),
+            ]),
+        )
+        .unwrap();
+
+    let mut results = manager.query("docs", "borrow checker", 4, None).unwrap();
+    results.sort_by(|a, b| a.id.cmp(&b.id));
+    let citations: Vec<&Citation> = results.iter().map(|result| &result.citation).collect();
+    assert_eq!(citations, vec![
+        &Citation {
+            label: "https://blog.example.com/lifetimes".to_string(),
+            link: Some("https://blog.example.com/lifetimes".to_string()),
+            short_id: "doc-blog".to_string(),
+        },
+        &Citation {
+            label: "References and Borrowing".to_string(),
+            link: Some("https://doc.rust-lang.org/book/ch04-02.html".to_string()),
+            short_id: "doc-book".to_string(),
+        },
+        &Citation {
+            label: "docs/errors/E0502.md".to_string(),
+            link: Some("docs/errors/E0502.md".to_string()),
+            short_id: "doc-e050".to_string(),
+        },
+        &Citation {
+            label: "notes".to_string(),
+            link: None,
+            short_id: "doc-note".to_string(),
+        },
+    ]);
+
+    assert_eq!(
+        format_citations(&results[..2]),
+        "[1] https://blog.example.com/lifetimes (doc-blog)\n\
+         [2] References and Borrowing (doc-book) <https://doc.rust-lang.org/book/ch04-02.html>"
+    );
+}
+
+#[test]
+fn test_stage_latency_percentiles() {
+    let samples: Vec<std::time::Duration> = (1..=100).rev().map(std::time::Duration::from_millis).collect();
+    let latency = StageLatency::from_samples(&samples);
+
+    assert_eq!(latency.samples, 100);
+    assert_eq!(latency.p50_ms, 50.0);
+    assert_eq!(latency.p95_ms, 95.0);
+    assert_eq!(latency.p99_ms, 99.0);
+    assert!((latency.mean_ms - 50.5).abs() < 1e-9);
+    assert_eq!(StageLatency::from_samples(&[]).p99_ms, 0.0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_benchmark_rag_times_each_stage_per_query() {
+    let mut server = mockito::Server::new_async().await;
+    let embeddings = server
+        .mock("POST", "/api/embeddings")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"embedding": [0.1, 0.2, 0.3]}"#)
+        .expect(3)
+        .create_async()
+        .await;
+    let generate = server
+        .mock("POST", "/api/generate")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"model":"test-model","response":"answer","done":true}"#)
+        .expect(3)
+        .create_async()
+        .await;
+
+    let client = OllamaClient::new(Some(server.url()));
+    let mut seeded = create_seeded_manager();
+    seeded.enable_batch_processing(client.clone(), Arc::new(ThreadPoolManager::new()), None);
+    let manager = tokio::sync::Mutex::new(seeded);
+    let queries = vec!["tokio tasks".to_string(), "serde derive".to_string(), "tokio runtime".to_string()];
+
+    let report = run_rag_benchmark(&client, &manager, "docs", &queries, "test-model", "embed-model")
+        .await
+        .unwrap();
+
+    embeddings.assert_async().await;
+    generate.assert_async().await;
+    assert_eq!(report.queries, 3);
+    for stage in [&report.embedding, &report.retrieval, &report.generation] {
+        assert_eq!(stage.samples, 3);
+        assert!(stage.p50_ms <= stage.p95_ms && stage.p95_ms <= stage.p99_ms);
+    }
+
+    let empty = run_rag_benchmark(&client, &manager, "docs", &[], "test-model", "embed-model").await;
+    assert!(empty.is_err());
+}
@@ -0,0 +1,122 @@
+use crate::chroma_manager::*;
+use crate::ollama_client::{OllamaClient, SharedOllamaClient};
+use crate::searxng_client::SearXNGClient;
+use crate::settings_store::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Helper function to create an empty settings directory
+fn temp_settings_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("settings_test_{}", uuid::Uuid::new_v4()))
+}
+
+fn create_metadata() -> DocumentMetadata {
+    DocumentMetadata {
+        source: "docs".to_string(),
+        document_type: "documentation".to_string(),
+        language: None,
+        timestamp: "2025-06-01T12:00:00Z".to_string(),
+        file_path: None,
+        url: None,
+        title: None,
+        additional: HashMap::new(),
+    }
+}
+
+#[test]
+fn test_missing_settings_file_loads_defaults() {
+    let dir = temp_settings_dir();
+    let store = SettingsStore::load(&dir).unwrap();
+
+    assert_eq!(store.get(), &Settings::default());
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_settings_persist_across_reload() {
+    let dir = temp_settings_dir();
+    let mut store = SettingsStore::load(&dir).unwrap();
+
+    let mut settings = store.get().clone();
+    settings.ollama.default_model = Some("llama3".to_string());
+    settings.chroma.cache.default_ttl_seconds = 30;
+    settings.analysis.max_rounds = 2;
+    settings.context.max_tokens = 32_000;
+    settings.context.reserved_tokens = 4_000;
+    store.update(settings.clone()).unwrap();
+
+    let reloaded = SettingsStore::load(&dir).unwrap();
+    assert_eq!(reloaded.get(), &settings);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_invalid_settings_are_rejected_and_not_saved() {
+    let dir = temp_settings_dir();
+    let mut store = SettingsStore::load(&dir).unwrap();
+
+    let mut settings = store.get().clone();
+    settings.context.reserved_tokens = settings.context.max_tokens;
+    assert!(store.update(settings).unwrap_err().to_string().contains("Reserved tokens"));
+
+    let mut settings = store.get().clone();
+    settings.searxng.base_url = "localhost:8080".to_string();
+    assert!(store.update(settings).is_err());
+
+    let mut settings = store.get().clone();
+    settings.chroma.query_limits.max_results = settings.chroma.query_limits.default_results - 1;
+    assert!(store.update(settings).unwrap_err().to_string().contains("Default query results"));
+
+    let mut settings = store.get().clone();
+    settings.chroma.chunking.overlap_chars = settings.chroma.chunking.max_chunk_chars;
+    assert!(store.update(settings).unwrap_err().to_string().contains("Chunk overlap"));
+
+    assert_eq!(store.get(), &Settings::default());
+    assert_eq!(SettingsStore::load(&dir).unwrap().get(), &Settings::default());
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_analysis_settings_build_config() {
+    let analysis = AnalysisSettings {
+        max_rounds: 2,
+        batch_concurrency: 1,
+        ..Default::default()
+    };
+    let config = analysis.to_config(crate::analysis_engine::AnalysisMode::Socratic);
+
+    assert_eq!(config.max_rounds, 2);
+    assert_eq!(config.batch_concurrency, 1);
+    assert!(matches!(config.mode, crate::analysis_engine::AnalysisMode::Socratic));
+}
+
+#[tokio::test]
+async fn test_cache_ttl_update_applies_to_running_cache() {
+    let mut manager = ChromaManager::new("test_db").unwrap();
+    manager
+        .add_documents("docs", vec!["Tokio spawns tasks".to_string()], vec![create_metadata()], None)
+        .unwrap();
+    manager.query("docs", "tokio", 1, None).unwrap();
+    manager.query("docs", "tokio", 1, None).unwrap();
+    assert_eq!(manager.get_cache_stats().total_hits, 1);
+
+    let chroma_manager = Mutex::new(manager);
+    let ollama_client: SharedOllamaClient = Arc::new(Mutex::new(OllamaClient::new(None)));
+    let searxng_client = SearXNGClient::new(None);
+
+    let mut settings = Settings::default();
+    settings.chroma.cache.default_ttl_seconds = 0;
+    settings.searxng.base_url = "http://search.local:8888".to_string();
+    apply_settings(&settings, &ollama_client, &searxng_client, &chroma_manager).await;
+
+    let mut manager = chroma_manager.lock().await;
+    assert_eq!(manager.get_cache_config().default_ttl_seconds, 0);
+
+    // Entries stored under the new TTL expire immediately
+    manager.query("docs", "tokio", 1, None).unwrap();
+    manager.query("docs", "tokio", 1, None).unwrap();
+    assert_eq!(manager.get_cache_stats().total_hits, 1);
+    assert_eq!(searxng_client.get_base_url().await, "http://search.local:8888");
+}
@@ -4,13 +4,25 @@
 //! to prevent blocking the main async runtime.
 
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::{oneshot, Semaphore};
+use tauri::State;
+use tokio::sync::oneshot;
 use tokio::task::JoinHandle;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use dashmap::DashMap;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+/// Every task type, in the order pools are reported
+const ALL_TASK_TYPES: [TaskType; 6] = [
+    TaskType::Embedding,
+    TaskType::CodeAnalysis,
+    TaskType::DocumentProcessing,
+    TaskType::FileSystemOps,
+    TaskType::Compression,
+    TaskType::General,
+];
+
 /// Different types of CPU-intensive tasks that require specialized handling
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum TaskType {
@@ -87,13 +99,131 @@ pub struct ThreadPoolStats {
     pub total_memory_used: usize,
 }
 
+/// Size and load of one task type's pool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolStats {
+    pub task_type: TaskType,
+    pub pool_size: usize,
+    pub active_workers: usize,
+    /// Tasks waiting for a free worker
+    pub queued_tasks: usize,
+    pub completed_tasks: u64,
+    /// Includes panicked tasks
+    pub failed_tasks: u64,
+    pub panicked_tasks: u64,
+}
+
+/// Admits tasks highest priority first, FIFO within a priority, so interactive work
+/// waiting for a worker isn't stuck behind queued background work
+pub struct PriorityGate {
+    state: std::sync::Mutex<GateState>,
+}
+
+struct GateState {
+    available: usize,
+    next_seq: u64,
+    waiters: BTreeMap<(TaskPriority, u64), oneshot::Sender<GatePermit>>,
+}
+
+/// A slot from a `PriorityGate`; handed to the next waiter, or returned, on drop
+pub struct GatePermit {
+    gate: Option<Arc<PriorityGate>>,
+}
+
+impl PriorityGate {
+    pub fn new(permits: usize) -> Arc<Self> {
+        Arc::new(Self {
+            state: std::sync::Mutex::new(GateState {
+                available: permits,
+                next_seq: 0,
+                waiters: BTreeMap::new(),
+            }),
+        })
+    }
+
+    pub async fn acquire(self: &Arc<Self>, priority: TaskPriority) -> GatePermit {
+        let receiver = {
+            let mut state = self.state.lock().unwrap();
+            if state.available > 0 && state.waiters.is_empty() {
+                state.available -= 1;
+                return GatePermit { gate: Some(self.clone()) };
+            }
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            let (sender, receiver) = oneshot::channel();
+            state.waiters.insert((priority, seq), sender);
+            receiver
+        };
+        // Senders are only dropped after sending, so this always receives a permit
+        receiver.await.expect("PriorityGate waiter dropped without a permit")
+    }
+
+    /// Add `count` slots, waking waiters first
+    pub fn add_permits(self: &Arc<Self>, count: usize) {
+        for _ in 0..count {
+            self.release();
+        }
+    }
+
+    pub fn available_permits(&self) -> usize {
+        self.state.lock().unwrap().available
+    }
+
+    pub fn waiting(&self) -> usize {
+        self.state.lock().unwrap().waiters.len()
+    }
+
+    /// Pass a slot to the highest-priority live waiter, or make it available.
+    /// The lock is released before sending so dropping a rejected permit can't deadlock.
+    fn release(self: &Arc<Self>) {
+        let mut permit = GatePermit { gate: Some(self.clone()) };
+        loop {
+            let waiter = {
+                let mut state = self.state.lock().unwrap();
+                match state.waiters.pop_first() {
+                    Some((_, waiter)) => waiter,
+                    None => {
+                        state.available += 1;
+                        permit.gate = None;
+                        return;
+                    }
+                }
+            };
+            // A waiter whose acquire was cancelled hands the permit back
+            match waiter.send(permit) {
+                Ok(()) => return,
+                Err(returned) => permit = returned,
+            }
+        }
+    }
+}
+
+impl GatePermit {
+    /// Consume the slot without returning it, shrinking the gate
+    pub fn forget(mut self) {
+        self.gate = None;
+    }
+}
+
+impl Drop for GatePermit {
+    fn drop(&mut self) {
+        if let Some(gate) = self.gate.take() {
+            gate.release();
+        }
+    }
+}
+
 /// Individual thread pool for a specific task type
 struct WorkerPool {
     config: ThreadPoolConfig,
-    semaphore: Arc<Semaphore>,
+    /// Current worker count; starts at `config.max_threads` and changes on resize
+    pool_size: AtomicUsize,
+    queued: AtomicUsize,
+    gate: Arc<PriorityGate>,
     stats: Arc<DashMap<String, TaskExecutionStats>>,
     task_count: Arc<std::sync::atomic::AtomicU64>,
     failed_count: Arc<std::sync::atomic::AtomicU64>,
+    panic_count: Arc<std::sync::atomic::AtomicU64>,
 }
 
 #[derive(Debug, Clone)]
@@ -106,10 +236,13 @@ struct TaskExecutionStats {
 impl WorkerPool {
     fn new(config: ThreadPoolConfig) -> Self {
         Self {
-            semaphore: Arc::new(Semaphore::new(config.max_threads)),
+            pool_size: AtomicUsize::new(config.max_threads),
+            queued: AtomicUsize::new(0),
+            gate: PriorityGate::new(config.max_threads),
             stats: Arc::new(DashMap::new()),
             task_count: Arc::new(std::sync::atomic::AtomicU64::new(0)),
             failed_count: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            panic_count: Arc::new(std::sync::atomic::AtomicU64::new(0)),
             config,
         }
     }
@@ -130,32 +263,31 @@ impl WorkerPool {
             memory_used: None,
         });
 
-        // Acquire semaphore permit
-        let permit = match self.semaphore.clone().acquire_owned().await {
-            Ok(permit) => permit,
-            Err(_) => {
-                return TaskResult {
-                    task_id,
-                    result: Err("Thread pool semaphore closed".to_string()),
-                    execution_time: start_time.elapsed(),
-                    memory_used: None,
-                };
-            }
-        };
+        // Wait for a worker; higher-priority tasks are admitted first
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        let permit = self.gate.acquire(task.priority.clone()).await;
+        self.queued.fetch_sub(1, Ordering::SeqCst);
 
         // Execute task on blocking thread pool
         let (tx, rx) = oneshot::channel();
         let task_timeout = task.timeout.unwrap_or(Duration::from_secs(self.config.task_timeout_seconds));
 
+        let panic_count = self.panic_count.clone();
         let handle: JoinHandle<()> = tokio::task::spawn_blocking(move || {
-            let result = executor(task.payload);
+            // A panicking executor becomes a task error instead of unwinding the worker. This
+            // needs panic = "unwind"; under "abort" the panic ends the process before it is caught
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| executor(task.payload)))
+                .unwrap_or_else(|panic| {
+                    panic_count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    Err(format!("Task panicked: {}", panic_message(panic.as_ref())))
+                });
             let _ = tx.send(result);
             drop(permit); // Release permit when done
         });
 
         // Wait for completion or timeout
         let result = tokio::select! {
-            Ok(result) = rx => result,
+            result = rx => result.unwrap_or_else(|_| Err("Task worker exited without a result".to_string())),
             _ = tokio::time::sleep(task_timeout) => {
                 handle.abort();
                 Err("Task execution timeout".to_string())
@@ -187,8 +319,8 @@ impl WorkerPool {
     }
 
     fn get_stats(&self) -> ThreadPoolStats {
-        let available_permits = self.semaphore.available_permits();
-        let active_threads = self.config.max_threads - available_permits;
+        let available_permits = self.gate.available_permits();
+        let active_threads = self.pool_size.load(Ordering::SeqCst).saturating_sub(available_permits);
         
         let completed_tasks = self.task_count.load(std::sync::atomic::Ordering::SeqCst);
         let failed_tasks = self.failed_count.load(std::sync::atomic::Ordering::SeqCst);
@@ -213,13 +345,48 @@ impl WorkerPool {
         ThreadPoolStats {
             active_threads,
             idle_threads: available_permits,
-            queued_tasks: 0, // TODO: Implement queue tracking
+            queued_tasks: self.queued.load(Ordering::SeqCst),
             completed_tasks,
             failed_tasks,
             average_execution_time_ms,
             total_memory_used: 0, // TODO: Implement memory tracking
         }
     }
+
+    fn get_pool_stats(&self, task_type: &TaskType) -> PoolStats {
+        let stats = self.get_stats();
+        PoolStats {
+            task_type: task_type.clone(),
+            pool_size: self.pool_size.load(Ordering::SeqCst),
+            active_workers: stats.active_threads,
+            queued_tasks: stats.queued_tasks,
+            completed_tasks: stats.completed_tasks,
+            failed_tasks: stats.failed_tasks,
+            panicked_tasks: self.panic_count.load(std::sync::atomic::Ordering::SeqCst),
+        }
+    }
+
+    /// Grow immediately; shrinking waits for enough running tasks to finish
+    async fn resize(&self, size: usize) -> Result<(), String> {
+        let current = self.pool_size.load(Ordering::SeqCst);
+        if size > current {
+            self.gate.add_permits(size - current);
+        } else if size < current {
+            for _ in size..current {
+                self.gate.acquire(TaskPriority::Critical).await.forget();
+            }
+        }
+        self.pool_size.store(size, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Text of a panic payload, which is usually a `&str` or `String`
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    panic.downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
 }
 
 /// Main thread pool manager
@@ -233,33 +400,34 @@ impl ThreadPoolManager {
         Self::new_with_config(ThreadPoolConfig::default())
     }
 
+    /// Pools sized from `size` workers instead of the CPU count
+    pub fn with_size(size: usize) -> Self {
+        Self::new_with_config(ThreadPoolConfig {
+            max_threads: size.max(1),
+            ..ThreadPoolConfig::default()
+        })
+    }
+
+    /// Pools sized relative to `default_config.max_threads`
     pub fn new_with_config(default_config: ThreadPoolConfig) -> Self {
         let mut pools = HashMap::new();
-        
-        // Create specialized pools for different task types
-        let task_types = vec![
-            TaskType::Embedding,
-            TaskType::CodeAnalysis,
-            TaskType::DocumentProcessing,
-            TaskType::FileSystemOps,
-            TaskType::Compression,
-            TaskType::General,
-        ];
+        let size = default_config.max_threads;
 
-        for task_type in task_types {
+        // Create specialized pools for different task types
+        for task_type in ALL_TASK_TYPES {
             let config = match task_type {
                 TaskType::Embedding => ThreadPoolConfig {
-                    max_threads: (num_cpus::get() / 2).max(1), // CPU intensive
+                    max_threads: (size / 2).max(1), // CPU intensive
                     queue_size: 50,
                     ..default_config.clone()
                 },
                 TaskType::CodeAnalysis => ThreadPoolConfig {
-                    max_threads: num_cpus::get().max(2),
+                    max_threads: size,
                     queue_size: 100,
                     ..default_config.clone()
                 },
                 TaskType::FileSystemOps => ThreadPoolConfig {
-                    max_threads: (num_cpus::get() * 2).min(8), // IO intensive
+                    max_threads: (size * 2).min(8), // IO intensive
                     queue_size: 200,
                     ..default_config.clone()
                 },
@@ -328,6 +496,23 @@ impl ThreadPoolManager {
         self.pools.get(task_type).map(|pool| pool.get_stats())
     }
 
+    /// Size, active workers, queue depth and completed counts for every pool
+    pub fn get_pool_stats(&self) -> Vec<PoolStats> {
+        ALL_TASK_TYPES.iter()
+            .filter_map(|task_type| self.pools.get(task_type).map(|pool| pool.get_pool_stats(task_type)))
+            .collect()
+    }
+
+    /// Change the number of workers for `task_type`
+    pub async fn resize_pool(&self, task_type: &TaskType, size: usize) -> Result<(), String> {
+        if size == 0 {
+            return Err("Thread pool size must be at least 1".to_string());
+        }
+        let pool = self.pools.get(task_type)
+            .ok_or_else(|| format!("No thread pool for {:?}", task_type))?;
+        pool.resize(size).await
+    }
+
     /// Create a task request with automatic ID generation
     pub fn create_task<T>(
         task_type: TaskType,
@@ -353,6 +538,22 @@ impl Default for ThreadPoolManager {
     }
 }
 
+#[tauri::command]
+pub fn get_thread_pool_stats(
+    thread_pool_manager: State<'_, Arc<ThreadPoolManager>>,
+) -> Result<Vec<PoolStats>, String> {
+    Ok(thread_pool_manager.get_pool_stats())
+}
+
+#[tauri::command]
+pub async fn resize_thread_pool(
+    task_type: TaskType,
+    size: usize,
+    thread_pool_manager: State<'_, Arc<ThreadPoolManager>>,
+) -> Result<(), String> {
+    thread_pool_manager.resize_pool(&task_type, size).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -423,4 +624,133 @@ mod tests {
         assert!(result.result.is_err());
         assert!(result.result.unwrap_err().contains("timeout"));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_configured_size_is_honored() {
+        let manager = ThreadPoolManager::with_size(3);
+        let stats = manager.get_pool_stats();
+
+        let size_of = |task_type: TaskType| stats.iter().find(|pool| pool.task_type == task_type).unwrap().pool_size;
+        assert_eq!(size_of(TaskType::General), 3);
+        assert_eq!(size_of(TaskType::Embedding), 1);
+        assert_eq!(size_of(TaskType::FileSystemOps), 6);
+        assert_eq!(stats.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_pool_stats_reflect_submitted_work() {
+        let manager = Arc::new(ThreadPoolManager::with_size(2));
+
+        let handles: Vec<_> = (0..5)
+            .map(|i| {
+                let manager = manager.clone();
+                tokio::spawn(async move {
+                    let task = ThreadPoolManager::create_task(TaskType::General, TaskPriority::Normal, i);
+                    manager.execute_task(task, |x| {
+                        std::thread::sleep(std::time::Duration::from_millis(200));
+                        Ok(x)
+                    }).await
+                })
+            })
+            .collect();
+
+        sleep(Duration::from_millis(50)).await;
+        let general = manager.get_pool_stats().into_iter().find(|pool| pool.task_type == TaskType::General).unwrap();
+        assert_eq!(general.active_workers, 2);
+        assert_eq!(general.queued_tasks, 3);
+
+        for handle in handles {
+            assert!(handle.await.unwrap().result.is_ok());
+        }
+        let general = manager.get_pool_stats().into_iter().find(|pool| pool.task_type == TaskType::General).unwrap();
+        assert_eq!(general.active_workers, 0);
+        assert_eq!(general.queued_tasks, 0);
+        assert_eq!(general.completed_tasks, 5);
+    }
+
+    #[tokio::test]
+    async fn test_resize_pool() {
+        let manager = ThreadPoolManager::with_size(2);
+
+        manager.resize_pool(&TaskType::General, 4).await.unwrap();
+        let general = manager.get_stats(&TaskType::General).unwrap();
+        assert_eq!(general.idle_threads, 4);
+
+        manager.resize_pool(&TaskType::General, 1).await.unwrap();
+        let general = manager.get_stats(&TaskType::General).unwrap();
+        assert_eq!(general.idle_threads, 1);
+        assert_eq!(general.active_threads, 0);
+
+        assert!(manager.resize_pool(&TaskType::General, 0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_panicking_task_is_reported_and_pool_keeps_working() {
+        let manager = ThreadPoolManager::with_size(1);
+
+        let task = ThreadPoolManager::create_task(TaskType::General, TaskPriority::Normal, ());
+        let result: TaskResult<()> = manager.execute_task(task, |_| panic!("boom")).await;
+        assert_eq!(result.result.unwrap_err(), "Task panicked: boom");
+
+        // The only worker's permit was released, so the next task still runs
+        let task = ThreadPoolManager::create_task(TaskType::General, TaskPriority::Normal, 21);
+        let result = manager.execute_task(task, |x| Ok(x * 2)).await;
+        assert_eq!(result.result.unwrap(), 42);
+
+        let general = manager.get_pool_stats().into_iter().find(|pool| pool.task_type == TaskType::General).unwrap();
+        assert_eq!(general.panicked_tasks, 1);
+        assert_eq!(general.failed_tasks, 1);
+        assert_eq!(general.completed_tasks, 1);
+    }
+
+    /// Wait until `count` embedding tasks are waiting for a worker
+    async fn wait_for_queued_embeddings(manager: &ThreadPoolManager, count: usize) {
+        let queued = || manager.get_pool_stats().into_iter().find(|pool| pool.task_type == TaskType::Embedding).unwrap().queued_tasks;
+        while queued() < count {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_high_priority_embedding_runs_before_queued_normal_batches() {
+        let manager = Arc::new(ThreadPoolManager::with_size(2)); // Embedding pool has one worker
+        let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let submit = |label: &'static str, priority: TaskPriority, release: Option<std::sync::mpsc::Receiver<()>>| {
+            let manager = manager.clone();
+            let order = order.clone();
+            tokio::spawn(async move {
+                let task = ThreadPoolManager::create_task(TaskType::Embedding, priority, ());
+                manager.execute_task(task, move |_| {
+                    order.lock().unwrap().push(label);
+                    if let Some(release) = release {
+                        release.recv().unwrap();
+                    }
+                    Ok(())
+                }).await
+            })
+        };
+
+        // The running ingest holds the only worker until everything else is queued
+        let (release, blocked) = std::sync::mpsc::channel();
+        let mut handles = vec![submit("running ingest", TaskPriority::Normal, Some(blocked))];
+        while order.lock().unwrap().is_empty() {
+            tokio::task::yield_now().await;
+        }
+        for (queued, label) in ["ingest batch 1", "ingest batch 2"].into_iter().enumerate() {
+            handles.push(submit(label, TaskPriority::Normal, None));
+            wait_for_queued_embeddings(&manager, queued + 1).await;
+        }
+        handles.push(submit("query embedding", TaskPriority::High, None));
+        wait_for_queued_embeddings(&manager, 3).await;
+        release.send(()).unwrap();
+
+        for handle in handles {
+            assert!(handle.await.unwrap().result.is_ok());
+        }
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec!["running ingest", "query embedding", "ingest batch 1", "ingest batch 2"]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Unused-export and dead-module detection from a dependency graph
+//!
+//! Only imports between analyzed files are visible, so dynamic imports, reflection,
+//! and use from outside the analyzed set can make live code look unused. Entry points
+//! and known-live symbols can be allowlisted.
+
+use crate::code_analysis::DependencyGraph;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// What `find_unused_exports` can't see, returned with every report
+const CAVEATS: [&str; 3] = [
+    "Dynamic imports (import(), require with computed paths, importlib) are not tracked",
+    "Exports used from outside the analyzed files, e.g. by tests or other packages, are not seen",
+    "Files only loaded by a framework or build configuration need to be allowlisted as entry points",
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnusedExportOptions {
+    /// Glob patterns of files used from outside the graph, e.g. `**/main.rs`; their
+    /// exports and missing importers are never reported
+    pub entry_points: Vec<String>,
+    /// Symbol names never reported, e.g. framework hooks
+    pub ignored_symbols: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnusedExport {
+    pub file_path: String,
+    pub symbol: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnusedExportReport {
+    pub unused_exports: Vec<UnusedExport>,
+    /// Files no other analyzed file imports: potential dead modules
+    pub unreferenced_files: Vec<String>,
+    pub caveats: Vec<String>,
+}
+
+pub fn find_unused_exports(graph: &DependencyGraph, options: &UnusedExportOptions) -> Result<UnusedExportReport, String> {
+    let entry_points = options.entry_points.iter()
+        .map(|pattern| glob::Pattern::new(pattern).map_err(|e| format!("Invalid entry point pattern '{}': {}", pattern, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let is_entry_point = |file_path: &str| entry_points.iter().any(|pattern| pattern.matches(file_path));
+
+    let mut imported: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for edge in &graph.edges {
+        // Self-imports don't keep a file alive
+        if edge.from != edge.to {
+            imported.entry(edge.to.as_str()).or_default().extend(edge.symbols.iter().map(String::as_str));
+        }
+    }
+
+    let mut unused_exports = Vec::new();
+    let mut unreferenced_files = Vec::new();
+    for node in graph.nodes.values() {
+        if is_entry_point(&node.file_path) {
+            continue;
+        }
+        let used = imported.get(node.file_path.as_str());
+        if used.is_none() {
+            unreferenced_files.push(node.file_path.clone());
+        }
+        for symbol in &node.exports {
+            let is_used = used.is_some_and(|used| used.contains(symbol.as_str()));
+            if !is_used && !options.ignored_symbols.contains(symbol) {
+                unused_exports.push(UnusedExport {
+                    file_path: node.file_path.clone(),
+                    symbol: symbol.clone(),
+                });
+            }
+        }
+    }
+    unused_exports.sort_by(|a, b| (&a.file_path, &a.symbol).cmp(&(&b.file_path, &b.symbol)));
+    unreferenced_files.sort();
+
+    Ok(UnusedExportReport {
+        unused_exports,
+        unreferenced_files,
+        caveats: CAVEATS.iter().map(|caveat| caveat.to_string()).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code_analysis::{DependencyEdge, DependencyNode, DependencyStrength};
+
+    fn graph() -> DependencyGraph {
+        let node = |file_path: &str, exports: &[&str]| (file_path.to_string(), DependencyNode {
+            file_path: file_path.to_string(),
+            language: "typescript".to_string(),
+            symbols: Vec::new(),
+            imports: Vec::new(),
+            exports: exports.iter().map(|export| export.to_string()).collect(),
+        });
+        let edge = |from: &str, to: &str, symbols: &[&str]| DependencyEdge {
+            from: from.to_string(),
+            to: to.to_string(),
+            symbols: symbols.iter().map(|symbol| symbol.to_string()).collect(),
+            strength: DependencyStrength::Weak,
+        };
+        DependencyGraph {
+            nodes: HashMap::from([
+                node("/repo/src/main.ts", &["run"]),
+                node("/repo/src/math.ts", &["add", "subtract"]),
+                node("/repo/src/format.ts", &["format"]),
+                node("/repo/src/legacy.ts", &["oldFormat"]),
+            ]),
+            edges: vec![
+                edge("/repo/src/main.ts", "/repo/src/format.ts", &["format"]),
+                edge("/repo/src/format.ts", "/repo/src/math.ts", &["add"]),
+            ],
+            summary: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_flags_unused_exports_and_unreferenced_files() {
+        let report = find_unused_exports(&graph(), &UnusedExportOptions::default()).unwrap();
+
+        assert!(report.unused_exports.contains(&UnusedExport {
+            file_path: "/repo/src/math.ts".to_string(),
+            symbol: "subtract".to_string(),
+        }));
+        assert!(!report.unused_exports.iter().any(|unused| unused.symbol == "add" || unused.symbol == "format"));
+        assert_eq!(report.unreferenced_files, vec!["/repo/src/legacy.ts", "/repo/src/main.ts"]);
+        assert!(!report.caveats.is_empty());
+    }
+
+    #[test]
+    fn test_allowlisted_entry_points_and_symbols_are_not_flagged() {
+        let options = UnusedExportOptions {
+            entry_points: vec!["**/main.ts".to_string()],
+            ignored_symbols: vec!["oldFormat".to_string()],
+        };
+        let report = find_unused_exports(&graph(), &options).unwrap();
+
+        let flagged: Vec<&str> = report.unused_exports.iter().map(|unused| unused.symbol.as_str()).collect();
+        assert_eq!(flagged, vec!["subtract"]);
+        assert_eq!(report.unreferenced_files, vec!["/repo/src/legacy.ts"]);
+
+        let invalid = UnusedExportOptions { entry_points: vec!["[".to_string()], ..Default::default() };
+        assert!(find_unused_exports(&graph(), &invalid).is_err());
+    }
+}
@@ -10,6 +10,34 @@ pub struct UserError {
     pub help_link: Option<String>,
     pub error_code: String,
     pub technical_details: Option<String>,
+    /// One-click fixes the UI can offer for this error
+    #[serde(default)]
+    pub remediation: Vec<RemediationAction>,
+}
+
+/// A fix the frontend can offer by invoking a Tauri command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemediationAction {
+    pub label: String,
+    pub command: String,
+    /// Arguments to invoke `command` with, when they are known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub args: Option<serde_json::Value>,
+}
+
+impl RemediationAction {
+    pub fn new(label: &str, command: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            command: command.to_string(),
+            args: None,
+        }
+    }
+
+    pub fn with_args(mut self, args: serde_json::Value) -> Self {
+        self.args = Some(args);
+        self
+    }
 }
 
 impl fmt::Display for UserError {
@@ -20,178 +48,349 @@ impl fmt::Display for UserError {
 
 impl std::error::Error for UserError {}
 
-/// Convert common error scenarios to user-friendly messages
-pub trait ToUserError {
-    fn to_user_error(&self) -> UserError;
+/// Failure modes recognised in low-level errors, each with its own user-facing message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorKind {
+    OllamaOffline,
+    /// Ollama is running but has no models, so AI features are unavailable
+    NoModelsInstalled,
+    ModelNotFound,
+    /// The prompt doesn't fit the model's context window
+    PromptTooLong,
+    OllamaServerError,
+    EmbeddingDimensionMismatch,
+    SearxngOffline,
+    ChromaOffline,
+    ConnectionFailed,
+    Timeout,
+    AccessDenied,
+    FileNotFound,
+    WritePermission,
+    DnsError,
+    OutOfMemory,
+    Unknown,
 }
 
-impl ToUserError for Box<dyn std::error::Error + Send> {
-    fn to_user_error(&self) -> UserError {
-        let error_str = self.to_string().to_lowercase();
-        
+impl ErrorKind {
+    /// Classify an error, inspecting reqwest errors directly and the full source chain otherwise
+    pub fn from_error(error: &(dyn std::error::Error + 'static)) -> Self {
+        let details = error_chain_text(error);
+
+        if let Some(request_error) = error.downcast_ref::<reqwest::Error>() {
+            if request_error.is_connect() {
+                return Self::connection_failure(&details.to_lowercase());
+            }
+            if request_error.is_timeout() {
+                return ErrorKind::Timeout;
+            }
+        }
+
+        Self::classify(&details)
+    }
+
+    /// Classify an error from its message text
+    pub fn classify(message: &str) -> Self {
+        let error_str = message.to_lowercase();
+
         // Connection-related errors
         if error_str.contains("connection refused") || error_str.contains("could not connect") {
-            if error_str.contains("11434") || error_str.contains("ollama") {
-                return UserError {
-                    title: "Ollama Not Running".to_string(),
-                    message: "Cannot connect to Ollama. The AI service appears to be offline.".to_string(),
-                    suggestion: Some("Please start Ollama by running 'ollama serve' in your terminal, or restart the Ollama application.".to_string()),
-                    help_link: Some("https://ollama.ai/download".to_string()),
-                    error_code: "OLLAMA_OFFLINE".to_string(),
-                    technical_details: Some(self.to_string()),
-                };
-            } else if error_str.contains("8080") || error_str.contains("searxng") {
-                return UserError {
-                    title: "Search Service Unavailable".to_string(),
-                    message: "Cannot connect to SearXNG web search service.".to_string(),
-                    suggestion: Some("Search functionality will be limited. You can continue using other features normally.".to_string()),
-                    help_link: Some("https://docs.searxng.org/admin/installation.html".to_string()),
-                    error_code: "SEARXNG_OFFLINE".to_string(),
-                    technical_details: Some(self.to_string()),
-                };
-            } else if error_str.contains("8000") || error_str.contains("chroma") {
-                return UserError {
-                    title: "Document Storage Unavailable".to_string(),
-                    message: "Cannot connect to ChromaDB document storage service.".to_string(),
-                    suggestion: Some("Document management features will be unavailable. Please check if ChromaDB is running.".to_string()),
-                    help_link: Some("https://docs.trychroma.com/getting-started".to_string()),
-                    error_code: "CHROMADB_OFFLINE".to_string(),
-                    technical_details: Some(self.to_string()),
-                };
-            } else {
-                return UserError {
-                    title: "Connection Failed".to_string(),
-                    message: "Unable to connect to a required service.".to_string(),
-                    suggestion: Some("Please check your network connection and ensure all services are running.".to_string()),
-                    help_link: None,
-                    error_code: "CONNECTION_FAILED".to_string(),
-                    technical_details: Some(self.to_string()),
-                };
-            }
+            return Self::connection_failure(&error_str);
         }
-        
+
         // Timeout errors
         if error_str.contains("timeout") || error_str.contains("timed out") {
-            return UserError {
-                title: "Operation Timed Out".to_string(),
-                message: "The operation took too long to complete.".to_string(),
-                suggestion: Some("This might be due to network issues or high server load. Please try again in a moment.".to_string()),
-                help_link: None,
-                error_code: "TIMEOUT".to_string(),
-                technical_details: Some(self.to_string()),
-            };
+            return ErrorKind::Timeout;
         }
-        
-        // Model-related errors
+
+        // Embeddings from a different model than the collection was built with
+        if error_str.contains("dimension") && (error_str.contains("mismatch") || error_str.contains("does not match")) {
+            return ErrorKind::EmbeddingDimensionMismatch;
+        }
+
+        // Degraded mode: nothing to run requests with
+        if error_str.contains("no ollama models are installed") {
+            return ErrorKind::NoModelsInstalled;
+        }
+
+        // Prompts rejected before sending, or with nothing left to trim
+        if error_str.contains("context window") && error_str.contains("tokens") {
+            return ErrorKind::PromptTooLong;
+        }
+
+        // Model-related errors, including Ollama's 404 for an unknown model
         if error_str.contains("model") && (error_str.contains("not found") || error_str.contains("does not exist")) {
-            return UserError {
-                title: "AI Model Not Available".to_string(),
-                message: "The requested AI model is not installed or available.".to_string(),
-                suggestion: Some("Please install the model using 'ollama pull <model-name>' or select a different model from the dropdown.".to_string()),
-                help_link: Some("https://ollama.ai/library".to_string()),
-                error_code: "MODEL_NOT_FOUND".to_string(),
-                technical_details: Some(self.to_string()),
-            };
+            return ErrorKind::ModelNotFound;
         }
-        
+        if let Some(status) = http_status(&error_str) {
+            if OLLAMA_REQUEST_PREFIXES.iter().any(|prefix| error_str.starts_with(prefix)) {
+                if status == 404 && !error_str.starts_with("failed to list models") {
+                    return ErrorKind::ModelNotFound;
+                }
+                if status >= 500 {
+                    return ErrorKind::OllamaServerError;
+                }
+            }
+        }
+
         // Authentication/Permission errors
+        if error_str.contains("permission denied") && (error_str.contains("write") || error_str.contains("create")) {
+            return ErrorKind::WritePermission;
+        }
         if error_str.contains("unauthorized") || error_str.contains("forbidden") || error_str.contains("permission denied") {
-            return UserError {
-                title: "Access Denied".to_string(),
-                message: "You don't have permission to perform this action.".to_string(),
-                suggestion: Some("Please check your credentials or contact your administrator.".to_string()),
-                help_link: None,
-                error_code: "ACCESS_DENIED".to_string(),
-                technical_details: Some(self.to_string()),
-            };
+            return ErrorKind::AccessDenied;
         }
-        
+
         // File system errors
         if error_str.contains("no such file") || error_str.contains("file not found") {
-            return UserError {
-                title: "File Not Found".to_string(),
-                message: "The requested file or directory could not be found.".to_string(),
-                suggestion: Some("Please check the file path and ensure the file exists.".to_string()),
-                help_link: None,
-                error_code: "FILE_NOT_FOUND".to_string(),
-                technical_details: Some(self.to_string()),
-            };
+            return ErrorKind::FileNotFound;
         }
-        
-        if error_str.contains("permission denied") && (error_str.contains("write") || error_str.contains("create")) {
-            return UserError {
-                title: "Cannot Write File".to_string(),
-                message: "Unable to save changes due to file permission issues.".to_string(),
-                suggestion: Some("Please check file permissions or try running as administrator.".to_string()),
-                help_link: None,
-                error_code: "WRITE_PERMISSION".to_string(),
-                technical_details: Some(self.to_string()),
-            };
-        }
-        
+
         // Network errors
         if error_str.contains("dns") || error_str.contains("name resolution") {
-            return UserError {
-                title: "Network Error".to_string(),
-                message: "Unable to resolve the server address.".to_string(),
-                suggestion: Some("Please check your internet connection and DNS settings.".to_string()),
-                help_link: None,
-                error_code: "DNS_ERROR".to_string(),
-                technical_details: Some(self.to_string()),
-            };
+            return ErrorKind::DnsError;
         }
-        
+
         // Memory/Resource errors
         if error_str.contains("out of memory") || error_str.contains("memory allocation") {
-            return UserError {
-                title: "Memory Error".to_string(),
-                message: "The system has run out of available memory.".to_string(),
-                suggestion: Some("Please close other applications or try working with smaller files.".to_string()),
-                help_link: None,
-                error_code: "OUT_OF_MEMORY".to_string(),
-                technical_details: Some(self.to_string()),
-            };
+            return ErrorKind::OutOfMemory;
         }
-        
-        // Generic fallback
+
+        ErrorKind::Unknown
+    }
+
+    fn connection_failure(error_str: &str) -> Self {
+        if error_str.contains("11434") || error_str.contains("ollama") {
+            ErrorKind::OllamaOffline
+        } else if error_str.contains("8080") || error_str.contains("searxng") {
+            ErrorKind::SearxngOffline
+        } else if error_str.contains("8000") || error_str.contains("chroma") {
+            ErrorKind::ChromaOffline
+        } else {
+            ErrorKind::ConnectionFailed
+        }
+    }
+
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            ErrorKind::OllamaOffline => "OLLAMA_OFFLINE",
+            ErrorKind::NoModelsInstalled => "NO_MODELS_INSTALLED",
+            ErrorKind::ModelNotFound => "MODEL_NOT_FOUND",
+            ErrorKind::PromptTooLong => "PROMPT_TOO_LONG",
+            ErrorKind::OllamaServerError => "OLLAMA_SERVER_ERROR",
+            ErrorKind::EmbeddingDimensionMismatch => "EMBEDDING_DIMENSION_MISMATCH",
+            ErrorKind::SearxngOffline => "SEARXNG_OFFLINE",
+            ErrorKind::ChromaOffline => "CHROMADB_OFFLINE",
+            ErrorKind::ConnectionFailed => "CONNECTION_FAILED",
+            ErrorKind::Timeout => "TIMEOUT",
+            ErrorKind::AccessDenied => "ACCESS_DENIED",
+            ErrorKind::FileNotFound => "FILE_NOT_FOUND",
+            ErrorKind::WritePermission => "WRITE_PERMISSION",
+            ErrorKind::DnsError => "DNS_ERROR",
+            ErrorKind::OutOfMemory => "OUT_OF_MEMORY",
+            ErrorKind::Unknown => "GENERIC_ERROR",
+        }
+    }
+
+    /// Build the user-facing error for this failure mode
+    pub fn to_user_error(self, technical_details: String) -> UserError {
+        let (title, message, suggestion, help_link) = match self {
+            ErrorKind::OllamaOffline => (
+                "Ollama Not Running",
+                "Ollama isn't running—start it with `ollama serve`.",
+                "Run 'ollama serve' in your terminal, or restart the Ollama application.",
+                Some("https://ollama.ai/download"),
+            ),
+            ErrorKind::NoModelsInstalled => (
+                "No AI Models Installed",
+                "Ollama is running but has no models—pull one to use AI features.",
+                "Pull a model with 'ollama pull <model-name>' or the Pull model button. Search, history and other non-AI features keep working meanwhile.",
+                Some("https://ollama.ai/library"),
+            ),
+            ErrorKind::ModelNotFound => (
+                "AI Model Not Available",
+                "Model not found—pull it first, or pick another model.",
+                "Install the model using 'ollama pull <model-name>' or select a different model from the dropdown.",
+                Some("https://ollama.ai/library"),
+            ),
+            ErrorKind::PromptTooLong => (
+                "Prompt Too Long",
+                "The request is too long for the model's context window.",
+                "Send less code or context at once, pick a model with a larger context window, or set the prompt limit policy to trim.",
+                None,
+            ),
+            ErrorKind::OllamaServerError => (
+                "Ollama Error",
+                "Ollama ran into a problem handling the request.",
+                "Check the Ollama logs, then try again. Restarting Ollama often clears this up.",
+                None,
+            ),
+            ErrorKind::EmbeddingDimensionMismatch => (
+                "Embedding Model Mismatch",
+                "The collection was built with a different embedding model.",
+                "Switch the collection back to its original embedding model, or re-index it with the new one.",
+                None,
+            ),
+            ErrorKind::SearxngOffline => (
+                "Search Service Unavailable",
+                "Cannot connect to SearXNG web search service.",
+                "Search functionality will be limited. You can continue using other features normally.",
+                Some("https://docs.searxng.org/admin/installation.html"),
+            ),
+            ErrorKind::ChromaOffline => (
+                "Document Storage Unavailable",
+                "Cannot connect to ChromaDB document storage service.",
+                "Document management features will be unavailable. Please check if ChromaDB is running.",
+                Some("https://docs.trychroma.com/getting-started"),
+            ),
+            ErrorKind::ConnectionFailed => (
+                "Connection Failed",
+                "Unable to connect to a required service.",
+                "Please check your network connection and ensure all services are running.",
+                None,
+            ),
+            ErrorKind::Timeout => (
+                "Operation Timed Out",
+                "The operation took too long to complete.",
+                "This might be due to network issues or high server load. Please try again in a moment.",
+                None,
+            ),
+            ErrorKind::AccessDenied => (
+                "Access Denied",
+                "You don't have permission to perform this action.",
+                "Please check your credentials or contact your administrator.",
+                None,
+            ),
+            ErrorKind::FileNotFound => (
+                "File Not Found",
+                "The requested file or directory could not be found.",
+                "Please check the file path and ensure the file exists.",
+                None,
+            ),
+            ErrorKind::WritePermission => (
+                "Cannot Write File",
+                "Unable to save changes due to file permission issues.",
+                "Please check file permissions or try running as administrator.",
+                None,
+            ),
+            ErrorKind::DnsError => (
+                "Network Error",
+                "Unable to resolve the server address.",
+                "Please check your internet connection and DNS settings.",
+                None,
+            ),
+            ErrorKind::OutOfMemory => (
+                "Memory Error",
+                "The system has run out of available memory.",
+                "Please close other applications or try working with smaller files.",
+                None,
+            ),
+            ErrorKind::Unknown => (
+                "Unexpected Error",
+                "An unexpected error occurred.",
+                "Please try again. If the problem persists, you can report this issue for assistance.",
+                None,
+            ),
+        };
+
         UserError {
-            title: "Unexpected Error".to_string(),
-            message: "An unexpected error occurred.".to_string(),
-            suggestion: Some("Please try again. If the problem persists, you can report this issue for assistance.".to_string()),
-            help_link: None,
-            error_code: "GENERIC_ERROR".to_string(),
-            technical_details: Some(self.to_string()),
+            title: title.to_string(),
+            message: message.to_string(),
+            suggestion: Some(suggestion.to_string()),
+            help_link: help_link.map(str::to_string),
+            error_code: self.error_code().to_string(),
+            technical_details: Some(technical_details),
+            remediation: self.remediation(),
         }
     }
+
+    /// Commands the UI can offer to fix this failure
+    pub fn remediation(&self) -> Vec<RemediationAction> {
+        match self {
+            ErrorKind::OllamaOffline => vec![
+                RemediationAction::new("Retry connection", "check_ollama_connection"),
+            ],
+            ErrorKind::NoModelsInstalled => vec![
+                RemediationAction::new("Pull model", "pull_ollama_model")
+                    .with_args(serde_json::json!({ "model": crate::ollama_client::SUGGESTED_MODEL })),
+                RemediationAction::new("Check again", "check_model_availability"),
+            ],
+            ErrorKind::ModelNotFound => vec![
+                RemediationAction::new("Pull model", "pull_ollama_model"),
+                RemediationAction::new("Choose another model", "list_models"),
+            ],
+            ErrorKind::EmbeddingDimensionMismatch => vec![
+                RemediationAction::new("Change embedding model", "set_collection_embedding_model"),
+                RemediationAction::new("View collection settings", "get_collection_metadata"),
+            ],
+            ErrorKind::SearxngOffline => vec![
+                RemediationAction::new("Retry connection", "check_searxng_connection"),
+                RemediationAction::new("Search with fallback", "search_web_with_fallback"),
+            ],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Message prefixes used by `OllamaClient` when a request returns an error status
+const OLLAMA_REQUEST_PREFIXES: &[&str] = &[
+    "failed to list models",
+    "failed to generate completion",
+    "failed to generate stream",
+    "failed to chat",
+    "failed to create embedding",
+    "failed to preload model",
+];
+
+/// HTTP status at the end of messages like "Failed to chat: 404 Not Found"
+fn http_status(error_str: &str) -> Option<u16> {
+    let (_, status) = error_str.rsplit_once(": ")?;
+    status.split_whitespace().next()?.parse().ok()
+}
+
+/// An error's message followed by those of its sources, e.g. reqwest's underlying "connection refused"
+fn error_chain_text(error: &(dyn std::error::Error + 'static)) -> String {
+    let mut text = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        text.push_str(": ");
+        text.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    text
+}
+
+/// Convert common error scenarios to user-friendly messages
+pub trait ToUserError {
+    fn to_user_error(&self) -> UserError;
+}
+
+impl ToUserError for Box<dyn std::error::Error> {
+    fn to_user_error(&self) -> UserError {
+        ErrorKind::from_error(self.as_ref()).to_user_error(error_chain_text(self.as_ref()))
+    }
+}
+
+impl ToUserError for Box<dyn std::error::Error + Send> {
+    fn to_user_error(&self) -> UserError {
+        ErrorKind::from_error(self.as_ref()).to_user_error(error_chain_text(self.as_ref()))
+    }
+}
+
+impl ToUserError for Box<dyn std::error::Error + Send + Sync> {
+    fn to_user_error(&self) -> UserError {
+        ErrorKind::from_error(self.as_ref()).to_user_error(error_chain_text(self.as_ref()))
+    }
+}
+
+impl ToUserError for str {
+    fn to_user_error(&self) -> UserError {
+        ErrorKind::classify(self).to_user_error(self.to_string())
+    }
 }
 
 impl ToUserError for String {
     fn to_user_error(&self) -> UserError {
-        let error_str = self.to_lowercase();
-        
-        // Use the same logic as Box<dyn Error + Send>
-        if error_str.contains("connection refused") || error_str.contains("could not connect") {
-            if error_str.contains("11434") || error_str.contains("ollama") {
-                return UserError {
-                    title: "Ollama Not Running".to_string(),
-                    message: "Cannot connect to Ollama. The AI service appears to be offline.".to_string(),
-                    suggestion: Some("Please start Ollama by running 'ollama serve' in your terminal, or restart the Ollama application.".to_string()),
-                    help_link: Some("https://ollama.ai/download".to_string()),
-                    error_code: "OLLAMA_OFFLINE".to_string(),
-                    technical_details: Some(self.clone()),
-                };
-            }
-        }
-        
-        // Add other patterns as needed...
-        UserError {
-            title: "Error".to_string(),
-            message: self.clone(),
-            suggestion: Some("Please try again or contact support if the issue persists.".to_string()),
-            help_link: None,
-            error_code: "GENERIC_ERROR".to_string(),
-            technical_details: Some(self.clone()),
-        }
+        self.as_str().to_user_error()
     }
 }
 
@@ -203,7 +402,7 @@ pub fn to_user_friendly_error<T: ToUserError>(error: T) -> String {
 
 /// Common user-friendly error scenarios
 pub mod common {
-    use super::UserError;
+    use super::{ErrorKind, RemediationAction, UserError};
     
     pub fn ollama_not_running() -> UserError {
         UserError {
@@ -213,6 +412,7 @@ pub mod common {
             help_link: Some("https://ollama.ai/download".to_string()),
             error_code: "OLLAMA_REQUIRED".to_string(),
             technical_details: None,
+            remediation: ErrorKind::OllamaOffline.remediation(),
         }
     }
     
@@ -224,6 +424,11 @@ pub mod common {
             help_link: Some("https://ollama.ai/library".to_string()),
             error_code: "MODEL_REQUIRED".to_string(),
             technical_details: None,
+            remediation: vec![
+                RemediationAction::new("Pull model", "pull_ollama_model")
+                    .with_args(serde_json::json!({ "model": model_name })),
+                RemediationAction::new("Choose another model", "list_models"),
+            ],
         }
     }
     
@@ -235,6 +440,7 @@ pub mod common {
             help_link: None,
             error_code: "SERVICE_UNAVAILABLE".to_string(),
             technical_details: None,
+            remediation: Vec::new(),
         }
     }
     
@@ -246,6 +452,7 @@ pub mod common {
             help_link: Some("docs/WINDOWS_SETUP.md".to_string()),
             error_code: "SETUP_INCOMPLETE".to_string(),
             technical_details: None,
+            remediation: Vec::new(),
         }
     }
 }
@@ -281,4 +488,129 @@ mod tests {
         assert_eq!(user_error.error_code, "TIMEOUT");
         assert!(user_error.message.contains("too long"));
     }
+    
+    /// Mimics reqwest, whose top-level message omits the underlying cause
+    #[derive(Debug)]
+    struct RequestError(std::io::Error);
+    
+    impl fmt::Display for RequestError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "error sending request for url (http://localhost:11434/api/tags)")
+        }
+    }
+    
+    impl std::error::Error for RequestError {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+    
+    #[test]
+    fn test_connection_refused_in_source_chain_maps_to_ollama_offline() {
+        let io_error = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "Connection refused (os error 111)");
+        let error: Box<dyn std::error::Error + Send + Sync> = Box::new(RequestError(io_error));
+        let user_error = error.to_user_error();
+        
+        assert_eq!(user_error.error_code, "OLLAMA_OFFLINE");
+        assert_eq!(user_error.message, "Ollama isn't running—start it with `ollama serve`.");
+        assert!(user_error.suggestion.unwrap().contains("ollama serve"));
+        assert!(user_error.technical_details.unwrap().contains("os error 111"));
+    }
+    
+    #[test]
+    fn test_ollama_404_maps_to_model_not_found() {
+        let error: Box<dyn std::error::Error> = "Failed to generate completion: 404 Not Found".into();
+        let user_error = error.to_user_error();
+        
+        assert_eq!(user_error.error_code, "MODEL_NOT_FOUND");
+        assert!(user_error.message.starts_with("Model not found—pull it first"));
+        assert!(user_error.suggestion.unwrap().contains("ollama pull"));
+        assert_eq!(ErrorKind::classify("Failed to chat: 404 Not Found"), ErrorKind::ModelNotFound);
+    }
+    
+    #[test]
+    fn test_ollama_server_status_maps_to_server_error() {
+        assert_eq!(ErrorKind::classify("Failed to list models: 500 Internal Server Error"), ErrorKind::OllamaServerError);
+        
+        let user_error = "Failed to list models: 503 Service Unavailable".to_string().to_user_error();
+        assert_eq!(user_error.error_code, "OLLAMA_SERVER_ERROR");
+        assert!(!user_error.message.contains("503"));
+    }
+    
+    #[test]
+    fn test_searxng_connection_error() {
+        let user_error = "could not connect to http://localhost:8080/search".to_user_error();
+        
+        assert_eq!(user_error.error_code, "SEARXNG_OFFLINE");
+        assert!(user_error.help_link.is_some());
+    }
+    
+    fn remediation_commands(user_error: &UserError) -> Vec<&str> {
+        user_error.remediation.iter().map(|action| action.command.as_str()).collect()
+    }
+    
+    #[test]
+    fn test_model_not_found_offers_pull() {
+        let user_error = "Failed to chat: 404 Not Found".to_user_error();
+        
+        assert_eq!(remediation_commands(&user_error), vec!["pull_ollama_model", "list_models"]);
+        assert_eq!(user_error.remediation[0].label, "Pull model");
+    }
+    
+    #[test]
+    fn test_model_not_available_pull_carries_model_name() {
+        let user_error = common::model_not_available("llama3");
+        
+        assert_eq!(user_error.remediation[0].command, "pull_ollama_model");
+        assert_eq!(user_error.remediation[0].args, Some(serde_json::json!({ "model": "llama3" })));
+    }
+    
+    #[test]
+    fn test_no_models_installed_offers_suggested_pull() {
+        let user_error = crate::ollama_client::NoModelsInstalled.to_string().to_user_error();
+        
+        assert_eq!(user_error.error_code, "NO_MODELS_INSTALLED");
+        assert_eq!(remediation_commands(&user_error), vec!["pull_ollama_model", "check_model_availability"]);
+        assert_eq!(user_error.remediation[0].args, Some(serde_json::json!({ "model": "llama3.2" })));
+    }
+    
+    #[test]
+    fn test_ollama_offline_offers_retry() {
+        let user_error = "connection refused to localhost:11434".to_user_error();
+        
+        assert_eq!(remediation_commands(&user_error), vec!["check_ollama_connection"]);
+        assert_eq!(remediation_commands(&common::ollama_not_running()), vec!["check_ollama_connection"]);
+    }
+    
+    #[test]
+    fn test_dimension_mismatch_offers_embedding_model_fix() {
+        let user_error = "Embedding dimension 768 does not match collection dimensionality 384".to_user_error();
+        
+        assert_eq!(user_error.error_code, "EMBEDDING_DIMENSION_MISMATCH");
+        assert_eq!(remediation_commands(&user_error), vec!["set_collection_embedding_model", "get_collection_metadata"]);
+    }
+    
+    #[test]
+    fn test_searxng_offline_offers_retry_and_fallback() {
+        let user_error = "connection refused: searxng at http://localhost:8080".to_user_error();
+        
+        assert_eq!(remediation_commands(&user_error), vec!["check_searxng_connection", "search_web_with_fallback"]);
+    }
+    
+    #[test]
+    fn test_remediation_serializes_for_frontend() {
+        let json = serde_json::to_value("Failed to chat: 404 Not Found".to_user_error()).unwrap();
+        
+        assert_eq!(json["remediation"][0]["command"], "pull_ollama_model");
+        assert!(json["remediation"][0].get("args").is_none());
+        assert!("something odd happened".to_user_error().remediation.is_empty());
+    }
+    
+    #[test]
+    fn test_unknown_error_keeps_technical_details() {
+        let user_error = "something odd happened".to_user_error();
+        
+        assert_eq!(user_error.error_code, "GENERIC_ERROR");
+        assert_eq!(user_error.technical_details.as_deref(), Some("something odd happened"));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,423 @@
+//! Startup warm-up
+//!
+//! Preloads the default chat and embedding models and checks that Chroma,
+//! SearXNG and enabled MCP servers are reachable, so the first user request
+//! doesn't pay for cold starts. The whole routine shares one time budget and
+//! can be cancelled at any point.
+
+use crate::chroma_manager::ChromaManager;
+use crate::mcp_manager::MCPManager;
+use crate::model_aliases::ModelRole;
+use crate::ollama_client::OllamaClient;
+use crate::searxng_client::SearXNGClient;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Mutex};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmUpConfig {
+    pub enabled: bool,
+    /// Chat model to preload; the first installed model is used when unset
+    pub chat_model: Option<String>,
+    pub embedding_model: Option<String>,
+    pub timeout_seconds: u64,
+}
+
+impl Default for WarmUpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            chat_model: None,
+            embedding_model: Some(ModelRole::Embed.alias().to_string()), // Matches the Chroma batch default
+            timeout_seconds: 30, // Budget for the whole warm-up, not per step
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmUpStep {
+    pub name: String,
+    pub success: bool,
+    pub duration_ms: u64,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WarmUpReport {
+    pub steps: Vec<WarmUpStep>,
+    pub completed: bool,
+    pub cancelled: bool,
+    pub timed_out: bool,
+    pub total_duration_ms: u64,
+}
+
+impl WarmUpReport {
+    pub fn is_ready(&self) -> bool {
+        self.completed && self.steps.iter().all(|step| step.success)
+    }
+
+    /// One-line readiness summary for the log
+    pub fn summary(&self) -> String {
+        let ready = self.steps.iter().filter(|step| step.success).count();
+        let failed: Vec<&str> = self.steps.iter()
+            .filter(|step| !step.success)
+            .map(|step| step.name.as_str())
+            .collect();
+
+        let status = if self.cancelled {
+            "cancelled"
+        } else if self.timed_out {
+            "timed out"
+        } else {
+            "completed"
+        };
+
+        if failed.is_empty() {
+            format!("Warm-up {} in {}ms: {}/{} checks ready", status, self.total_duration_ms, ready, self.steps.len())
+        } else {
+            format!(
+                "Warm-up {} in {}ms: {}/{} checks ready, not ready: {}",
+                status, self.total_duration_ms, ready, self.steps.len(), failed.join(", ")
+            )
+        }
+    }
+}
+
+enum StepOutcome {
+    Finished,
+    Cancelled,
+    TimedOut,
+}
+
+/// Run one warm-up step against the shared deadline, recording its result
+async fn run_step<F>(
+    report: &mut WarmUpReport,
+    name: &str,
+    deadline: Instant,
+    cancel: &mut watch::Receiver<bool>,
+    step: F,
+) -> StepOutcome
+where
+    F: Future<Output = Result<String, String>>,
+{
+    let started = Instant::now();
+    let remaining = deadline.saturating_duration_since(started);
+
+    let (success, detail, outcome) = tokio::select! {
+        result = tokio::time::timeout(remaining, step) => match result {
+            Ok(Ok(detail)) => (true, detail, StepOutcome::Finished),
+            Ok(Err(error)) => (false, error, StepOutcome::Finished),
+            Err(_) => (false, "Timed out".to_string(), StepOutcome::TimedOut),
+        },
+        _ = wait_for_cancel(cancel) => (false, "Cancelled".to_string(), StepOutcome::Cancelled),
+    };
+
+    report.steps.push(WarmUpStep {
+        name: name.to_string(),
+        success,
+        duration_ms: started.elapsed().as_millis() as u64,
+        detail,
+    });
+
+    outcome
+}
+
+async fn wait_for_cancel(cancel: &mut watch::Receiver<bool>) {
+    while !*cancel.borrow() {
+        if cancel.changed().await.is_err() {
+            // Sender dropped without cancelling; never resolve
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// Preload models and check service connections, stopping early on timeout or cancellation
+pub async fn warm_up(
+    ollama: &OllamaClient,
+    chroma: &Mutex<ChromaManager>,
+    searxng: &SearXNGClient,
+    mcp: &MCPManager,
+    config: &WarmUpConfig,
+    mut cancel: watch::Receiver<bool>,
+) -> WarmUpReport {
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(config.timeout_seconds);
+    let mut report = WarmUpReport::default();
+
+    macro_rules! step {
+        ($name:expr, $future:expr) => {
+            match run_step(&mut report, $name, deadline, &mut cancel, $future).await {
+                StepOutcome::Finished => {}
+                StepOutcome::Cancelled => {
+                    report.cancelled = true;
+                    report.total_duration_ms = started.elapsed().as_millis() as u64;
+                    return report;
+                }
+                StepOutcome::TimedOut => {
+                    report.timed_out = true;
+                    report.total_duration_ms = started.elapsed().as_millis() as u64;
+                    return report;
+                }
+            }
+        };
+    }
+
+    step!("ollama_chat_model", async {
+        let model = match &config.chat_model {
+            Some(model) => model.clone(),
+            None => ollama.list_models().await
+                .map_err(|e| format!("Failed to list models: {}", e))?
+                .into_iter()
+                .next()
+                .map(|model| model.name)
+                .ok_or_else(|| "No chat models installed".to_string())?,
+        };
+        ollama.preload_model(&model, false).await
+            .map_err(|e| format!("Failed to preload {}: {}", model, e))?;
+        Ok(format!("Preloaded {}", model))
+    });
+
+    if let Some(model) = &config.embedding_model {
+        step!("ollama_embedding_model", async {
+            ollama.preload_model(model, true).await
+                .map_err(|e| format!("Failed to preload {}: {}", model, e))?;
+            Ok(format!("Preloaded {}", model))
+        });
+    }
+
+    step!("chroma", async {
+        let manager = chroma.lock().await;
+        match manager.validate_connection().await.map_err(|e| e.to_string())? {
+            true => Ok("Connected".to_string()),
+            false => Err("Connection validation failed".to_string()),
+        }
+    });
+
+    step!("searxng", async {
+        match searxng.check_connection().await.map_err(|e| e.to_string())? {
+            true => Ok("Connected".to_string()),
+            false => Err("Health check failed".to_string()),
+        }
+    });
+
+    step!("mcp", async {
+        let servers: Vec<_> = mcp.list_servers().await
+            .into_iter()
+            .filter(|server| server.enabled)
+            .collect();
+        let mut failed = Vec::new();
+        for server in &servers {
+            if let Err(e) = mcp.test_connection(&server.id).await {
+                failed.push(format!("{} ({})", server.name, e));
+            }
+        }
+        if failed.is_empty() {
+            Ok(format!("{} enabled server(s) connected", servers.len()))
+        } else {
+            Err(format!("Unreachable: {}", failed.join(", ")))
+        }
+    });
+
+    report.completed = true;
+    report.total_duration_ms = started.elapsed().as_millis() as u64;
+    report
+}
+
+/// Managed state for the startup warm-up: its cancel switch and last report
+pub struct WarmUpState {
+    cancel: watch::Sender<bool>,
+    report: std::sync::Mutex<Option<WarmUpReport>>,
+}
+
+impl WarmUpState {
+    pub fn new() -> Self {
+        let (cancel, _) = watch::channel(false);
+        Self {
+            cancel,
+            report: std::sync::Mutex::new(None),
+        }
+    }
+
+    pub fn cancel_receiver(&self) -> watch::Receiver<bool> {
+        self.cancel.subscribe()
+    }
+
+    pub fn cancel(&self) {
+        let _ = self.cancel.send(true);
+    }
+
+    pub fn set_report(&self, report: WarmUpReport) {
+        if let Ok(mut current) = self.report.lock() {
+            *current = Some(report);
+        }
+    }
+
+    pub fn report(&self) -> Option<WarmUpReport> {
+        self.report.lock().ok().and_then(|report| report.clone())
+    }
+}
+
+/// Run the warm-up in the background without delaying startup
+pub fn spawn_warm_up(app_handle: tauri::AppHandle, config: WarmUpConfig) {
+    if !config.enabled {
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let ollama = app_handle.state::<OllamaClient>();
+        let chroma = app_handle.state::<Mutex<ChromaManager>>();
+        let searxng = app_handle.state::<SearXNGClient>();
+        let mcp = app_handle.state::<MCPManager>();
+        let state = app_handle.state::<WarmUpState>();
+
+        let report = warm_up(&ollama, &chroma, &searxng, &mcp, &config, state.cancel_receiver()).await;
+        println!("{}", report.summary());
+        state.set_report(report);
+    });
+}
+
+// Tauri command implementations
+use tauri::{Manager, State};
+
+#[tauri::command]
+pub async fn get_warm_up_report(state: State<'_, WarmUpState>) -> Result<Option<WarmUpReport>, String> {
+    Ok(state.report())
+}
+
+#[tauri::command]
+pub async fn cancel_warm_up(state: State<'_, WarmUpState>) -> Result<(), String> {
+    state.cancel();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ollama_client::HealthConfig;
+    use crate::searxng_client::SearXNGHealthConfig;
+    use mockito::Matcher;
+
+    fn services(ollama_url: String, searxng_url: String) -> (OllamaClient, Mutex<ChromaManager>, SearXNGClient, MCPManager, tempfile::TempDir) {
+        let dir = tempfile::TempDir::new().unwrap();
+        let ollama = OllamaClient::new_with_health_config(Some(ollama_url), HealthConfig {
+            max_retry_attempts: 1,
+            ..Default::default()
+        });
+        let searxng = SearXNGClient::new_with_health_config(Some(searxng_url), SearXNGHealthConfig {
+            max_retry_attempts: 1,
+            ..Default::default()
+        });
+        let chroma = ChromaManager::new(dir.path().to_str().unwrap()).unwrap();
+        (ollama, Mutex::new(chroma), searxng, MCPManager::new(), dir)
+    }
+
+    fn config(timeout_seconds: u64) -> WarmUpConfig {
+        WarmUpConfig {
+            enabled: true,
+            chat_model: Some("llama3".to_string()),
+            embedding_model: Some("nomic-embed-text".to_string()),
+            timeout_seconds,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_preloads_models_and_checks_services() {
+        let mut ollama_server = mockito::Server::new_async().await;
+        let mut searxng_server = mockito::Server::new_async().await;
+
+        let chat_preload = ollama_server.mock("POST", "/api/generate")
+            .match_body(Matcher::PartialJsonString(r#"{"model": "llama3"}"#.to_string()))
+            .with_status(200)
+            .with_body(r#"{"model": "llama3", "response": "", "done": true}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let embedding_preload = ollama_server.mock("POST", "/api/embeddings")
+            .match_body(Matcher::PartialJsonString(r#"{"model": "nomic-embed-text"}"#.to_string()))
+            .with_status(200)
+            .with_body(r#"{"embedding": []}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let searxng_health = searxng_server.mock("GET", "/healthz")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let (ollama, chroma, searxng, mcp, _dir) = services(ollama_server.url(), searxng_server.url());
+        let (_cancel_tx, cancel_rx) = watch::channel(false);
+
+        let started = Instant::now();
+        let report = warm_up(&ollama, &chroma, &searxng, &mcp, &config(5), cancel_rx).await;
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        chat_preload.assert_async().await;
+        embedding_preload.assert_async().await;
+        searxng_health.assert_async().await;
+
+        assert!(report.completed, "{}", report.summary());
+        assert!(report.is_ready(), "{}", report.summary());
+        let names: Vec<&str> = report.steps.iter().map(|step| step.name.as_str()).collect();
+        assert_eq!(names, vec!["ollama_chat_model", "ollama_embedding_model", "chroma", "searxng", "mcp"]);
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_respects_time_bound() {
+        // Sync servers run on their own thread, so the slow body doesn't block this runtime
+        let mut ollama_server = mockito::Server::new();
+        let searxng_server = mockito::Server::new();
+
+        // Preload hangs well past the warm-up budget
+        let _slow_preload = ollama_server.mock("POST", "/api/generate")
+            .with_status(200)
+            .with_chunked_body(|_| {
+                std::thread::sleep(Duration::from_secs(5));
+                Ok(())
+            })
+            .create();
+
+        let (ollama, chroma, searxng, mcp, _dir) = services(ollama_server.url(), searxng_server.url());
+        let (_cancel_tx, cancel_rx) = watch::channel(false);
+
+        let started = Instant::now();
+        let report = warm_up(&ollama, &chroma, &searxng, &mcp, &config(1), cancel_rx).await;
+
+        assert!(started.elapsed() < Duration::from_secs(3));
+        assert!(report.timed_out);
+        assert!(!report.completed);
+        assert_eq!(report.steps.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_can_be_cancelled() {
+        // Sync servers run on their own thread, so the slow body doesn't block this runtime
+        let mut ollama_server = mockito::Server::new();
+        let searxng_server = mockito::Server::new();
+
+        let _slow_preload = ollama_server.mock("POST", "/api/generate")
+            .with_status(200)
+            .with_chunked_body(|_| {
+                std::thread::sleep(Duration::from_secs(5));
+                Ok(())
+            })
+            .create();
+
+        let (ollama, chroma, searxng, mcp, _dir) = services(ollama_server.url(), searxng_server.url());
+        let (cancel_tx, cancel_rx) = watch::channel(false);
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let _ = cancel_tx.send(true);
+        });
+
+        let started = Instant::now();
+        let report = warm_up(&ollama, &chroma, &searxng, &mcp, &config(30), cancel_rx).await;
+
+        assert!(started.elapsed() < Duration::from_secs(3));
+        assert!(report.cancelled);
+        assert!(!report.completed);
+        assert!(report.summary().contains("cancelled"));
+    }
+}
@@ -10,7 +10,7 @@ import SettingsPanel from './components/SettingsPanel';
 import DependencyGraph from './components/DependencyGraph';
 import ImpactAnalysisPanel from './components/ImpactAnalysisPanel';
 import CodeRefactoringPanel from './components/CodeRefactoringPanel';
-import { ChatSession, ChatMessage } from './types';
+import { ChatSession, ChatMessage, SessionPage } from './types';
 import { OperationMonitor } from './components/OperationMonitor';
 import { OperationTestButton } from './components/OperationTestButton';
 
@@ -26,7 +26,7 @@ function App() {
   useEffect(() => {
     const loadSessions = async () => {
       try {
-        const sessions = await invoke<ChatSession[]>('list_chat_sessions');
+        const { sessions } = await invoke<SessionPage>('list_chat_sessions');
         setChatSessions(sessions);
         
         // Create a new session if none exist
@@ -91,7 +91,9 @@ function App() {
       const updatedSession = await invoke<ChatSession>('add_chat_message', {
         sessionId: currentSession.id,
         role: message.role,
-        content: message.content
+        content: message.content,
+        messageId: message.id,
+        attachments: message.attachments
       });
       
       setChatSessions(prev => 
@@ -105,7 +107,7 @@ function App() {
         const sessionWithTitle = { ...updatedSession, title: newTitle };
         await invoke('update_chat_session', { session: sessionWithTitle });
         
-        const sessions = await invoke<ChatSession[]>('list_chat_sessions');
+        const { sessions } = await invoke<SessionPage>('list_chat_sessions');
         setChatSessions(sessions);
         const updated = sessions.find(s => s.id === sessionWithTitle.id);
         if (updated) {
@@ -54,6 +54,7 @@ export const UserErrorDisplay: React.FC<UserErrorDisplayProps> = ({
         return '📚';
       case 'MODEL_NOT_FOUND':
       case 'MODEL_REQUIRED':
+      case 'NO_MODELS_INSTALLED':
         return '🧠';
       case 'CONNECTION_FAILED':
         return '🌐';
@@ -76,6 +77,7 @@ export const UserErrorDisplay: React.FC<UserErrorDisplayProps> = ({
       case 'OLLAMA_REQUIRED':
       case 'MODEL_NOT_FOUND':
       case 'MODEL_REQUIRED':
+      case 'NO_MODELS_INSTALLED':
         return 'error-high';
       case 'SEARXNG_OFFLINE':
       case 'CHROMADB_OFFLINE':
@@ -1,5 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
-import { ChatSession } from '../types';
+import { ChatSession, MessageAttachment, SessionPage, SessionSort } from '../types';
 
 export interface ApiOptions {
   onError?: (error: Error) => void;
@@ -54,9 +54,9 @@ class Api {
   }
 
   // Chat history
-  async listChatSessions(): Promise<ChatSession[]> {
+  async listChatSessions(options: { limit?: number; offset?: number; sort?: SessionSort } = {}): Promise<SessionPage> {
     try {
-      return await invoke<ChatSession[]>('list_chat_sessions');
+      return await invoke<SessionPage>('list_chat_sessions', options);
     } catch (err) {
       const error = err instanceof Error ? err : new Error(String(err));
       this.options.onError?.(error);
@@ -104,9 +104,15 @@ class Api {
     }
   }
 
-  async addChatMessage(sessionId: string, role: string, content: string): Promise<any> {
+  async addChatMessage(
+    sessionId: string,
+    role: string,
+    content: string,
+    messageId?: string,
+    attachments?: MessageAttachment[]
+  ): Promise<any> {
     try {
-      return await invoke<any>('add_chat_message', { sessionId, role, content });
+      return await invoke<any>('add_chat_message', { sessionId, role, content, messageId, attachments });
     } catch (err) {
       const error = err instanceof Error ? err : new Error(String(err));
       this.options.onError?.(error);
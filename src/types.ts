@@ -6,9 +6,23 @@ export type ChatRole = 'user' | 'assistant' | 'system';
 
 // === Message === (matches backend ChatMessage)
 export interface ChatMessage {
+  /** Supplied by the client or generated; appending the same id twice stores the message once */
+  id?: string;
+  /** Position in the session, assigned by the backend from 1 */
+  sequence?: number;
   role: string;
   content: string;
   timestamp: string;
+  /** Files and images that were in the context for this message */
+  attachments?: MessageAttachment[];
+}
+
+// === Message Attachment === (matches backend Attachment)
+export interface MessageAttachment {
+  path: string;
+  kind: 'file' | 'image';
+  /** Tokens it took up in the context window */
+  token_count: number;
 }
 
 // === Code Snippet === (matches backend)
@@ -38,6 +52,38 @@ export interface ChatSession {
   updated_at: string;
   tags: string[];
   context?: ChatContext;
+  branches?: ChatBranch[];
+  active_branch?: string;
+  /** Set while the session is deleted but can still be restored */
+  deleted_at?: string;
+  /** Running summary used in place of the earlier messages once the history is long */
+  summary?: SessionSummary;
+}
+
+// === Session Page === (matches backend SessionPage)
+export type SessionSort = 'recent' | 'title';
+
+export interface SessionPage {
+  sessions: ChatSession[];
+  /** Sessions matching the filter across all pages */
+  total: number;
+}
+
+// === Session Summary === (matches backend SessionSummary)
+export interface SessionSummary {
+  content: string;
+  /** Sequence of the last message the summary covers */
+  covers_through: number;
+  created_at: string;
+}
+
+// === Chat Branch === (matches backend ChatBranch; messages only for inactive branches)
+export interface ChatBranch {
+  id: string;
+  parent_id?: string;
+  forked_at?: number;
+  created_at: string;
+  messages?: ChatMessage[];
 }
 
 // === Frontend-only types for UI state ===
@@ -47,7 +93,7 @@ export interface ChatAttachment {
   content: string; // base64 or raw text
 }
 
-export interface UIMessage extends ChatMessage {
+export interface UIMessage extends Omit<ChatMessage, 'attachments'> {
   id?: string;
   attachments?: ChatAttachment[];
 }